@@ -0,0 +1,8 @@
+#![no_main]
+
+use fuzz::test_random_bytes_cursor_decode;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    test_random_bytes_cursor_decode(data);
+});
@@ -0,0 +1,8 @@
+#![no_main]
+
+use fuzz::test_random_bytes_import_blob_meta;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    test_random_bytes_import_blob_meta(data);
+});
@@ -0,0 +1,61 @@
+//! Feed arbitrary bytes into the decoders. They should return an error on
+//! malformed input instead of panicking.
+use loro::{cursor::Cursor, ExportMode, ImportOptions, LoroDoc};
+
+const XXH_SEED: u32 = u32::from_le_bytes(*b"LORO");
+
+/// Rewrite the checksum in the header of an exported blob, so that the
+/// corrupted bodies reach the decoders instead of being rejected by the checksum.
+pub fn fix_checksum(bytes: &mut [u8]) {
+    if bytes.len() < 22 || &bytes[..4] != b"loro" {
+        return;
+    }
+
+    let mode = u16::from_be_bytes([bytes[20], bytes[21]]);
+    match mode {
+        1 | 2 => {
+            let checksum = md5::compute(&bytes[20..]).0;
+            bytes[4..20].copy_from_slice(&checksum);
+        }
+        3..=6 => {
+            let checksum = xxhash_rust::xxh32::xxh32(&bytes[20..], XXH_SEED);
+            bytes[16..20].copy_from_slice(&checksum.to_le_bytes());
+        }
+        _ => {}
+    }
+}
+
+pub fn test_random_bytes_doc_import(bytes: &[u8]) {
+    let import = |bytes: &[u8], options: Option<ImportOptions>| {
+        let doc = LoroDoc::new();
+        doc.set_import_options(options);
+        // The default imports decode parts of a snapshot lazily, when they are read. The
+        // strict imports check them all, so reading the doc should not panic then.
+        if doc.import(bytes).is_ok() && options.is_some_and(|x| x.strict) {
+            doc.get_deep_value();
+            let _ = doc.export(ExportMode::Snapshot);
+        }
+    };
+
+    let strict = ImportOptions {
+        max_value_len: Some(1024),
+        max_depth: Some(16),
+        strict: true,
+        ..Default::default()
+    };
+    let mut fixed = bytes.to_vec();
+    fix_checksum(&mut fixed);
+    for bytes in [bytes, fixed.as_slice()] {
+        import(bytes, None);
+        import(bytes, Some(strict));
+    }
+}
+
+pub fn test_random_bytes_import_blob_meta(bytes: &[u8]) {
+    let _ = LoroDoc::decode_import_blob_meta(bytes, true);
+    let _ = LoroDoc::decode_import_blob_meta(bytes, false);
+}
+
+pub fn test_random_bytes_cursor_decode(bytes: &[u8]) {
+    let _ = Cursor::decode(bytes);
+}
@@ -3,10 +3,15 @@ pub mod actions;
 pub mod actor;
 pub mod container;
 pub mod crdt_fuzzer;
+mod decode_fuzzer;
 mod macros;
 pub mod one_doc_fuzzer;
 mod value;
 pub use crdt_fuzzer::{test_multi_sites, test_multi_sites_with_gc, Action, FuzzTarget};
+pub use decode_fuzzer::{
+    fix_checksum, test_random_bytes_cursor_decode, test_random_bytes_doc_import,
+    test_random_bytes_import_blob_meta,
+};
 mod mem_kv_fuzzer;
 pub use mem_kv_fuzzer::{
     minify_simple as kv_minify_simple, test_mem_kv_fuzzer, test_random_bytes_import,
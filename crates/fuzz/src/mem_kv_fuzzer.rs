@@ -263,12 +263,14 @@ pub fn test_mem_kv_fuzzer(actions: &mut [Action]) {
 
 pub fn test_random_bytes_import(bytes: &[u8]) {
     let mut kv = MemKvStore::new(MemKvConfig::new().should_encode_none(true));
-    match kv.import_all(Bytes::from(bytes.to_vec())) {
-        Ok(_) => {
-            // do nothing
-        }
-        Err(_) => {
-            // do nothing
+    if kv.import_all(Bytes::from(bytes.to_vec())).is_err() {
+        return;
+    }
+
+    // The blocks are parsed lazily, so only scan them after they are checked
+    if kv.check_all_blocks().is_ok() {
+        for (k, _) in kv.scan(Bound::Unbounded, Bound::Unbounded) {
+            kv.get(&k);
         }
     }
 }
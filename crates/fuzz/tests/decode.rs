@@ -0,0 +1,164 @@
+use fuzz::{
+    fix_checksum, test_random_bytes_cursor_decode, test_random_bytes_doc_import,
+    test_random_bytes_import, test_random_bytes_import_blob_meta,
+};
+use loro::{cursor::Side, CompressionType, ExportMode, LoroDoc, ToJson};
+
+#[ctor::ctor]
+fn init() {
+    dev_utils::setup_test_log();
+}
+
+fn create_doc() -> LoroDoc {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1).unwrap();
+    let text = doc.get_text("text");
+    text.insert(0, "Hello world!").unwrap();
+    text.mark(0..5, "bold", true).unwrap();
+    let map = doc.get_map("map");
+    map.insert("key", "value").unwrap();
+    map.insert("number", 1.5).unwrap();
+    doc.commit();
+
+    doc.set_peer_id(2).unwrap();
+    let list = doc.get_list("list");
+    list.insert(0, 1).unwrap();
+    list.insert(1, "two").unwrap();
+    list.delete(0, 1).unwrap();
+    let movable_list = doc.get_movable_list("movable_list");
+    movable_list.insert(0, "a").unwrap();
+    movable_list.insert(1, "b").unwrap();
+    movable_list.mov(0, 1).unwrap();
+    movable_list.set(0, "c").unwrap();
+    doc.commit();
+
+    let tree = doc.get_tree("tree");
+    let root = tree.create(None).unwrap();
+    let child = tree.create(root).unwrap();
+    tree.get_meta(child)
+        .unwrap()
+        .insert("name", "child")
+        .unwrap();
+    tree.mov(child, None).unwrap();
+    let counter = doc.get_counter("counter");
+    counter.increment(2.0).unwrap();
+    counter.decrement(0.5).unwrap();
+    text.delete(5, 6).unwrap();
+    doc.commit();
+    doc
+}
+
+fn export_all(doc: &LoroDoc) -> Vec<Vec<u8>> {
+    let frontiers = doc.oplog_frontiers();
+    let mut ans = vec![
+        doc.export(ExportMode::Snapshot).unwrap(),
+        doc.export(ExportMode::all_updates()).unwrap(),
+        doc.export(ExportMode::shallow_snapshot(&frontiers))
+            .unwrap(),
+        doc.export_from(&Default::default()),
+        doc.export_snapshot(),
+    ];
+    // The blocks are not compressed, so the corrupted bytes reach the block decoders
+    doc.set_snapshot_compression(CompressionType::None, None)
+        .unwrap();
+    ans.push(doc.export(ExportMode::Snapshot).unwrap());
+    doc.set_snapshot_compression(CompressionType::LZ4, None)
+        .unwrap();
+    ans
+}
+
+/// Truncate the blob and flip its bytes at different positions. The checksum is fixed
+/// so that the corrupted bodies are decoded.
+fn corrupt(bytes: &[u8]) -> Vec<Vec<u8>> {
+    let step = bytes.len() / 200 + 1;
+    let mut ans = Vec::new();
+    for i in (0..bytes.len()).step_by(step) {
+        ans.push(bytes[..i].to_vec());
+        for mask in [0x01, 0x80, 0xff] {
+            let mut corrupted = bytes.to_vec();
+            corrupted[i] ^= mask;
+            fix_checksum(&mut corrupted);
+            ans.push(corrupted);
+        }
+    }
+    ans
+}
+
+#[test]
+fn exported_blobs_can_be_imported_in_strict_mode() {
+    let doc = create_doc();
+    for bytes in export_all(&doc) {
+        let new_doc = LoroDoc::new();
+        new_doc.set_import_options(Some(loro::ImportOptions {
+            max_value_len: Some(1024),
+            max_depth: Some(16),
+            strict: true,
+            ..Default::default()
+        }));
+        new_doc.import(&bytes).unwrap();
+        assert_eq!(
+            new_doc.get_deep_value().to_json_value(),
+            doc.get_deep_value().to_json_value()
+        );
+    }
+}
+
+#[test]
+fn corrupted_blobs_do_not_panic() {
+    let doc = create_doc();
+    for bytes in export_all(&doc) {
+        for corrupted in corrupt(&bytes) {
+            test_random_bytes_doc_import(&corrupted);
+            test_random_bytes_import_blob_meta(&corrupted);
+        }
+    }
+}
+
+#[test]
+fn corrupted_kv_blobs_do_not_panic() {
+    let doc = create_doc();
+    // The body of a fast snapshot starts with the bytes of the change store
+    let bytes = doc.export(ExportMode::Snapshot).unwrap();
+    let body = &bytes[22..];
+    let len = u32::from_le_bytes(body[..4].try_into().unwrap()) as usize;
+    let kv = &body[4..4 + len];
+    test_random_bytes_import(kv);
+    for corrupted in corrupt(kv) {
+        test_random_bytes_import(&corrupted);
+    }
+}
+
+#[test]
+fn corrupted_cursor_do_not_panic() {
+    let doc = create_doc();
+    let cursor = doc.get_text("text").get_cursor(3, Side::Left).unwrap();
+    let bytes = cursor.encode();
+    test_random_bytes_cursor_decode(&bytes);
+    for corrupted in corrupt(&bytes) {
+        test_random_bytes_cursor_decode(&corrupted);
+    }
+}
+
+#[test]
+fn random_bytes_do_not_panic() {
+    let inputs: [&[u8]; 6] = [
+        b"",
+        b"loro",
+        &[
+            b'l', b'o', b'r', b'o', 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 255,
+        ],
+        &[
+            b'l', b'o', b'r', b'o', 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 3,
+        ],
+        &[
+            b'l', b'o', b'r', b'o', 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 4, 255,
+        ],
+        &[255; 64],
+    ];
+    for bytes in inputs {
+        test_random_bytes_doc_import(bytes);
+        test_random_bytes_import_blob_meta(bytes);
+        test_random_bytes_cursor_decode(bytes);
+        test_random_bytes_import(bytes);
+    }
+}
@@ -1,10 +1,10 @@
 use std::{fmt::Debug, io::Write, ops::{Bound, Range}, sync::Arc};
 
 use bytes::{Buf,  Bytes};
-use loro_common::LoroResult;
+use loro_common::{LoroError, LoroResult};
 use once_cell::sync::OnceCell;
 
-use crate::{compress::{compress, decompress, CompressionType}, iter::KvIterator, sstable::{get_common_prefix_len_and_strip,  SIZE_OF_U32, XXH_SEED}};
+use crate::{compress::{compress, decompress, CompressionDict, CompressionType}, encrypt::{decrypt, encrypt, EncryptionKey}, iter::KvIterator, sstable::{get_common_prefix_len_and_strip,  SIZE_OF_U32, XXH_SEED}};
 
 use super::sstable::{ SIZE_OF_U16, SIZE_OF_U8};
 
@@ -13,7 +13,8 @@ use super::sstable::{ SIZE_OF_U16, SIZE_OF_U8};
 pub struct LargeValueBlock{
     // without checksum
     pub value_bytes: Bytes,
-    pub encoded_bytes: OnceCell<(Bytes, CompressionType)>,
+    /// The encoded bytes with the compression type and the id of the encryption key
+    pub encoded_bytes: OnceCell<(Bytes, CompressionType, Option<u32>)>,
     pub key: Bytes,
 }
 
@@ -25,33 +26,38 @@ impl LargeValueBlock{
     /// ││ bytes │      u32        │
     /// │ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ┘│
     /// └──────────────────────────┘
-    fn encode(&self, w: &mut Vec<u8>, mut compression_type: CompressionType) -> CompressionType {
-        if let Some((bytes, encoded_compression_type)) = self.encoded_bytes.get() {
-            if encoded_compression_type == &compression_type {
+    fn encode(&self, w: &mut Vec<u8>, compression_type: CompressionType, dict: Option<&CompressionDict>, encryption: Option<&EncryptionKey>) -> CompressionType {
+        if let Some((bytes, encoded_compression_type, key_id)) = self.encoded_bytes.get() {
+            if encoded_compression_type == &compression_type && *key_id == encryption.map(|k| k.id()) {
                 w.extend_from_slice(bytes);
                 return compression_type;
             }
         }
 
         let origin_len = w.len();
-        compress(w, &self.value_bytes, compression_type);
+        let mut compression_type = compress(w, &self.value_bytes, compression_type, dict);
         if !compression_type.is_none() && w.len() - origin_len > self.value_bytes.len(){
             w.truncate(origin_len);
-            compress(w, &self.value_bytes, CompressionType::None);
+            compress(w, &self.value_bytes, CompressionType::None, None);
             ensure_cov::notify_cov("kv_store::block::LargeValueBlock::encode::compress_fallback");
             compression_type = CompressionType::None;
         }
+        encrypt_in_place(w, origin_len, encryption);
         let checksum = xxhash_rust::xxh32::xxh32(&w[origin_len..], XXH_SEED);
         w.write_all(&checksum.to_le_bytes()).unwrap();
         compression_type
     }
 
-    fn decode(bytes: Bytes, key: Bytes, compression_type: CompressionType)->LoroResult<Self>{
+    fn decode(bytes: Bytes, key: Bytes, compression_type: CompressionType, dict: Option<&CompressionDict>, encryption: Option<&EncryptionKey>, max_len: usize)->LoroResult<Self>{
+        if bytes.len() < SIZE_OF_U32 {
+            return Err(LoroError::DecodeError("Invalid block bytes".into()));
+        }
         let mut value_bytes = vec![];
-        decompress(&mut value_bytes, bytes.slice(..bytes.len() - SIZE_OF_U32), compression_type)?;
+        let body = decrypt_body(bytes.slice(..bytes.len() - SIZE_OF_U32), encryption)?;
+        decompress(&mut value_bytes, body, compression_type, dict, max_len)?;
         Ok(LargeValueBlock{
             value_bytes: Bytes::from(value_bytes),
-            encoded_bytes: OnceCell::with_value((bytes, compression_type)),
+            encoded_bytes: OnceCell::with_value((bytes, compression_type, encryption.map(|k| k.id()))),
             key,
         })
     }
@@ -60,7 +66,8 @@ impl LargeValueBlock{
 #[derive(Debug, Clone)]
 pub struct NormalBlock {
     pub data: Bytes,
-    pub encoded_data: OnceCell<(Bytes, CompressionType)>,
+    /// The encoded bytes with the compression type and the id of the encryption key
+    pub encoded_data: OnceCell<(Bytes, CompressionType, Option<u32>)>,
     pub first_key: Bytes,
     pub offsets: Vec<u16>,
 }
@@ -74,10 +81,11 @@ impl NormalBlock {
     /// │ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ┘─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ┘─ ─ ─ ┘─ ─ ─ ─ ─ ─ ─ ─ ┘─ ─ ─ ─ ─ ─ ─ ┘│
     /// └────────────────────────────────────────────────────────────────────────────────────────┘
     /// 
-    /// The block body may be compressed then we calculate its checksum (the checksum is not compressed).
-    fn encode(&self, w: &mut Vec<u8>, mut compression_type: CompressionType) -> CompressionType  {
-        if let Some((encoded_data, encoded_compression_type)) = self.encoded_data.get() {
-            if encoded_compression_type == &compression_type {
+    /// The block body may be compressed and then encrypted, then we calculate its checksum
+    /// (the checksum is not compressed or encrypted).
+    fn encode(&self, w: &mut Vec<u8>, compression_type: CompressionType, dict: Option<&CompressionDict>, encryption: Option<&EncryptionKey>) -> CompressionType  {
+        if let Some((encoded_data, encoded_compression_type, key_id)) = self.encoded_data.get() {
+            if encoded_compression_type == &compression_type && *key_id == encryption.map(|k| k.id()) {
                 w.extend_from_slice(encoded_data);
                 return compression_type;
             }
@@ -89,33 +97,73 @@ impl NormalBlock {
             buf.extend_from_slice(&offset.to_le_bytes());
         }
         buf.extend_from_slice(&(self.offsets.len() as u16).to_le_bytes());
-        compress(w, &buf, compression_type);
+        let mut compression_type = compress(w, &buf, compression_type, dict);
         if !compression_type.is_none() && w.len() - origin_len > buf.len(){
             w.truncate(origin_len);
-            compress(w, &buf, CompressionType::None);
+            compress(w, &buf, CompressionType::None, None);
             ensure_cov::notify_cov("kv_store::block::NormalBlock::encode::compress_fallback");
             compression_type = CompressionType::None;
         }
+        encrypt_in_place(w, origin_len, encryption);
         let checksum = xxhash_rust::xxh32::xxh32(&w[origin_len..], XXH_SEED);
         w.extend_from_slice(&checksum.to_le_bytes());
         compression_type
     }
 
-    fn decode(raw_block_and_check: Bytes, first_key: Bytes, compression_type: CompressionType)-> LoroResult<NormalBlock>{
+    fn decode(raw_block_and_check: Bytes, first_key: Bytes, compression_type: CompressionType, dict: Option<&CompressionDict>, encryption: Option<&EncryptionKey>, max_len: usize)-> LoroResult<NormalBlock>{
+        if raw_block_and_check.len() < SIZE_OF_U32 {
+            return Err(LoroError::DecodeError("Invalid block bytes".into()));
+        }
         let buf = raw_block_and_check.slice(..raw_block_and_check.len() - SIZE_OF_U32);
+        let buf = decrypt_body(buf, encryption)?;
         let mut data = vec![];
-        decompress(&mut data, buf, compression_type)?;
+        decompress(&mut data, buf, compression_type, dict, max_len)?;
+        if data.len() < SIZE_OF_U16 {
+            return Err(LoroError::DecodeError("Invalid block bytes".into()));
+        }
         let offsets_len = (&data[data.len() - SIZE_OF_U16..]).get_u16_le() as usize;
-        let data_end = data.len() - SIZE_OF_U16 * (offsets_len + 1);
+        let Some(data_end) = data.len().checked_sub(SIZE_OF_U16 * (offsets_len + 1)) else {
+            return Err(LoroError::DecodeError("Invalid block bytes".into()));
+        };
         let offsets = &data[data_end..data.len() - SIZE_OF_U16];
-        let offsets = offsets.chunks(SIZE_OF_U16).map(|mut chunk| chunk.get_u16_le()).collect();
+        let offsets: Vec<u16> = offsets.chunks(SIZE_OF_U16).map(|mut chunk| chunk.get_u16_le()).collect();
+        if !Self::is_valid(&data[..data_end], &offsets, &first_key) {
+            return Err(LoroError::DecodeError("Invalid block bytes".into()));
+        }
         Ok(NormalBlock{
             data: Bytes::copy_from_slice(&data[..data_end]),
-            encoded_data: OnceCell::with_value((raw_block_and_check, compression_type)),
+            encoded_data: OnceCell::with_value((raw_block_and_check, compression_type, encryption.map(|k| k.id()))),
             offsets,
             first_key,
         })
     }
+
+    /// Check the offsets and the key value chunks, so that reading the block never goes out of bounds
+    fn is_valid(data: &[u8], offsets: &[u16], first_key: &[u8]) -> bool {
+        if offsets.is_empty() || first_key.is_empty() || data.len() > u16::MAX as usize {
+            return false;
+        }
+        for (i, &offset) in offsets.iter().enumerate() {
+            let offset = offset as usize;
+            let offset_end = offsets.get(i + 1).map_or(data.len(), |&o| o as usize);
+            if offset > offset_end || offset_end > data.len() {
+                return false;
+            }
+            if i == 0 {
+                continue;
+            }
+            let mut chunk = &data[offset..offset_end];
+            if chunk.len() < SIZE_OF_U8 + SIZE_OF_U16 {
+                return false;
+            }
+            let common_prefix_len = chunk.get_u8() as usize;
+            let key_suffix_len = chunk.get_u16_le() as usize;
+            if common_prefix_len > first_key.len() || key_suffix_len > chunk.len() || common_prefix_len + key_suffix_len == 0 {
+                return false;
+            }
+        }
+        true
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -163,19 +211,36 @@ impl Block{
         }
     }
 
-    pub fn encode(&self,  w: &mut Vec<u8>, compression_type: CompressionType)->CompressionType{
+    /// Encode the block and return the compression type that is really used.
+    /// The block is encrypted if `encryption` is given.
+    pub fn encode(&self,  w: &mut Vec<u8>, compression_type: CompressionType, dict: Option<&CompressionDict>, encryption: Option<&EncryptionKey>)->CompressionType{
         match self{
-            Block::Normal(block) => block.encode(w,compression_type),
-            Block::Large(block) => block.encode(w,compression_type),
+            Block::Normal(block) => block.encode(w,compression_type, dict, encryption),
+            Block::Large(block) => block.encode(w,compression_type, dict, encryption),
         }
     }
 
-    pub fn decode(raw_block_and_check: Bytes, is_large: bool, key: Bytes, compression_type: CompressionType)->Self{
-        // we have checked the checksum, so the block should be valid when decompressing
+    /// `encryption` should be `None` if the block is not encrypted.
+    pub fn decode(raw_block_and_check: Bytes, is_large: bool, key: Bytes, compression_type: CompressionType, dict: Option<&CompressionDict>, encryption: Option<&EncryptionKey>)->Self{
+        // we have checked the checksum, the dictionary and the key, so the block should be valid when decompressing.
+        // The untrusted blocks should be checked by `SsTable::check_all_blocks` on import instead
+        Self::try_decode(raw_block_and_check, is_large, key, compression_type, dict, encryption, usize::MAX).expect("Failed to decode the block, check the untrusted blocks on import")
+    }
+
+    /// It fails with [LoroError::LimitExceeded] if the block body decompresses to more than `max_len` bytes.
+    pub fn try_decode(raw_block_and_check: Bytes, is_large: bool, key: Bytes, compression_type: CompressionType, dict: Option<&CompressionDict>, encryption: Option<&EncryptionKey>, max_len: usize)->LoroResult<Self>{
         if is_large{
-            return LargeValueBlock::decode(raw_block_and_check, key, compression_type).map(Block::Large).unwrap()
+            return LargeValueBlock::decode(raw_block_and_check, key, compression_type, dict, encryption, max_len).map(Block::Large)
+        }
+        NormalBlock::decode(raw_block_and_check, key, compression_type, dict, encryption, max_len).map(Block::Normal)
+    }
+
+    /// The length of the decompressed block body
+    pub fn decompressed_len(&self)->usize{
+        match self{
+            Block::Normal(block)=>block.data.len() + SIZE_OF_U16 * (block.offsets.len() + 1),
+            Block::Large(block)=>block.value_bytes.len(),
         }
-        NormalBlock::decode(raw_block_and_check, key, compression_type).map(Block::Normal).unwrap()
     }
 
     pub fn len(&self)->usize{
@@ -193,6 +258,21 @@ impl Block{
     }
 }
 
+/// Replace the bytes after `start` with their encrypted form
+fn encrypt_in_place(w: &mut Vec<u8>, start: usize, encryption: Option<&EncryptionKey>){
+    if let Some(key) = encryption {
+        let plain = w.split_off(start);
+        encrypt(w, &plain, key);
+    }
+}
+
+fn decrypt_body(body: Bytes, encryption: Option<&EncryptionKey>)->LoroResult<Bytes>{
+    match encryption {
+        Some(_) => decrypt(&body, encryption).map(Bytes::from),
+        None => Ok(body),
+    }
+}
+
 #[derive(Debug)]
 pub struct BlockBuilder {
     data: Vec<u8>,
@@ -1,18 +1,42 @@
-use std::io::{self, Write};
+use std::{
+    fmt::Debug,
+    io::{self, Read, Write},
+    sync::Arc,
+};
 
 use bytes::Bytes;
 use loro_common::LoroError;
 
+/// The default level used when a zstd block is decoded, since the level is not stored.
+pub const DEFAULT_ZSTD_LEVEL: i32 = 3;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CompressionType {
     None,
     LZ4,
+    /// Zstandard, requires the `zstd` feature.
+    Zstd {
+        level: i32,
+    },
+    /// Zstandard with the shared [CompressionDict] of the store, requires the `zstd` feature.
+    ///
+    /// It falls back to [CompressionType::Zstd] if the store has no dictionary.
+    ZstdWithDict {
+        level: i32,
+    },
 }
 
 impl CompressionType {
     pub fn is_none(&self) -> bool {
         matches!(self, CompressionType::None)
     }
+
+    pub fn is_zstd(&self) -> bool {
+        matches!(
+            self,
+            CompressionType::Zstd { .. } | CompressionType::ZstdWithDict { .. }
+        )
+    }
 }
 
 impl TryFrom<u8> for CompressionType {
@@ -22,6 +46,17 @@ impl TryFrom<u8> for CompressionType {
         match value {
             0 => Ok(CompressionType::None),
             1 => Ok(CompressionType::LZ4),
+            #[cfg(feature = "zstd")]
+            2 => Ok(CompressionType::Zstd {
+                level: DEFAULT_ZSTD_LEVEL,
+            }),
+            #[cfg(feature = "zstd")]
+            3 => Ok(CompressionType::ZstdWithDict {
+                level: DEFAULT_ZSTD_LEVEL,
+            }),
+            // The data is compressed with zstd, which requires the `zstd` feature
+            #[cfg(not(feature = "zstd"))]
+            2 | 3 => Err(LoroError::IncompatibleFutureEncodingError(value as usize)),
             _ => Err(LoroError::DecodeError(
                 format!("Invalid compression type: {}", value).into(),
             )),
@@ -34,11 +69,60 @@ impl From<CompressionType> for u8 {
         match value {
             CompressionType::None => 0,
             CompressionType::LZ4 => 1,
+            CompressionType::Zstd { .. } => 2,
+            CompressionType::ZstdWithDict { .. } => 3,
         }
     }
 }
 
-pub fn compress(w: &mut Vec<u8>, data: &[u8], compression_type: CompressionType) {
+/// A compression dictionary shared by many stores.
+///
+/// Small blocks barely compress on their own. A dictionary trained on typical blocks lets
+/// [CompressionType::ZstdWithDict] compress them well. The same dictionary is required to
+/// decompress the blocks.
+#[derive(Clone, PartialEq, Eq)]
+pub struct CompressionDict {
+    bytes: Arc<[u8]>,
+}
+
+impl CompressionDict {
+    pub fn new(bytes: impl Into<Arc<[u8]>>) -> Self {
+        Self {
+            bytes: bytes.into(),
+        }
+    }
+
+    /// Train a dictionary of at most `max_size` bytes from the samples,
+    /// e.g. the values that are usually stored.
+    #[cfg(feature = "zstd")]
+    pub fn train<T: AsRef<[u8]>>(samples: &[T], max_size: usize) -> Result<Self, LoroError> {
+        let bytes = zstd::dict::from_samples(samples, max_size)?;
+        Ok(Self::new(bytes))
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl Debug for CompressionDict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CompressionDict")
+            .field("len", &self.bytes.len())
+            .finish()
+    }
+}
+
+/// Compress `data` into `w` and return the compression type that is really used.
+///
+/// Zstd falls back to LZ4 without the `zstd` feature, and [CompressionType::ZstdWithDict]
+/// falls back to [CompressionType::Zstd] without a dictionary.
+pub fn compress(
+    w: &mut Vec<u8>,
+    data: &[u8],
+    compression_type: CompressionType,
+    dict: Option<&CompressionDict>,
+) -> CompressionType {
     match compression_type {
         CompressionType::None => {
             w.write_all(data).unwrap();
@@ -48,24 +132,98 @@ pub fn compress(w: &mut Vec<u8>, data: &[u8], compression_type: CompressionType)
             encoder.write_all(data).unwrap();
             let _w = encoder.finish().unwrap();
         }
+        #[cfg(feature = "zstd")]
+        CompressionType::Zstd { level } => {
+            zstd::stream::copy_encode(data, w, level).unwrap();
+        }
+        #[cfg(feature = "zstd")]
+        CompressionType::ZstdWithDict { level } => {
+            let Some(dict) = dict else {
+                return compress(w, data, CompressionType::Zstd { level }, None);
+            };
+            let mut encoder =
+                zstd::stream::write::Encoder::with_dictionary(w, level, dict.as_bytes()).unwrap();
+            encoder.write_all(data).unwrap();
+            let _w = encoder.finish().unwrap();
+        }
+        #[cfg(not(feature = "zstd"))]
+        CompressionType::Zstd { .. } | CompressionType::ZstdWithDict { .. } => {
+            let _ = dict;
+            return compress(w, data, CompressionType::LZ4, None);
+        }
     }
+
+    compression_type
 }
 
+/// Decompress `data` into `out`.
+///
+/// # Errors
+/// - [LoroError::LimitExceeded] if it decompresses to more than `max_len` bytes.
+///   The decompression stops there, so a small bomb can't take much memory.
 pub fn decompress(
     out: &mut Vec<u8>,
     data: Bytes,
     compression_type: CompressionType,
+    dict: Option<&CompressionDict>,
+    max_len: usize,
 ) -> Result<(), LoroError> {
     match compression_type {
         CompressionType::None => {
+            if data.len() > max_len {
+                return Err(decompressed_limit_exceeded(max_len));
+            }
             out.write_all(&data).unwrap();
             Ok(())
         }
         CompressionType::LZ4 => {
-            let mut decoder = lz4_flex::frame::FrameDecoder::new(data.as_ref());
-            io::copy(&mut decoder, out)
+            let decoder = lz4_flex::frame::FrameDecoder::new(data.as_ref());
+            copy_at_most(decoder, out, max_len)
+        }
+        #[cfg(feature = "zstd")]
+        CompressionType::Zstd { .. } => {
+            let decoder = zstd::stream::read::Decoder::with_buffer(data.as_ref())
                 .map_err(|e| LoroError::DecodeError(e.to_string().into()))?;
-            Ok(())
+            copy_at_most(decoder, out, max_len)
+        }
+        #[cfg(feature = "zstd")]
+        CompressionType::ZstdWithDict { .. } => {
+            let Some(dict) = dict else {
+                return Err(LoroError::DecodeError(
+                    "The data is compressed with a dictionary, but no dictionary is given".into(),
+                ));
+            };
+            let decoder =
+                zstd::stream::read::Decoder::with_dictionary(data.as_ref(), dict.as_bytes())
+                    .map_err(|e| LoroError::DecodeError(e.to_string().into()))?;
+            copy_at_most(decoder, out, max_len)
         }
+        #[cfg(not(feature = "zstd"))]
+        CompressionType::Zstd { .. } | CompressionType::ZstdWithDict { .. } => {
+            let _ = dict;
+            Err(LoroError::IncompatibleFutureEncodingError(
+                u8::from(compression_type) as usize,
+            ))
+        }
+    }
+}
+
+/// Copy the output of `decoder` into `out`, but not more than `max_len` bytes
+fn copy_at_most(decoder: impl Read, out: &mut Vec<u8>, max_len: usize) -> Result<(), LoroError> {
+    let start = out.len();
+    // Read one more byte to tell whether there are more than `max_len` bytes
+    let mut decoder = decoder.take((max_len as u64).saturating_add(1));
+    io::copy(&mut decoder, out).map_err(|e| LoroError::DecodeError(e.to_string().into()))?;
+    if out.len() - start > max_len {
+        return Err(decompressed_limit_exceeded(max_len));
+    }
+
+    Ok(())
+}
+
+fn decompressed_limit_exceeded(max: usize) -> LoroError {
+    LoroError::LimitExceeded {
+        limit: "max_decompressed_bytes",
+        max,
     }
 }
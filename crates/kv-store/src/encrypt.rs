@@ -0,0 +1,158 @@
+use std::{fmt::Debug, sync::Arc};
+
+use loro_common::{LoroError, LoroResult};
+
+use crate::sstable::SIZE_OF_U32;
+
+/// The size of the nonce stored in front of every encrypted block
+pub const NONCE_SIZE: usize = 12;
+/// The size of the authentication tag appended to every encrypted block
+pub const TAG_SIZE: usize = 16;
+
+/// A 256-bit key used to encrypt the blocks with ChaCha20-Poly1305.
+///
+/// The `id` is stored in plaintext in every encrypted block, so a store encrypted with
+/// another key is reported as [LoroError::DecryptionError] before trying to decrypt it.
+/// It also makes key rotation possible: the caller can tell which key a block needs.
+#[derive(Clone)]
+pub struct EncryptionKey {
+    id: u32,
+    key: Arc<[u8; 32]>,
+}
+
+impl EncryptionKey {
+    /// Requires the `encryption` feature.
+    #[cfg(feature = "encryption")]
+    pub fn new(id: u32, key: [u8; 32]) -> Self {
+        Self {
+            id,
+            key: Arc::new(key),
+        }
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+}
+
+impl PartialEq for EncryptionKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id && self.key == other.key
+    }
+}
+
+impl Eq for EncryptionKey {}
+
+impl Debug for EncryptionKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Never print the key itself
+        f.debug_struct("EncryptionKey")
+            .field("id", &self.id)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Encrypt `data` into `w`.
+///
+/// ┌────────────────────────────────────────────┐
+/// │ Encrypted Block Body                       │
+/// │┌ ─ ─ ─ ─ ─ ┬ ─ ─ ─ ─ ┬ ─ ─ ─ ─ ─ ─ ┬ ─ ─ ─ ┐│
+/// │   key id   │  nonce  │ ciphertext │  tag  ││
+/// ││    u32    │   12    │   bytes    │  16   ││
+/// │ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ┘│
+/// └────────────────────────────────────────────┘
+///
+/// The key id is authenticated as the associated data.
+#[cfg(feature = "encryption")]
+pub(crate) fn encrypt(w: &mut Vec<u8>, data: &[u8], key: &EncryptionKey) {
+    use chacha20poly1305::{
+        aead::{Aead, Payload},
+        ChaCha20Poly1305, Key, KeyInit, Nonce,
+    };
+
+    let aad = key.id.to_le_bytes();
+    let mut nonce = [0u8; NONCE_SIZE];
+    getrandom::getrandom(&mut nonce).expect("failed to generate the nonce");
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(key.key.as_ref()));
+    let ciphertext = cipher
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: data,
+                aad: &aad,
+            },
+        )
+        .expect("the block is too large to encrypt");
+    w.extend_from_slice(&aad);
+    w.extend_from_slice(&nonce);
+    w.extend_from_slice(&ciphertext);
+}
+
+#[cfg(not(feature = "encryption"))]
+pub(crate) fn encrypt(_w: &mut Vec<u8>, _data: &[u8], _key: &EncryptionKey) {
+    unreachable!("EncryptionKey cannot be created without the `encryption` feature")
+}
+
+/// Decrypt the block body written by [encrypt].
+///
+/// # Errors
+/// [LoroError::DecryptionError] if the key is missing or wrong, or the body is malformed
+pub(crate) fn decrypt(data: &[u8], key: Option<&EncryptionKey>) -> LoroResult<Vec<u8>> {
+    if data.len() < SIZE_OF_U32 + NONCE_SIZE + TAG_SIZE {
+        return Err(LoroError::DecryptionError(
+            "The encrypted block is truncated".into(),
+        ));
+    }
+    let key_id = u32::from_le_bytes(data[..SIZE_OF_U32].try_into().unwrap());
+    let Some(key) = key else {
+        return Err(LoroError::DecryptionError(
+            format!(
+                "The data is encrypted with key {}, but no key is given",
+                key_id
+            )
+            .into(),
+        ));
+    };
+    if key.id != key_id {
+        return Err(LoroError::DecryptionError(
+            format!(
+                "The data is encrypted with key {}, but key {} is given",
+                key_id, key.id
+            )
+            .into(),
+        ));
+    }
+
+    decrypt_inner(data, key)
+}
+
+#[cfg(feature = "encryption")]
+fn decrypt_inner(data: &[u8], key: &EncryptionKey) -> LoroResult<Vec<u8>> {
+    use chacha20poly1305::{
+        aead::{Aead, Payload},
+        ChaCha20Poly1305, Key, KeyInit, Nonce,
+    };
+
+    let (aad, rest) = data.split_at(SIZE_OF_U32);
+    let (nonce, ciphertext) = rest.split_at(NONCE_SIZE);
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(key.key.as_ref()));
+    cipher
+        .decrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad,
+            },
+        )
+        .map_err(|_| {
+            LoroError::DecryptionError(
+                "Failed to authenticate the block. The key is wrong or the data is corrupted"
+                    .into(),
+            )
+        })
+}
+
+#[cfg(not(feature = "encryption"))]
+fn decrypt_inner(_data: &[u8], _key: &EncryptionKey) -> LoroResult<Vec<u8>> {
+    unreachable!("EncryptionKey cannot be created without the `encryption` feature")
+}
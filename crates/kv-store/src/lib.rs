@@ -64,15 +64,24 @@
 //! 2. Write offsets for each key-value pair.
 //! 3. Write the number of key-value pairs.
 //! 4. By default, **Compress** the entire block using LZ4. If you set `compression_type` to `None`, it will not compress the block.
-//!     - For now, there are two compression type: `None` and `LZ4`.
-//! 5. Calculate and append xxhash_32 checksum.
+//!     - There are four compression types: `None`, `LZ4`, `Zstd` and `ZstdWithDict`. The zstd ones require the `zstd` feature.
+//!     - `ZstdWithDict` uses the [compress::CompressionDict] shared by the stores, which is also required to decompress the block.
+//!     - If the compressed block is larger than the original one, the block is stored uncompressed.
+//! 5. If the store has an [encrypt::EncryptionKey], **Encrypt** the block with ChaCha20-Poly1305.
+//!     - The encrypted body is `key id (u32) | nonce (12 bytes) | ciphertext | tag (16 bytes)`.
+//!     - It requires the `encryption` feature.
+//!     - Only the block body is encrypted. The Block Meta, including the first and the last key
+//!       of each block, stays in plaintext, so the keys shouldn't hold secrets.
+//! 6. Calculate and append xxhash_32 checksum.
 //!
 //! Decoding:
 //! 1. Verify the xxhash_32 checksum.
-//! 2. By default, **Decompress** the block using LZ4. If you set `compression_type` to `None`, it will not decompress the block.
-//! 3. Read the number of key-value pairs.
-//! 4. Read offsets for each key-value pair.
-//! 5. Parse individual key-value chunks.
+//! 2. **Decrypt** the block if it's marked as encrypted in its Block Meta. A missing or wrong key
+//!    is reported as `LoroError::DecryptionError`.
+//! 3. **Decompress** the block according to the compression type in its Block Meta.
+//! 4. Read the number of key-value pairs.
+//! 5. Read offsets for each key-value pair.
+//! 6. Parse individual key-value chunks.
 //!
 //! ### Large Value Block
 //!
@@ -121,7 +130,7 @@
 //! Encoding:
 //! 1. Write the number of blocks.
 //! 2. For each block, write its metadata (offset, first key, block type, and last key if not large).
-//!     - block type: the first bit for is_large, the next 7 bits for compression_type.
+//!     - block type: the first bit for is_large, the second bit for encrypted, the next 6 bits for compression_type.
 //! 3. Calculate and append xxhash_32 checksum.
 //!
 //! Decoding:
@@ -134,6 +143,7 @@
 //! Other iterators will still return empty value.
 pub mod block;
 pub mod compress;
+pub mod encrypt;
 pub mod iter;
 pub mod mem_store;
 pub mod sstable;
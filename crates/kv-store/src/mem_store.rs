@@ -1,9 +1,12 @@
 use crate::block::BlockIter;
-use crate::compress::CompressionType;
+use crate::compress::{CompressionDict, CompressionType};
+use crate::encrypt::EncryptionKey;
 use crate::sstable::{SsTable, SsTableBuilder, SsTableIter};
 use crate::{KvIterator, MergeIterator};
 use bytes::Bytes;
+use loro_common::LoroResult;
 
+use std::io::{self, Write};
 use std::ops::Bound;
 use std::{cmp::Ordering, collections::BTreeMap};
 
@@ -14,6 +17,10 @@ pub struct MemKvStore {
     ss_table: Vec<SsTable>,
     block_size: usize,
     compression_type: CompressionType,
+    compression_dict: Option<CompressionDict>,
+    encryption_key: Option<EncryptionKey>,
+    /// The max total length of the decompressed blocks of each imported table
+    max_decompressed_len: Option<usize>,
     /// It's only true when using it to fuzz.
     /// Otherwise, importing and exporting GC snapshot relies on this field being false to work.
     should_encode_none: bool,
@@ -22,6 +29,8 @@ pub struct MemKvStore {
 pub struct MemKvConfig {
     block_size: usize,
     compression_type: CompressionType,
+    compression_dict: Option<CompressionDict>,
+    encryption_key: Option<EncryptionKey>,
     should_encode_none: bool,
 }
 
@@ -30,6 +39,8 @@ impl Default for MemKvConfig {
         Self {
             block_size: MemKvStore::DEFAULT_BLOCK_SIZE,
             compression_type: CompressionType::LZ4,
+            compression_dict: None,
+            encryption_key: None,
             should_encode_none: false,
         }
     }
@@ -50,6 +61,23 @@ impl MemKvConfig {
         self
     }
 
+    /// The dictionary used to compress blocks with [CompressionType::ZstdWithDict].
+    /// It's also required to import the blocks compressed with it.
+    pub fn compression_dict(mut self, compression_dict: CompressionDict) -> Self {
+        self.compression_dict = Some(compression_dict);
+        self
+    }
+
+    /// The key used to encrypt every block of the store.
+    /// It's also required to import the encrypted blocks.
+    ///
+    /// Only the values are hidden: the first and the last key of every block are stored in
+    /// plaintext in the block meta.
+    pub fn encryption_key(mut self, encryption_key: EncryptionKey) -> Self {
+        self.encryption_key = Some(encryption_key);
+        self
+    }
+
     pub fn should_encode_none(mut self, should_encode_none: bool) -> Self {
         self.should_encode_none = should_encode_none;
         self
@@ -68,6 +96,9 @@ impl MemKvStore {
             ss_table: Vec::new(),
             block_size: config.block_size,
             compression_type: config.compression_type,
+            compression_dict: config.compression_dict,
+            encryption_key: config.encryption_key,
+            max_decompressed_len: None,
             should_encode_none: config.should_encode_none,
         }
     }
@@ -195,8 +226,38 @@ impl MemKvStore {
                 .sum::<usize>()
     }
 
+    /// Set how the blocks are compressed and encrypted from now on.
+    ///
+    /// The dictionary and the key are also used to read the blocks imported later.
+    /// The blocks encoded with other settings are encoded again on the next export.
+    pub fn set_codec(
+        &mut self,
+        compression_type: CompressionType,
+        compression_dict: Option<CompressionDict>,
+        encryption_key: Option<EncryptionKey>,
+    ) {
+        self.compression_type = compression_type;
+        self.compression_dict = compression_dict;
+        self.encryption_key = encryption_key;
+    }
+
+    /// Limit the total length of the decompressed blocks of each table imported later.
+    ///
+    /// The tables are decoded eagerly on import if it's set, so that a small table that
+    /// decompresses to a huge size is rejected before it takes the memory.
+    pub fn set_max_decompressed_len(&mut self, max_decompressed_len: Option<usize>) {
+        self.max_decompressed_len = max_decompressed_len;
+    }
+
     pub fn export_all(&mut self) -> Bytes {
-        if self.mem_table.is_empty() && self.ss_table.len() == 1 {
+        if self.mem_table.is_empty()
+            && self.ss_table.len() == 1
+            && self.ss_table[0].is_encoded_with(
+                self.compression_type,
+                self.compression_dict.as_ref(),
+                self.encryption_key.as_ref(),
+            )
+        {
             return self.ss_table[0].export_all();
         }
 
@@ -204,27 +265,8 @@ impl MemKvStore {
             return self.export_with_encoded_block();
         }
 
-        let mut builder = SsTableBuilder::new(
-            self.block_size,
-            self.compression_type,
-            self.should_encode_none,
-        );
-        // we could use scan() here, we should keep the empty value
-        let iter = MemStoreIterator::new(
-            self.mem_table
-                .range::<[u8], _>((Bound::Unbounded, Bound::Unbounded))
-                .map(|(k, v)| (k.clone(), v.clone())),
-            MergeIterator::new(
-                self.ss_table
-                    .iter()
-                    .rev()
-                    .map(|table| SsTableIter::new_scan(table, Bound::Unbounded, Bound::Unbounded))
-                    .collect(),
-            ),
-            false,
-        );
-
-        for (k, v) in iter {
+        let mut builder = self.new_table_builder();
+        for (k, v) in self.iter_all_with_empty_values() {
             builder.add(k, v);
         }
 
@@ -239,26 +281,73 @@ impl MemKvStore {
     }
 
     /// We can import several times, the latter will override the former.
+    ///
+    /// See [MemKvStore::try_import_all] for the kinds of the errors.
     pub fn import_all(&mut self, bytes: Bytes) -> Result<(), String> {
+        self.try_import_all(bytes).map_err(|e| e.to_string())
+    }
+
+    /// Like [MemKvStore::import_all], but keeps the kind of the error.
+    ///
+    /// # Errors
+    /// - [loro_common::LoroError::DecryptionError] if the key is missing or wrong
+    /// - [loro_common::LoroError::LimitExceeded] if the blocks decompress to more bytes than
+    ///   [MemKvStore::set_max_decompressed_len] allows
+    pub fn try_import_all(&mut self, bytes: Bytes) -> LoroResult<()> {
         if bytes.is_empty() {
             return Ok(());
         }
-        let ss_table = SsTable::import_all(bytes).map_err(|e| e.to_string())?;
+        let ss_table = SsTable::import_all_with(
+            bytes,
+            self.compression_dict.clone(),
+            self.encryption_key.clone(),
+            self.max_decompressed_len,
+        )?;
         self.ss_table.push(ss_table);
         Ok(())
     }
 
+    /// Decode all the imported blocks eagerly.
+    ///
+    /// The blocks are decoded lazily when they are read, where malformed blocks panic.
+    /// Call this after importing untrusted bytes to get an error instead.
+    pub fn check_all_blocks(&self) -> LoroResult<()> {
+        for table in self.ss_table.iter() {
+            table.check_all_blocks()?;
+        }
+        Ok(())
+    }
+
     #[tracing::instrument(level = "debug", skip(self))]
     fn export_with_encoded_block(&mut self) -> Bytes {
         ensure_cov::notify_cov("kv-store::mem_store::export_with_encoded_block");
+        let mut builder = self.new_table_builder();
+        self.merge_with_encoded_blocks(&mut builder, &mut |_| Ok(()))
+            .unwrap();
+        if builder.is_empty() {
+            return Bytes::new();
+        }
+
+        self.mem_table.clear();
+        let ss = builder.build();
+        let ans = ss.export_all();
+        let _ = std::mem::replace(&mut self.ss_table, vec![ss]);
+        ans
+    }
+
+    /// Merge the mem table with the only table into `builder`, reusing the blocks of the table
+    /// that don't overlap with the mem table.
+    ///
+    /// `flush` is called whenever a block may have been finished.
+    fn merge_with_encoded_blocks(
+        &self,
+        builder: &mut SsTableBuilder,
+        flush: &mut dyn FnMut(&mut SsTableBuilder) -> io::Result<()>,
+    ) -> io::Result<()> {
         let mut mem_iter = self.mem_table.iter().peekable();
         let mut sstable_iter = self.ss_table[0].iter();
-        let mut builder = SsTableBuilder::new(
-            self.block_size,
-            self.compression_type,
-            self.should_encode_none,
-        );
         'outer: while let Some(next_mem_pair) = mem_iter.peek() {
+            flush(builder)?;
             let block = loop {
                 let Some(block) = sstable_iter.peek_next_block() else {
                     builder.add(next_mem_pair.0.clone(), next_mem_pair.1.clone());
@@ -284,6 +373,7 @@ impl MemKvStore {
             let mut next_mem_pair = mem_iter.peek();
             while let Some(k) = iter.peek_next_key() {
                 loop {
+                    flush(builder)?;
                     match next_mem_pair {
                         Some(next_mem_pair_inner) => {
                             if k > next_mem_pair_inner.0 {
@@ -320,20 +410,71 @@ impl MemKvStore {
         }
 
         while let Some(block) = sstable_iter.peek_next_block() {
+            flush(builder)?;
             builder.add_new_block(block.clone());
             sstable_iter.next_block();
         }
 
-        if builder.is_empty() {
-            return Bytes::new();
+        flush(builder)
+    }
+
+    /// Write the bytes that [MemKvStore::export_all] would return to `w`.
+    ///
+    /// Each block is written as soon as it's encoded, and the tables of the store are
+    /// not replaced.
+    pub fn export_to(&self, w: &mut dyn Write) -> io::Result<()> {
+        if self.mem_table.is_empty()
+            && self.ss_table.len() == 1
+            && self.ss_table[0].is_encoded_with(
+                self.compression_type,
+                self.compression_dict.as_ref(),
+                self.encryption_key.as_ref(),
+            )
+        {
+            return w.write_all(&self.ss_table[0].export_all());
         }
 
-        drop(mem_iter);
-        self.mem_table.clear();
-        let ss = builder.build();
-        let ans = ss.export_all();
-        let _ = std::mem::replace(&mut self.ss_table, vec![ss]);
-        ans
+        let mut builder = self.new_table_builder();
+        let mut flush = |builder: &mut SsTableBuilder| -> io::Result<()> {
+            w.write_all(&builder.take_finished_bytes())
+        };
+        if self.ss_table.len() == 1 {
+            self.merge_with_encoded_blocks(&mut builder, &mut flush)?;
+        } else {
+            for (k, v) in self.iter_all_with_empty_values() {
+                builder.add(k, v);
+                flush(&mut builder)?;
+            }
+        }
+
+        w.write_all(&builder.finish())
+    }
+
+    fn iter_all_with_empty_values(&self) -> impl Iterator<Item = (Bytes, Bytes)> + '_ {
+        // we could use scan() here, we should keep the empty value
+        MemStoreIterator::new(
+            self.mem_table
+                .range::<[u8], _>((Bound::Unbounded, Bound::Unbounded))
+                .map(|(k, v)| (k.clone(), v.clone())),
+            MergeIterator::new(
+                self.ss_table
+                    .iter()
+                    .rev()
+                    .map(|table| SsTableIter::new_scan(table, Bound::Unbounded, Bound::Unbounded))
+                    .collect(),
+            ),
+            false,
+        )
+    }
+
+    fn new_table_builder(&self) -> SsTableBuilder {
+        SsTableBuilder::new(
+            self.block_size,
+            self.compression_type,
+            self.should_encode_none,
+        )
+        .with_dict(self.compression_dict.clone())
+        .with_key(self.encryption_key.clone())
     }
 
     #[allow(unused)]
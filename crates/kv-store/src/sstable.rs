@@ -1,9 +1,11 @@
 use super::block::BlockIter;
 use crate::{
     block::{Block, BlockBuilder},
-    compress::CompressionType,
+    compress::{CompressionDict, CompressionType},
+    encrypt::{decrypt, EncryptionKey},
     iter::KvIterator,
     utils::{get_u16_le, get_u32_le, get_u8_le},
+    MemKvStore,
 };
 use bytes::{Buf, BufMut, Bytes};
 use ensure_cov::*;
@@ -33,6 +35,7 @@ const MAX_BLOCK_NUM: u32 = 10_000_000;
 pub(crate) struct BlockMeta {
     offset: usize,
     is_large: bool,
+    encrypted: bool,
     compression_type: CompressionType,
     first_key: Bytes,
     last_key: Option<Bytes>,
@@ -76,7 +79,8 @@ impl BlockMeta {
             buf.put_u32_le(m.offset as u32);
             buf.put_u16_le(m.first_key.len() as u16);
             buf.put_slice(&m.first_key);
-            let large_and_compress = (m.is_large as u8) << 7 | m.compression_type as u8;
+            let large_and_compress =
+                (m.is_large as u8) << 7 | (m.encrypted as u8) << 6 | u8::from(m.compression_type);
             buf.put_u8(large_and_compress);
             if m.is_large {
                 continue;
@@ -107,11 +111,13 @@ impl BlockMeta {
             let first_key = buf.copy_to_bytes(first_key_len as usize);
             let (is_large_and_compression_type, buf) = get_u8_le(buf)?;
             let is_large = is_large_and_compression_type & 0b1000_0000 != 0;
-            let compression_type = is_large_and_compression_type & 0b0111_1111;
+            let encrypted = is_large_and_compression_type & 0b0100_0000 != 0;
+            let compression_type = is_large_and_compression_type & 0b0011_1111;
             if is_large {
                 ans.push(BlockMeta {
                     offset: offset as usize,
                     is_large,
+                    encrypted,
                     compression_type: compression_type.try_into()?,
                     first_key,
                     last_key: None,
@@ -127,6 +133,7 @@ impl BlockMeta {
             ans.push(BlockMeta {
                 offset: offset as usize,
                 is_large,
+                encrypted,
                 compression_type: compression_type.try_into()?,
                 first_key,
                 last_key: Some(last_key),
@@ -139,6 +146,23 @@ impl BlockMeta {
         }
         Ok(ans)
     }
+
+    /// Whether the blocks are non-empty and ordered by their keys,
+    /// which the lookups by key rely on
+    fn is_sorted(meta: &[BlockMeta]) -> bool {
+        if meta.is_empty() {
+            return false;
+        }
+        let mut prev_last: Option<&Bytes> = None;
+        for m in meta {
+            let last = m.last_key.as_ref().unwrap_or(&m.first_key);
+            if m.first_key > last || prev_last.is_some_and(|prev| prev > &m.first_key) {
+                return false;
+            }
+            prev_last = Some(last);
+        }
+        true
+    }
 }
 
 pub(crate) struct SsTableBuilder {
@@ -146,9 +170,13 @@ pub(crate) struct SsTableBuilder {
     first_key: Bytes,
     last_key: Bytes,
     data: Vec<u8>,
+    /// The length of the bytes taken by [SsTableBuilder::take_finished_bytes]
+    taken_len: usize,
     meta: Vec<BlockMeta>,
     block_size: usize,
     compression_type: CompressionType,
+    dict: Option<CompressionDict>,
+    key: Option<EncryptionKey>,
     include_none: bool, // TODO: bloom filter
 }
 
@@ -162,13 +190,28 @@ impl SsTableBuilder {
             first_key: Bytes::new(),
             last_key: Bytes::new(),
             data,
+            taken_len: 0,
             meta: Vec::new(),
             block_size,
             compression_type,
+            dict: None,
+            key: None,
             include_none,
         }
     }
 
+    /// Set the dictionary used by [CompressionType::ZstdWithDict]
+    pub fn with_dict(mut self, dict: Option<CompressionDict>) -> Self {
+        self.dict = dict;
+        self
+    }
+
+    /// Set the key used to encrypt every block
+    pub fn with_key(mut self, key: Option<EncryptionKey>) -> Self {
+        self.key = key;
+        self
+    }
+
     pub fn add(&mut self, key: Bytes, value: Bytes) {
         if !self.include_none && value.is_empty() {
             return;
@@ -234,12 +277,18 @@ impl SsTableBuilder {
 
     fn add_new_block_inner(&mut self, block: &Block) {
         assert!(self.block_builder.is_empty());
-        let offset = self.data.len();
-        let real_compression_type = block.encode(&mut self.data, self.compression_type);
+        let offset = self.taken_len + self.data.len();
+        let real_compression_type = block.encode(
+            &mut self.data,
+            self.compression_type,
+            self.dict.as_ref(),
+            self.key.as_ref(),
+        );
         let is_large = block.is_large();
         let meta = BlockMeta {
             offset,
             is_large,
+            encrypted: self.key.is_some(),
             compression_type: real_compression_type,
             first_key: std::mem::take(&mut self.first_key),
             last_key: if is_large {
@@ -251,6 +300,35 @@ impl SsTableBuilder {
         self.meta.push(meta);
     }
 
+    /// Take the bytes of the blocks finished since the last call, so the table can be
+    /// written out block by block while it's built.
+    ///
+    /// Nothing is taken before the first block is finished, so an empty table is still
+    /// written as empty bytes. The rest of the table is returned by [SsTableBuilder::finish].
+    pub fn take_finished_bytes(&mut self) -> Vec<u8> {
+        if self.meta.is_empty() {
+            return Vec::new();
+        }
+
+        self.taken_len += self.data.len();
+        std::mem::take(&mut self.data)
+    }
+
+    /// Finish the table and return the bytes not taken by
+    /// [SsTableBuilder::take_finished_bytes], i.e. the last blocks and the block meta.
+    pub fn finish(mut self) -> Vec<u8> {
+        if self.is_empty() {
+            return Vec::new();
+        }
+
+        self.finish_current_block();
+        let mut buf = self.data;
+        let meta_offset = (self.taken_len + buf.len()) as u32;
+        BlockMeta::encode_meta(&self.meta, &mut buf);
+        buf.put_u32_le(meta_offset);
+        buf
+    }
+
     /// ```log
     /// ┌─────────────────────────────────────────────────────────────────────────────────────────────────┐
     /// │ SsTable                                                                                         │
@@ -261,6 +339,7 @@ impl SsTableBuilder {
     /// └─────────────────────────────────────────────────────────────────────────────────────────────────┘
     /// ```
     pub fn build(mut self) -> SsTable {
+        assert_eq!(self.taken_len, 0, "the table is written out already");
         self.finish_current_block();
         let mut buf = self.data;
         let meta_offset = buf.len() as u32;
@@ -289,6 +368,8 @@ impl SsTableBuilder {
             last_key,
             meta: self.meta,
             meta_offset: meta_offset as usize,
+            dict: self.dict,
+            key: self.key,
             block_cache: BlockCache::new(DEFAULT_CACHE_SIZE),
         }
     }
@@ -304,6 +385,8 @@ pub struct SsTable {
     pub(crate) last_key: Bytes,
     meta: Vec<BlockMeta>,
     meta_offset: usize,
+    dict: Option<CompressionDict>,
+    key: Option<EncryptionKey>,
     block_cache: BlockCache,
 }
 
@@ -315,6 +398,8 @@ impl Clone for SsTable {
             last_key: self.last_key.clone(),
             meta: self.meta.clone(),
             meta_offset: self.meta_offset,
+            dict: self.dict.clone(),
+            key: self.key.clone(),
             block_cache: BlockCache::new(DEFAULT_CACHE_SIZE),
         }
     }
@@ -337,6 +422,26 @@ impl SsTable {
     ///    - "Invalid magic number"
     ///    - "Invalid schema version"
     pub fn import_all(bytes: Bytes) -> LoroResult<Self> {
+        Self::import_all_with(bytes, None, None, None)
+    }
+
+    /// Import the table whose blocks may be compressed with the given dictionary
+    /// or encrypted with the given key.
+    ///
+    /// The blocks compressed with a dictionary and the encrypted blocks are decoded eagerly,
+    /// so a missing or mismatched dictionary or key is reported here instead of on reading.
+    /// If `max_decompressed_len` is given, all the blocks are decoded eagerly, and their
+    /// total decompressed length must not exceed it.
+    ///
+    /// # Errors
+    /// - [LoroError::DecryptionError] if the key is missing or wrong
+    /// - [LoroError::LimitExceeded] if the blocks decompress to more than `max_decompressed_len` bytes
+    pub fn import_all_with(
+        bytes: Bytes,
+        dict: Option<CompressionDict>,
+        key: Option<EncryptionKey>,
+        max_decompressed_len: Option<usize>,
+    ) -> LoroResult<Self> {
         // magic number + schema version + meta offset
         if bytes.len() < SIZE_OF_U32 + SIZE_OF_U8 + SIZE_OF_U32 {
             return Err(LoroError::DecodeError("Invalid sstable bytes".into()));
@@ -366,6 +471,9 @@ impl SsTable {
         }
         let raw_meta = &bytes[meta_offset..data_len - SIZE_OF_U32];
         let meta = BlockMeta::decode_meta(raw_meta)?;
+        if !BlockMeta::is_sorted(&meta) {
+            return Err(LoroError::DecodeError("Invalid bytes".into()));
+        }
         Self::check_block_checksum(&meta, &bytes, meta_offset)?;
         let first_key = meta
             .first()
@@ -385,11 +493,118 @@ impl SsTable {
             last_key,
             meta,
             meta_offset,
+            dict,
+            key,
             block_cache: BlockCache::new(DEFAULT_CACHE_SIZE),
         };
+        ans.check_blocks(max_decompressed_len)?;
         Ok(ans)
     }
 
+    fn check_blocks(&self, max_decompressed_len: Option<usize>) -> LoroResult<()> {
+        let mut decompressed_len = 0;
+        for (idx, meta) in self.meta.iter().enumerate() {
+            if max_decompressed_len.is_none()
+                && !meta.encrypted
+                && !matches!(meta.compression_type, CompressionType::ZstdWithDict { .. })
+            {
+                continue;
+            }
+
+            let raw = self.raw_block(idx);
+            if meta.encrypted && self.key.is_none() {
+                // Report the id of the missing key
+                decrypt(&raw[..raw.len() - SIZE_OF_U32], None)?;
+            }
+
+            // Each block may only use what the previous blocks left of the limit
+            let max_len = max_decompressed_len.map_or(usize::MAX, |max| max - decompressed_len);
+            let block = Block::try_decode(
+                raw,
+                meta.is_large,
+                meta.first_key.clone(),
+                meta.compression_type,
+                self.dict.as_ref(),
+                self.block_key(idx),
+                max_len,
+            )
+            .map_err(|e| match e {
+                LoroError::LimitExceeded { limit, max } => LoroError::LimitExceeded {
+                    limit,
+                    max: max_decompressed_len.unwrap_or(max),
+                },
+                e => e,
+            })?;
+            decompressed_len += block.decompressed_len();
+            self.block_cache.insert(idx, Arc::new(block));
+        }
+        Ok(())
+    }
+
+    /// Decode all the blocks that are not decoded yet, so that malformed blocks are
+    /// reported as errors here instead of panicking when they are read.
+    pub fn check_all_blocks(&self) -> LoroResult<()> {
+        for (idx, meta) in self.meta.iter().enumerate() {
+            if self.block_cache.get(&idx).is_some() {
+                continue;
+            }
+
+            let block = Block::try_decode(
+                self.raw_block(idx),
+                meta.is_large,
+                meta.first_key.clone(),
+                meta.compression_type,
+                self.dict.as_ref(),
+                self.block_key(idx),
+                usize::MAX,
+            )?;
+            self.block_cache.insert(idx, Arc::new(block));
+        }
+        Ok(())
+    }
+
+    /// Whether the blocks are compressed with `compression_type` and `dict`, or not compressed,
+    /// and they are encrypted with `key` if and only if it's given.
+    ///
+    /// The bytes of such a table can be exported as they are.
+    pub(crate) fn is_encoded_with(
+        &self,
+        compression_type: CompressionType,
+        dict: Option<&CompressionDict>,
+        key: Option<&EncryptionKey>,
+    ) -> bool {
+        self.dict.as_ref() == dict
+            && self.key.as_ref() == key
+            && self.meta.iter().all(|meta| {
+                meta.encrypted == key.is_some()
+                    && (meta.compression_type.is_none()
+                        || u8::from(meta.compression_type) == u8::from(compression_type))
+            })
+    }
+
+    /// Encode all the blocks again with the given compression.
+    ///
+    /// Block boundaries are kept, and the empty values are kept as they are.
+    /// The blocks are encrypted if `key` is given.
+    pub fn recompress(
+        &self,
+        compression_type: CompressionType,
+        dict: Option<CompressionDict>,
+        key: Option<EncryptionKey>,
+    ) -> SsTable {
+        let mut builder =
+            SsTableBuilder::new(MemKvStore::DEFAULT_BLOCK_SIZE, compression_type, true)
+                .with_dict(dict)
+                .with_key(key);
+        for idx in 0..self.meta.len() {
+            let block = self.read_block_cached(idx);
+            builder.first_key = block.first_key();
+            builder.last_key = block.last_key();
+            builder.add_new_block_inner(&block);
+        }
+        builder.build()
+    }
+
     fn check_block_checksum(
         meta: &[BlockMeta],
         bytes: &Bytes,
@@ -398,7 +613,8 @@ impl SsTable {
         for i in 0..meta.len() {
             let offset = meta[i].offset;
             let offset_end = meta.get(i + 1).map_or(meta_offset, |m| m.offset);
-            if offset_end > bytes.len() {
+            if offset_end > bytes.len() || offset > offset_end || offset_end - offset < SIZE_OF_U32
+            {
                 return Err(LoroError::DecodeError("Invalid bytes".into()));
             }
             let raw_block_and_check = bytes.slice(offset..offset_end);
@@ -429,18 +645,28 @@ impl SsTable {
             .min(self.meta.len() - 1)
     }
 
-    fn read_block(&self, block_idx: usize) -> Arc<Block> {
+    fn raw_block(&self, block_idx: usize) -> Bytes {
         let offset = self.meta[block_idx].offset;
         let offset_end = self
             .meta
             .get(block_idx + 1)
             .map_or(self.meta_offset, |m| m.offset);
-        let raw_block_and_check = self.data.slice(offset..offset_end);
+        self.data.slice(offset..offset_end)
+    }
+
+    /// The key to decrypt the block, `None` if the block is not encrypted
+    fn block_key(&self, block_idx: usize) -> Option<&EncryptionKey> {
+        self.key.as_ref().filter(|_| self.meta[block_idx].encrypted)
+    }
+
+    fn read_block(&self, block_idx: usize) -> Arc<Block> {
         Arc::new(Block::decode(
-            raw_block_and_check,
+            self.raw_block(block_idx),
             self.meta[block_idx].is_large,
             self.meta[block_idx].first_key.clone(),
             self.meta[block_idx].compression_type,
+            self.dict.as_ref(),
+            self.block_key(block_idx),
         ))
     }
 
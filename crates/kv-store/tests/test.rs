@@ -104,6 +104,27 @@ fn large_value() {
     assert_eq!(iter.next(), None);
 }
 
+#[test]
+fn limit_decompressed_len() {
+    let mut store = MemKvStore::new(MemKvConfig::new());
+    store.set(b"a", Bytes::from_static(b"1"));
+    store.set(b"large", Bytes::from(vec![0; 1_000_000]));
+    let bytes = store.export_all();
+    assert!(bytes.len() < 100_000);
+
+    let mut new_store = MemKvStore::new(MemKvConfig::new());
+    new_store.set_max_decompressed_len(Some(100_000));
+    assert!(matches!(
+        new_store.try_import_all(bytes.clone()),
+        Err(loro_common::LoroError::LimitExceeded { max: 100_000, .. })
+    ));
+    assert!(new_store.is_empty());
+
+    new_store.set_max_decompressed_len(Some(1_100_000));
+    new_store.import_all(bytes).unwrap();
+    assert_eq!(new_store.get(b"large").unwrap().len(), 1_000_000);
+}
+
 #[test]
 fn export_import_edit_export() {
     let mut store = MemKvStore::new(MemKvConfig::default());
@@ -130,3 +151,218 @@ fn export_import_edit_export() {
     assert_eq!(new_new_store.get(b"b99"), Some(Bytes::from_static(b"2")));
     assert_eq!(new_new_store.get(b"a"), Some(Bytes::from_static(b"2")));
 }
+
+#[test]
+fn export_to_writes_the_exported_bytes() {
+    let mut store = MemKvStore::new(MemKvConfig::default());
+    let mut buf = Vec::new();
+    store.export_to(&mut buf).unwrap();
+    assert!(buf.is_empty());
+
+    for i in 0..3000 {
+        let s = format!("b{}", i);
+        store.set(s.as_bytes(), Bytes::from(s.clone()));
+    }
+
+    // Only the mem table
+    let mut buf = Vec::new();
+    store.export_to(&mut buf).unwrap();
+    let bytes = store.export_all();
+    assert_eq!(buf, bytes);
+
+    // The mem table overlaps with the table
+    let mut new_store = MemKvStore::new(MemKvConfig::default());
+    new_store.import_all(bytes).unwrap();
+    new_store.set(b"a", Bytes::from_static(b"1"));
+    new_store.set(b"b1001", Bytes::from_static(b"2"));
+    new_store.set(b"c", Bytes::from_static(b"3"));
+    let mut buf = Vec::new();
+    new_store.export_to(&mut buf).unwrap();
+    assert_eq!(buf, new_store.export_all());
+
+    // Several tables
+    new_store.import_all(store.export_all()).unwrap();
+    new_store.set(b"d", Bytes::from_static(b"4"));
+    let mut buf = Vec::new();
+    new_store.export_to(&mut buf).unwrap();
+    assert_eq!(buf, new_store.export_all());
+}
+
+#[cfg(feature = "zstd")]
+mod zstd {
+    use bytes::Bytes;
+    use loro_kv_store::{
+        compress::{CompressionDict, CompressionType},
+        mem_store::MemKvConfig,
+        sstable::SsTable,
+        MemKvStore,
+    };
+
+    fn sample(i: usize) -> Bytes {
+        format!(
+            r#"{{"id":{},"name":"user-{}","email":"user-{}@example.com","active":true}}"#,
+            i, i, i
+        )
+        .into()
+    }
+
+    fn train_dict() -> CompressionDict {
+        let samples: Vec<Bytes> = (0..2000).map(sample).collect();
+        CompressionDict::train(&samples, 4096).unwrap()
+    }
+
+    fn export_with(config: MemKvConfig) -> Bytes {
+        let mut store = config.block_size(256).build();
+        for i in 0..200 {
+            store.set(format!("key{:04}", i).as_bytes(), sample(i));
+        }
+        store.export_all()
+    }
+
+    #[test]
+    fn zstd_export_import() {
+        let bytes =
+            export_with(MemKvConfig::new().compression_type(CompressionType::Zstd { level: 19 }));
+        let mut store = MemKvStore::new(MemKvConfig::new());
+        store.import_all(bytes).unwrap();
+        assert_eq!(store.get(b"key0000"), Some(sample(0)));
+        assert_eq!(store.get(b"key0199"), Some(sample(199)));
+        assert_eq!(store.len(), 200);
+    }
+
+    #[test]
+    fn zstd_with_dict_requires_the_dict() {
+        let dict = train_dict();
+        let with_dict = export_with(
+            MemKvConfig::new()
+                .compression_type(CompressionType::ZstdWithDict { level: 3 })
+                .compression_dict(dict.clone()),
+        );
+        let without_dict =
+            export_with(MemKvConfig::new().compression_type(CompressionType::Zstd { level: 3 }));
+        assert!(with_dict.len() < without_dict.len());
+
+        assert!(MemKvStore::new(MemKvConfig::new())
+            .import_all(with_dict.clone())
+            .is_err());
+        let other_dict = CompressionDict::train(
+            &(0..2000)
+                .map(|i| Bytes::from(format!("other sample {}", i * 7)))
+                .collect::<Vec<_>>(),
+            4096,
+        )
+        .unwrap();
+        assert!(SsTable::import_all_with(with_dict.clone(), Some(other_dict), None, None).is_err());
+
+        let mut store = MemKvStore::new(MemKvConfig::new().compression_dict(dict));
+        store.import_all(with_dict).unwrap();
+        assert_eq!(store.get(b"key0100"), Some(sample(100)));
+        store.set(b"key0100", sample(1000));
+        let bytes = store.export_all();
+        assert!(MemKvStore::new(MemKvConfig::new())
+            .import_all(bytes)
+            .is_ok());
+    }
+
+    #[test]
+    fn recompress_table() {
+        let dict = train_dict();
+        let bytes = export_with(MemKvConfig::new());
+        let table = SsTable::import_all(bytes).unwrap();
+        let recompressed = table
+            .recompress(
+                CompressionType::ZstdWithDict { level: 3 },
+                Some(dict.clone()),
+                None,
+            )
+            .export_all();
+        assert!(SsTable::import_all(recompressed.clone()).is_err());
+        let table = SsTable::import_all_with(recompressed, Some(dict), None, None).unwrap();
+        let back = table
+            .recompress(CompressionType::LZ4, None, None)
+            .export_all();
+        let mut store = MemKvStore::new(MemKvConfig::new());
+        store.import_all(back).unwrap();
+        assert_eq!(store.get(b"key0042"), Some(sample(42)));
+        assert_eq!(store.len(), 200);
+    }
+}
+
+#[cfg(feature = "encryption")]
+mod encryption {
+    use bytes::Bytes;
+    use loro_common::LoroError;
+    use loro_kv_store::{
+        compress::CompressionType, encrypt::EncryptionKey, mem_store::MemKvConfig,
+        sstable::SsTable, MemKvStore,
+    };
+
+    fn export_with(config: MemKvConfig) -> Bytes {
+        let mut store = config.block_size(256).build();
+        for i in 0..200 {
+            store.set(
+                format!("key{:04}", i).as_bytes(),
+                format!("secret value {}", i).into(),
+            );
+        }
+        store.set(b"large", vec![7; 1024].into());
+        store.export_all()
+    }
+
+    #[test]
+    fn encrypted_export_import() {
+        let key = EncryptionKey::new(1, [42; 32]);
+        let bytes = export_with(MemKvConfig::new().encryption_key(key.clone()));
+        assert!(!bytes
+            .windows(b"secret value".len())
+            .any(|w| w == b"secret value"));
+
+        let mut store = MemKvStore::new(MemKvConfig::new().encryption_key(key.clone()));
+        store.import_all(bytes).unwrap();
+        assert_eq!(store.get(b"key0000"), Some(Bytes::from("secret value 0")));
+        assert_eq!(store.get(b"large"), Some(Bytes::from(vec![7; 1024])));
+        assert_eq!(store.len(), 201);
+
+        // Export again with both the encoded blocks and the new values
+        store.set(b"key0100", Bytes::from("new value"));
+        let bytes = store.export_all();
+        let mut store = MemKvStore::new(MemKvConfig::new().encryption_key(key));
+        store.import_all(bytes).unwrap();
+        assert_eq!(store.get(b"key0100"), Some(Bytes::from("new value")));
+    }
+
+    #[test]
+    fn wrong_key_is_a_decryption_error() {
+        let bytes = export_with(
+            MemKvConfig::new()
+                .compression_type(CompressionType::None)
+                .encryption_key(EncryptionKey::new(1, [42; 32])),
+        );
+        for key in [
+            None,
+            Some(EncryptionKey::new(2, [42; 32])),
+            Some(EncryptionKey::new(1, [43; 32])),
+        ] {
+            assert!(matches!(
+                SsTable::import_all_with(bytes.clone(), None, key, None),
+                Err(LoroError::DecryptionError(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn recompress_encrypted_table() {
+        let key = EncryptionKey::new(1, [42; 32]);
+        let table = SsTable::import_all(export_with(MemKvConfig::new())).unwrap();
+        let encrypted = table
+            .recompress(CompressionType::LZ4, None, Some(key.clone()))
+            .export_all();
+        let table = SsTable::import_all_with(encrypted, None, Some(key), None).unwrap();
+        let plain = table
+            .recompress(CompressionType::LZ4, None, None)
+            .export_all();
+        let mut store = MemKvStore::new(MemKvConfig::new());
+        store.import_all(plain).unwrap();
+        assert_eq!(store.get(b"key0042"), Some(Bytes::from("secret value 42")));
+    }
+}
@@ -0,0 +1,224 @@
+//! The commands of `loro-inspect`, a tool to inspect the blobs exported by Loro.
+//!
+//! Every command works on the bytes of a local file. Nothing is sent anywhere.
+use std::{
+    cmp::Reverse,
+    fmt::Write as _,
+    io::Write,
+    panic::{catch_unwind, AssertUnwindSafe},
+};
+
+use anyhow::{anyhow, Context};
+use loro::{ChangeMeta, ExportMode, Frontiers, LoroDoc, ToJson, VersionVector, ID};
+
+/// The kind of the blob written by the `export` command
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ExportKind {
+    /// A shallow snapshot at the version, or at the latest version
+    Shallow,
+    /// A snapshot of the state at the version without the history
+    StateOnly,
+    /// The updates since the version, or all the updates
+    Updates,
+}
+
+/// Import the snapshot or updates into a new doc
+pub fn load_doc(bytes: &[u8]) -> anyhow::Result<LoroDoc> {
+    let doc = LoroDoc::new();
+    let status = doc.import(bytes).context("failed to import the blob")?;
+    if let Some(pending) = status.pending {
+        let peers = pending
+            .iter()
+            .map(|(peer, _)| peer.to_string())
+            .collect::<Vec<_>>();
+        eprintln!(
+            "warning: some changes of peers [{}] miss their dependencies and are not imported",
+            peers.join(", ")
+        );
+    }
+    Ok(doc)
+}
+
+/// Parse a version such as `3@1,5@2`, where each item is `counter@peer`
+pub fn parse_frontiers(s: &str) -> anyhow::Result<Frontiers> {
+    let mut frontiers = Frontiers::default();
+    for id in s.split(',').map(str::trim).filter(|x| !x.is_empty()) {
+        let id =
+            ID::try_from(id).map_err(|_| anyhow!("invalid ID {:?}, expected counter@peer", id))?;
+        frontiers.push(id);
+    }
+    Ok(frontiers)
+}
+
+/// Print the metadata of the blob without importing it
+pub fn meta(bytes: &[u8]) -> anyhow::Result<String> {
+    let meta = LoroDoc::decode_import_blob_meta(bytes, true)?;
+    let mut out = String::new();
+    writeln!(out, "mode: {}", meta.mode)?;
+    writeln!(out, "changes: {}", meta.change_num)?;
+    writeln!(out, "start timestamp: {}", meta.start_timestamp)?;
+    writeln!(out, "end timestamp: {}", meta.end_timestamp)?;
+    writeln!(
+        out,
+        "start frontiers: {}",
+        fmt_frontiers(&meta.start_frontiers)
+    )?;
+    writeln!(
+        out,
+        "partial start version: {}",
+        fmt_vv(&meta.partial_start_vv)
+    )?;
+    writeln!(out, "partial end version: {}", fmt_vv(&meta.partial_end_vv))?;
+    Ok(out)
+}
+
+/// List all the changes in the doc, ordered by lamport
+pub fn log(doc: &LoroDoc) -> String {
+    let mut out = String::new();
+    for change in changes(doc) {
+        write!(
+            out,
+            "{} lamport={} timestamp={} len={} deps={}",
+            change.id,
+            change.lamport,
+            change.timestamp,
+            change.len,
+            fmt_frontiers(&change.deps)
+        )
+        .unwrap();
+        if let Some(msg) = &change.message {
+            write!(out, " message={:?}", msg).unwrap();
+        }
+        out.push('\n');
+    }
+    out
+}
+
+fn changes(doc: &LoroDoc) -> Vec<ChangeMeta> {
+    let shallow_since = doc.shallow_since_vv();
+    let mut ans = Vec::new();
+    for (&peer, &end) in doc.oplog_vv().iter() {
+        let mut counter = shallow_since.get(&peer).copied().unwrap_or(0);
+        while counter < end {
+            let Some(change) = doc.get_change(ID::new(peer, counter)) else {
+                break;
+            };
+            counter = change.id.counter + change.len as i32;
+            ans.push(change);
+        }
+    }
+    ans.sort_by_key(|c| (c.lamport, c.id.peer));
+    ans
+}
+
+/// Dump the state as JSON, at the given version or at the latest version
+pub fn state(doc: &LoroDoc, version: Option<&Frontiers>) -> anyhow::Result<String> {
+    if let Some(version) = version {
+        doc.checkout(version)?;
+    }
+    Ok(doc.get_deep_value().to_json_pretty())
+}
+
+/// Print the analysis of the containers, the largest first
+pub fn analyze(doc: &LoroDoc) -> String {
+    let analysis = doc.analyze();
+    let mut out = String::new();
+    writeln!(
+        out,
+        "containers: {}, dropped: {}, tiny: {}, large: {}",
+        analysis.containers.len(),
+        analysis.dropped_len(),
+        analysis.tiny_container_len(),
+        analysis.large_container_len()
+    )
+    .unwrap();
+    let mut containers = analysis.containers.iter().collect::<Vec<_>>();
+    containers.sort_by_key(|(id, info)| (Reverse(info.size), id.to_string()));
+    for (id, info) in containers {
+        writeln!(
+            out,
+            "{} size={} ops={} depth={} dropped={} last_edit_time={}",
+            id, info.size, info.ops_num, info.depth, info.dropped, info.last_edit_time
+        )
+        .unwrap();
+    }
+    out
+}
+
+/// Print the causal graph as a mermaid flowchart, or the heads of the graph
+pub fn dag(doc: &LoroDoc, mermaid: bool) -> String {
+    if mermaid {
+        return doc.dag_mermaid();
+    }
+
+    let mut out = String::new();
+    writeln!(out, "frontiers: {}", fmt_frontiers(&doc.oplog_frontiers())).unwrap();
+    writeln!(out, "version: {}", fmt_vv(&doc.oplog_vv())).unwrap();
+    if doc.is_shallow() {
+        writeln!(
+            out,
+            "shallow since: {}",
+            fmt_frontiers(&doc.shallow_since_frontiers())
+        )
+        .unwrap();
+    }
+    out
+}
+
+/// Export the doc in the given kind into `w`
+pub fn export<W: Write>(
+    doc: &LoroDoc,
+    kind: ExportKind,
+    version: Option<&Frontiers>,
+    w: W,
+) -> anyhow::Result<()> {
+    let latest = doc.oplog_frontiers();
+    let mode = match kind {
+        ExportKind::Shallow => ExportMode::shallow_snapshot(version.unwrap_or(&latest)),
+        ExportKind::StateOnly => ExportMode::state_only(version),
+        ExportKind::Updates => match version {
+            Some(version) => {
+                let vv = doc
+                    .frontiers_to_vv(version)
+                    .ok_or_else(|| anyhow!("the version is not in the doc"))?;
+                ExportMode::updates_owned(vv)
+            }
+            None => ExportMode::all_updates(),
+        },
+    };
+    doc.export_to_writer(mode, w)?;
+    Ok(())
+}
+
+/// Check that the state of the doc matches its history.
+///
+/// The check panics on a failure, which may be a mismatch or a part of the doc that can't
+/// be decoded, so the message of the panic is returned as the error.
+pub fn verify(doc: &LoroDoc) -> anyhow::Result<()> {
+    catch_unwind(AssertUnwindSafe(|| doc.check_state_correctness_slow())).map_err(|payload| {
+        let msg = payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown error".to_string());
+        anyhow!("failed to check the state against the history: {}", msg)
+    })
+}
+
+fn fmt_frontiers(f: &Frontiers) -> String {
+    let ids = f.iter().map(|id| id.to_string()).collect::<Vec<_>>();
+    format!("[{}]", ids.join(", "))
+}
+
+fn fmt_vv(vv: &VersionVector) -> String {
+    let mut items = vv
+        .iter()
+        .map(|(&peer, &counter)| (peer, counter))
+        .collect::<Vec<_>>();
+    items.sort_unstable();
+    let items = items
+        .into_iter()
+        .map(|(peer, counter)| format!("{}: {}", peer, counter))
+        .collect::<Vec<_>>();
+    format!("{{{}}}", items.join(", "))
+}
@@ -0,0 +1,109 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use clap::{Parser, Subcommand};
+use loro_cli::ExportKind;
+
+/// Inspect the snapshots and updates exported by Loro
+#[derive(Parser)]
+#[command(name = "loro-inspect", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print the metadata of the blob without importing it
+    Meta { file: PathBuf },
+    /// List the changes with their peer, counter, lamport, timestamp and message
+    Log { file: PathBuf },
+    /// Print the state as JSON
+    State {
+        file: PathBuf,
+        /// The version to check out, e.g. `3@1,5@2`
+        #[arg(long)]
+        version: Option<String>,
+    },
+    /// Print the size and the ops of every container
+    Analyze { file: PathBuf },
+    /// Print the causal graph of the changes
+    Dag {
+        file: PathBuf,
+        /// Print a mermaid flowchart instead of the heads of the graph
+        #[arg(long)]
+        mermaid: bool,
+    },
+    /// Export the doc in another mode
+    Export {
+        file: PathBuf,
+        #[arg(long, value_enum)]
+        mode: ExportKind,
+        /// The version of the shallow root, of the state, or to export the updates since,
+        /// e.g. `3@1,5@2`
+        #[arg(long)]
+        version: Option<String>,
+        /// Write to this file instead of the stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Check that the state matches the history
+    Verify { file: PathBuf },
+}
+
+fn read(path: &Path) -> anyhow::Result<Vec<u8>> {
+    std::fs::read(path).with_context(|| format!("failed to read {}", path.display()))
+}
+
+fn load(path: &Path) -> anyhow::Result<loro::LoroDoc> {
+    loro_cli::load_doc(&read(path)?)
+}
+
+fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+    match cli.command {
+        Command::Meta { file } => print!("{}", loro_cli::meta(&read(&file)?)?),
+        Command::Log { file } => print!("{}", loro_cli::log(&load(&file)?)),
+        Command::State { file, version } => {
+            let version = version
+                .as_deref()
+                .map(loro_cli::parse_frontiers)
+                .transpose()?;
+            println!("{}", loro_cli::state(&load(&file)?, version.as_ref())?);
+        }
+        Command::Analyze { file } => print!("{}", loro_cli::analyze(&load(&file)?)),
+        Command::Dag { file, mermaid } => print!("{}", loro_cli::dag(&load(&file)?, mermaid)),
+        Command::Export {
+            file,
+            mode,
+            version,
+            output,
+        } => {
+            let doc = load(&file)?;
+            let version = version
+                .as_deref()
+                .map(loro_cli::parse_frontiers)
+                .transpose()?;
+            let mut w: Box<dyn Write> = match &output {
+                Some(path) => {
+                    Box::new(BufWriter::new(File::create(path).with_context(|| {
+                        format!("failed to create {}", path.display())
+                    })?))
+                }
+                None => Box::new(io::stdout().lock()),
+            };
+            loro_cli::export(&doc, mode, version.as_ref(), &mut w)?;
+            w.flush()?;
+        }
+        Command::Verify { file } => {
+            loro_cli::verify(&load(&file)?)?;
+            println!("ok");
+        }
+    }
+
+    Ok(())
+}
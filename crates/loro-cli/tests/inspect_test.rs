@@ -0,0 +1,88 @@
+use loro::{ExportMode, LoroDoc, ID};
+use loro_cli::ExportKind;
+
+fn new_doc() -> anyhow::Result<LoroDoc> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1)?;
+    doc.set_change_merge_interval(0);
+    doc.get_text("text").insert(0, "Hello")?;
+    doc.commit();
+    doc.get_map("map").insert("key", "value")?;
+    doc.commit_with(loro::CommitOptions::new().commit_msg("set key"));
+    let other = LoroDoc::new();
+    other.set_peer_id(2)?;
+    other.import(&doc.export(ExportMode::all_updates())?)?;
+    other.get_text("text").insert(5, " world")?;
+    other.commit();
+    doc.import(&other.export(ExportMode::all_updates())?)?;
+    Ok(doc)
+}
+
+#[test]
+fn meta_and_log() -> anyhow::Result<()> {
+    let doc = new_doc()?;
+    let snapshot = doc.export(ExportMode::Snapshot)?;
+    let meta = loro_cli::meta(&snapshot)?;
+    assert!(meta.contains("mode: snapshot"));
+    assert!(meta.contains("changes: 3"));
+    assert!(meta.contains("partial end version: {1: 6, 2: 6}"));
+
+    let loaded = loro_cli::load_doc(&snapshot)?;
+    let log = loro_cli::log(&loaded);
+    let lines = log.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("0@1 lamport=0"));
+    assert!(lines[1].starts_with("5@1 lamport=5"));
+    assert!(lines[1].ends_with("message=\"set key\""));
+    assert!(lines[2].starts_with("0@2 lamport=6"));
+    assert!(lines[2].contains("deps=[5@1]"));
+    Ok(())
+}
+
+#[test]
+fn state_at_version() -> anyhow::Result<()> {
+    let doc = loro_cli::load_doc(&new_doc()?.export(ExportMode::Snapshot)?)?;
+    let state = loro_cli::state(&doc, None)?;
+    assert!(state.contains("Hello world"));
+
+    let doc = loro_cli::load_doc(&new_doc()?.export(ExportMode::Snapshot)?)?;
+    let version = loro_cli::parse_frontiers("4@1")?;
+    let state = loro_cli::state(&doc, Some(&version))?;
+    assert!(state.contains("\"Hello\""));
+    assert!(!state.contains("key"));
+    assert!(loro_cli::parse_frontiers("1@x").is_err());
+    Ok(())
+}
+
+#[test]
+fn analyze_dag_and_verify() -> anyhow::Result<()> {
+    let doc = loro_cli::load_doc(&new_doc()?.export(ExportMode::Snapshot)?)?;
+    assert!(loro_cli::analyze(&doc).starts_with("containers: 2,"));
+    assert!(loro_cli::dag(&doc, true).starts_with("flowchart"));
+    assert!(loro_cli::dag(&doc, false).contains("frontiers: [5@2]"));
+    loro_cli::verify(&doc)?;
+    Ok(())
+}
+
+#[test]
+fn export_modes() -> anyhow::Result<()> {
+    let doc = loro_cli::load_doc(&new_doc()?.export(ExportMode::Snapshot)?)?;
+    let version = loro_cli::parse_frontiers("5@1")?;
+    for kind in [ExportKind::Shallow, ExportKind::StateOnly] {
+        let mut buf = Vec::new();
+        loro_cli::export(&doc, kind, Some(&version), &mut buf)?;
+        let new_doc = loro_cli::load_doc(&buf)?;
+        assert!(new_doc.is_shallow());
+        if kind == ExportKind::StateOnly {
+            assert_eq!(new_doc.oplog_frontiers(), version);
+        } else {
+            assert_eq!(new_doc.oplog_frontiers(), ID::new(2, 5).into());
+        }
+    }
+
+    let mut buf = Vec::new();
+    loro_cli::export(&doc, ExportKind::Updates, Some(&version), &mut buf)?;
+    let meta = LoroDoc::decode_import_blob_meta(&buf, true)?;
+    assert_eq!(meta.change_num, 1);
+    Ok(())
+}
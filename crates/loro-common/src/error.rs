@@ -20,6 +20,8 @@ pub enum LoroError {
     DecodeDataCorruptionError,
     #[error("Decode error: Checksum mismatch. The data is corrupted.")]
     DecodeChecksumMismatchError,
+    #[error("Decryption error: ({0})")]
+    DecryptionError(Box<str>),
     #[error("Decode error: Encoding version \"{0}\" is incompatible. Loro's encoding is backward compatible but not forward compatible. Please upgrade the version of Loro to support this version of the exported data.")]
     IncompatibleFutureEncodingError(usize),
     #[error("Js error ({0})")]
@@ -94,6 +96,18 @@ pub enum LoroError {
     ContainerDeleted { container: Box<ContainerID> },
     #[error("You cannot set the `PeerID` with `PeerID::MAX`, which is an internal specific value")]
     InvalidPeerID,
+    #[error("The ref ({0}) already exists")]
+    RefAlreadyExists(Box<str>),
+    #[error("Cannot find the ref ({0})")]
+    RefNotFound(Box<str>),
+    #[error("IO error ({0})")]
+    IoError(Box<str>),
+    #[error("The signature of change {0} is missing or invalid")]
+    InvalidChangeSignature(ID),
+    #[error("The shallow snapshot cannot be verified, because the history before its shallow root is missing")]
+    UnverifiableShallowSnapshot,
+    #[error("The imported data exceeds the limit of {limit} ({max})")]
+    LimitExceeded { limit: &'static str, max: usize },
 }
 
 #[derive(Error, Debug, PartialEq)]
@@ -123,6 +137,14 @@ pub enum LoroEncodeError {
     ShallowSnapshotIncompatibleWithOldFormat,
     #[error("Cannot export shallow snapshot with unknown container type. Please upgrade the Loro version.")]
     UnknownContainer,
+    #[error("Cannot export containers from a shallow doc, the history before the shallow root is missing.")]
+    ContainersExportOnShallowDoc,
+    #[error("The target encoding version cannot represent {0}.")]
+    IncompatibleEncodingVersion(String),
+    #[error("IO error: {0}")]
+    IoError(String),
+    #[error("Serialization error: {0}")]
+    SerializeError(String),
 }
 
 #[cfg(feature = "wasm")]
@@ -154,6 +176,18 @@ pub mod wasm {
     }
 }
 
+impl From<std::io::Error> for LoroError {
+    fn from(e: std::io::Error) -> Self {
+        LoroError::IoError(e.to_string().into_boxed_str())
+    }
+}
+
+impl From<std::io::Error> for LoroEncodeError {
+    fn from(e: std::io::Error) -> Self {
+        LoroEncodeError::IoError(e.to_string())
+    }
+}
+
 impl From<ColumnarError> for LoroError {
     fn from(e: ColumnarError) -> Self {
         match e {
@@ -223,30 +223,40 @@ impl ContainerID {
     }
 
     pub fn from_bytes(bytes: &[u8]) -> Self {
-        let first_byte = bytes[0];
-        let container_type = ContainerType::try_from_u8(first_byte & 0b01111111).unwrap();
-        let is_root = (first_byte & 0b10000000) != 0;
+        Self::try_from_bytes(bytes).unwrap()
+    }
 
-        let mut reader = &bytes[1..];
+    /// Decode the bytes encoded by [ContainerID::encode].
+    ///
+    /// # Errors
+    /// - [LoroError::DecodeError] if the bytes are malformed
+    pub fn try_from_bytes(bytes: &[u8]) -> LoroResult<Self> {
+        let invalid = || LoroError::DecodeError("Invalid container id bytes".into());
+        let (&first_byte, mut reader) = bytes.split_first().ok_or_else(invalid)?;
+        let container_type = ContainerType::try_from_u8(first_byte & 0b01111111)?;
+        let is_root = (first_byte & 0b10000000) != 0;
         match is_root {
             true => {
-                let name_len = leb128::read::unsigned(&mut reader).unwrap();
-                let name = InternalString::from(
-                    std::str::from_utf8(&reader[..name_len as usize]).unwrap(),
-                );
-                Self::Root {
-                    name,
+                let name_len = leb128::read::unsigned(&mut reader).map_err(|_| invalid())?;
+                let name = reader.get(..name_len as usize).ok_or_else(invalid)?;
+                let name = std::str::from_utf8(name).map_err(|_| invalid())?;
+                Ok(Self::Root {
+                    name: InternalString::from(name),
                     container_type,
-                }
+                })
             }
             false => {
+                if reader.len() < 12 {
+                    return Err(invalid());
+                }
+
                 let peer = PeerID::from_le_bytes(reader[..8].try_into().unwrap());
                 let counter = i32::from_le_bytes(reader[8..12].try_into().unwrap());
-                Self::Normal {
+                Ok(Self::Normal {
                     peer,
                     counter,
                     container_type,
-                }
+                })
             }
         }
     }
@@ -748,6 +748,7 @@ pub struct ContainerPath {
 pub struct ImportStatus {
     pub success: HashMap<u64, CounterSpan>,
     pub pending: Option<HashMap<u64, CounterSpan>>,
+    pub quarantined: Option<HashMap<u64, CounterSpan>>,
 }
 
 impl From<loro::ImportStatus> for ImportStatus {
@@ -756,6 +757,7 @@ impl From<loro::ImportStatus> for ImportStatus {
         Self {
             success: vr_to_map(a),
             pending: value.pending.as_ref().map(vr_to_map),
+            quarantined: value.quarantined.as_ref().map(vr_to_map),
         }
     }
 }
@@ -89,7 +89,7 @@ impl<'a> ArenaGuards<'a> {
         match parent {
             Some(p) => {
                 if let Some(d) = get_depth(p, &mut self.depth, &self.parents) {
-                    self.depth[child.to_index() as usize] = NonZeroU16::new(d.get() + 1);
+                    self.depth[child.to_index() as usize] = d.checked_add(1);
                 } else {
                     self.depth[child.to_index() as usize] = None;
                 }
@@ -127,9 +127,9 @@ impl SharedArena {
         }
     }
 
-    pub(crate) fn with_guards(&self, f: impl FnOnce(&mut ArenaGuards)) {
+    pub(crate) fn with_guards<R>(&self, f: impl FnOnce(&mut ArenaGuards) -> R) -> R {
         let mut guards = self.get_arena_guards();
-        f(&mut guards);
+        f(&mut guards)
     }
 
     fn get_arena_guards(&self) -> ArenaGuards {
@@ -236,7 +236,7 @@ impl SharedArena {
         match parent {
             Some(p) => {
                 if let Some(d) = get_depth(p, &mut depth, parents) {
-                    depth[child.to_index() as usize] = NonZeroU16::new(d.get() + 1);
+                    depth[child.to_index() as usize] = d.checked_add(1);
                 } else {
                     depth[child.to_index() as usize] = None;
                 }
@@ -601,22 +601,36 @@ fn get_depth(
     depth: &mut Vec<Option<NonZeroU16>>,
     parents: &FxHashMap<ContainerIdx, Option<ContainerIdx>>,
 ) -> Option<NonZeroU16> {
-    let mut d = depth[target.to_index() as usize];
-    if d.is_some() {
-        return d;
-    }
-
-    let parent = parents.get(&target)?;
-    match parent {
-        Some(p) => {
-            d = NonZeroU16::new(get_depth(*p, depth, parents)?.get() + 1);
-            depth[target.to_index() as usize] = d;
+    // Walk up to the first ancestor with a known depth. The walk is bounded,
+    // so a cycle of parents from malformed data ends it instead of recursing forever.
+    let mut path = Vec::new();
+    let mut current = target;
+    let mut d = loop {
+        if let Some(d) = depth[current.to_index() as usize] {
+            break d;
         }
-        None => {
-            depth[target.to_index() as usize] = NonZeroU16::new(1);
-            d = NonZeroU16::new(1);
+
+        match parents.get(&current)? {
+            Some(p) => {
+                if path.len() >= depth.len() {
+                    return None;
+                }
+
+                path.push(current);
+                current = *p;
+            }
+            None => {
+                let d = NonZeroU16::MIN;
+                depth[current.to_index() as usize] = Some(d);
+                break d;
+            }
         }
+    };
+
+    for idx in path.into_iter().rev() {
+        d = d.checked_add(1)?;
+        depth[idx.to_index() as usize] = Some(d);
     }
 
-    d
+    Some(d)
 }
@@ -35,6 +35,8 @@ pub struct Change<O = Op> {
     /// It is the number of seconds that have elapsed since 00:00:00 UTC on 1 January 1970.
     pub(crate) timestamp: Timestamp,
     pub(crate) commit_msg: Option<Arc<str>>,
+    /// The signature created by the [crate::signature::ChangeSigner] when it's committed
+    pub(crate) signature: Option<Arc<[u8]>>,
     pub(crate) ops: RleVec<[O; 1]>,
 }
 
@@ -53,6 +55,7 @@ impl<O> Change<O> {
             lamport,
             timestamp,
             commit_msg: None,
+            signature: None,
         }
     }
 
@@ -98,6 +101,10 @@ impl<O> Change<O> {
     pub fn message(&self) -> Option<&Arc<str>> {
         self.commit_msg.as_ref()
     }
+
+    pub fn signature(&self) -> Option<&[u8]> {
+        self.signature.as_deref()
+    }
 }
 
 impl<O: EstimatedSize> EstimatedSize for Change<O> {
@@ -108,12 +115,13 @@ impl<O: EstimatedSize> EstimatedSize for Change<O> {
         let lamport_size = 1;
         let timestamp_size = 1;
         let deps_size = (self.deps.len().max(1) - 1) * 4;
+        let signature_size = self.signature.as_ref().map_or(0, |s| s.len());
         let ops_size = self
             .ops
             .iter()
             .map(|op| op.estimate_storage_size())
             .sum::<usize>();
-        id_size + lamport_size + timestamp_size + ops_size + deps_size
+        id_size + lamport_size + timestamp_size + ops_size + deps_size + signature_size
     }
 }
 
@@ -232,6 +240,12 @@ impl<O: Mergable + HasLength + HasIndex + Sliceable + HasCounter + Debug> Slicea
             lamport: self.lamport + from as Lamport,
             timestamp: self.timestamp,
             commit_msg: self.commit_msg.clone(),
+            // The signature only covers the whole change
+            signature: if from == 0 && to == self.atom_len() {
+                self.signature.clone()
+            } else {
+                None
+            },
         }
     }
 }
@@ -250,6 +264,8 @@ impl Change {
             && other.deps.as_single().unwrap().peer == self.id.peer
             && other.timestamp - self.timestamp < merge_interval
             && self.commit_msg == other.commit_msg
+            && self.signature.is_none()
+            && other.signature.is_none()
         {
             debug_assert!(other.timestamp >= self.timestamp);
             debug_assert!(other.lamport == self.lamport + self.len() as Lamport);
@@ -5,6 +5,7 @@ use rle::HasLength;
 
 use crate::{
     change::{Change, Lamport, Timestamp},
+    encoding::json_schema::json::JsonOp,
     id::ID,
     version::Frontiers,
 };
@@ -91,3 +92,13 @@ impl ChangeMeta {
         }
     }
 }
+
+/// A [ChangeMeta] with the ops inside the change.
+///
+/// The ops are in the form of the JSON schema, but the peer ids in them are not compressed.
+/// The id of an op is `ID::new(meta.id.peer, op.counter)`.
+#[derive(Debug, Clone)]
+pub struct ChangeWithOps {
+    pub meta: ChangeMeta,
+    pub ops: Vec<JsonOp>,
+}
@@ -1,4 +1,8 @@
 pub use crate::container::richtext::config::{StyleConfig, StyleConfigMap};
+use crate::encoding::ImportOptions;
+use crate::import_filter::ImportFilter;
+use crate::kv_store::{CompressionDict, CompressionType, EncryptionKey, KvStore};
+use crate::signature::{ChangeSigner, ChangeVerifier};
 use crate::LoroDoc;
 
 #[derive(Clone, Debug)]
@@ -7,6 +11,12 @@ pub struct Configure {
     record_timestamp: Arc<AtomicBool>,
     pub(crate) merge_interval: Arc<AtomicI64>,
     pub(crate) editable_detached_mode: Arc<AtomicBool>,
+    snapshot_compression: Arc<RwLock<(CompressionType, Option<CompressionDict>)>>,
+    encryption_key: Arc<RwLock<Option<EncryptionKey>>>,
+    change_signer: Arc<RwLock<Option<ChangeSigner>>>,
+    change_verifier: Arc<RwLock<Option<ChangeVerifier>>>,
+    import_filter: Arc<RwLock<Option<ImportFilter>>>,
+    import_options: Arc<RwLock<Option<ImportOptions>>>,
 }
 
 impl LoroDoc {
@@ -15,6 +25,19 @@ impl LoroDoc {
         self.set_record_timestamp(config.record_timestamp());
         self.set_change_merge_interval(config.merge_interval());
         self.set_detached_editing(config.detached_editing());
+        self.set_codec_config(config);
+        self.config.set_change_signer(config.change_signer());
+        self.config.set_change_verifier(config.change_verifier());
+        self.config.set_import_filter(config.import_filter());
+        self.config.set_import_options(config.import_options());
+    }
+
+    /// Use the snapshot compression and the encryption key of `config`, so that the stores
+    /// encoded with them can be decoded by this doc
+    pub(crate) fn set_codec_config(&self, config: &Configure) {
+        let (compression_type, dict) = config.snapshot_compression();
+        self.config.set_snapshot_compression(compression_type, dict);
+        self.config.set_encryption_key(config.encryption_key());
     }
 }
 
@@ -25,6 +48,12 @@ impl Default for Configure {
             record_timestamp: Arc::new(AtomicBool::new(false)),
             editable_detached_mode: Arc::new(AtomicBool::new(false)),
             merge_interval: Arc::new(AtomicI64::new(1000 * 1000)),
+            snapshot_compression: Arc::new(RwLock::new((CompressionType::LZ4, None))),
+            encryption_key: Arc::new(RwLock::new(None)),
+            change_signer: Arc::new(RwLock::new(None)),
+            change_verifier: Arc::new(RwLock::new(None)),
+            import_filter: Arc::new(RwLock::new(None)),
+            import_options: Arc::new(RwLock::new(None)),
         }
     }
 }
@@ -47,6 +76,12 @@ impl Configure {
                 self.editable_detached_mode
                     .load(std::sync::atomic::Ordering::Relaxed),
             )),
+            snapshot_compression: Arc::new(RwLock::new(self.snapshot_compression())),
+            encryption_key: Arc::new(RwLock::new(self.encryption_key())),
+            change_signer: Arc::new(RwLock::new(self.change_signer())),
+            change_verifier: Arc::new(RwLock::new(self.change_verifier())),
+            import_filter: Arc::new(RwLock::new(self.import_filter())),
+            import_options: Arc::new(RwLock::new(self.import_options())),
         }
     }
 
@@ -83,6 +118,74 @@ impl Configure {
         self.merge_interval
             .store(interval, std::sync::atomic::Ordering::Relaxed);
     }
+
+    /// The compression of the stores in the exported snapshots, and the dictionary used by
+    /// [CompressionType::ZstdWithDict] on both exporting and importing.
+    pub fn snapshot_compression(&self) -> (CompressionType, Option<CompressionDict>) {
+        self.snapshot_compression.read().unwrap().clone()
+    }
+
+    pub fn set_snapshot_compression(
+        &self,
+        compression_type: CompressionType,
+        dict: Option<CompressionDict>,
+    ) {
+        *self.snapshot_compression.write().unwrap() = (compression_type, dict);
+    }
+
+    /// The key to encrypt the stores in the exported snapshots and to decrypt the imported ones.
+    pub fn encryption_key(&self) -> Option<EncryptionKey> {
+        self.encryption_key.read().unwrap().clone()
+    }
+
+    pub fn set_encryption_key(&self, key: Option<EncryptionKey>) {
+        *self.encryption_key.write().unwrap() = key;
+    }
+
+    /// Make `kv` encode its blocks with the snapshot compression and the encryption key,
+    /// and decode the imported blocks with the dictionary and the key, within the
+    /// [ImportOptions::max_decompressed_bytes] limit
+    pub(crate) fn apply_codec(&self, kv: &mut dyn KvStore) {
+        let (compression_type, dict) = self.snapshot_compression();
+        kv.set_codec(compression_type, dict, self.encryption_key());
+        kv.set_max_decompressed_len(self.import_options().and_then(|x| x.max_decompressed_bytes));
+    }
+
+    /// The signer of the local changes
+    pub fn change_signer(&self) -> Option<ChangeSigner> {
+        self.change_signer.read().unwrap().clone()
+    }
+
+    pub fn set_change_signer(&self, signer: Option<ChangeSigner>) {
+        *self.change_signer.write().unwrap() = signer;
+    }
+
+    /// The verifier of the imported changes
+    pub fn change_verifier(&self) -> Option<ChangeVerifier> {
+        self.change_verifier.read().unwrap().clone()
+    }
+
+    pub fn set_change_verifier(&self, verifier: Option<ChangeVerifier>) {
+        *self.change_verifier.write().unwrap() = verifier;
+    }
+
+    /// The filter of the imported changes
+    pub fn import_filter(&self) -> Option<ImportFilter> {
+        self.import_filter.read().unwrap().clone()
+    }
+
+    pub fn set_import_filter(&self, filter: Option<ImportFilter>) {
+        *self.import_filter.write().unwrap() = filter;
+    }
+
+    /// The resource limits of the imported data
+    pub fn import_options(&self) -> Option<ImportOptions> {
+        *self.import_options.read().unwrap()
+    }
+
+    pub fn set_import_options(&self, options: Option<ImportOptions>) {
+        *self.import_options.write().unwrap() = options;
+    }
 }
 
 #[derive(Debug)]
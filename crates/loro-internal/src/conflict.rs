@@ -0,0 +1,362 @@
+//! Conflicts between two versions of a document.
+//!
+//! Concurrent edits are always merged automatically by the CRDT rules. But some of them touch
+//! the same thing, so one of the edits is overridden or dropped after merging. This module
+//! finds them so that they can be shown to the user before merging a branch.
+//!
+//! The conflicts are found by comparing the edits made after the common ancestor of the two
+//! versions. The winners are resolved with the same history caches used by the diff calculators.
+use fxhash::{FxHashMap, FxHashSet};
+use loro_common::{
+    ContainerID, ContainerType, CounterSpan, HasIdSpan, IdLp, IdSpan, LoroError, LoroResult,
+    LoroValue, PeerID, TreeID, ID,
+};
+
+use crate::{
+    change::Lamport,
+    container::{idx::ContainerIdx, list::list_op::InnerListOp},
+    dag::Dag,
+    diff_calc::tree::find_cyclic_moves,
+    op::InnerContent,
+    state::TreeParentId,
+    version::Frontiers,
+    InternalString, LoroDoc, OpLog, VersionVector,
+};
+
+/// A conflict between two versions that is resolved by the CRDT rules.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Conflict {
+    /// The same key of the map was set concurrently.
+    ///
+    /// The value with the greater (lamport, peer) wins.
+    MapKey {
+        container: ContainerID,
+        key: InternalString,
+        winner: ConflictValue,
+        loser: ConflictValue,
+    },
+    /// The same element of the movable list was set concurrently.
+    ///
+    /// The value with the greater (lamport, peer) wins.
+    MovableListSet {
+        container: ContainerID,
+        /// The id of the element
+        elem: IdLp,
+        winner: ConflictValue,
+        loser: ConflictValue,
+    },
+    /// The same element of the movable list was moved concurrently.
+    ///
+    /// The element stays at the position of the move with the greater (lamport, peer).
+    MovableListMove {
+        container: ContainerID,
+        /// The id of the element
+        elem: IdLp,
+        /// The id of the move op that wins
+        winner: IdLp,
+        /// The id of the move op that loses
+        loser: IdLp,
+    },
+    /// The move of the tree node was dropped after merging, because it would create a cycle
+    /// with a concurrent move.
+    TreeCycle {
+        container: ContainerID,
+        target: TreeID,
+        /// The id of the dropped move op
+        dropped: ID,
+        /// The parent that the dropped move op wanted to move the node to
+        parent: TreeParentId,
+    },
+    /// The same span of the text was deleted concurrently.
+    TextDelete {
+        container: ContainerID,
+        /// The ids of the deleted characters
+        span: IdSpan,
+    },
+}
+
+/// One of the concurrent writes in a [Conflict].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConflictValue {
+    /// The id of the op
+    pub id: IdLp,
+    /// The value written by the op. `None` means the key was deleted.
+    pub value: Option<LoroValue>,
+}
+
+/// The edits made on one side since the common ancestor that may conflict with the other side.
+#[derive(Default)]
+struct ConcurrentEdits {
+    map_keys: FxHashSet<(ContainerIdx, InternalString)>,
+    movable_list_sets: FxHashSet<(ContainerIdx, IdLp)>,
+    movable_list_moves: FxHashSet<(ContainerIdx, IdLp)>,
+    text_deletions: FxHashMap<(ContainerIdx, PeerID), Vec<CounterSpan>>,
+    trees: FxHashSet<ContainerIdx>,
+}
+
+impl ConcurrentEdits {
+    fn collect(oplog: &OpLog, spans: impl Iterator<Item = IdSpan>) -> Self {
+        let mut ans = Self::default();
+        for span in spans {
+            for op in oplog.iter_ops(span) {
+                let idx = op.container();
+                match &op.raw_op().content {
+                    InnerContent::Map(map) => {
+                        ans.map_keys.insert((idx, map.key.clone()));
+                    }
+                    InnerContent::List(InnerListOp::Set { elem_id, .. }) => {
+                        ans.movable_list_sets.insert((idx, *elem_id));
+                    }
+                    InnerContent::List(InnerListOp::Move { elem_id, .. }) => {
+                        ans.movable_list_moves.insert((idx, *elem_id));
+                    }
+                    InnerContent::List(InnerListOp::Delete(_))
+                        if idx.get_type() == ContainerType::Text =>
+                    {
+                        // The op may be sliced by the span
+                        if let InnerContent::List(InnerListOp::Delete(del)) = &op.op().content {
+                            let span = del.id_span();
+                            ans.text_deletions
+                                .entry((idx, span.peer))
+                                .or_default()
+                                .push(span.counter);
+                        }
+                    }
+                    InnerContent::Tree(_) => {
+                        ans.trees.insert(idx);
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        ans
+    }
+}
+
+fn sorted_intersection<T: Clone + Eq + std::hash::Hash + Ord>(
+    a: &FxHashSet<T>,
+    b: &FxHashSet<T>,
+) -> Vec<T> {
+    let mut ans: Vec<T> = a.intersection(b).cloned().collect();
+    ans.sort();
+    ans
+}
+
+impl LoroDoc {
+    /// Get the conflicts that are resolved by the CRDT rules when version `a` and `b` are merged.
+    ///
+    /// Only the edits made after the common ancestor of `a` and `b` are compared. It reports
+    ///
+    /// - concurrent sets on the same key of a map
+    /// - concurrent sets or moves on the same element of a movable list
+    /// - concurrent tree moves where one of them is dropped because it would create a cycle
+    /// - overlapping concurrent deletions in a text
+    pub fn conflicts(&self, a: &Frontiers, b: &Frontiers) -> LoroResult<Vec<Conflict>> {
+        self.commit_then_renew();
+        let oplog = self.oplog.try_lock().unwrap();
+        for id in a.iter().chain(b.iter()) {
+            if !oplog.dag.contains(id) {
+                return Err(LoroError::FrontiersNotFound(id));
+            }
+        }
+
+        let vv_a = oplog.dag.frontiers_to_vv(a).unwrap();
+        let vv_b = oplog.dag.frontiers_to_vv(b).unwrap();
+        let mut merged = vv_a.clone();
+        merged.merge(&vv_b);
+        let edits_a = ConcurrentEdits::collect(&oplog, vv_a.sub_iter(&vv_b));
+        let edits_b = ConcurrentEdits::collect(&oplog, vv_b.sub_iter(&vv_a));
+
+        let mut ans = Vec::new();
+        collect_register_conflicts(&oplog, &vv_a, &vv_b, &edits_a, &edits_b, &mut ans);
+        collect_tree_conflicts(&oplog, &vv_a, &vv_b, &merged, &edits_a, &edits_b, &mut ans);
+        collect_text_conflicts(&oplog, &edits_a, &edits_b, &mut ans);
+        Ok(ans)
+    }
+}
+
+/// Find the concurrent writes to the same map key or movable list element
+fn collect_register_conflicts(
+    oplog: &OpLog,
+    vv_a: &VersionVector,
+    vv_b: &VersionVector,
+    edits_a: &ConcurrentEdits,
+    edits_b: &ConcurrentEdits,
+    ans: &mut Vec<Conflict>,
+) {
+    let map_keys = sorted_intersection(&edits_a.map_keys, &edits_b.map_keys);
+    let sets = sorted_intersection(&edits_a.movable_list_sets, &edits_b.movable_list_sets);
+    let moves = sorted_intersection(&edits_a.movable_list_moves, &edits_b.movable_list_moves);
+    if map_keys.is_empty() && sets.is_empty() && moves.is_empty() {
+        return;
+    }
+
+    oplog.with_history_cache(|h| {
+        let index = h.get_checkout_index();
+        let mut last_container = None;
+        let mut map_a = FxHashMap::default();
+        let mut map_b = FxHashMap::default();
+        for (idx, key) in map_keys {
+            if last_container != Some(idx) {
+                last_container = Some(idx);
+                map_a = index
+                    .map
+                    .get_container_latest_op_at_vv(idx, vv_a, Lamport::MAX, oplog);
+                map_b = index
+                    .map
+                    .get_container_latest_op_at_vv(idx, vv_b, Lamport::MAX, oplog);
+            }
+
+            let (Some(a), Some(b)) = (map_a.get(&key), map_b.get(&key)) else {
+                continue;
+            };
+            let (winner, loser) = if a > b { (a, b) } else { (b, a) };
+            ans.push(Conflict::MapKey {
+                container: oplog.arena.idx_to_id(idx).unwrap(),
+                key,
+                winner: ConflictValue {
+                    id: IdLp::new(winner.peer, winner.lamport),
+                    value: winner.value.clone(),
+                },
+                loser: ConflictValue {
+                    id: IdLp::new(loser.peer, loser.lamport),
+                    value: loser.value.clone(),
+                },
+            });
+        }
+
+        for (idx, elem) in sets {
+            let list = &index.movable_list;
+            let (Some(a), Some(b)) = (
+                list.last_value(elem, vv_a, Lamport::MAX, oplog),
+                list.last_value(elem, vv_b, Lamport::MAX, oplog),
+            ) else {
+                continue;
+            };
+            let (winner, loser) = if a > b { (a, b) } else { (b, a) };
+            ans.push(Conflict::MovableListSet {
+                container: oplog.arena.idx_to_id(idx).unwrap(),
+                elem,
+                winner: ConflictValue {
+                    id: IdLp::new(winner.peer, winner.lamport),
+                    value: Some(winner.value),
+                },
+                loser: ConflictValue {
+                    id: IdLp::new(loser.peer, loser.lamport),
+                    value: Some(loser.value),
+                },
+            });
+        }
+
+        for (idx, elem) in moves {
+            let list = &index.movable_list;
+            let (Some(a), Some(b)) = (
+                list.last_pos(elem, vv_a, Lamport::MAX, oplog),
+                list.last_pos(elem, vv_b, Lamport::MAX, oplog),
+            ) else {
+                continue;
+            };
+            let (winner, loser) = if a.idlp() > b.idlp() {
+                (a.idlp(), b.idlp())
+            } else {
+                (b.idlp(), a.idlp())
+            };
+            ans.push(Conflict::MovableListMove {
+                container: oplog.arena.idx_to_id(idx).unwrap(),
+                elem,
+                winner,
+                loser,
+            });
+        }
+    });
+}
+
+/// Find the moves that are applied on their own side but dropped after merging
+fn collect_tree_conflicts(
+    oplog: &OpLog,
+    vv_a: &VersionVector,
+    vv_b: &VersionVector,
+    merged: &VersionVector,
+    edits_a: &ConcurrentEdits,
+    edits_b: &ConcurrentEdits,
+    ans: &mut Vec<Conflict>,
+) {
+    let mut trees: Vec<_> = edits_a.trees.union(&edits_b.trees).copied().collect();
+    trees.sort();
+    for idx in trees {
+        let dropped = find_cyclic_moves(idx, merged, oplog);
+        if dropped.is_empty() {
+            continue;
+        }
+
+        let dropped_ids = |vv: &VersionVector| -> FxHashSet<ID> {
+            find_cyclic_moves(idx, vv, oplog)
+                .into_iter()
+                .map(|x| x.id.id())
+                .collect()
+        };
+        let dropped_in_a = dropped_ids(vv_a);
+        let dropped_in_b = dropped_ids(vv_b);
+        for node in dropped {
+            let id = node.id.id();
+            let applied_on_its_side = match (vv_a.includes_id(id), vv_b.includes_id(id)) {
+                (true, false) => !dropped_in_a.contains(&id),
+                (false, true) => !dropped_in_b.contains(&id),
+                _ => false,
+            };
+            if applied_on_its_side {
+                ans.push(Conflict::TreeCycle {
+                    container: oplog.arena.idx_to_id(idx).unwrap(),
+                    target: node.op.target(),
+                    dropped: id,
+                    parent: node.op.parent_id(),
+                });
+            }
+        }
+    }
+}
+
+/// Find the characters deleted by both sides
+fn collect_text_conflicts(
+    oplog: &OpLog,
+    edits_a: &ConcurrentEdits,
+    edits_b: &ConcurrentEdits,
+    ans: &mut Vec<Conflict>,
+) {
+    let mut keys: Vec<_> = edits_a.text_deletions.keys().copied().collect();
+    keys.sort();
+    for key in keys {
+        let Some(spans_b) = edits_b.text_deletions.get(&key) else {
+            continue;
+        };
+
+        let mut overlaps = Vec::new();
+        for a in edits_a.text_deletions[&key].iter() {
+            for b in spans_b.iter() {
+                let start = a.start.max(b.start);
+                let end = a.end.min(b.end);
+                if start < end {
+                    overlaps.push(CounterSpan::new(start, end));
+                }
+            }
+        }
+
+        overlaps.sort_by_key(|x| x.start);
+        let mut merged: Vec<CounterSpan> = Vec::new();
+        for span in overlaps {
+            match merged.last_mut() {
+                Some(last) if last.end >= span.start => last.end = last.end.max(span.end),
+                _ => merged.push(span),
+            }
+        }
+
+        let (idx, peer) = key;
+        for span in merged {
+            ans.push(Conflict::TextDelete {
+                container: oplog.arena.idx_to_id(idx).unwrap(),
+                span: IdSpan::new(peer, span.start, span.end),
+            });
+        }
+    }
+}
@@ -59,6 +59,26 @@ impl From<Styles> for StyleMeta {
     }
 }
 
+impl From<&FxHashMap<String, LoroValue>> for StyleMeta {
+    /// The styles created from the user provided map don't have the lamport and peer info
+    fn from(map: &FxHashMap<String, LoroValue>) -> Self {
+        let map = map
+            .iter()
+            .map(|(key, value)| {
+                (
+                    key.as_str().into(),
+                    StyleMetaItem {
+                        lamport: 0,
+                        peer: 0,
+                        value: value.clone(),
+                    },
+                )
+            })
+            .collect();
+        Self { map }
+    }
+}
+
 impl Meta for StyleMeta {
     fn is_empty(&self) -> bool {
         self.map.is_empty()
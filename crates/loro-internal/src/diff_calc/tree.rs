@@ -425,6 +425,44 @@ impl TreeDiffCalculator {
     }
 }
 
+/// Replay the ops of the tree included by `vv` in the order of (lamport, peer), and return the
+/// moves that are not applied because they would create cycles.
+///
+/// It uses a fresh [TreeCacheForDiff] so the result doesn't depend on the version the cached
+/// tree was checked out to.
+pub(crate) fn find_cyclic_moves(
+    container: ContainerIdx,
+    vv: &VersionVector,
+    oplog: &OpLog,
+) -> Vec<MoveLamportAndID> {
+    oplog.with_history_cache(|h| {
+        let mark = h.ensure_importing_caches_exist();
+        let Some(group) = h.get_importing_cache(&container, mark) else {
+            return vec![];
+        };
+
+        let mut tree_cache = TreeCacheForDiff::default();
+        let mut ans = vec![];
+        for (idlp, op) in group.as_tree().unwrap().ops().iter() {
+            let id = IdFull::new(idlp.peer, op.counter, idlp.lamport);
+            if !vv.includes_id(id.id()) {
+                continue;
+            }
+
+            let node = MoveLamportAndID {
+                id,
+                op: op.value.clone(),
+                effected: false,
+            };
+            if !tree_cache.apply(node.clone()) {
+                ans.push(node);
+            }
+        }
+
+        ans
+    })
+}
+
 /// All information of an operation for diff calculating of movable tree.
 #[derive(Debug, Clone)]
 pub struct MoveLamportAndID {
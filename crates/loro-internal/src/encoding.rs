@@ -1,24 +1,39 @@
 pub(crate) mod arena;
+mod containers_export;
 pub(crate) mod fast_snapshot;
 pub(crate) mod json_schema;
 mod outdated_encode_reordered;
 mod shallow_snapshot;
+pub(crate) mod stream;
 pub(crate) mod value;
 pub(crate) mod value_register;
+use fast_snapshot::Snapshot;
 pub(crate) use outdated_encode_reordered::{
     decode_op, encode_op, get_op_prop, EncodedDeleteStartId, IterableEncodedDeleteStartId,
 };
 use outdated_encode_reordered::{import_changes_to_oplog, ImportChangesResult};
 pub(crate) use value::OwnedValue;
+use value::ValueLimits;
 
+use crate::arena::SharedArena;
+use crate::change::Change;
+use crate::container::idx::ContainerIdx;
+use crate::kv_store::{CompressionDict, CompressionType, EncryptionKey};
 use crate::op::OpWithId;
+use crate::undo::DiffBatch;
 use crate::version::{Frontiers, VersionRange};
 use crate::LoroDoc;
 use crate::{oplog::OpLog, LoroError, VersionVector};
-use loro_common::{HasIdSpan, IdLpSpan, IdSpan, LoroEncodeError, LoroResult, PeerID, ID};
+use bytes::Bytes;
+use fxhash::{FxHashMap, FxHashSet};
+use loro_common::{
+    ContainerID, Counter, HasIdSpan, IdLpSpan, IdSpan, LoroEncodeError, LoroResult, PeerID, ID,
+};
 use num_traits::{FromPrimitive, ToPrimitive};
 use rle::{HasLength, Sliceable};
 use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::io::Write;
 
 /// The mode of the export.
 ///
@@ -49,6 +64,11 @@ use std::borrow::Cow;
 ///
 /// - Exports the state of the target version.
 /// - Includes a minimal set of operation history.
+///
+/// ### Containers Snapshot
+///
+/// - Contains the history and the state of the target containers and their descendants only.
+/// - The IDs of the ops are rewritten, so it can't be merged with the original document.
 #[non_exhaustive]
 #[derive(Debug, Clone)]
 pub enum ExportMode<'a> {
@@ -69,6 +89,17 @@ pub enum ExportMode<'a> {
     /// The snapshot at the specified frontiers. It contains the full history
     /// till the target frontiers and the state at the target frontiers.
     SnapshotAt { version: Cow<'a, Frontiers> },
+    /// The snapshot of a new document that only contains the ops on the `roots` containers
+    /// and their descendants.
+    ///
+    /// The ops are renumbered, and a dep on an op of the other containers is replaced by the
+    /// latest exported ops in its causal past. A root that is not a root container becomes a
+    /// root container named after the ID of the op that created it, e.g. `3@1`.
+    /// The changes keep their original lamports, so the concurrent edits ordered by lamport
+    /// are resolved the same way as in the original document.
+    ///
+    /// It's not supported on a shallow document.
+    Containers { roots: Vec<ContainerID> },
 }
 
 impl<'a> ExportMode<'a> {
@@ -138,6 +169,14 @@ impl<'a> ExportMode<'a> {
         }
     }
 
+    /// The snapshot of a new document that only contains the ops on the `roots` containers
+    /// and their descendants.
+    pub fn containers(roots: impl IntoIterator<Item = ContainerID>) -> Self {
+        ExportMode::Containers {
+            roots: roots.into_iter().collect(),
+        }
+    }
+
     /// This mode exports the history within the specified version vector.
     pub fn updates_till(vv: &VersionVector) -> ExportMode<'static> {
         let mut spans = Vec::with_capacity(vv.len());
@@ -153,6 +192,27 @@ impl<'a> ExportMode<'a> {
     }
 }
 
+/// The version of the encoding format of the exported blobs, see
+/// [LoroDoc::export_with_version].
+///
+/// The blobs in a newer version can't be imported by the older versions of Loro, which
+/// fail with [LoroError::IncompatibleFutureEncodingError].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum EncodingVersion {
+    /// The outdated reordered encoding, which can be imported by Loro 0.16 and later.
+    ///
+    /// Only [ExportMode::Updates] and [ExportMode::Snapshot] can be exported in it,
+    /// and the changes can't be signed.
+    Outdated,
+    /// The encoding introduced in Loro 1.0.
+    ///
+    /// The stores of the snapshots are compressed with LZ4, and they can't be encrypted.
+    Fast,
+    /// The encoding of the current version.
+    #[default]
+    Latest,
+}
+
 const MAGIC_BYTES: [u8; 4] = *b"loro";
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -163,6 +223,15 @@ pub(crate) enum EncodeMode {
     OutdatedSnapshot = 2,
     FastSnapshot = 3,
     FastUpdates = 4,
+    /// The same layout as [EncodeMode::FastSnapshot], but the blocks of the stores may be
+    /// compressed with zstd, which the readers before it cannot decompress.
+    FastSnapshotZstd = 5,
+    /// The same layout as [EncodeMode::FastSnapshot], but the blocks of the stores are
+    /// encrypted, and may also be compressed with zstd.
+    FastSnapshotEncrypted = 6,
+    /// A snapshot or updates written while they are encoded, whose checksum follows the body,
+    /// see [stream]
+    FastStream = 7,
 }
 
 impl num_traits::FromPrimitive for EncodeMode {
@@ -175,6 +244,11 @@ impl num_traits::FromPrimitive for EncodeMode {
             n if n == EncodeMode::OutdatedSnapshot as i64 => Some(EncodeMode::OutdatedSnapshot),
             n if n == EncodeMode::FastSnapshot as i64 => Some(EncodeMode::FastSnapshot),
             n if n == EncodeMode::FastUpdates as i64 => Some(EncodeMode::FastUpdates),
+            n if n == EncodeMode::FastSnapshotZstd as i64 => Some(EncodeMode::FastSnapshotZstd),
+            n if n == EncodeMode::FastSnapshotEncrypted as i64 => {
+                Some(EncodeMode::FastSnapshotEncrypted)
+            }
+            n if n == EncodeMode::FastStream as i64 => Some(EncodeMode::FastStream),
             _ => None,
         }
     }
@@ -194,6 +268,9 @@ impl num_traits::ToPrimitive for EncodeMode {
             EncodeMode::OutdatedSnapshot => EncodeMode::OutdatedSnapshot as i64,
             EncodeMode::FastSnapshot => EncodeMode::FastSnapshot as i64,
             EncodeMode::FastUpdates => EncodeMode::FastUpdates as i64,
+            EncodeMode::FastSnapshotZstd => EncodeMode::FastSnapshotZstd as i64,
+            EncodeMode::FastSnapshotEncrypted => EncodeMode::FastSnapshotEncrypted as i64,
+            EncodeMode::FastStream => EncodeMode::FastStream as i64,
         })
     }
     #[inline]
@@ -209,9 +286,19 @@ impl EncodeMode {
     }
 
     pub fn is_snapshot(self) -> bool {
+        self == EncodeMode::OutdatedSnapshot || self.is_fast_snapshot()
+    }
+
+    /// Whether it's a snapshot in the layout of [EncodeMode::FastSnapshot].
+    ///
+    /// The stores are decoded with the snapshot compression dictionary and the encryption key
+    /// of the doc.
+    pub fn is_fast_snapshot(self) -> bool {
         matches!(
             self,
-            EncodeMode::OutdatedSnapshot | EncodeMode::FastSnapshot
+            EncodeMode::FastSnapshot
+                | EncodeMode::FastSnapshotZstd
+                | EncodeMode::FastSnapshotEncrypted
         )
     }
 }
@@ -229,6 +316,187 @@ impl TryFrom<[u8; 2]> for EncodeMode {
 pub struct ImportStatus {
     pub success: VersionRange,
     pub pending: Option<VersionRange>,
+    /// The changes rejected by the import filter and their descendants,
+    /// see [LoroDoc::set_import_filter]
+    pub quarantined: Option<VersionRange>,
+}
+
+/// The effect of importing a blob, calculated by [LoroDoc::import_preview] without
+/// changing the doc.
+#[derive(Debug, Clone)]
+pub struct ImportPreview {
+    /// The changes that would be applied and the ones that would stay pending
+    pub status: ImportStatus,
+    /// The containers that would be changed by the applied changes
+    pub changed_containers: FxHashSet<ContainerID>,
+    /// The oplog frontiers after the import
+    pub frontiers: Frontiers,
+    /// The diff from the current oplog version to the version after the import.
+    /// It's only calculated when requested.
+    pub diff: Option<DiffBatch>,
+}
+
+/// The resource limits and the validation of the imported data, see
+/// [LoroDoc::set_import_options].
+///
+/// They are meant for the data from untrusted sources. The limits are checked before the
+/// doc is changed, and an import that exceeds any of them fails with
+/// [LoroError::LimitExceeded]. `None` means unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ImportOptions {
+    /// The max length of the imported blob in bytes
+    pub max_input_bytes: Option<usize>,
+    /// The max total length of the decompressed blocks of each store in a snapshot.
+    ///
+    /// The blocks are decompressed on import, and the decompression stops at this limit,
+    /// so a small blob can't expand to a huge size in memory.
+    pub max_decompressed_bytes: Option<usize>,
+    /// The max number of the new ops in a blob
+    pub max_ops: Option<usize>,
+    /// The max length of a decoded string or binary value
+    pub max_value_len: Option<usize>,
+    /// The max nesting depth of both the containers and the decoded values
+    pub max_depth: Option<usize>,
+    /// The max number of the pending changes in the doc after the import
+    pub max_pending_changes: Option<usize>,
+    /// Decode and check every part of a snapshot on import.
+    ///
+    /// The blocks, the changes and the container states of a snapshot are decoded lazily
+    /// when they are read, so a malformed snapshot with a valid checksum may panic later.
+    /// In strict mode it's rejected with [LoroError::DecodeError] or
+    /// [LoroError::DecodeDataCorruptionError] instead, at the cost of decoding the whole
+    /// history and state on import.
+    pub strict: bool,
+}
+
+impl ImportOptions {
+    pub(crate) fn value_limits(&self) -> ValueLimits {
+        let default = ValueLimits::default();
+        ValueLimits {
+            max_len: self.max_value_len.unwrap_or(default.max_len),
+            max_depth: self.max_depth.unwrap_or(default.max_depth),
+        }
+    }
+
+    pub(crate) fn check_input_len(&self, len: usize) -> LoroResult<()> {
+        check_limit("max_input_bytes", self.max_input_bytes, len)
+    }
+
+    pub(crate) fn check_ops(&self, ops: usize) -> LoroResult<()> {
+        check_limit("max_ops", self.max_ops, ops)
+    }
+
+    /// Check the decoded changes before they are imported into `oplog`
+    pub(crate) fn check_changes(&self, changes: &[Change], oplog: &OpLog) -> LoroResult<()> {
+        if self.max_ops.is_some() {
+            self.check_ops(changes.iter().map(|c| c.atom_len()).sum())?;
+        }
+
+        if let Some(max) = self.max_depth {
+            check_container_depth(changes, &oplog.arena, max)?;
+        }
+
+        if self.max_pending_changes.is_some() {
+            let pending = count_pending_changes(changes, oplog);
+            check_limit("max_pending_changes", self.max_pending_changes, pending)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The limits of the values decoded by `oplog`, set by [ImportOptions]
+pub(crate) fn import_value_limits(oplog: &OpLog) -> ValueLimits {
+    oplog
+        .configure
+        .import_options()
+        .unwrap_or_default()
+        .value_limits()
+}
+
+fn check_limit(limit: &'static str, max: Option<usize>, value: usize) -> LoroResult<()> {
+    match max {
+        Some(max) if value > max => Err(LoroError::LimitExceeded { limit, max }),
+        _ => Ok(()),
+    }
+}
+
+/// Check the depth of the containers created by `changes`. Their parents may be created
+/// by `changes` too, so they are not registered in the arena yet.
+fn check_container_depth(changes: &[Change], arena: &SharedArena, max: usize) -> LoroResult<()> {
+    let mut parents: FxHashMap<ContainerID, ContainerIdx> = FxHashMap::default();
+    for op in changes.iter().flat_map(|c| c.ops.iter()) {
+        op.content.visit_created_children(arena, &mut |child| {
+            parents.insert(child.clone(), op.container);
+        });
+    }
+
+    for parent in parents.values() {
+        let mut depth = 1;
+        let mut current = *parent;
+        loop {
+            if let Some(d) = arena.get_depth(current) {
+                depth += d.get() as usize;
+                break;
+            }
+
+            match arena
+                .get_container_id(current)
+                .and_then(|id| parents.get(&id))
+            {
+                Some(p) => {
+                    depth += 1;
+                    current = *p;
+                }
+                None => break,
+            }
+
+            // It also stops the walk on the cyclic parents in a malicious blob
+            check_limit("max_depth", Some(max), depth)?;
+        }
+
+        check_limit("max_depth", Some(max), depth)?;
+    }
+
+    Ok(())
+}
+
+/// Count the pending changes of `oplog` after importing `changes`, by applying the changes
+/// whose deps are met until nothing can be applied
+fn count_pending_changes(changes: &[Change], oplog: &OpLog) -> usize {
+    fn missing_dep(change: &Change, vv: &VersionVector) -> Option<ID> {
+        let peer = change.id.peer;
+        if change.id.counter > vv.get(&peer).copied().unwrap_or(0) {
+            return Some(ID::new(peer, change.id.counter - 1));
+        }
+
+        change.deps.iter().find(|dep| !vv.includes_id(*dep))
+    }
+
+    let mut vv = oplog.vv().clone();
+    // The changes waiting for an id, indexed by the peer and the counter of the id
+    let mut waiting: FxHashMap<PeerID, BTreeMap<Counter, Vec<&Change>>> = FxHashMap::default();
+    let mut pending = 0;
+    let mut stack: Vec<&Change> = oplog.pending_changes.iter().chain(changes).collect();
+    while let Some(change) = stack.pop() {
+        if let Some(id) = missing_dep(change, &vv) {
+            let by_counter = waiting.entry(id.peer).or_default();
+            by_counter.entry(id.counter).or_default().push(change);
+            pending += 1;
+            continue;
+        }
+
+        vv.extend_to_include_last_id(change.id_last());
+        if let Some(by_counter) = waiting.get_mut(&change.id.peer) {
+            let rest = by_counter.split_off(&change.ctr_end());
+            for (_, ready) in std::mem::replace(by_counter, rest) {
+                pending -= ready.len();
+                stack.extend(ready);
+            }
+        }
+    }
+
+    pending
 }
 
 /// The encoder used to encode the container states.
@@ -314,10 +582,49 @@ pub(crate) fn decode_oplog(
         EncodeMode::OutdatedRle | EncodeMode::OutdatedSnapshot => {
             outdated_encode_reordered::decode_updates(oplog, body)
         }
-        EncodeMode::FastSnapshot => fast_snapshot::decode_oplog(oplog, body),
+        EncodeMode::FastSnapshot
+        | EncodeMode::FastSnapshotZstd
+        | EncodeMode::FastSnapshotEncrypted => fast_snapshot::decode_oplog(oplog, body),
         EncodeMode::FastUpdates => fast_snapshot::decode_updates(oplog, body.to_vec().into()),
-        EncodeMode::Auto => unreachable!(),
+        // The streams are unwrapped by `stream::unwrap_stream` before they are parsed
+        EncodeMode::FastStream | EncodeMode::Auto => unreachable!(),
     }?;
+    import_decoded_changes(oplog, changes)
+}
+
+/// Decode the changes of the updates or the snapshot read from `body` and import them.
+///
+/// The updates are decoded block by block, and only the oplog part of a snapshot is kept
+/// in memory until the checksum is checked.
+pub(crate) fn decode_oplog_from_reader(
+    oplog: &mut OpLog,
+    mode: EncodeMode,
+    mut body: stream::BodyReader,
+) -> Result<ImportStatus, LoroError> {
+    let changes = if mode.is_fast_snapshot() {
+        let oplog_bytes = body.read_part()?;
+        body.skip_part()?;
+        body.skip_part()?;
+        body.finish()?;
+        fast_snapshot::decode_oplog_bytes(oplog, oplog_bytes)?
+    } else {
+        let changes = fast_snapshot::read_updates(oplog, &mut body)?;
+        body.finish()?;
+        changes
+    };
+    import_decoded_changes(oplog, changes)
+}
+
+/// Check the decoded changes and import them into `oplog`
+fn import_decoded_changes(
+    oplog: &mut OpLog,
+    mut changes: Vec<Change>,
+) -> Result<ImportStatus, LoroError> {
+    if let Some(options) = oplog.configure.import_options() {
+        options.check_changes(&changes, oplog)?;
+    }
+    oplog.verify_change_signatures(&changes)?;
+    let quarantined = oplog.quarantine_rejected_changes(&mut changes);
     let ImportChangesResult {
         mut imported,
         latest_ids,
@@ -338,6 +645,7 @@ pub(crate) fn decode_oplog(
     Ok(ImportStatus {
         success: imported,
         pending: (!pending.is_empty()).then_some(pending),
+        quarantined: (!quarantined.is_empty()).then_some(quarantined),
     })
 }
 
@@ -358,12 +666,28 @@ impl ParsedHeaderAndBody<'_> {
                     return Err(LoroError::DecodeChecksumMismatchError);
                 }
             }
-            EncodeMode::FastSnapshot | EncodeMode::FastUpdates => {
+            EncodeMode::FastSnapshot
+            | EncodeMode::FastUpdates
+            | EncodeMode::FastSnapshotZstd
+            | EncodeMode::FastSnapshotEncrypted => {
                 let expected = u32::from_le_bytes(self.checksum[12..16].try_into().unwrap());
                 if xxhash_rust::xxh32::xxh32(self.checksum_body, XXH_SEED) != expected {
                     return Err(LoroError::DecodeChecksumMismatchError);
                 }
             }
+            EncodeMode::FastStream => {
+                // The checksum follows the body
+                let len = self
+                    .checksum_body
+                    .len()
+                    .checked_sub(4)
+                    .ok_or(LoroError::DecodeDataCorruptionError)?;
+                let (checksum_body, checksum) = self.checksum_body.split_at(len);
+                let expected = u32::from_le_bytes(checksum.try_into().unwrap());
+                if xxhash_rust::xxh32::xxh32(checksum_body, XXH_SEED) != expected {
+                    return Err(LoroError::DecodeChecksumMismatchError);
+                }
+            }
             EncodeMode::Auto => unreachable!(),
         }
 
@@ -371,7 +695,7 @@ impl ParsedHeaderAndBody<'_> {
     }
 }
 
-const MIN_HEADER_SIZE: usize = 22;
+pub(crate) const MIN_HEADER_SIZE: usize = 22;
 pub(crate) fn parse_header_and_body(
     bytes: &[u8],
     check_checksum: bool,
@@ -391,6 +715,10 @@ pub(crate) fn parse_header_and_body(
     let checksum_body = reader;
     let (mode_bytes, reader) = reader.split_at(2);
     let mode: EncodeMode = [mode_bytes[0], mode_bytes[1]].try_into()?;
+    if matches!(mode, EncodeMode::Auto) {
+        // It's only used when exporting
+        return Err(LoroError::DecodeError("Invalid encode mode".into()));
+    }
 
     let ans = ParsedHeaderAndBody {
         mode,
@@ -429,11 +757,7 @@ pub(crate) fn export_snapshot(doc: &LoroDoc) -> Vec<u8> {
 }
 
 pub(crate) fn export_fast_snapshot(doc: &LoroDoc) -> Vec<u8> {
-    encode_with(EncodeMode::FastSnapshot, &mut |ans| {
-        fast_snapshot::encode_snapshot(doc, ans);
-        Ok(())
-    })
-    .unwrap()
+    encode_snapshot(doc, fast_snapshot::encode_snapshot_inner(doc))
 }
 
 pub(crate) fn export_snapshot_at(
@@ -441,9 +765,8 @@ pub(crate) fn export_snapshot_at(
     frontiers: &Frontiers,
 ) -> Result<Vec<u8>, LoroEncodeError> {
     check_target_version_reachable(doc, frontiers)?;
-    encode_with(EncodeMode::FastSnapshot, &mut |ans| {
-        shallow_snapshot::encode_snapshot_at(doc, frontiers, ans)
-    })
+    let snapshot = shallow_snapshot::encode_snapshot_at_inner(doc, frontiers)?;
+    Ok(encode_snapshot(doc, snapshot))
 }
 
 pub(crate) fn export_fast_updates(doc: &LoroDoc, vv: &VersionVector) -> Vec<u8> {
@@ -462,15 +785,164 @@ pub(crate) fn export_fast_updates_in_range(oplog: &OpLog, spans: &[IdSpan]) -> V
     .unwrap()
 }
 
+/// Export all the updates with the ops in `range` redacted, see [LoroDoc::redact]
+pub(crate) fn export_redacted_updates(
+    doc: &LoroDoc,
+    range: &VersionRange,
+) -> Result<Vec<u8>, LoroError> {
+    let blocks = doc
+        .oplog()
+        .try_lock()
+        .unwrap()
+        .export_redacted_block_bytes(range)?;
+    Ok(encode_chunks(
+        EncodeMode::FastUpdates,
+        &block_chunks(blocks),
+    ))
+}
+
+/// Merge the updates blobs into one, which contains the union of their changes.
+///
+/// The change blocks are decoded and re-encoded directly, without creating a doc.
+/// The overlapping changes are deduplicated. Only the blobs exported in the
+/// [ExportMode::Updates] or [ExportMode::UpdatesInRange] format are supported.
+pub fn merge_updates(blobs: &[&[u8]]) -> LoroResult<Vec<u8>> {
+    let mut bodies = Vec::with_capacity(blobs.len());
+    for blob in blobs {
+        let blob = stream::unwrap_stream(blob)?;
+        let parsed = parse_header_and_body(&blob, true)?;
+        if parsed.mode != EncodeMode::FastUpdates {
+            return Err(LoroError::DecodeError(
+                format!("Cannot merge a blob in the {:?} mode", parsed.mode).into_boxed_str(),
+            ));
+        }
+
+        bodies.push(Bytes::copy_from_slice(parsed.body));
+    }
+
+    let blocks = fast_snapshot::merge_updates_blocks(bodies)?;
+    Ok(encode_chunks(
+        EncodeMode::FastUpdates,
+        &block_chunks(blocks),
+    ))
+}
+
+/// Export the pending changes of the doc as an updates blob.
+///
+/// The pending changes of a peer may not be continuous, so they are encoded in runs
+/// like [merge_updates] does.
+pub(crate) fn export_pending_changes(doc: &LoroDoc) -> Option<Vec<u8>> {
+    let oplog = doc.oplog.try_lock().unwrap();
+    if oplog.pending_changes.is_empty() {
+        return None;
+    }
+
+    let blocks =
+        fast_snapshot::encode_changes_in_runs(oplog.pending_changes.iter().cloned(), &doc.arena);
+    Some(encode_chunks(
+        EncodeMode::FastUpdates,
+        &block_chunks(blocks),
+    ))
+}
+
+/// Export the updates or the snapshot in [EncodingVersion::Outdated].
+///
+/// # Errors
+/// - [LoroEncodeError::IncompatibleEncodingVersion] if it's another export mode,
+///   if any of the exported changes is signed, or if it's a snapshot of a doc with an
+///   encryption key, because the outdated snapshots would expose the state unencrypted
+/// - [LoroEncodeError::ShallowSnapshotIncompatibleWithOldFormat] if the history
+///   before the shallow root is required
+pub(crate) fn export_outdated(doc: &LoroDoc, mode: ExportMode) -> Result<Vec<u8>, LoroEncodeError> {
+    let (from, is_snapshot) = match mode {
+        ExportMode::Updates { from } => (from.into_owned(), false),
+        ExportMode::Snapshot => (VersionVector::new(), true),
+        _ => {
+            return Err(LoroEncodeError::IncompatibleEncodingVersion(
+                "the export modes other than the updates and the snapshot".into(),
+            ))
+        }
+    };
+
+    if is_snapshot && doc.config.encryption_key().is_some() {
+        return Err(LoroEncodeError::IncompatibleEncodingVersion(
+            "the encrypted snapshots".into(),
+        ));
+    }
+
+    {
+        let oplog = doc.oplog.try_lock().unwrap();
+        if !from.includes_vv(&oplog.shallow_since_vv().to_vv()) {
+            return Err(LoroEncodeError::ShallowSnapshotIncompatibleWithOldFormat);
+        }
+
+        if oplog
+            .iter_changes_peer_by_peer(&from, oplog.vv())
+            .any(|c| c.signature.is_some())
+        {
+            return Err(LoroEncodeError::IncompatibleEncodingVersion(
+                "the signed changes".into(),
+            ));
+        }
+
+        if !is_snapshot {
+            return Ok(oplog.export_from(&from));
+        }
+    }
+
+    // The outdated snapshot encodes the state of the latest version
+    let was_detached = doc.is_detached();
+    let old_state_frontiers = doc.state_frontiers();
+    if was_detached {
+        doc.checkout_without_emitting(&doc.oplog_frontiers(), false)
+            .unwrap();
+    }
+
+    let ans = export_snapshot(doc);
+    if was_detached {
+        doc.checkout_without_emitting(&old_state_frontiers, false)
+            .unwrap();
+        doc.drop_pending_events();
+    }
+
+    Ok(ans)
+}
+
+/// Convert a blob exported in [EncodingVersion::Latest] into [EncodingVersion::Fast].
+///
+/// The stores of the zstd compressed snapshots are compressed with LZ4 instead. The
+/// encrypted snapshots are rejected with [LoroEncodeError::IncompatibleEncodingVersion],
+/// because decrypting them would expose the stores.
+pub(crate) fn downgrade_to_fast(doc: &LoroDoc, blob: Vec<u8>) -> Result<Vec<u8>, LoroEncodeError> {
+    let to_encode_error = |e: LoroError| LoroEncodeError::SerializeError(e.to_string());
+    let parsed = parse_header_and_body(&blob, false).map_err(to_encode_error)?;
+    match parsed.mode {
+        EncodeMode::FastSnapshotEncrypted => Err(LoroEncodeError::IncompatibleEncodingVersion(
+            "the encrypted snapshots".into(),
+        )),
+        EncodeMode::FastSnapshotZstd => {
+            let (_, dict) = doc.config.snapshot_compression();
+            decompress_snapshot(parsed.body, dict.as_ref(), None).map_err(to_encode_error)
+        }
+        _ => Ok(blob),
+    }
+}
+
 pub(crate) fn export_shallow_snapshot(
     doc: &LoroDoc,
     f: &Frontiers,
 ) -> Result<Vec<u8>, LoroEncodeError> {
     check_target_version_reachable(doc, f)?;
-    encode_with(EncodeMode::FastSnapshot, &mut |ans| {
-        shallow_snapshot::export_shallow_snapshot(doc, f, ans)?;
-        Ok(())
-    })
+    let (snapshot, _) = shallow_snapshot::export_shallow_snapshot_inner(doc, f)?;
+    Ok(encode_snapshot(doc, snapshot))
+}
+
+pub(crate) fn export_containers(
+    doc: &LoroDoc,
+    roots: &[ContainerID],
+) -> Result<Vec<u8>, LoroEncodeError> {
+    let snapshot = containers_export::export_containers_inner(doc, roots)?;
+    Ok(encode_snapshot(doc, snapshot))
 }
 
 fn check_target_version_reachable(doc: &LoroDoc, f: &Frontiers) -> Result<(), LoroEncodeError> {
@@ -487,10 +959,59 @@ pub(crate) fn export_state_only_snapshot(
     f: &Frontiers,
 ) -> Result<Vec<u8>, LoroEncodeError> {
     check_target_version_reachable(doc, f)?;
-    encode_with(EncodeMode::FastSnapshot, &mut |ans| {
-        shallow_snapshot::export_state_only_snapshot(doc, f, ans)?;
+    let (snapshot, _) = shallow_snapshot::export_state_only_snapshot_inner(doc, f)?;
+    Ok(encode_snapshot(doc, snapshot))
+}
+
+/// The encode mode of the snapshots exported by the doc.
+///
+/// The stores are compressed as configured by [LoroDoc::set_snapshot_compression] and encrypted
+/// if [LoroDoc::set_encryption_key] is set when they are exported, so the mode tells the readers
+/// which codecs they need.
+fn snapshot_mode(doc: &LoroDoc) -> EncodeMode {
+    let (compression_type, _) = doc.config().snapshot_compression();
+    if doc.config().encryption_key().is_some() {
+        EncodeMode::FastSnapshotEncrypted
+    } else if compression_type.is_zstd() {
+        EncodeMode::FastSnapshotZstd
+    } else {
+        EncodeMode::FastSnapshot
+    }
+}
+
+fn encode_snapshot(doc: &LoroDoc, snapshot: Snapshot) -> Vec<u8> {
+    encode_chunks(snapshot_mode(doc), &snapshot.into_chunks())
+}
+
+fn encode_chunks(mode: EncodeMode, chunks: &[Bytes]) -> Vec<u8> {
+    encode_with(mode, &mut |ans| {
+        for chunk in chunks {
+            ans.extend_from_slice(chunk);
+        }
         Ok(())
     })
+    .unwrap()
+}
+
+/// Convert the body of a [EncodeMode::FastSnapshotZstd] or [EncodeMode::FastSnapshotEncrypted]
+/// blob into a [EncodeMode::FastSnapshot] blob, whose stores are compressed with LZ4 and not encrypted.
+///
+/// It's only used to export the snapshots for the older readers, the docs import these blobs
+/// directly.
+///
+/// # Errors
+/// - [LoroError::DecryptionError] if the key is missing or wrong
+pub(crate) fn decompress_snapshot(
+    body: &[u8],
+    dict: Option<&CompressionDict>,
+    key: Option<&EncryptionKey>,
+) -> Result<Vec<u8>, LoroError> {
+    let snapshot = fast_snapshot::_decode_snapshot_bytes(Bytes::copy_from_slice(body))?;
+    let snapshot = snapshot.recompress(CompressionType::LZ4, dict, None, key, None)?;
+    Ok(encode_chunks(
+        EncodeMode::FastSnapshot,
+        &snapshot.into_chunks(),
+    ))
 }
 
 fn encode_with(
@@ -514,6 +1035,97 @@ fn encode_with(
     Ok(ans)
 }
 
+/// Export the doc to `w` as a [EncodeMode::FastStream] blob.
+///
+/// The change blocks of the updates and the blocks of the stores of a snapshot are written
+/// as soon as they are encoded. The other snapshots are encoded from the temporary stores
+/// built for them, which are written after they are built.
+pub(crate) fn export_to_writer(
+    doc: &LoroDoc,
+    mode: ExportMode,
+    w: &mut dyn Write,
+) -> Result<(), LoroEncodeError> {
+    let snapshot = match mode {
+        ExportMode::Snapshot => {
+            return write_stream(w, snapshot_mode(doc), |w| {
+                fast_snapshot::encode_snapshot_to(doc, w)
+            });
+        }
+        ExportMode::Updates { from } => {
+            let oplog = doc.oplog().try_lock().unwrap();
+            return write_stream(w, EncodeMode::FastUpdates, |w| {
+                oplog.for_each_block_from(&from, &mut |block| w.write_block(&block))?;
+                w.end_part()
+            });
+        }
+        ExportMode::UpdatesInRange { spans } => {
+            let oplog = doc.oplog().try_lock().unwrap();
+            return write_stream(w, EncodeMode::FastUpdates, |w| {
+                oplog.for_each_block_in_range(&spans, &mut |block| w.write_block(&block))?;
+                w.end_part()
+            });
+        }
+        ExportMode::ShallowSnapshot(f) => {
+            check_target_version_reachable(doc, &f)?;
+            shallow_snapshot::export_shallow_snapshot_inner(doc, &f)?.0
+        }
+        ExportMode::StateOnly(f) => {
+            let f = f.map_or_else(|| doc.oplog_frontiers(), Cow::into_owned);
+            check_target_version_reachable(doc, &f)?;
+            shallow_snapshot::export_state_only_snapshot_inner(doc, &f)?.0
+        }
+        ExportMode::SnapshotAt { version } => {
+            check_target_version_reachable(doc, &version)?;
+            shallow_snapshot::encode_snapshot_at_inner(doc, &version)?
+        }
+        ExportMode::Containers { roots } => {
+            containers_export::export_containers_inner(doc, &roots)?
+        }
+    };
+
+    write_stream(w, snapshot_mode(doc), |w| w.write_snapshot(snapshot))
+}
+
+fn write_stream(
+    w: &mut dyn Write,
+    mode: EncodeMode,
+    f: impl FnOnce(&mut stream::StreamWriter<'_>) -> std::io::Result<()>,
+) -> Result<(), LoroEncodeError> {
+    let mut w = stream::StreamWriter::new(w, mode)?;
+    f(&mut w)?;
+    w.finish()?;
+    Ok(())
+}
+
+/// Prefix each encoded change block with its length, as in the body of [EncodeMode::FastUpdates]
+fn block_chunks(blocks: Vec<Bytes>) -> Vec<Bytes> {
+    let mut ans = Vec::with_capacity(blocks.len() * 2);
+    for block in blocks {
+        let mut len = Vec::new();
+        leb128::write::unsigned(&mut len, block.len() as u64).unwrap();
+        ans.push(len.into());
+        ans.push(block);
+    }
+    ans
+}
+
+/// Decode the snapshot read from `body` into the doc.
+///
+/// The doc should be empty, see [LoroDoc::can_reset_with_snapshot].
+pub(crate) fn decode_snapshot_from_reader(
+    doc: &LoroDoc,
+    mut body: stream::BodyReader,
+) -> Result<ImportStatus, LoroError> {
+    let snapshot = fast_snapshot::_read_snapshot(&mut body)?;
+    body.finish()?;
+    fast_snapshot::decode_snapshot_inner(snapshot, doc)?;
+    Ok(ImportStatus {
+        success: VersionRange::from_vv(&doc.oplog_vv()),
+        pending: None,
+        quarantined: None,
+    })
+}
+
 pub(crate) fn decode_snapshot(
     doc: &LoroDoc,
     mode: EncodeMode,
@@ -521,12 +1133,17 @@ pub(crate) fn decode_snapshot(
 ) -> Result<ImportStatus, LoroError> {
     match mode {
         EncodeMode::OutdatedSnapshot => outdated_encode_reordered::decode_snapshot(doc, body)?,
-        EncodeMode::FastSnapshot => fast_snapshot::decode_snapshot(doc, body.to_vec().into())?,
+        EncodeMode::FastSnapshot
+        | EncodeMode::FastSnapshotZstd
+        | EncodeMode::FastSnapshotEncrypted => {
+            fast_snapshot::decode_snapshot(doc, body.to_vec().into())?
+        }
         _ => unreachable!(),
     };
     Ok(ImportStatus {
         success: VersionRange::from_vv(&doc.oplog_vv()),
         pending: None,
+        quarantined: None,
     })
 }
 
@@ -585,17 +1202,39 @@ pub struct ImportBlobMetadata {
 
 impl LoroDoc {
     /// Decodes the metadata for an imported blob from the provided bytes.
+    ///
+    /// The snapshots compressed with a dictionary or encrypted can't be decoded without
+    /// the dictionary or the key, see [LoroDoc::decode_import_blob_meta_with].
     pub fn decode_import_blob_meta(
         blob: &[u8],
         check_checksum: bool,
     ) -> LoroResult<ImportBlobMetadata> {
-        let parsed = parse_header_and_body(blob, check_checksum)?;
+        Self::decode_import_blob_meta_with(blob, check_checksum, None, None)
+    }
+
+    /// Decodes the metadata for an imported blob from the provided bytes, reading the snapshots
+    /// with the given compression dictionary and encryption key.
+    ///
+    /// # Errors
+    /// - [LoroError::DecryptionError] if the snapshot is encrypted and the key is missing or wrong
+    pub fn decode_import_blob_meta_with(
+        blob: &[u8],
+        check_checksum: bool,
+        dict: Option<CompressionDict>,
+        key: Option<EncryptionKey>,
+    ) -> LoroResult<ImportBlobMetadata> {
+        let blob = stream::unwrap_stream(blob)?;
+        let parsed = parse_header_and_body(&blob, check_checksum)?;
         match parsed.mode {
-            EncodeMode::Auto => unreachable!(),
+            EncodeMode::FastStream | EncodeMode::Auto => unreachable!(),
             EncodeMode::OutdatedRle | EncodeMode::OutdatedSnapshot => {
                 outdated_encode_reordered::decode_import_blob_meta(parsed)
             }
-            EncodeMode::FastSnapshot => fast_snapshot::decode_snapshot_blob_meta(parsed),
+            EncodeMode::FastSnapshot
+            | EncodeMode::FastSnapshotZstd
+            | EncodeMode::FastSnapshotEncrypted => {
+                fast_snapshot::decode_snapshot_blob_meta(parsed, dict, key)
+            }
             EncodeMode::FastUpdates => fast_snapshot::decode_updates_blob_meta(parsed),
         }
     }
@@ -474,22 +474,24 @@ impl<'a> PositionArena<'a> {
         Self { positions: ans }
     }
 
-    pub fn parse_to_positions(self) -> Vec<Vec<u8>> {
+    pub fn parse_to_positions(self) -> LoroResult<Vec<Vec<u8>>> {
         let mut ans: Vec<Vec<u8>> = Vec::with_capacity(self.positions.len());
         for PositionDelta {
             common_prefix_length,
             rest,
         } in self.positions
         {
+            let last_bytes = ans.last().map_or(&[][..], |x| x.as_slice());
+            let prefix = last_bytes
+                .get(..common_prefix_length)
+                .ok_or(LoroError::DecodeDataCorruptionError)?;
             // +1 for Fractional Index
-            let mut p = Vec::with_capacity(rest.len() + common_prefix_length + 1);
-            if let Some(last_bytes) = ans.last() {
-                p.extend_from_slice(&last_bytes[0..common_prefix_length]);
-            }
+            let mut p = Vec::with_capacity(rest.len() + prefix.len() + 1);
+            p.extend_from_slice(prefix);
             p.extend_from_slice(rest.as_ref());
             ans.push(p);
         }
-        ans
+        Ok(ans)
     }
 
     pub fn encode(&self) -> Vec<u8> {
@@ -0,0 +1,313 @@
+//! Export the history of a few containers and their descendants, see [super::ExportMode::Containers].
+//!
+//! The ops of the other containers are removed, so the counters of the remaining ops are
+//! compacted and every ID referenced by them is rewritten. A dep on a removed op is replaced
+//! by the last remaining ops in its causal past.
+//!
+//! The changes keep their original lamports, which are still valid because every new dep
+//! is in the causal past of the change. So the concurrent edits ordered by lamport, e.g.
+//! the concurrent insertions of a map key, are resolved the same way as in the original
+//! doc, and the IDs with lamports don't need to be rewritten.
+use std::{collections::BTreeMap, ops::Range};
+
+use either::Either;
+use fxhash::{FxHashMap, FxHashSet};
+use loro_common::{
+    ContainerID, Counter, HasCounterSpan, Lamport, LoroEncodeError, LoroValue, PeerID, TreeID, ID,
+};
+
+use super::{
+    fast_snapshot::Snapshot,
+    json_schema::{
+        self,
+        json::{self, JsonChange, JsonOp, JsonOpContent, JsonSchema},
+    },
+    value_register::ValueRegister,
+};
+use crate::{
+    change::Change,
+    op::Op,
+    version::{shrink_frontiers, Frontiers},
+    LoroDoc, OpLog, VersionVector,
+};
+
+pub(crate) fn export_containers_inner(
+    doc: &LoroDoc,
+    roots: &[ContainerID],
+) -> Result<Snapshot, LoroEncodeError> {
+    let json = {
+        let oplog = doc.oplog().try_lock().unwrap();
+        if oplog.is_shallow() {
+            return Err(LoroEncodeError::ContainersExportOnShallowDoc);
+        }
+
+        rewrite_changes(&oplog, roots)
+    };
+
+    // The stores are encoded by the new doc, so they need the codec of the doc
+    let new_doc = LoroDoc::new();
+    new_doc.set_codec_config(doc.config());
+    {
+        let mut oplog = new_doc.oplog().try_lock().unwrap();
+        let changes = json_schema::decode_changes(json, &oplog.arena)
+            .map_err(|e| LoroEncodeError::SerializeError(e.to_string()))?;
+        // The changes are sorted causally. They are inserted directly because an import
+        // would recalculate their lamports from the deps.
+        for change in changes {
+            oplog.insert_new_change(change, false);
+        }
+    }
+
+    new_doc.detach();
+    new_doc.checkout_to_latest();
+    Ok(super::fast_snapshot::encode_snapshot_inner(&new_doc))
+}
+
+/// The ops of a change in the original doc that are kept
+struct KeptChange {
+    /// The last kept ops in the causal past of the change
+    deps: Frontiers,
+    /// The counter ranges of the kept ops
+    runs: Vec<Range<Counter>>,
+}
+
+/// A run of kept ops and its new counter
+struct KeptSpan {
+    counters: Range<Counter>,
+    new_counter: Counter,
+}
+
+#[derive(Default)]
+struct Rewriter {
+    changes: FxHashMap<PeerID, BTreeMap<Counter, KeptChange>>,
+    spans: FxHashMap<PeerID, Vec<KeptSpan>>,
+}
+
+impl Rewriter {
+    /// The last kept ops in the causal past of `id`, including `id` itself
+    fn kept_past(&self, id: ID) -> Frontiers {
+        let Some((_, change)) = self
+            .changes
+            .get(&id.peer)
+            .and_then(|changes| changes.range(..=id.counter).next_back())
+        else {
+            return Frontiers::default();
+        };
+
+        match change.runs.iter().rev().find(|r| r.start <= id.counter) {
+            Some(run) => Frontiers::from_id(ID::new(id.peer, (run.end - 1).min(id.counter))),
+            None => change.deps.clone(),
+        }
+    }
+
+    fn add_change(&mut self, change: &Change, runs: Vec<Range<Counter>>, oplog: &OpLog) {
+        let mut deps = Frontiers::default();
+        for dep in change.deps.iter() {
+            for id in self.kept_past(dep).iter() {
+                deps.push(id);
+            }
+        }
+        let deps = shrink_frontiers(&deps, &oplog.dag).unwrap();
+
+        let spans = self.spans.entry(change.id.peer).or_default();
+        let mut new_counter = spans
+            .last()
+            .map_or(0, |s| s.new_counter + s.counters.end - s.counters.start);
+        for run in runs.iter() {
+            spans.push(KeptSpan {
+                counters: run.clone(),
+                new_counter,
+            });
+            new_counter += run.end - run.start;
+        }
+
+        self.changes
+            .entry(change.id.peer)
+            .or_default()
+            .insert(change.id.counter, KeptChange { deps, runs });
+    }
+
+    /// The new counter of a kept op
+    fn new_counter(&self, id: ID) -> Option<Counter> {
+        let spans = self.spans.get(&id.peer)?;
+        let i = spans.partition_point(|s| s.counters.end <= id.counter);
+        let span = spans.get(i).filter(|s| s.counters.start <= id.counter)?;
+        Some(span.new_counter + id.counter - span.counters.start)
+    }
+}
+
+/// Rewrite the IDs in the ops, whose peers are the indexes of `peers`
+struct IdMapper<'a> {
+    rewriter: &'a Rewriter,
+    peers: &'a [PeerID],
+}
+
+impl IdMapper<'_> {
+    fn id(&self, id: ID) -> ID {
+        let Some(&peer) = self.peers.get(id.peer as usize) else {
+            return id;
+        };
+        match self.rewriter.new_counter(ID::new(peer, id.counter)) {
+            Some(counter) => ID::new(id.peer, counter),
+            None => id,
+        }
+    }
+
+    fn tree_id(&self, id: TreeID) -> TreeID {
+        let ID { peer, counter } = self.id(id.id());
+        TreeID { peer, counter }
+    }
+
+    /// A kept container is created by a kept op, unless it's one of the exported roots.
+    /// Such a root becomes a root container named after the ID of the op that created it.
+    fn container(&self, container: ContainerID) -> ContainerID {
+        match container {
+            ContainerID::Normal {
+                peer,
+                counter,
+                container_type,
+            } => {
+                let real_id = ID::new(self.peers[peer as usize], counter);
+                match self.rewriter.new_counter(real_id) {
+                    Some(counter) => ContainerID::Normal {
+                        peer,
+                        counter,
+                        container_type,
+                    },
+                    None => ContainerID::new_root(&real_id.to_string(), container_type),
+                }
+            }
+            root => root,
+        }
+    }
+
+    fn value(&self, value: &mut LoroValue) {
+        if let LoroValue::Container(c) = value {
+            *c = self.container(c.clone());
+        }
+    }
+
+    fn op(&self, op: &mut JsonOp) {
+        op.container = self.container(op.container.clone());
+        match &mut op.content {
+            JsonOpContent::List(json::ListOp::Insert { value, .. })
+            | JsonOpContent::MovableList(json::MovableListOp::Insert { value, .. }) => {
+                value.iter_mut().for_each(|v| self.value(v));
+            }
+            JsonOpContent::List(json::ListOp::Delete { start_id, .. })
+            | JsonOpContent::MovableList(json::MovableListOp::Delete { start_id, .. })
+            | JsonOpContent::Text(json::TextOp::Delete { start_id, .. }) => {
+                *start_id = self.id(*start_id);
+            }
+            JsonOpContent::MovableList(json::MovableListOp::Set { value, .. }) => {
+                self.value(value);
+            }
+            JsonOpContent::Map(json::MapOp::Insert { value, .. }) => self.value(value),
+            JsonOpContent::Tree(
+                json::TreeOp::Create { target, parent, .. }
+                | json::TreeOp::Move { target, parent, .. },
+            ) => {
+                *target = self.tree_id(*target);
+                *parent = parent.map(|p| self.tree_id(p));
+            }
+            JsonOpContent::Tree(json::TreeOp::Delete { target }) => {
+                *target = self.tree_id(*target);
+            }
+            JsonOpContent::MovableList(json::MovableListOp::Move { .. })
+            | JsonOpContent::Map(json::MapOp::Delete { .. })
+            | JsonOpContent::Text(_)
+            | JsonOpContent::Future(_) => {}
+        }
+    }
+}
+
+fn rewrite_changes(oplog: &OpLog, roots: &[ContainerID]) -> JsonSchema {
+    let arena = &oplog.arena;
+    let roots: FxHashSet<_> = roots.iter().filter_map(|c| arena.id_to_idx(c)).collect();
+    let mut is_kept = FxHashMap::default();
+    let mut kept = |op: &Op| {
+        *is_kept.entry(op.container).or_insert_with(|| {
+            let mut ans = false;
+            arena.with_ancestors(op.container, |c, _| ans |= roots.contains(&c));
+            ans
+        })
+    };
+
+    // The changes are sorted by lamport, so the deps are visited before the changes
+    let changes = json_schema::init_encode(oplog, &VersionVector::default(), oplog.vv());
+    let mut rewriter = Rewriter::default();
+    let mut kept_ops = Vec::with_capacity(changes.len());
+    for change in changes.iter() {
+        let change: &Change = match change {
+            Either::Left(c) => c,
+            Either::Right(c) => c,
+        };
+        let ops = change.ops().iter().map(&mut kept).collect::<Vec<_>>();
+        let mut runs: Vec<Range<Counter>> = Vec::new();
+        for (op, _) in change.ops().iter().zip(ops.iter()).filter(|(_, k)| **k) {
+            match runs.last_mut() {
+                Some(last) if last.end == op.counter => last.end = op.ctr_end(),
+                _ => runs.push(op.counter..op.ctr_end()),
+            }
+        }
+        rewriter.add_change(change, runs, oplog);
+        kept_ops.push(ops);
+    }
+
+    let mut peer_register =
+        ValueRegister::from_existing(oplog.vv().keys().copied().collect::<Vec<_>>());
+    let peers = oplog.vv().keys().copied().collect::<Vec<_>>();
+    let mapper = IdMapper {
+        rewriter: &rewriter,
+        peers: &peers,
+    };
+    let mut json_changes = Vec::new();
+    for (change, kept_ops) in changes.iter().zip(kept_ops) {
+        let change: &Change = match change {
+            Either::Left(c) => c,
+            Either::Right(c) => c,
+        };
+        let kept_change = &rewriter.changes[&change.id.peer][&change.id.counter];
+        let peer = peer_register.register(&change.id.peer) as PeerID;
+        let mut ops = json_schema::encode_ops(change, arena, &mut peer_register)
+            .into_iter()
+            .zip(kept_ops)
+            .filter_map(|(op, kept)| kept.then_some(op))
+            .peekable();
+        let mut deps: Vec<ID> = kept_change
+            .deps
+            .iter()
+            .map(|id| {
+                let peer = peer_register.register(&id.peer) as PeerID;
+                mapper.id(ID::new(peer, id.counter))
+            })
+            .collect();
+        for run in kept_change.runs.iter() {
+            let id = mapper.id(ID::new(peer, run.start));
+            let mut json_ops = Vec::new();
+            while let Some(mut op) = ops.next_if(|op| op.counter < run.end) {
+                op.counter = mapper.id(ID::new(peer, op.counter)).counter;
+                mapper.op(&mut op);
+                json_ops.push(op);
+            }
+
+            let json_change = JsonChange {
+                id,
+                timestamp: change.timestamp,
+                deps: std::mem::take(&mut deps),
+                lamport: change.lamport + (run.start - change.id.counter) as Lamport,
+                msg: change.message().map(|x| x.to_string()),
+                ops: json_ops,
+            };
+            deps = vec![mapper.id(ID::new(peer, run.end - 1))];
+            json_changes.push(json_change);
+        }
+    }
+
+    JsonSchema {
+        schema_version: json_schema::SCHEMA_VERSION,
+        start_version: Frontiers::default(),
+        peers,
+        changes: json_changes,
+    }
+}
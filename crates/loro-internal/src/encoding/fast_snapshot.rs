@@ -13,16 +13,30 @@
 //!
 //!
 //!
-use std::io::{Read, Write};
+use std::{cmp::Reverse, io::Read};
 
 use crate::{
-    change::Change, encoding::shallow_snapshot, oplog::ChangeStore, LoroDoc, OpLog, VersionVector,
+    arena::SharedArena,
+    change::Change,
+    encoding::{shallow_snapshot, value::ValueLimits},
+    kv_store::{CompressionDict, CompressionType, EncryptionKey},
+    oplog::ChangeStore,
+    state::container_store::ContainerStore,
+    version::Frontiers,
+    LoroDoc, OpLog, VersionVector,
 };
 use bytes::{Buf, Bytes};
-use loro_common::{HasCounterSpan, IdSpan, LoroError, LoroResult};
+use fxhash::FxHashMap;
+use loro_common::{HasCounterSpan, IdSpan, LoroError, LoroResult, PeerID};
+use loro_kv_store::sstable::SsTable;
+use rle::{HasLength, Sliceable};
 use tracing::trace;
 
-use super::{EncodedBlobMode, ImportBlobMetadata, ParsedHeaderAndBody};
+use super::{
+    import_value_limits,
+    stream::{BodyReader, StreamWriter},
+    EncodedBlobMode, ImportBlobMetadata, ParsedHeaderAndBody,
+};
 pub(crate) const EMPTY_MARK: &[u8] = b"E";
 pub(crate) struct Snapshot {
     pub oplog_bytes: Bytes,
@@ -30,34 +44,72 @@ pub(crate) struct Snapshot {
     pub shallow_root_state_bytes: Bytes,
 }
 
-pub(super) fn _encode_snapshot<W: Write>(s: Snapshot, w: &mut W) {
-    w.write_all(&(s.oplog_bytes.len() as u32).to_le_bytes())
-        .unwrap();
-    w.write_all(&s.oplog_bytes).unwrap();
-    let state_bytes = s
-        .state_bytes
-        .unwrap_or_else(|| Bytes::from_static(EMPTY_MARK));
-    w.write_all(&(state_bytes.len() as u32).to_le_bytes())
-        .unwrap();
-    w.write_all(&state_bytes).unwrap();
-    w.write_all(&(s.shallow_root_state_bytes.len() as u32).to_le_bytes())
-        .unwrap();
-    w.write_all(&s.shallow_root_state_bytes).unwrap();
+impl Snapshot {
+    /// The bytes of the oplog, the state and the shallow root state, where a missing state
+    /// is marked by [EMPTY_MARK]
+    pub(super) fn into_parts(self) -> [Bytes; 3] {
+        let state_bytes = self
+            .state_bytes
+            .unwrap_or_else(|| Bytes::from_static(EMPTY_MARK));
+        [self.oplog_bytes, state_bytes, self.shallow_root_state_bytes]
+    }
+
+    /// Split the encoded snapshot into the length prefixes and the bytes of each part.
+    ///
+    /// The bytes of the stores are not copied.
+    pub(super) fn into_chunks(self) -> Vec<Bytes> {
+        let mut ans = Vec::with_capacity(6);
+        for bytes in self.into_parts() {
+            ans.push(Bytes::copy_from_slice(&(bytes.len() as u32).to_le_bytes()));
+            ans.push(bytes);
+        }
+        ans
+    }
+
+    /// Encode the blocks of all the stores again with the given compression,
+    /// and encrypt them if `to_key` is given.
+    ///
+    /// `from_dict` is required if the stores are compressed with a dictionary,
+    /// and `from_key` is required if they are encrypted.
+    pub(super) fn recompress(
+        self,
+        compression_type: CompressionType,
+        from_dict: Option<&CompressionDict>,
+        to_dict: Option<&CompressionDict>,
+        from_key: Option<&EncryptionKey>,
+        to_key: Option<&EncryptionKey>,
+    ) -> LoroResult<Snapshot> {
+        let recompress = |bytes: Bytes| -> LoroResult<Bytes> {
+            if bytes.is_empty() {
+                return Ok(bytes);
+            }
+
+            let table =
+                SsTable::import_all_with(bytes, from_dict.cloned(), from_key.cloned(), None)?;
+            Ok(table
+                .recompress(compression_type, to_dict.cloned(), to_key.cloned())
+                .export_all())
+        };
+        Ok(Snapshot {
+            oplog_bytes: recompress(self.oplog_bytes)?,
+            state_bytes: self.state_bytes.map(recompress).transpose()?,
+            shallow_root_state_bytes: recompress(self.shallow_root_state_bytes)?,
+        })
+    }
 }
 
-pub(super) fn _decode_snapshot_bytes(bytes: Bytes) -> LoroResult<Snapshot> {
-    let mut r = bytes.reader();
-    let oplog_bytes_len = read_u32_le(&mut r) as usize;
-    let oplog_bytes = r.get_mut().copy_to_bytes(oplog_bytes_len);
-    let state_bytes_len = read_u32_le(&mut r) as usize;
-    let state_bytes = r.get_mut().copy_to_bytes(state_bytes_len);
+/// Read the snapshot part by part.
+///
+/// Each part is read into its own buffer, which is then used by the stores without copying.
+pub(super) fn _read_snapshot(body: &mut BodyReader) -> LoroResult<Snapshot> {
+    let oplog_bytes = body.read_part()?;
+    let state_bytes = body.read_part()?;
     let state_bytes = if state_bytes == EMPTY_MARK {
         None
     } else {
         Some(state_bytes)
     };
-    let shallow_bytes_len = read_u32_le(&mut r) as usize;
-    let shallow_root_state_bytes = r.get_mut().copy_to_bytes(shallow_bytes_len);
+    let shallow_root_state_bytes = body.read_part()?;
     Ok(Snapshot {
         oplog_bytes,
         state_bytes,
@@ -65,27 +117,60 @@ pub(super) fn _decode_snapshot_bytes(bytes: Bytes) -> LoroResult<Snapshot> {
     })
 }
 
-pub(super) fn _decode_snapshot_meta_partial(bytes: &[u8]) -> (&[u8], bool) {
+pub(super) fn _decode_snapshot_bytes(mut bytes: Bytes) -> LoroResult<Snapshot> {
+    let oplog_bytes = read_part(&mut bytes)?;
+    let state_bytes = read_part(&mut bytes)?;
+    let state_bytes = if state_bytes == EMPTY_MARK {
+        None
+    } else {
+        Some(state_bytes)
+    };
+    let shallow_root_state_bytes = read_part(&mut bytes)?;
+    Ok(Snapshot {
+        oplog_bytes,
+        state_bytes,
+        shallow_root_state_bytes,
+    })
+}
+
+pub(super) fn _decode_snapshot_meta_partial(bytes: &[u8]) -> LoroResult<(&[u8], bool)> {
     let mut r = bytes;
-    let oplog_bytes_len = read_u32_le_slice(&mut r) as usize;
-    let oplog_bytes = &r[..oplog_bytes_len];
-    r = &r[oplog_bytes_len..];
-    let state_bytes_len = read_u32_le_slice(&mut r) as usize;
-    r = &r[state_bytes_len..];
-    let shallow_bytes_len = read_u32_le_slice(&mut r) as usize;
-    (oplog_bytes, shallow_bytes_len > 0)
+    let oplog_bytes = read_part_slice(&mut r)?;
+    let _state_bytes = read_part_slice(&mut r)?;
+    let shallow_root_state_bytes = read_part_slice(&mut r)?;
+    Ok((oplog_bytes, !shallow_root_state_bytes.is_empty()))
 }
 
-fn read_u32_le_slice(r: &mut &[u8]) -> u32 {
-    let mut buf = [0; 4];
-    r.read_exact(&mut buf).unwrap();
-    u32::from_le_bytes(buf)
+/// Read a part of the snapshot, which is prefixed by its length
+fn read_part(bytes: &mut Bytes) -> LoroResult<Bytes> {
+    let len = read_u32_le(bytes)? as usize;
+    if bytes.len() < len {
+        return Err(LoroError::DecodeDataCorruptionError);
+    }
+
+    Ok(bytes.split_to(len))
 }
 
-fn read_u32_le(r: &mut bytes::buf::Reader<Bytes>) -> u32 {
+fn read_part_slice<'a>(r: &mut &'a [u8]) -> LoroResult<&'a [u8]> {
     let mut buf = [0; 4];
-    r.read_exact(&mut buf).unwrap();
-    u32::from_le_bytes(buf)
+    r.read_exact(&mut buf)
+        .map_err(|_| LoroError::DecodeDataCorruptionError)?;
+    let len = u32::from_le_bytes(buf) as usize;
+    if r.len() < len {
+        return Err(LoroError::DecodeDataCorruptionError);
+    }
+
+    let (part, rest) = r.split_at(len);
+    *r = rest;
+    Ok(part)
+}
+
+fn read_u32_le(bytes: &mut Bytes) -> LoroResult<u32> {
+    if bytes.len() < 4 {
+        return Err(LoroError::DecodeDataCorruptionError);
+    }
+
+    Ok(bytes.get_u32_le())
 }
 
 pub(crate) fn decode_snapshot(doc: &LoroDoc, bytes: Bytes) -> LoroResult<()> {
@@ -117,45 +202,81 @@ pub(crate) fn decode_snapshot_inner(snapshot: Snapshot, doc: &LoroDoc) -> Result
         )
     })?;
 
-    if !oplog.is_empty() {
-        panic!("InternalError importing snapshot to an non-empty doc");
+    if !oplog.is_empty() || !state.frontiers.is_empty() {
+        return Err(LoroError::DecodeError(
+            "Cannot import a snapshot into a non-empty doc".into(),
+        ));
+    }
+
+    let import_options = oplog.configure.import_options();
+    let verified = oplog.configure.change_verifier().is_some();
+    if verified && !shallow_root_state_bytes.is_empty() {
+        return Err(LoroError::UnverifiableShallowSnapshot);
     }
 
-    assert!(state.frontiers.is_empty());
-    assert!(oplog.frontiers().is_empty());
-    oplog.decode_change_store(oplog_bytes)?;
+    // The oplog and the states are decoded into new stores, which replace the empty ones of
+    // the doc after all the checks pass
+    let change_store = ChangeStore::new_mem(&oplog.arena, oplog.configure.clone());
+    let decode_info = change_store.import_all(oplog_bytes)?;
+    if verified || import_options.is_some() {
+        let options = import_options.unwrap_or_default();
+        let changes = change_store.decode_external_changes(&VersionVector::default(), &options)?;
+        options.check_changes(&changes, &oplog)?;
+        oplog.verify_change_signatures(&changes)?;
+    }
+
+    // Only the changes are signed, so the state is calculated from the verified changes
+    // instead of being decoded
+    let state_bytes = if verified { None } else { state_bytes };
     let need_calc = state_bytes.is_none();
-    let state_frontiers;
-    if shallow_root_state_bytes.is_empty() {
+    let mut store = ContainerStore::new(
+        state.arena.clone(),
+        state.config.clone(),
+        state.peer.clone(),
+    );
+    let is_shallow = !shallow_root_state_bytes.is_empty();
+    // The version of the decoded state, if it's not the latest version
+    let mut state_frontiers = need_calc.then(Frontiers::default);
+    if !is_shallow {
         ensure_cov::notify_cov("loro_internal::import::snapshot::normal");
         if let Some(bytes) = state_bytes {
-            state.store.decode(bytes)?;
+            store.decode(bytes)?;
         }
-        state_frontiers = oplog.frontiers().clone();
     } else {
         ensure_cov::notify_cov("loro_internal::import::snapshot::gc");
-        let shallow_root_state_frontiers = state.store.decode_gc(
-            shallow_root_state_bytes.clone(),
-            oplog.dag().shallow_since_frontiers().clone(),
-        )?;
-        state
-            .store
+        let shallow_since_frontiers = decode_info
+            .start_version
+            .as_ref()
+            .map(|(_, f)| f.clone())
+            .unwrap_or_default();
+        let shallow_root_state_frontiers =
+            store.decode_gc(shallow_root_state_bytes.clone(), shallow_since_frontiers)?;
+        store
             .decode_state_by_two_bytes(shallow_root_state_bytes, state_bytes.unwrap_or_default())?;
-
-        let shallow_root_store = state.shallow_root_store().cloned();
-        oplog.with_history_cache(|h| {
-            h.set_shallow_root_store(shallow_root_store);
-        });
-
         if need_calc {
             ensure_cov::notify_cov("shallow_snapshot::need_calc");
-            state_frontiers = shallow_root_state_frontiers.unwrap();
+            state_frontiers =
+                Some(shallow_root_state_frontiers.ok_or(LoroError::DecodeDataCorruptionError)?);
         } else {
             ensure_cov::notify_cov("shallow_snapshot::dont_need_calc");
-            state_frontiers = oplog.frontiers().clone();
         }
     }
 
+    if let Some(options) = import_options {
+        store.check_all_containers(options.value_limits(), options.strict)?;
+    }
+
+    oplog.set_change_store(change_store, decode_info)?;
+    state.store = store;
+    if is_shallow {
+        let shallow_root_store = state.shallow_root_store().cloned();
+        oplog.with_history_cache(|h| {
+            h.set_shallow_root_store(shallow_root_store);
+        });
+    }
+
+    let state_frontiers = state_frontiers.unwrap_or_else(|| oplog.frontiers().clone());
+
     // FIXME: we may need to extract the unknown containers here?
     // Or we should lazy load it when the time comes?
 
@@ -174,70 +295,102 @@ pub(crate) fn decode_snapshot_inner(snapshot: Snapshot, doc: &LoroDoc) -> Result
 impl OpLog {
     pub(super) fn decode_change_store(&mut self, bytes: bytes::Bytes) -> LoroResult<()> {
         let v = self.change_store().import_all(bytes)?;
-        self.dag.set_version_by_fast_snapshot_import(v);
-        Ok(())
+        self.dag.set_version_by_fast_snapshot_import(v)
     }
 }
 
-pub(crate) fn encode_snapshot<W: std::io::Write>(doc: &LoroDoc, w: &mut W) {
-    let snapshot = encode_snapshot_inner(doc);
-    _encode_snapshot(snapshot, w);
+pub(crate) fn encode_snapshot_inner(doc: &LoroDoc) -> Snapshot {
+    if let Some(snapshot) = encode_shallow_doc_snapshot(doc) {
+        return snapshot;
+    }
+
+    with_latest_stores(doc, |oplog, store| Snapshot {
+        oplog_bytes: oplog.encode_change_store(),
+        state_bytes: Some(store.encode()),
+        shallow_root_state_bytes: Bytes::new(),
+    })
 }
 
-pub(crate) fn encode_snapshot_inner(doc: &LoroDoc) -> Snapshot {
+/// Write the snapshot to `w` part by part, where the blocks of the stores are written as
+/// soon as they are encoded
+pub(super) fn encode_snapshot_to(doc: &LoroDoc, w: &mut StreamWriter) -> std::io::Result<()> {
+    if let Some(snapshot) = encode_shallow_doc_snapshot(doc) {
+        return w.write_snapshot(snapshot);
+    }
+
+    with_latest_stores(doc, |oplog, store| {
+        oplog.encode_change_store_to(w)?;
+        w.end_part()?;
+        store.encode_to(w)?;
+        w.end_part()?;
+        // The shallow root state is empty
+        w.end_part()
+    })
+}
+
+/// The snapshot of a shallow doc, which is exported from its shallow root, or `None` if
+/// the doc is not shallow
+fn encode_shallow_doc_snapshot(doc: &LoroDoc) -> Option<Snapshot> {
     assert!(doc.drop_pending_events().is_empty());
+    let f = {
+        let state = doc.app_state().try_lock().unwrap();
+        let oplog = doc.oplog().try_lock().unwrap();
+        state.store.shallow_root_store()?;
+        oplog.shallow_since_frontiers().clone()
+    };
+
+    // TODO: PERF: this can be optimized by reusing the bytes of gc store
+    let (snapshot, _) = shallow_snapshot::export_shallow_snapshot_inner(doc, &f).unwrap();
+    Some(snapshot)
+}
+
+/// Call `f` with the oplog and the container store of the latest version.
+///
+/// A detached doc is checked out to the latest version and back.
+fn with_latest_stores<R>(doc: &LoroDoc, f: impl FnOnce(&OpLog, &mut ContainerStore) -> R) -> R {
     let old_state_frontiers = doc.state_frontiers();
     let was_detached = doc.is_detached();
-    let mut state = doc.app_state().try_lock().unwrap();
-    let oplog = doc.oplog().try_lock().unwrap();
-    let is_gc = state.store.shallow_root_store().is_some();
-    if is_gc {
-        // TODO: PERF: this can be optimized by reusing the bytes of gc store
-        let f = oplog.shallow_since_frontiers().clone();
-        drop(oplog);
-        drop(state);
-        let (snapshot, _) = shallow_snapshot::export_shallow_snapshot_inner(doc, &f).unwrap();
-        return snapshot;
+    if was_detached {
+        let latest = doc.oplog_frontiers();
+        doc.checkout_without_emitting(&latest, false).unwrap();
     }
 
+    let mut state = doc.app_state().try_lock().unwrap();
+    let oplog = doc.oplog().try_lock().unwrap();
     assert!(!state.is_in_txn());
-    let oplog_bytes = oplog.encode_change_store();
     if oplog.is_shallow() {
         assert_eq!(
             oplog.shallow_since_frontiers(),
             state.store.shallow_root_frontiers().unwrap()
         );
     }
-    if was_detached {
-        let latest = oplog.frontiers().clone();
-        drop(oplog);
-        drop(state);
-        doc.checkout_without_emitting(&latest, false).unwrap();
-        state = doc.app_state().try_lock().unwrap();
-    }
     state.ensure_all_alive_containers();
-    let state_bytes = state.store.encode();
-    let snapshot = Snapshot {
-        oplog_bytes,
-        state_bytes: Some(state_bytes),
-        shallow_root_state_bytes: Bytes::new(),
-    };
+    let ans = f(&oplog, &mut state.store);
+    drop(oplog);
+    drop(state);
     if was_detached {
-        drop(state);
         doc.checkout_without_emitting(&old_state_frontiers, false)
             .unwrap();
         doc.drop_pending_events();
     }
 
-    snapshot
+    ans
 }
 
 pub(crate) fn decode_oplog(oplog: &mut OpLog, bytes: &[u8]) -> Result<Vec<Change>, LoroError> {
-    let oplog_len = u32::from_le_bytes(bytes[0..4].try_into().unwrap());
-    let oplog_bytes = &bytes[4..4 + oplog_len as usize];
+    let (oplog_bytes, _) = _decode_snapshot_meta_partial(bytes)?;
+    decode_oplog_bytes(oplog, oplog_bytes.to_vec().into())
+}
+
+/// Decode the changes of the oplog part of a snapshot that are not in `oplog`
+pub(super) fn decode_oplog_bytes(
+    oplog: &OpLog,
+    oplog_bytes: Bytes,
+) -> Result<Vec<Change>, LoroError> {
     let mut changes = ChangeStore::decode_snapshot_for_updates(
-        oplog_bytes.to_vec().into(),
+        oplog_bytes,
         &oplog.arena,
+        &oplog.configure,
         oplog.vv(),
     )?;
     changes.sort_unstable_by_key(|x| x.lamport);
@@ -258,30 +411,134 @@ pub(crate) fn encode_updates_in_range<W: std::io::Write>(
 }
 
 pub(crate) fn decode_updates(oplog: &mut OpLog, body: Bytes) -> Result<Vec<Change>, LoroError> {
+    let mut changes = decode_blocks(body, &oplog.arena, oplog.vv(), import_value_limits(oplog))?;
+    changes.sort_unstable_by_key(|x| x.lamport);
+    Ok(changes)
+}
+
+/// Decode the changes of the updates read from `body` block by block, skipping the ones
+/// included by `oplog`.
+///
+/// Only the decoded changes are kept, the bytes of each block are dropped after it's decoded.
+pub(super) fn read_updates(oplog: &OpLog, body: &mut BodyReader) -> LoroResult<Vec<Change>> {
+    let limits = import_value_limits(oplog);
+    let mut changes = Vec::new();
+    while let Some(block) = body.next_block()? {
+        changes.extend(ChangeStore::decode_block_bytes(
+            block,
+            &oplog.arena,
+            oplog.vv(),
+            limits,
+        )?);
+    }
+
+    changes.sort_unstable_by_key(|x| x.lamport);
+    Ok(changes)
+}
+
+/// Decode the changes in the blocks of the body of an updates blob, skipping the ones
+/// included by `self_vv`
+fn decode_blocks(
+    body: Bytes,
+    arena: &SharedArena,
+    self_vv: &VersionVector,
+    limits: ValueLimits,
+) -> Result<Vec<Change>, LoroError> {
     let mut reader: &[u8] = body.as_ref();
     let mut index = 0;
-    let self_vv = oplog.vv();
     let mut changes = Vec::new();
     while !reader.is_empty() {
         let old_reader_len = reader.len();
-        let len = leb128::read::unsigned(&mut reader).unwrap() as usize;
+        let len = leb128::read::unsigned(&mut reader)
+            .map_err(|_| LoroError::DecodeDataCorruptionError)? as usize;
         index += old_reader_len - reader.len();
+        if reader.len() < len {
+            return Err(LoroError::DecodeDataCorruptionError);
+        }
+
         let block_bytes = body.slice(index..index + len);
         trace!("decoded block_bytes = {:?}", &block_bytes);
-        let new_changes = ChangeStore::decode_block_bytes(block_bytes, &oplog.arena, self_vv)?;
+        let new_changes = ChangeStore::decode_block_bytes(block_bytes, arena, self_vv, limits)?;
         changes.extend(new_changes);
         index += len;
         reader = &reader[len..];
     }
 
-    changes.sort_unstable_by_key(|x| x.lamport);
     Ok(changes)
 }
 
+/// Merge the bodies of the updates blobs into the blocks of the union of their changes.
+///
+/// The changes are decoded into a standalone arena, so no doc is needed.
+pub(crate) fn merge_updates_blocks(bodies: Vec<Bytes>) -> Result<Vec<Bytes>, LoroError> {
+    let arena = SharedArena::new();
+    let empty_vv = VersionVector::new();
+    let mut changes = Vec::new();
+    for body in bodies {
+        changes.extend(decode_blocks(
+            body,
+            &arena,
+            &empty_vv,
+            ValueLimits::default(),
+        )?);
+    }
+
+    Ok(encode_changes_in_runs(changes, &arena))
+}
+
+/// Encode the changes into blocks, where the changes of a peer may overlap or have gaps.
+///
+/// The overlapping parts of the changes are removed, and a change is sliced if it's
+/// partially overlapped. Each continuous run of the changes is encoded separately.
+pub(crate) fn encode_changes_in_runs(
+    changes: impl IntoIterator<Item = Change>,
+    arena: &SharedArena,
+) -> Vec<Bytes> {
+    let mut peer_to_changes: FxHashMap<PeerID, Vec<Change>> = FxHashMap::default();
+    for change in changes {
+        peer_to_changes
+            .entry(change.id.peer)
+            .or_default()
+            .push(change);
+    }
+
+    let mut blocks = Vec::new();
+    for (_, mut peer_changes) in peer_to_changes {
+        // The longest change comes first if several changes start at the same counter
+        peer_changes.sort_unstable_by_key(|c| (c.id.counter, Reverse(c.ctr_end())));
+        let mut run: Vec<Change> = Vec::new();
+        for change in peer_changes {
+            let end = run.last().map_or(change.id.counter, |c| c.ctr_end());
+            if change.ctr_end() <= end {
+                continue;
+            }
+
+            if change.id.counter > end {
+                // The changes in a block should be continuous
+                blocks.extend(ChangeStore::encode_changes_to_blocks(
+                    std::mem::take(&mut run),
+                    arena,
+                ));
+                run.push(change);
+            } else if change.id.counter < end {
+                run.push(change.slice((end - change.id.counter) as usize, change.atom_len()));
+            } else {
+                run.push(change);
+            }
+        }
+        blocks.extend(ChangeStore::encode_changes_to_blocks(run, arena));
+    }
+
+    blocks
+}
+
+/// Decode the metadata of the snapshot from its oplog, whose blocks are read with `dict` and `key`
 pub(crate) fn decode_snapshot_blob_meta(
     parsed: ParsedHeaderAndBody,
+    dict: Option<CompressionDict>,
+    key: Option<EncryptionKey>,
 ) -> LoroResult<ImportBlobMetadata> {
-    let (oplog_bytes, is_shallow) = _decode_snapshot_meta_partial(parsed.body);
+    let (oplog_bytes, is_shallow) = _decode_snapshot_meta_partial(parsed.body)?;
     let mode = if is_shallow {
         EncodedBlobMode::ShallowSnapshot
     } else {
@@ -289,6 +546,10 @@ pub(crate) fn decode_snapshot_blob_meta(
     };
 
     let doc = LoroDoc::new();
+    let (compression_type, _) = doc.config().snapshot_compression();
+    doc.config()
+        .set_snapshot_compression(compression_type, dict);
+    doc.config().set_encryption_key(key);
     let mut oplog = doc.oplog.try_lock().unwrap();
     oplog.decode_change_store(oplog_bytes.to_vec().into())?;
     let timestamp = oplog.get_greatest_timestamp(oplog.dag.frontiers());
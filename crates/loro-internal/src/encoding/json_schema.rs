@@ -14,7 +14,7 @@ use crate::{
     op::{FutureInnerContent, InnerContent, Op, SliceRange},
     oplog::BlockChangeRef,
     version::{Frontiers, VersionRange},
-    OpLog, VersionVector,
+    ChangeMeta, ChangeWithOps, OpLog, VersionVector,
 };
 use either::Either;
 use json::{JsonOpContent, JsonSchema};
@@ -25,7 +25,7 @@ use loro_common::{
 use rle::{HasLength, RleVec, Sliceable};
 use std::sync::Arc;
 
-const SCHEMA_VERSION: u8 = 1;
+pub(super) const SCHEMA_VERSION: u8 = 1;
 
 fn refine_vv(vv: &VersionVector, oplog: &OpLog) -> VersionVector {
     let mut refined = VersionVector::new();
@@ -64,8 +64,43 @@ pub(crate) fn export_json<'a, 'c: 'a>(
     }
 }
 
+/// Get the changes between the two versions with their ops.
+///
+/// Unlike [export_json], the peer ids in the ops are not compressed.
+pub(crate) fn export_changes_with_ops(
+    oplog: &OpLog,
+    start_vv: &VersionVector,
+    end_vv: &VersionVector,
+) -> Vec<ChangeWithOps> {
+    let actual_start_vv = refine_vv(start_vv, oplog);
+    let actual_end_vv = refine_vv(end_vv, oplog);
+    init_encode(oplog, &actual_start_vv, &actual_end_vv)
+        .iter()
+        .map(|change| {
+            let change: &Change = match change {
+                Either::Left(c) => c,
+                Either::Right(c) => c,
+            };
+            ChangeWithOps {
+                meta: ChangeMeta::from_change(change),
+                ops: encode_ops(change, &oplog.arena, &mut KeepPeer),
+            }
+        })
+        .collect()
+}
+
+/// Encode the ops of the change with the real peer ids
+pub(crate) fn encode_ops_with_peers(change: &Change, arena: &SharedArena) -> Vec<json::JsonOp> {
+    encode_ops(change, arena, &mut KeepPeer)
+}
+
 pub(crate) fn import_json(oplog: &mut OpLog, json: JsonSchema) -> LoroResult<ImportStatus> {
-    let changes = decode_changes(json, &oplog.arena)?;
+    let mut changes = decode_changes(json, &oplog.arena)?;
+    if let Some(options) = oplog.configure.import_options() {
+        options.check_changes(&changes, oplog)?;
+    }
+    oplog.verify_change_signatures(&changes)?;
+    let quarantined = oplog.quarantine_rejected_changes(&mut changes);
     let ImportChangesResult {
         latest_ids,
         pending_changes,
@@ -88,10 +123,15 @@ pub(crate) fn import_json(oplog: &mut OpLog, json: JsonSchema) -> LoroResult<Imp
         } else {
             Some(pending)
         },
+        quarantined: if quarantined.is_empty() {
+            None
+        } else {
+            Some(quarantined)
+        },
     })
 }
 
-fn init_encode<'s, 'a: 's>(
+pub(super) fn init_encode<'s, 'a: 's>(
     oplog: &'a OpLog,
     start_vv: &VersionVector,
     end_vv: &VersionVector,
@@ -124,28 +164,50 @@ fn init_encode<'s, 'a: 's>(
     diff_changes
 }
 
-fn register_id(id: &ID, peer_register: &mut ValueRegister<PeerID>) -> ID {
-    let peer = peer_register.register(&id.peer);
-    ID::new(peer as PeerID, id.counter)
+/// Maps the peer ids of the encoded ops.
+///
+/// [ValueRegister] compresses the peer ids into the indexes of the `peers` table of [JsonSchema],
+/// while [KeepPeer] keeps the peer ids unchanged.
+pub(super) trait PeerRegister {
+    fn register_peer(&mut self, peer: &PeerID) -> PeerID;
+}
+
+impl PeerRegister for ValueRegister<PeerID> {
+    fn register_peer(&mut self, peer: &PeerID) -> PeerID {
+        self.register(peer) as PeerID
+    }
+}
+
+struct KeepPeer;
+
+impl PeerRegister for KeepPeer {
+    fn register_peer(&mut self, peer: &PeerID) -> PeerID {
+        *peer
+    }
 }
 
-fn register_idlp(idlp: &IdLp, peer_register: &mut ValueRegister<PeerID>) -> IdLp {
+fn register_id(id: &ID, peer_register: &mut impl PeerRegister) -> ID {
+    let peer = peer_register.register_peer(&id.peer);
+    ID::new(peer, id.counter)
+}
+
+fn register_idlp(idlp: &IdLp, peer_register: &mut impl PeerRegister) -> IdLp {
     IdLp {
-        peer: peer_register.register(&idlp.peer) as PeerID,
+        peer: peer_register.register_peer(&idlp.peer),
         lamport: idlp.lamport,
     }
 }
 
-fn register_tree_id(tree: &TreeID, peer_register: &mut ValueRegister<PeerID>) -> TreeID {
+fn register_tree_id(tree: &TreeID, peer_register: &mut impl PeerRegister) -> TreeID {
     TreeID {
-        peer: peer_register.register(&tree.peer) as PeerID,
+        peer: peer_register.register_peer(&tree.peer),
         counter: tree.counter,
     }
 }
 
 fn register_container_id(
     container: ContainerID,
-    peer_register: &mut ValueRegister<PeerID>,
+    peer_register: &mut impl PeerRegister,
 ) -> ContainerID {
     match container {
         ContainerID::Normal {
@@ -153,7 +215,7 @@ fn register_container_id(
             counter,
             container_type,
         } => ContainerID::Normal {
-            peer: peer_register.register(&peer) as PeerID,
+            peer: peer_register.register_peer(&peer),
             counter,
             container_type,
         },
@@ -208,246 +270,255 @@ fn encode_changes(
             Either::Left(c) => c,
             Either::Right(c) => c,
         };
-        let mut ops = Vec::with_capacity(change.ops().len());
-        for Op {
-            counter,
-            container,
-            content,
-        } in change.ops().iter()
-        {
-            let mut container = arena.get_container_id(*container).unwrap();
-            if container.is_normal() {
-                container = register_container_id(container, peer_register);
-            }
-            let op = match container.container_type() {
-                ContainerType::List => match content {
-                    InnerContent::List(list) => JsonOpContent::List(match list {
-                        InnerListOp::Insert { slice, pos } => {
-                            let mut values =
-                                arena.get_values(slice.0.start as usize..slice.0.end as usize);
-                            values.iter_mut().for_each(|x| {
-                                if let LoroValue::Container(id) = x {
-                                    if id.is_normal() {
-                                        *id = register_container_id(id.clone(), peer_register);
-                                    }
+        let ops = encode_ops(change, arena, peer_register);
+        let c = json::JsonChange {
+            id: register_id(&change.id, peer_register),
+            ops,
+            deps: change
+                .deps
+                .iter()
+                .map(|id| register_id(&id, peer_register))
+                .collect(),
+            lamport: change.lamport,
+            timestamp: change.timestamp,
+            msg: change.message().map(|x| x.to_string()),
+        };
+
+        changes.push(c);
+    }
+    changes
+}
+
+pub(super) fn encode_ops(
+    change: &Change,
+    arena: &SharedArena,
+    peer_register: &mut impl PeerRegister,
+) -> Vec<json::JsonOp> {
+    let mut ops = Vec::with_capacity(change.ops().len());
+    for Op {
+        counter,
+        container,
+        content,
+    } in change.ops().iter()
+    {
+        let mut container = arena.get_container_id(*container).unwrap();
+        if container.is_normal() {
+            container = register_container_id(container, peer_register);
+        }
+        let op = match container.container_type() {
+            ContainerType::List => match content {
+                InnerContent::List(list) => JsonOpContent::List(match list {
+                    InnerListOp::Insert { slice, pos } => {
+                        let mut values =
+                            arena.get_values(slice.0.start as usize..slice.0.end as usize);
+                        values.iter_mut().for_each(|x| {
+                            if let LoroValue::Container(id) = x {
+                                if id.is_normal() {
+                                    *id = register_container_id(id.clone(), peer_register);
                                 }
-                            });
-                            json::ListOp::Insert {
-                                pos: *pos as u32,
-                                value: values,
                             }
+                        });
+                        json::ListOp::Insert {
+                            pos: *pos as u32,
+                            value: values,
                         }
-                        InnerListOp::Delete(DeleteSpanWithId {
-                            id_start,
-                            span: DeleteSpan { pos, signed_len },
-                        }) => json::ListOp::Delete {
-                            pos: *pos as i32,
-                            len: *signed_len as i32,
-                            start_id: register_id(id_start, peer_register),
-                        },
-                        _ => unreachable!(),
-                    }),
+                    }
+                    InnerListOp::Delete(DeleteSpanWithId {
+                        id_start,
+                        span: DeleteSpan { pos, signed_len },
+                    }) => json::ListOp::Delete {
+                        pos: *pos as i32,
+                        len: *signed_len as i32,
+                        start_id: register_id(id_start, peer_register),
+                    },
                     _ => unreachable!(),
-                },
-                ContainerType::MovableList => match content {
-                    InnerContent::List(list) => JsonOpContent::MovableList(match list {
-                        InnerListOp::Insert { slice, pos } => {
-                            let mut values =
-                                arena.get_values(slice.0.start as usize..slice.0.end as usize);
-                            values.iter_mut().for_each(|x| {
-                                if let LoroValue::Container(id) = x {
-                                    if id.is_normal() {
-                                        *id = register_container_id(id.clone(), peer_register);
-                                    }
+                }),
+                _ => unreachable!(),
+            },
+            ContainerType::MovableList => match content {
+                InnerContent::List(list) => JsonOpContent::MovableList(match list {
+                    InnerListOp::Insert { slice, pos } => {
+                        let mut values =
+                            arena.get_values(slice.0.start as usize..slice.0.end as usize);
+                        values.iter_mut().for_each(|x| {
+                            if let LoroValue::Container(id) = x {
+                                if id.is_normal() {
+                                    *id = register_container_id(id.clone(), peer_register);
                                 }
-                            });
-                            json::MovableListOp::Insert {
-                                pos: *pos as u32,
-                                value: values,
                             }
+                        });
+                        json::MovableListOp::Insert {
+                            pos: *pos as u32,
+                            value: values,
                         }
-                        InnerListOp::Delete(DeleteSpanWithId {
-                            id_start,
-                            span: DeleteSpan { pos, signed_len },
-                        }) => json::MovableListOp::Delete {
-                            pos: *pos as i32,
-                            len: *signed_len as i32,
-                            start_id: register_id(id_start, peer_register),
-                        },
-                        InnerListOp::Move {
-                            from,
-                            elem_id: from_id,
-                            to,
-                        } => json::MovableListOp::Move {
-                            from: *from,
-                            to: *to,
-                            elem_id: register_idlp(from_id, peer_register),
-                        },
-                        InnerListOp::Set { elem_id, value } => {
-                            let value = if let LoroValue::Container(id) = value {
-                                if id.is_normal() {
-                                    LoroValue::Container(register_container_id(
-                                        id.clone(),
-                                        peer_register,
-                                    ))
-                                } else {
-                                    value.clone()
-                                }
+                    }
+                    InnerListOp::Delete(DeleteSpanWithId {
+                        id_start,
+                        span: DeleteSpan { pos, signed_len },
+                    }) => json::MovableListOp::Delete {
+                        pos: *pos as i32,
+                        len: *signed_len as i32,
+                        start_id: register_id(id_start, peer_register),
+                    },
+                    InnerListOp::Move {
+                        from,
+                        elem_id: from_id,
+                        to,
+                    } => json::MovableListOp::Move {
+                        from: *from,
+                        to: *to,
+                        elem_id: register_idlp(from_id, peer_register),
+                    },
+                    InnerListOp::Set { elem_id, value } => {
+                        let value = if let LoroValue::Container(id) = value {
+                            if id.is_normal() {
+                                LoroValue::Container(register_container_id(
+                                    id.clone(),
+                                    peer_register,
+                                ))
                             } else {
                                 value.clone()
-                            };
-                            json::MovableListOp::Set {
-                                elem_id: register_idlp(elem_id, peer_register),
-                                value,
                             }
+                        } else {
+                            value.clone()
+                        };
+                        json::MovableListOp::Set {
+                            elem_id: register_idlp(elem_id, peer_register),
+                            value,
                         }
-                        _ => unreachable!(),
-                    }),
+                    }
                     _ => unreachable!(),
-                },
-                ContainerType::Text => match content {
-                    InnerContent::List(list) => JsonOpContent::Text(match list {
-                        InnerListOp::InsertText {
-                            slice,
-                            unicode_start: _,
-                            unicode_len: _,
-                            pos,
-                        } => {
-                            let text = String::from_utf8(slice.as_bytes().to_vec()).unwrap();
-                            json::TextOp::Insert { pos: *pos, text }
-                        }
-                        InnerListOp::Delete(DeleteSpanWithId {
-                            id_start,
-                            span: DeleteSpan { pos, signed_len },
-                        }) => json::TextOp::Delete {
-                            pos: *pos as i32,
-                            len: *signed_len as i32,
-                            start_id: register_id(id_start, peer_register),
-                        },
-                        InnerListOp::StyleStart {
-                            start,
-                            end,
-                            key,
-                            value,
-                            info,
-                        } => json::TextOp::Mark {
-                            start: *start,
-                            end: *end,
-                            style_key: key.to_string(),
-                            style_value: value.clone(),
-                            info: info.to_byte(),
-                        },
-                        InnerListOp::StyleEnd => json::TextOp::MarkEnd,
-                        _ => unreachable!(),
-                    }),
+                }),
+                _ => unreachable!(),
+            },
+            ContainerType::Text => match content {
+                InnerContent::List(list) => JsonOpContent::Text(match list {
+                    InnerListOp::InsertText {
+                        slice,
+                        unicode_start: _,
+                        unicode_len: _,
+                        pos,
+                    } => {
+                        let text = String::from_utf8(slice.as_bytes().to_vec()).unwrap();
+                        json::TextOp::Insert { pos: *pos, text }
+                    }
+                    InnerListOp::Delete(DeleteSpanWithId {
+                        id_start,
+                        span: DeleteSpan { pos, signed_len },
+                    }) => json::TextOp::Delete {
+                        pos: *pos as i32,
+                        len: *signed_len as i32,
+                        start_id: register_id(id_start, peer_register),
+                    },
+                    InnerListOp::StyleStart {
+                        start,
+                        end,
+                        key,
+                        value,
+                        info,
+                    } => json::TextOp::Mark {
+                        start: *start,
+                        end: *end,
+                        style_key: key.to_string(),
+                        style_value: value.clone(),
+                        info: info.to_byte(),
+                    },
+                    InnerListOp::StyleEnd => json::TextOp::MarkEnd,
                     _ => unreachable!(),
-                },
-                ContainerType::Map => match content {
-                    InnerContent::Map(MapSet { key, value }) => {
-                        JsonOpContent::Map(if let Some(v) = value {
-                            let value = if let LoroValue::Container(id) = v {
-                                if id.is_normal() {
-                                    LoroValue::Container(register_container_id(
-                                        id.clone(),
-                                        peer_register,
-                                    ))
-                                } else {
-                                    v.clone()
-                                }
+                }),
+                _ => unreachable!(),
+            },
+            ContainerType::Map => match content {
+                InnerContent::Map(MapSet { key, value }) => {
+                    JsonOpContent::Map(if let Some(v) = value {
+                        let value = if let LoroValue::Container(id) = v {
+                            if id.is_normal() {
+                                LoroValue::Container(register_container_id(
+                                    id.clone(),
+                                    peer_register,
+                                ))
                             } else {
                                 v.clone()
-                            };
-                            json::MapOp::Insert {
-                                key: key.to_string(),
-                                value,
                             }
                         } else {
-                            json::MapOp::Delete {
-                                key: key.to_string(),
-                            }
-                        })
-                    }
-
-                    _ => unreachable!(),
-                },
-
-                ContainerType::Tree => match content {
-                    InnerContent::Tree(op) => JsonOpContent::Tree(match &**op {
-                        TreeOp::Create {
-                            target,
-                            parent,
-                            position,
-                        } => json::TreeOp::Create {
-                            target: register_tree_id(target, peer_register),
-                            parent: parent.map(|p| register_tree_id(&p, peer_register)),
-                            fractional_index: position.clone(),
-                        },
-                        TreeOp::Move {
-                            target,
-                            parent,
-                            position,
-                        } => json::TreeOp::Move {
-                            target: register_tree_id(target, peer_register),
-                            parent: parent.map(|p| register_tree_id(&p, peer_register)),
-                            fractional_index: position.clone(),
-                        },
-                        TreeOp::Delete { target } => json::TreeOp::Delete {
-                            target: register_tree_id(target, peer_register),
-                        },
-                    }),
-                    _ => unreachable!(),
-                },
-                ContainerType::Unknown(_) => {
-                    let InnerContent::Future(FutureInnerContent::Unknown { prop, value }) = content
-                    else {
-                        unreachable!();
-                    };
-                    JsonOpContent::Future(json::FutureOpWrapper {
-                        prop: *prop,
-                        value: json::FutureOp::Unknown((**value).clone()),
+                            v.clone()
+                        };
+                        json::MapOp::Insert {
+                            key: key.to_string(),
+                            value,
+                        }
+                    } else {
+                        json::MapOp::Delete {
+                            key: key.to_string(),
+                        }
                     })
                 }
-                #[cfg(feature = "counter")]
-                ContainerType::Counter => {
-                    let InnerContent::Future(f) = content else {
-                        unreachable!()
-                    };
-                    match f {
-                        FutureInnerContent::Counter(x) => {
-                            JsonOpContent::Future(json::FutureOpWrapper {
-                                prop: 0,
-                                value: json::FutureOp::Counter(super::OwnedValue::F64(*x)),
-                            })
-                        }
-                        _ => unreachable!(),
+
+                _ => unreachable!(),
+            },
+
+            ContainerType::Tree => match content {
+                InnerContent::Tree(op) => JsonOpContent::Tree(match &**op {
+                    TreeOp::Create {
+                        target,
+                        parent,
+                        position,
+                    } => json::TreeOp::Create {
+                        target: register_tree_id(target, peer_register),
+                        parent: parent.map(|p| register_tree_id(&p, peer_register)),
+                        fractional_index: position.clone(),
+                    },
+                    TreeOp::Move {
+                        target,
+                        parent,
+                        position,
+                    } => json::TreeOp::Move {
+                        target: register_tree_id(target, peer_register),
+                        parent: parent.map(|p| register_tree_id(&p, peer_register)),
+                        fractional_index: position.clone(),
+                    },
+                    TreeOp::Delete { target } => json::TreeOp::Delete {
+                        target: register_tree_id(target, peer_register),
+                    },
+                }),
+                _ => unreachable!(),
+            },
+            ContainerType::Unknown(_) => {
+                let InnerContent::Future(FutureInnerContent::Unknown { prop, value }) = content
+                else {
+                    unreachable!();
+                };
+                JsonOpContent::Future(json::FutureOpWrapper {
+                    prop: *prop,
+                    value: json::FutureOp::Unknown((**value).clone()),
+                })
+            }
+            #[cfg(feature = "counter")]
+            ContainerType::Counter => {
+                let InnerContent::Future(f) = content else {
+                    unreachable!()
+                };
+                match f {
+                    FutureInnerContent::Counter(x) => {
+                        JsonOpContent::Future(json::FutureOpWrapper {
+                            prop: 0,
+                            value: json::FutureOp::Counter(super::OwnedValue::F64(*x)),
+                        })
                     }
+                    _ => unreachable!(),
                 }
-            };
-            ops.push(json::JsonOp {
-                counter: *counter,
-                container,
-                content: op,
-            });
-        }
-        let c = json::JsonChange {
-            id: register_id(&change.id, peer_register),
-            ops,
-            deps: change
-                .deps
-                .iter()
-                .map(|id| register_id(&id, peer_register))
-                .collect(),
-            lamport: change.lamport,
-            timestamp: change.timestamp,
-            msg: change.message().map(|x| x.to_string()),
+            }
         };
-
-        changes.push(c);
+        ops.push(json::JsonOp {
+            counter: *counter,
+            container,
+            content: op,
+        });
     }
-    changes
+    ops
 }
 
-fn decode_changes(json: JsonSchema, arena: &SharedArena) -> LoroResult<Vec<Change>> {
+pub(super) fn decode_changes(json: JsonSchema, arena: &SharedArena) -> LoroResult<Vec<Change>> {
     let JsonSchema { peers, changes, .. } = json;
     let mut ans = Vec::with_capacity(changes.len());
     for json::JsonChange {
@@ -472,6 +543,7 @@ fn decode_changes(json: JsonSchema, arena: &SharedArena) -> LoroResult<Vec<Chang
             lamport,
             ops,
             commit_msg: msg.map(|x| x.into()),
+            signature: None,
         };
         ans.push(change);
     }
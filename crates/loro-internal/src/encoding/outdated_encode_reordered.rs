@@ -33,10 +33,10 @@ use self::encode::{encode_changes, encode_ops, init_encode, TempOp};
 
 use super::{
     arena::*,
-    value::{Value, ValueDecodedArenasTrait, ValueKind, ValueReader, ValueWriter},
+    value::{Value, ValueDecodedArenasTrait, ValueKind, ValueLimits, ValueReader, ValueWriter},
     ImportBlobMetadata,
 };
-use super::{ImportStatus, ParsedHeaderAndBody};
+use super::{import_value_limits, ImportStatus, ParsedHeaderAndBody};
 
 pub(crate) use crate::encoding::value_register::ValueRegister;
 
@@ -147,6 +147,7 @@ pub(crate) fn decode_updates(oplog: &mut OpLog, bytes: &[u8]) -> LoroResult<Vec<
         &oplog.arena,
         &mut arenas,
         false,
+        import_value_limits(oplog),
     )?
     .ops_map;
     let DecodedArenas {
@@ -171,6 +172,12 @@ pub(crate) fn decode_updates(oplog: &mut OpLog, bytes: &[u8]) -> LoroResult<Vec<
 pub fn decode_import_blob_meta(parsed: ParsedHeaderAndBody) -> LoroResult<ImportBlobMetadata> {
     let iterators = serde_columnar::iter_from_bytes::<EncodedDoc>(parsed.body)?;
     let DecodedArenas { peer_ids, .. } = decode_arena(&iterators.arenas)?;
+    if iterators.start_counters.len() > peer_ids.peer_ids.len()
+        || iterators.start_counters.iter().any(|&counter| counter < 0)
+    {
+        return Err(LoroError::DecodeDataCorruptionError);
+    }
+
     let start_vv: VersionVector = iterators
         .start_counters
         .iter()
@@ -186,8 +193,16 @@ pub fn decode_import_blob_meta(parsed: ParsedHeaderAndBody) -> LoroResult<Import
     let frontiers = iterators
         .start_frontiers
         .iter()
-        .map(|x| ID::new(peer_ids.peer_ids[x.0], x.1))
-        .collect();
+        .map(|x| {
+            Ok(ID::new(
+                *peer_ids
+                    .peer_ids
+                    .get(x.0)
+                    .ok_or(LoroError::DecodeDataCorruptionError)?,
+                x.1,
+            ))
+        })
+        .collect::<LoroResult<_>>()?;
     let mut end_vv_counters = iterators.start_counters;
     let mut change_num = 0;
     let mut start_timestamp = Timestamp::MAX;
@@ -200,7 +215,13 @@ pub fn decode_import_blob_meta(parsed: ParsedHeaderAndBody) -> LoroResult<Import
             timestamp,
             ..
         } = iter?;
-        end_vv_counters[peer_idx] += len as Counter;
+        let counter = end_vv_counters
+            .get_mut(peer_idx)
+            .ok_or(LoroError::DecodeDataCorruptionError)?;
+        *counter = Counter::try_from(len)
+            .ok()
+            .and_then(|len| counter.checked_add(len))
+            .ok_or(LoroError::DecodeDataCorruptionError)?;
         start_timestamp = start_timestamp.min(timestamp);
         end_timestamp = end_timestamp.max(timestamp);
         change_num += 1;
@@ -210,9 +231,11 @@ pub fn decode_import_blob_meta(parsed: ParsedHeaderAndBody) -> LoroResult<Import
         mode: match parsed.mode {
             super::EncodeMode::OutdatedRle => super::EncodedBlobMode::OutdatedRle,
             super::EncodeMode::OutdatedSnapshot => super::EncodedBlobMode::OutdatedSnapshot,
-            super::EncodeMode::FastSnapshot => super::EncodedBlobMode::Snapshot,
+            super::EncodeMode::FastSnapshot
+            | super::EncodeMode::FastSnapshotZstd
+            | super::EncodeMode::FastSnapshotEncrypted => super::EncodedBlobMode::Snapshot,
             super::EncodeMode::FastUpdates => super::EncodedBlobMode::Updates,
-            super::EncodeMode::Auto => unreachable!(),
+            super::EncodeMode::FastStream | super::EncodeMode::Auto => unreachable!(),
         },
         start_frontiers: frontiers,
         partial_start_vv: start_vv,
@@ -304,7 +327,10 @@ fn decode_changes<'a>(
         }
 
         let counter = counters[peer_idx];
-        counters[peer_idx] += len as Counter;
+        counters[peer_idx] = Counter::try_from(len)
+            .ok()
+            .and_then(|len| counter.checked_add(len))
+            .ok_or(LoroError::DecodeDataCorruptionError)?;
         let peer = peer_ids.peer_ids[peer_idx];
         let mut change: Change = Change {
             id: ID::new(peer, counter),
@@ -314,11 +340,14 @@ fn decode_changes<'a>(
             commit_msg: if msg_idx_plus_one == 0 {
                 None
             } else {
-                let key = keys.get(msg_idx_plus_one as usize - 1).unwrap();
+                let key = keys
+                    .get((msg_idx_plus_one as usize).wrapping_sub(1))
+                    .ok_or(LoroError::DecodeDataCorruptionError)?;
                 let s = key.to_string();
                 Some(Arc::from(s))
             },
             timestamp,
+            signature: None,
         };
 
         if dep_on_self {
@@ -331,9 +360,11 @@ fn decode_changes<'a>(
 
         for _ in 0..deps_len {
             let dep = deps.next().ok_or(LoroError::DecodeDataCorruptionError)??;
-            change
-                .deps
-                .push(ID::new(peer_ids.peer_ids[dep.peer_idx], dep.counter));
+            let dep_peer = peer_ids
+                .peer_ids
+                .get(dep.peer_idx)
+                .ok_or(LoroError::DecodeDataCorruptionError)?;
+            change.deps.push(ID::new(*dep_peer, dep.counter));
         }
 
         let ops = ops_map
@@ -341,7 +372,9 @@ fn decode_changes<'a>(
             .ok_or(LoroError::DecodeDataCorruptionError)?;
         while len > 0 {
             let op = ops.pop().ok_or(LoroError::DecodeDataCorruptionError)?;
-            len -= op.atom_len();
+            len = len
+                .checked_sub(op.atom_len())
+                .ok_or(LoroError::DecodeDataCorruptionError)?;
             change.ops.push(op);
         }
 
@@ -365,8 +398,9 @@ fn extract_ops(
     shared_arena: &SharedArena,
     arenas: &mut DecodedArenas<'_>,
     should_extract_ops_with_ids: bool,
+    limits: ValueLimits,
 ) -> LoroResult<ExtractedOps> {
-    let mut value_reader = ValueReader::new(raw_values);
+    let mut value_reader = ValueReader::with_limits(raw_values, limits);
     let mut ops_map: FxHashMap<PeerID, Vec<Op>> = FxHashMap::default();
     let containers: Vec<_> = arenas
         .containers
@@ -374,7 +408,7 @@ fn extract_ops(
         .map(|x| x.as_container_id(arenas))
         .try_collect()?;
     let mut ops = Vec::new();
-    let positions = std::mem::take(&mut arenas.positions).parse_to_positions();
+    let positions = std::mem::take(&mut arenas.positions).parse_to_positions()?;
     for op in iter {
         let EncodedOp {
             container_index,
@@ -681,6 +715,7 @@ pub(crate) fn decode_snapshot(doc: &LoroDoc, bytes: &[u8]) -> LoroResult<()> {
         &oplog.arena,
         &mut arenas,
         true,
+        import_value_limits(oplog),
     )?;
     let DecodedArenas {
         peer_ids,
@@ -698,6 +733,10 @@ pub(crate) fn decode_snapshot(doc: &LoroDoc, bytes: &[u8]) -> LoroResult<()> {
         deps,
         ops_map,
     )?;
+    if let Some(options) = oplog.configure.import_options() {
+        options.check_changes(&changes, &oplog)?;
+    }
+    oplog.verify_change_signatures(&changes)?;
 
     let ImportChangesResult {
         latest_ids,
@@ -720,10 +759,12 @@ pub(crate) fn decode_snapshot(doc: &LoroDoc, bytes: &[u8]) -> LoroResult<()> {
         ops,
         &oplog,
         &peer_ids,
-    )
-    .unwrap();
+    )?;
 
-    assert!(pending_changes.is_empty());
+    if !pending_changes.is_empty() {
+        // The snapshot should include all the dependencies of its changes
+        return Err(LoroError::DecodeDataCorruptionError);
+    }
     // we cannot assert this because frontiers of oplog is not updated yet when batch_importing
     // assert_eq!(&state.frontiers, oplog.frontiers());
     if !oplog.pending_changes.is_empty() {
@@ -737,6 +778,7 @@ pub(crate) fn decode_snapshot(doc: &LoroDoc, bytes: &[u8]) -> LoroResult<()> {
                 Ok(ImportStatus {
                     success: Default::default(),
                     pending: None,
+                    quarantined: None,
                 })
             },
             "".into(),
@@ -866,7 +908,10 @@ fn decode_snapshot_states(
             // There are two cases:
             // 1. The container is encoded as unknown, but now it's known. we should rebuild the state by `diff_calc`.
             // 2. The container is unknown, and it's still unknown. we should init an unknown state and emit an unknown event.
-            let container_id = containers[container_index as usize].clone();
+            let container_id = containers
+                .get(container_index as usize)
+                .ok_or(LoroError::DecodeDataCorruptionError)?
+                .clone();
             let container = state.arena.register_container(&container_id);
             unknown_containers.push(container);
             if container.is_unknown() {
@@ -905,7 +950,7 @@ fn decode_snapshot_states(
                 if op_len == 0 {
                     false
                 } else {
-                    op_len -= x.op.atom_len() as u32;
+                    op_len = op_len.saturating_sub(x.op.atom_len() as u32);
                     ops_index += 1;
                     true
                 }
@@ -1316,6 +1361,13 @@ pub(crate) fn decode_op(
     prop: i32,
     op_id: ID,
 ) -> LoroResult<crate::op::InnerContent> {
+    let peer = |peer_idx: usize| {
+        arenas
+            .peers()
+            .get(peer_idx)
+            .copied()
+            .ok_or(LoroError::DecodeDataCorruptionError)
+    };
     let content = match cid.container_type() {
         ContainerType::Text => match value {
             Value::Str(s) => {
@@ -1330,13 +1382,15 @@ pub(crate) fn decode_op(
                 )
             }
             Value::DeleteSeq => {
-                let del_start = del_iter.next().unwrap()?;
+                let del_start = del_iter
+                    .next()
+                    .ok_or(LoroError::DecodeDataCorruptionError)??;
                 let peer_idx = del_start.peer_idx;
                 let cnt = del_start.counter;
                 let len = del_start.len;
                 crate::op::InnerContent::List(crate::container::list::list_op::InnerListOp::Delete(
                     DeleteSpanWithId::new(
-                        ID::new(arenas.peers()[peer_idx], cnt as Counter),
+                        ID::new(peer(peer_idx)?, cnt as Counter),
                         prop as isize,
                         len,
                     ),
@@ -1345,7 +1399,9 @@ pub(crate) fn decode_op(
             Value::MarkStart(mark) => crate::op::InnerContent::List(
                 crate::container::list::list_op::InnerListOp::StyleStart {
                     start: prop as u32,
-                    end: prop as u32 + mark.len,
+                    end: (prop as u32)
+                        .checked_add(mark.len)
+                        .ok_or(LoroError::DecodeDataCorruptionError)?,
                     key: mark.key,
                     value: mark.value,
                     info: TextStyleInfoFlag::from_byte(mark.info),
@@ -1354,7 +1410,7 @@ pub(crate) fn decode_op(
             Value::Null => crate::op::InnerContent::List(
                 crate::container::list::list_op::InnerListOp::StyleEnd,
             ),
-            _ => unreachable!(),
+            _ => return Err(LoroError::DecodeDataCorruptionError),
         },
         ContainerType::Map => {
             let key = arenas
@@ -1372,14 +1428,19 @@ pub(crate) fn decode_op(
                         value: Some(v.clone()),
                     })
                 }
-                _ => unreachable!(),
+                _ => return Err(LoroError::DecodeDataCorruptionError),
             }
         }
         ContainerType::List => {
             let pos = prop as usize;
             match value {
                 Value::LoroValue(arr) => {
-                    let range = shared_arena.alloc_values(arr.into_list().unwrap().iter().cloned());
+                    let range = shared_arena.alloc_values(
+                        arr.into_list()
+                            .map_err(|_| LoroError::DecodeDataCorruptionError)?
+                            .iter()
+                            .cloned(),
+                    );
                     crate::op::InnerContent::List(
                         crate::container::list::list_op::InnerListOp::Insert {
                             slice: SliceRange::new(range.start as u32..range.end as u32),
@@ -1388,21 +1449,23 @@ pub(crate) fn decode_op(
                     )
                 }
                 Value::DeleteSeq => {
-                    let del_start = del_iter.next().unwrap()?;
+                    let del_start = del_iter
+                        .next()
+                        .ok_or(LoroError::DecodeDataCorruptionError)??;
                     let peer_idx = del_start.peer_idx;
                     let cnt = del_start.counter;
                     let len = del_start.len;
                     crate::op::InnerContent::List(
                         crate::container::list::list_op::InnerListOp::Delete(
                             DeleteSpanWithId::new(
-                                ID::new(arenas.peers()[peer_idx], cnt as Counter),
+                                ID::new(peer(peer_idx)?, cnt as Counter),
                                 pos as isize,
                                 len,
                             ),
                         ),
                     )
                 }
-                _ => unreachable!(),
+                _ => return Err(LoroError::DecodeDataCorruptionError),
             }
         }
         ContainerType::Tree => match value {
@@ -1410,15 +1473,12 @@ pub(crate) fn decode_op(
                 arenas.decode_tree_op(positions, op, op_id)?,
             )),
             Value::RawTreeMove(op) => {
-                let subject = TreeID::new(
-                    arenas.peers()[op.subject_peer_idx],
-                    op.subject_cnt as Counter,
-                );
+                let subject = TreeID::new(peer(op.subject_peer_idx)?, op.subject_cnt as Counter);
                 let parent = if op.is_parent_null {
                     None
                 } else {
                     let parent_id =
-                        TreeID::new(arenas.peers()[op.parent_peer_idx], op.parent_cnt as Counter);
+                        TreeID::new(peer(op.parent_peer_idx)?, op.parent_cnt as Counter);
                     if parent_id.is_deleted_root() {
                         return Ok(crate::op::InnerContent::Tree(Arc::new(TreeOp::Delete {
                             target: subject,
@@ -1428,7 +1488,12 @@ pub(crate) fn decode_op(
                     Some(parent_id)
                 };
 
-                let fi = FractionalIndex::from_bytes(positions[op.position_idx].clone());
+                let fi = FractionalIndex::from_bytes(
+                    positions
+                        .get(op.position_idx)
+                        .ok_or(LoroError::DecodeDataCorruptionError)?
+                        .clone(),
+                );
                 let is_create = subject.id() == op_id;
                 let ans = if is_create {
                     TreeOp::Create {
@@ -1445,15 +1510,18 @@ pub(crate) fn decode_op(
                 };
                 crate::op::InnerContent::Tree(Arc::new(ans))
             }
-            _ => {
-                unreachable!()
-            }
+            _ => return Err(LoroError::DecodeDataCorruptionError),
         },
         ContainerType::MovableList => {
             let pos = prop as usize;
             match value {
                 Value::LoroValue(arr) => {
-                    let range = shared_arena.alloc_values(arr.into_list().unwrap().iter().cloned());
+                    let range = shared_arena.alloc_values(
+                        arr.into_list()
+                            .map_err(|_| LoroError::DecodeDataCorruptionError)?
+                            .iter()
+                            .cloned(),
+                    );
                     crate::op::InnerContent::List(
                         crate::container::list::list_op::InnerListOp::Insert {
                             slice: SliceRange::new(range.start as u32..range.end as u32),
@@ -1462,14 +1530,16 @@ pub(crate) fn decode_op(
                     )
                 }
                 Value::DeleteSeq => {
-                    let del_start = del_iter.next().unwrap()?;
+                    let del_start = del_iter
+                        .next()
+                        .ok_or(LoroError::DecodeDataCorruptionError)??;
                     let peer_idx = del_start.peer_idx;
                     let cnt = del_start.counter;
                     let len = del_start.len;
                     crate::op::InnerContent::List(
                         crate::container::list::list_op::InnerListOp::Delete(
                             DeleteSpanWithId::new(
-                                ID::new(arenas.peers()[peer_idx], cnt as Counter),
+                                ID::new(peer(peer_idx)?, cnt as Counter),
                                 pos as isize,
                                 len,
                             ),
@@ -1483,7 +1553,7 @@ pub(crate) fn decode_op(
                 } => crate::op::InnerContent::List(
                     crate::container::list::list_op::InnerListOp::Move {
                         from: from as u32,
-                        elem_id: IdLp::new(arenas.peers()[from_idx], lamport as Lamport),
+                        elem_id: IdLp::new(peer(from_idx)?, lamport as Lamport),
                         to: prop as u32,
                     },
                 ),
@@ -1493,18 +1563,18 @@ pub(crate) fn decode_op(
                     value,
                 } => crate::op::InnerContent::List(
                     crate::container::list::list_op::InnerListOp::Set {
-                        elem_id: IdLp::new(arenas.peers()[peer_idx], lamport as Lamport),
+                        elem_id: IdLp::new(peer(peer_idx)?, lamport as Lamport),
                         value,
                     },
                 ),
-                _ => unreachable!(),
+                _ => return Err(LoroError::DecodeDataCorruptionError),
             }
         }
         #[cfg(feature = "counter")]
         ContainerType::Counter => match value {
             Value::F64(c) => crate::op::InnerContent::Future(FutureInnerContent::Counter(c)),
             Value::I64(c) => crate::op::InnerContent::Future(FutureInnerContent::Counter(c as f64)),
-            _ => unreachable!(),
+            _ => return Err(LoroError::DecodeDataCorruptionError),
         },
         // NOTE: The future container type need also try to parse the unknown type
         ContainerType::Unknown(_) => crate::op::InnerContent::Future(FutureInnerContent::Unknown {
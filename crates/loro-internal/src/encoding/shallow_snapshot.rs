@@ -8,7 +8,7 @@ use tracing::debug;
 use crate::{
     container::list::list_op::InnerListOp,
     dag::{Dag, DagUtils},
-    encoding::fast_snapshot::{Snapshot, _encode_snapshot},
+    encoding::fast_snapshot::Snapshot,
     state::container_store::FRONTIERS_KEY,
     version::Frontiers,
     LoroDoc,
@@ -20,16 +20,6 @@ const MAX_OPS_NUM_TO_ENCODE_WITHOUT_LATEST_STATE: usize = 16;
 const MAX_OPS_NUM_TO_ENCODE_WITHOUT_LATEST_STATE: usize = 256;
 
 #[tracing::instrument(skip_all)]
-pub(crate) fn export_shallow_snapshot<W: std::io::Write>(
-    doc: &LoroDoc,
-    start_from: &Frontiers,
-    w: &mut W,
-) -> Result<Frontiers, LoroEncodeError> {
-    let (snapshot, start_from) = export_shallow_snapshot_inner(doc, start_from)?;
-    _encode_snapshot(snapshot, w);
-    Ok(start_from)
-}
-
 pub(crate) fn export_shallow_snapshot_inner(
     doc: &LoroDoc,
     start_from: &Frontiers,
@@ -137,11 +127,10 @@ fn has_unknown_container<'a>(mut cids: impl Iterator<Item = &'a ContainerID>) ->
     cids.any(|cid| matches!(cid.container_type(), ContainerType::Unknown(_)))
 }
 
-pub(crate) fn export_state_only_snapshot<W: std::io::Write>(
+pub(crate) fn export_state_only_snapshot_inner(
     doc: &LoroDoc,
     start_from: &Frontiers,
-    w: &mut W,
-) -> Result<Frontiers, LoroEncodeError> {
+) -> Result<(Snapshot, Frontiers), LoroEncodeError> {
     let oplog = doc.oplog().try_lock().unwrap();
     let start_from = calc_shallow_doc_start(&oplog, start_from);
     let mut start_vv = oplog.dag().frontiers_to_vv(&start_from).unwrap();
@@ -182,7 +171,6 @@ pub(crate) fn export_state_only_snapshot<W: std::io::Write>(
         state_bytes: None,
         shallow_root_state_bytes: shallow_state_bytes,
     };
-    _encode_snapshot(snapshot, w);
 
     if state_frontiers != start_from {
         doc.checkout_without_emitting(&state_frontiers, false)
@@ -194,7 +182,7 @@ pub(crate) fn export_state_only_snapshot<W: std::io::Write>(
     }
 
     doc.drop_pending_events();
-    Ok(start_from)
+    Ok((snapshot, start_from))
 }
 
 fn cids_to_bytes(
@@ -247,11 +235,10 @@ fn calc_shallow_doc_start(oplog: &crate::OpLog, frontiers: &Frontiers) -> Fronti
     ans
 }
 
-pub(crate) fn encode_snapshot_at<W: std::io::Write>(
+pub(crate) fn encode_snapshot_at_inner(
     doc: &LoroDoc,
     frontiers: &Frontiers,
-    w: &mut W,
-) -> Result<(), LoroEncodeError> {
+) -> Result<Snapshot, LoroEncodeError> {
     let was_detached = doc.is_detached();
     let version_before_start = doc.oplog_frontiers();
     doc.checkout_without_emitting(frontiers, true).unwrap();
@@ -288,16 +275,11 @@ pub(crate) fn encode_snapshot_at<W: std::io::Write>(
         let state_kv = state.store.get_kv().clone();
         state_kv.retain_keys(&alive_c_bytes);
         let bytes = state_kv.export();
-        _encode_snapshot(
-            Snapshot {
-                oplog_bytes,
-                state_bytes: Some(bytes),
-                shallow_root_state_bytes: Bytes::new(),
-            },
-            w,
-        );
-
-        Ok(())
+        Ok(Snapshot {
+            oplog_bytes,
+            state_bytes: Some(bytes),
+            shallow_root_state_bytes: Bytes::new(),
+        })
     };
     doc.checkout_without_emitting(&version_before_start, false)
         .unwrap();
@@ -0,0 +1,361 @@
+//! The streaming encoding of [crate::LoroDoc::export_to_writer].
+//!
+//! The checksum of the other encodings is in the header, so their bodies are encoded before
+//! any byte is written. The body of this encoding is written while it's encoded, and its
+//! checksum follows it.
+//!
+//! # Layout
+//!
+//! - the header, whose mode is [EncodeMode::FastStream] and whose checksum is zero
+//! - u16 in big endian for the mode of the wrapped blob, which is [EncodeMode::FastUpdates]
+//!   or one of the fast snapshot modes
+//! - the parts of the wrapped blob, each of which is a sequence of frames ending with an
+//!   empty frame. A frame is
+//!   - u32 in little endian for len of the frame bytes
+//!   - frame bytes
+//! - u32 in little endian for the xxh32 checksum of the bytes from the mode in the header
+//!   to the end of the last part
+//!
+//! A snapshot has three parts, the bytes of the oplog store, the state store and the shallow
+//! root state store. Updates have one part, where each frame is a change block.
+use std::{
+    borrow::Cow,
+    io::{self, Read, Write},
+};
+
+use bytes::Bytes;
+use loro_common::{LoroError, LoroResult};
+use xxhash_rust::xxh32::Xxh32;
+
+use super::{
+    block_chunks, encode_chunks, fast_snapshot, parse_header_and_body, EncodeMode, MAGIC_BYTES,
+    MIN_HEADER_SIZE, XXH_SEED,
+};
+
+/// The bytes of a part are buffered up to this length before they are written as a frame
+const FRAME_LEN: usize = 64 * 1024;
+
+/// Writes a [EncodeMode::FastStream] blob while its body is encoded.
+///
+/// The bytes written by [Write] are framed into the current part, which is ended by
+/// [StreamWriter::end_part].
+pub(super) struct StreamWriter<'a> {
+    w: &'a mut dyn Write,
+    hasher: Xxh32,
+    buf: Vec<u8>,
+}
+
+impl<'a> StreamWriter<'a> {
+    /// Write the header and the mode of the wrapped blob
+    pub fn new(w: &'a mut dyn Write, mode: EncodeMode) -> io::Result<Self> {
+        let mut header = [0; MIN_HEADER_SIZE];
+        header[..4].copy_from_slice(&MAGIC_BYTES);
+        header[20..].copy_from_slice(&EncodeMode::FastStream.to_bytes());
+        w.write_all(&header)?;
+        let mut ans = Self {
+            w,
+            hasher: Xxh32::new(XXH_SEED),
+            buf: Vec::new(),
+        };
+        ans.hasher.update(&header[20..]);
+        ans.write_raw(&mode.to_bytes())?;
+        Ok(ans)
+    }
+
+    fn write_raw(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.hasher.update(bytes);
+        self.w.write_all(bytes)
+    }
+
+    fn write_frames(&mut self, bytes: &[u8]) -> io::Result<()> {
+        for frame in bytes.chunks(u32::MAX as usize) {
+            self.write_raw(&(frame.len() as u32).to_le_bytes())?;
+            self.write_raw(frame)?;
+        }
+        Ok(())
+    }
+
+    fn write_buf(&mut self) -> io::Result<()> {
+        let mut buf = std::mem::take(&mut self.buf);
+        self.write_frames(&buf)?;
+        buf.clear();
+        self.buf = buf;
+        Ok(())
+    }
+
+    /// Write a change block of the updates as a frame
+    pub fn write_block(&mut self, block: &[u8]) -> io::Result<()> {
+        assert!(!block.is_empty() && self.buf.is_empty());
+        let len = u32::try_from(block.len()).map_err(|_| {
+            io::Error::new(io::ErrorKind::InvalidInput, "the change block is too large")
+        })?;
+        self.write_raw(&len.to_le_bytes())?;
+        self.write_raw(block)
+    }
+
+    /// Write the parts of an encoded snapshot
+    pub fn write_snapshot(&mut self, snapshot: fast_snapshot::Snapshot) -> io::Result<()> {
+        for part in snapshot.into_parts() {
+            self.write_all(&part)?;
+            self.end_part()?;
+        }
+        Ok(())
+    }
+
+    /// End the current part with an empty frame
+    pub fn end_part(&mut self) -> io::Result<()> {
+        self.write_buf()?;
+        self.write_raw(&0u32.to_le_bytes())
+    }
+
+    /// Write the checksum after the last part
+    pub fn finish(mut self) -> io::Result<()> {
+        assert!(self.buf.is_empty(), "the last part is not ended");
+        self.w.write_all(&self.hasher.digest().to_le_bytes())?;
+        self.w.flush()
+    }
+}
+
+impl Write for StreamWriter<'_> {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        if self.buf.len() + bytes.len() > FRAME_LEN {
+            self.write_buf()?;
+        }
+
+        if bytes.len() >= FRAME_LEN {
+            self.write_frames(bytes)?;
+        } else {
+            self.buf.extend_from_slice(bytes);
+        }
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.write_buf()?;
+        self.w.flush()
+    }
+}
+
+/// Reads the body of a fast blob after its header, hashing the bytes read for the checksum.
+///
+/// The parts of a [EncodeMode::FastStream] blob are read frame by frame, and the parts of
+/// the other blobs by their length prefixes.
+pub(crate) struct BodyReader<'a> {
+    r: &'a mut dyn Read,
+    hasher: Xxh32,
+    /// The checksum in the header, or `None` if it follows the body
+    checksum: Option<u32>,
+}
+
+impl<'a> BodyReader<'a> {
+    /// Start reading the body after `header` from `r`.
+    ///
+    /// The returned mode is the mode of the wrapped blob if it's a [EncodeMode::FastStream] blob.
+    pub fn new(
+        r: &'a mut dyn Read,
+        header: &[u8; MIN_HEADER_SIZE],
+    ) -> LoroResult<(Self, EncodeMode)> {
+        let mode = parse_header_and_body(header, false)?.mode;
+        let mut ans = Self {
+            r,
+            hasher: Xxh32::new(XXH_SEED),
+            checksum: Some(u32::from_le_bytes(header[16..20].try_into().unwrap())),
+        };
+        ans.hasher.update(&header[20..]);
+        if mode != EncodeMode::FastStream {
+            return Ok((ans, mode));
+        }
+
+        ans.checksum = None;
+        let mut mode = [0; 2];
+        ans.read_exact(&mut mode)?;
+        let mode = EncodeMode::try_from(mode)?;
+        if mode != EncodeMode::FastUpdates && !mode.is_fast_snapshot() {
+            return Err(LoroError::DecodeError("Invalid encode mode".into()));
+        }
+
+        Ok((ans, mode))
+    }
+
+    fn is_framed(&self) -> bool {
+        self.checksum.is_none()
+    }
+
+    fn read_u32_le(&mut self) -> LoroResult<u32> {
+        let mut buf = [0; 4];
+        self.read_exact(&mut buf)?;
+        Ok(u32::from_le_bytes(buf))
+    }
+
+    /// Read `len` bytes into `buf`. The length is untrusted, so the buffer grows with the
+    /// bytes actually read.
+    fn read_to_buf(&mut self, len: u64, buf: &mut Vec<u8>) -> LoroResult<()> {
+        let start = buf.len();
+        self.by_ref().take(len).read_to_end(buf)?;
+        if (buf.len() - start) as u64 != len {
+            return Err(LoroError::DecodeDataCorruptionError);
+        }
+
+        Ok(())
+    }
+
+    /// Read the next part of a snapshot
+    pub fn read_part(&mut self) -> LoroResult<Bytes> {
+        let mut buf = Vec::new();
+        if !self.is_framed() {
+            let len = self.read_u32_le()?;
+            self.read_to_buf(len as u64, &mut buf)?;
+            return Ok(buf.into());
+        }
+
+        loop {
+            match self.read_u32_le()? {
+                0 => return Ok(buf.into()),
+                len => self.read_to_buf(len as u64, &mut buf)?,
+            }
+        }
+    }
+
+    /// Skip the next part of a snapshot without keeping its bytes
+    pub fn skip_part(&mut self) -> LoroResult<()> {
+        if !self.is_framed() {
+            let len = self.read_u32_le()?;
+            return self.skip(len as u64);
+        }
+
+        loop {
+            match self.read_u32_le()? {
+                0 => return Ok(()),
+                len => self.skip(len as u64)?,
+            }
+        }
+    }
+
+    fn skip(&mut self, len: u64) -> LoroResult<()> {
+        if io::copy(&mut self.by_ref().take(len), &mut io::sink())? != len {
+            return Err(LoroError::DecodeDataCorruptionError);
+        }
+
+        Ok(())
+    }
+
+    /// Read the next change block of the updates, or `None` at the end of the updates
+    pub fn next_block(&mut self) -> LoroResult<Option<Bytes>> {
+        let len = if self.is_framed() {
+            match self.read_u32_le()? {
+                0 => return Ok(None),
+                len => len as u64,
+            }
+        } else {
+            // The updates end with the input
+            let mut first = [0];
+            if self.read(&mut first)? == 0 {
+                return Ok(None);
+            }
+
+            leb128::read::unsigned(&mut first.as_slice().chain(self.by_ref())).map_err(
+                |e| match e {
+                    leb128::read::Error::IoError(e) => e.into(),
+                    leb128::read::Error::Overflow => LoroError::DecodeDataCorruptionError,
+                },
+            )?
+        };
+
+        let mut buf = Vec::new();
+        self.read_to_buf(len, &mut buf)?;
+        Ok(Some(buf.into()))
+    }
+
+    /// Check the checksum after the whole body is read
+    pub fn finish(mut self) -> LoroResult<()> {
+        let expected = match self.checksum {
+            Some(checksum) => checksum,
+            None => {
+                let mut buf = [0; 4];
+                self.r.read_exact(&mut buf)?;
+                u32::from_le_bytes(buf)
+            }
+        };
+
+        if self.hasher.digest() != expected {
+            return Err(LoroError::DecodeChecksumMismatchError);
+        }
+
+        Ok(())
+    }
+}
+
+impl Read for BodyReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.r.read(buf)?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+}
+
+/// Convert a [EncodeMode::FastStream] blob into the blob it wraps, so that it can be imported
+/// like the others. The other blobs are returned as they are.
+pub(crate) fn unwrap_stream(bytes: &[u8]) -> LoroResult<Cow<'_, [u8]>> {
+    if bytes.len() < MIN_HEADER_SIZE
+        || bytes[20..MIN_HEADER_SIZE] != EncodeMode::FastStream.to_bytes()
+    {
+        return Ok(Cow::Borrowed(bytes));
+    }
+
+    let (header, mut rest) = bytes.split_at(MIN_HEADER_SIZE);
+    let (mut body, mode) = BodyReader::new(&mut rest, header.try_into().unwrap())?;
+    let chunks = if mode.is_fast_snapshot() {
+        fast_snapshot::_read_snapshot(&mut body)?.into_chunks()
+    } else {
+        let mut blocks = Vec::new();
+        while let Some(block) = body.next_block()? {
+            blocks.push(block);
+        }
+        block_chunks(blocks)
+    };
+
+    body.finish()?;
+    Ok(Cow::Owned(encode_chunks(mode, &chunks)))
+}
+
+/// Fails the reads once more than `max` bytes are read, so that an input that exceeds
+/// [super::ImportOptions::max_input_bytes] is rejected without being buffered
+pub(crate) struct LimitedReader<R> {
+    r: R,
+    max: Option<usize>,
+    read: usize,
+}
+
+impl<R: Read> LimitedReader<R> {
+    pub fn new(r: R, max: Option<usize>) -> Self {
+        Self { r, max, read: 0 }
+    }
+
+    /// Whether the input is longer than the limit
+    pub fn is_exceeded(&self) -> bool {
+        self.max.is_some_and(|max| self.read > max)
+    }
+}
+
+impl<R: Read> Read for LimitedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let Some(max) = self.max else {
+            return self.r.read(buf);
+        };
+
+        // Read at most one byte more than the limit to tell whether the input exceeds it
+        let len = buf
+            .len()
+            .min(max.saturating_sub(self.read).saturating_add(1));
+        let n = self.r.read(&mut buf[..len])?;
+        self.read += n;
+        if self.is_exceeded() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the input exceeds max_input_bytes",
+            ));
+        }
+
+        Ok(n)
+    }
+}
@@ -72,8 +72,8 @@ pub enum LoroValueKind {
     ContainerType,
 }
 impl LoroValueKind {
-    fn from_u8(kind: u8) -> Self {
-        match kind {
+    fn from_u8(kind: u8) -> LoroResult<Self> {
+        Ok(match kind {
             0 => LoroValueKind::Null,
             1 => LoroValueKind::True,
             2 => LoroValueKind::False,
@@ -84,8 +84,8 @@ impl LoroValueKind {
             7 => LoroValueKind::List,
             8 => LoroValueKind::Map,
             9 => LoroValueKind::ContainerType,
-            _ => unreachable!(),
-        }
+            _ => return Err(LoroError::DecodeDataCorruptionError),
+        })
     }
 
     fn to_u8(&self) -> u8 {
@@ -495,7 +495,9 @@ impl EncodedTreeMove {
         let parent = if self.is_parent_null {
             None
         } else {
-            let EncodedTreeID { peer_idx, counter } = tree_ids[self.parent_idx];
+            let EncodedTreeID { peer_idx, counter } = *tree_ids
+                .get(self.parent_idx)
+                .ok_or(LoroError::DecodeDataCorruptionError)?;
             Some(TreeID::new(
                 *(peer_ids
                     .get(peer_idx)
@@ -507,10 +509,14 @@ impl EncodedTreeMove {
         let position = if is_delete {
             None
         } else {
-            let bytes = &positions[self.position];
+            let bytes = positions
+                .get(self.position)
+                .ok_or(LoroError::DecodeDataCorruptionError)?;
             Some(FractionalIndex::from_bytes(bytes.clone()))
         };
-        let EncodedTreeID { peer_idx, counter } = tree_ids[self.target_idx];
+        let EncodedTreeID { peer_idx, counter } = *tree_ids
+            .get(self.target_idx)
+            .ok_or(LoroError::DecodeDataCorruptionError)?;
         let target = TreeID::new(
             *(peer_ids
                 .get(peer_idx)
@@ -596,13 +602,83 @@ pub struct ValueWriter {
     buffer: Vec<u8>,
 }
 
+/// The limits of the decoded values, see [crate::encoding::ImportOptions].
+#[derive(Debug, Clone, Copy)]
+pub struct ValueLimits {
+    /// The max length of a string or binary
+    pub max_len: usize,
+    /// The max nesting depth of the lists and maps
+    pub max_depth: usize,
+}
+
+impl Default for ValueLimits {
+    fn default() -> Self {
+        Self {
+            max_len: usize::MAX,
+            max_depth: usize::MAX,
+        }
+    }
+}
+
+impl ValueLimits {
+    fn check_len(&self, len: usize) -> LoroResult<()> {
+        if len > self.max_len {
+            return Err(LoroError::LimitExceeded {
+                limit: "max_value_len",
+                max: self.max_len,
+            });
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn check_depth(&self, depth: usize) -> LoroResult<()> {
+        if depth > self.max_depth {
+            return Err(LoroError::LimitExceeded {
+                limit: "max_depth",
+                max: self.max_depth,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Check the strings and the binaries in a value that is already decoded,
+    /// and the nesting depth of its lists and maps
+    pub(crate) fn check_value(&self, value: &LoroValue) -> LoroResult<()> {
+        let mut stack = vec![(value, 0)];
+        while let Some((value, depth)) = stack.pop() {
+            match value {
+                LoroValue::String(s) => self.check_len(s.len())?,
+                LoroValue::Binary(b) => self.check_len(b.len())?,
+                LoroValue::List(list) => {
+                    self.check_depth(depth + 1)?;
+                    stack.extend(list.iter().map(|v| (v, depth + 1)));
+                }
+                LoroValue::Map(map) => {
+                    self.check_depth(depth + 1)?;
+                    stack.extend(map.values().map(|v| (v, depth + 1)));
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
 pub struct ValueReader<'a> {
     raw: &'a [u8],
+    limits: ValueLimits,
 }
 
 impl<'a> ValueReader<'a> {
     pub fn new(raw: &'a [u8]) -> Self {
-        ValueReader { raw }
+        Self::with_limits(raw, ValueLimits::default())
+    }
+
+    pub fn with_limits(raw: &'a [u8], limits: ValueLimits) -> Self {
+        ValueReader { raw, limits }
     }
 
     pub fn read_value_type_and_content(
@@ -611,7 +687,7 @@ impl<'a> ValueReader<'a> {
         id: ID,
     ) -> LoroResult<LoroValue> {
         let kind = self.read_u8()?;
-        self.read_value_content(LoroValueKind::from_u8(kind), keys, id)
+        self.read_value_content(LoroValueKind::from_u8(kind)?, keys, id)
     }
 
     pub fn read_value_content(
@@ -628,22 +704,28 @@ impl<'a> ValueReader<'a> {
             LoroValueKind::F64 => LoroValue::Double(self.read_f64()?),
             LoroValueKind::Str => LoroValue::String((self.read_str()?.to_owned()).into()),
             LoroValueKind::List => {
+                self.limits.check_depth(1)?;
                 let len = self.read_usize()?;
                 if len > MAX_COLLECTION_SIZE {
                     return Err(LoroError::DecodeDataCorruptionError);
                 }
-                let mut ans = Vec::with_capacity(len);
+                // Every element takes at least one byte
+                let mut ans = Vec::with_capacity(len.min(self.raw.len()));
                 for i in 0..len {
                     ans.push(self.recursive_read_value_type_and_content(keys, id.inc(i as i32))?);
                 }
                 ans.into()
             }
             LoroValueKind::Map => {
+                self.limits.check_depth(1)?;
                 let len = self.read_usize()?;
                 if len > MAX_COLLECTION_SIZE {
                     return Err(LoroError::DecodeDataCorruptionError);
                 }
-                let mut ans = FxHashMap::with_capacity_and_hasher(len, Default::default());
+                let mut ans = FxHashMap::with_capacity_and_hasher(
+                    len.min(self.raw.len()),
+                    Default::default(),
+                );
                 for _ in 0..len {
                     let key_idx = self.read_usize()?;
                     let key = keys
@@ -728,7 +810,7 @@ impl<'a> ValueReader<'a> {
                     0
                 };
                 let kind = self.read_u8()?;
-                let kind = LoroValueKind::from_u8(kind);
+                let kind = LoroValueKind::from_u8(kind)?;
                 let value = match kind {
                     LoroValueKind::Null => LoroValue::Null,
                     LoroValueKind::True => LoroValue::Bool(true),
@@ -741,13 +823,15 @@ impl<'a> ValueReader<'a> {
                         if len > MAX_COLLECTION_SIZE {
                             return Err(LoroError::DecodeDataCorruptionError);
                         }
-                        let ans = Vec::with_capacity(len);
+                        let ans = Vec::with_capacity(len.min(self.raw.len()));
                         stack.push(task);
                         stack.push(Task::ReadList {
                             left: len,
                             vec: ans,
                             key_idx_in_parent: key_idx,
                         });
+                        // The outermost value and the lists and maps in the stack
+                        self.limits.check_depth(stack.len())?;
                         continue;
                     }
                     LoroValueKind::Map => {
@@ -756,13 +840,17 @@ impl<'a> ValueReader<'a> {
                             return Err(LoroError::DecodeDataCorruptionError);
                         }
 
-                        let ans = FxHashMap::with_capacity_and_hasher(len, Default::default());
+                        let ans = FxHashMap::with_capacity_and_hasher(
+                            len.min(self.raw.len()),
+                            Default::default(),
+                        );
                         stack.push(task);
                         stack.push(Task::ReadMap {
                             left: len,
                             map: ans,
                             key_idx_in_parent: key_idx,
                         });
+                        self.limits.check_depth(stack.len())?;
                         continue;
                     }
                     LoroValueKind::Binary => {
@@ -889,11 +977,13 @@ impl<'a> ValueReader<'a> {
 
     pub fn read_str(&mut self) -> LoroResult<&'a str> {
         let len = self.read_usize()?;
+        self.limits.check_len(len)?;
         if self.raw.len() < len {
             return Err(LoroError::DecodeDataCorruptionError);
         }
 
-        let ans = std::str::from_utf8(&self.raw[..len]).unwrap();
+        let ans = std::str::from_utf8(&self.raw[..len])
+            .map_err(|_| LoroError::DecodeDataCorruptionError)?;
         self.raw = &self.raw[len..];
         Ok(ans)
     }
@@ -901,6 +991,7 @@ impl<'a> ValueReader<'a> {
     #[allow(unused)]
     fn read_binary_vec(&mut self) -> LoroResult<Vec<u8>> {
         let len = self.read_usize()?;
+        self.limits.check_len(len)?;
         if self.raw.len() < len {
             return Err(LoroError::DecodeDataCorruptionError);
         }
@@ -921,6 +1012,7 @@ impl<'a> ValueReader<'a> {
 
     pub fn read_binary(&mut self) -> LoroResult<&'a [u8]> {
         let len = self.read_usize()?;
+        self.limits.check_len(len)?;
         if self.raw.len() < len {
             return Err(LoroError::DecodeDataCorruptionError);
         }
@@ -1,17 +1,22 @@
 use super::{state::DocState, txn::Transaction};
 use crate::{
     arena::SharedArena,
+    change_meta::ChangeMeta,
     container::{
         idx::ContainerIdx,
         list::list_op::{DeleteSpan, DeleteSpanWithId, ListOp},
-        richtext::{richtext_state::PosType, RichtextState, StyleOp, TextStyleInfoFlag},
+        richtext::{
+            richtext_state::{PosType, RichtextStateChunk},
+            RichtextState, StyleOp, TextStyleInfoFlag,
+        },
     },
     cursor::{Cursor, Side},
     delta::{DeltaItem, Meta, StyleMeta, TreeExternalDiff},
     diff::{diff, diff_impl::UpdateTimeoutError, OperateProxy},
-    event::{Diff, TextDiffItem},
+    event::{Diff, TextDiff, TextDiffItem},
+    loro::TextBlameSpan,
     op::ListSlice,
-    state::{IndexType, State, TreeParentId},
+    state::{IndexType, State, StateHash, TreeParentId},
     txn::EventHint,
     utils::{string_slice::StringSlice, utf16::count_utf16_len},
 };
@@ -20,8 +25,8 @@ use enum_as_inner::EnumAsInner;
 use fxhash::FxHashMap;
 use generic_btree::rle::HasLength;
 use loro_common::{
-    ContainerID, ContainerType, IdFull, InternalString, LoroError, LoroResult, LoroValue, PeerID,
-    TreeID, ID,
+    ContainerID, ContainerType, Counter, IdFull, InternalString, Lamport, LoroError, LoroResult,
+    LoroValue, PeerID, TreeID, ID,
 };
 use serde::{Deserialize, Serialize};
 use std::{
@@ -88,6 +93,18 @@ pub trait HandlerTrait: Clone + Sized {
         let mut guard = state.try_lock().unwrap();
         guard.with_state_mut(inner.container_idx, f)
     }
+
+    /// Get a digest of the state of the container and its descendants.
+    ///
+    /// It returns an error if the container is detached.
+    fn state_hash(&self) -> LoroResult<StateHash> {
+        let inner = self
+            .attached_handler()
+            .ok_or(LoroError::MisuseDetachedContainer {
+                method: "state_hash",
+            })?;
+        Ok(inner.state_hash())
+    }
 }
 
 fn create_handler(inner: &BasicHandler, id: ContainerID) -> Handler {
@@ -247,6 +264,11 @@ impl BasicHandler {
             .get_container_deep_value(self.container_idx)
     }
 
+    /// Get a digest of the state of the container, see [DocState::state_hash].
+    pub fn state_hash(&self) -> StateHash {
+        self.with_doc_state(|state| state.container_state_hash(self.container_idx))
+    }
+
     fn with_state<R>(&self, f: impl FnOnce(&mut State) -> R) -> R {
         let state = self.state.upgrade().unwrap();
         let mut guard = state.try_lock().unwrap();
@@ -383,6 +405,31 @@ pub enum TextDelta {
 }
 
 impl TextDelta {
+    pub fn into_text_diff(vec: impl Iterator<Item = Self>) -> TextDiff {
+        let mut delta = TextDiff::new();
+        for item in vec {
+            match item {
+                TextDelta::Retain { retain, attributes } => {
+                    delta.push_retain(
+                        retain,
+                        attributes.as_ref().map(StyleMeta::from).unwrap_or_default(),
+                    );
+                }
+                TextDelta::Insert { insert, attributes } => {
+                    delta.push_insert(
+                        StringSlice::from(insert),
+                        attributes.as_ref().map(StyleMeta::from).unwrap_or_default(),
+                    );
+                }
+                TextDelta::Delete { delete } => {
+                    delta.push_delete(delete);
+                }
+            }
+        }
+
+        delta
+    }
+
     pub fn from_text_diff<'a>(diff: impl Iterator<Item = &'a TextDiffItem>) -> Vec<TextDelta> {
         let mut ans = Vec::with_capacity(diff.size_hint().0);
         for iter in diff {
@@ -1089,18 +1136,19 @@ impl Handler {
                 let diff = diff.into_map().unwrap();
                 for (key, value) in diff.updated.into_iter() {
                     match value.value {
-                        Some(ValueOrHandler::Handler(h)) => {
-                            let old_id = h.id();
-                            let new_h = x.insert_container(
-                                &key,
-                                Handler::new_unattached(old_id.container_type()),
-                            )?;
-                            let new_id = new_h.id();
-                            on_container_remap(old_id, new_id);
-                        }
-                        Some(ValueOrHandler::Value(v)) => {
-                            x.insert_without_skipping(&key, v)?;
-                        }
+                        Some(v) => match v.container_id() {
+                            Some(old_id) => {
+                                let new_h = x.insert_container(
+                                    &key,
+                                    Handler::new_unattached(old_id.container_type()),
+                                )?;
+                                let new_id = new_h.id();
+                                on_container_remap(old_id, new_id);
+                            }
+                            None => {
+                                x.insert_without_skipping(&key, v.to_value())?;
+                            }
+                        },
                         None => {
                             x.delete(&key)?;
                         }
@@ -1256,6 +1304,18 @@ impl ValueOrHandler {
         }
     }
 
+    /// Get the id of the container this value refers to.
+    ///
+    /// A diff that is detached from its source doc (e.g. a deserialized [crate::undo::DiffBatch])
+    /// refers to its child containers with [LoroValue::Container] instead of handlers.
+    pub(crate) fn container_id(&self) -> Option<ContainerID> {
+        match self {
+            Self::Value(LoroValue::Container(id)) => Some(id.clone()),
+            Self::Value(_) => None,
+            Self::Handler(h) => Some(h.id()),
+        }
+    }
+
     pub(crate) fn to_deep_value(&self) -> LoroValue {
         match self {
             Self::Value(v) => v.clone(),
@@ -1307,6 +1367,91 @@ impl TextHandler {
         }
     }
 
+    /// Get the ids of the chars in the current state.
+    ///
+    /// It returns run-length encoded `(unicode_len, id of the first char)` pairs in the order of the text.
+    pub(crate) fn get_id_spans(&self) -> LoroResult<Vec<(usize, IdFull)>> {
+        match &self.inner {
+            MaybeDetached::Detached(_) => Err(LoroError::MisuseDetachedContainer {
+                method: "get_id_spans",
+            }),
+            MaybeDetached::Attached(a) => Ok(a.with_state(|state| {
+                let mut ans = Vec::new();
+                state
+                    .as_richtext_state_mut()
+                    .unwrap()
+                    .iter_raw(&mut |chunk| {
+                        if let RichtextStateChunk::Text(t) = chunk {
+                            if t.unicode_len() > 0 {
+                                ans.push((t.unicode_len() as usize, t.id_full()));
+                            }
+                        }
+                    });
+                ans
+            })),
+        }
+    }
+
+    /// Get the origin of each char in the current state of the text.
+    ///
+    /// It returns run-length encoded spans in the order of the text. The chars in a span are
+    /// continuous in the text, inserted by continuous ops of the same change. It works on any
+    /// checked out version.
+    ///
+    /// The [ChangeMeta] of a span is `None` if its change is not included by the shallow history,
+    /// or if it's inserted by the pending transaction, which is not committed yet.
+    pub fn blame(&self) -> LoroResult<Vec<TextBlameSpan>> {
+        let id_spans = self.get_id_spans()?;
+        let MaybeDetached::Attached(a) = &self.inner else {
+            unreachable!()
+        };
+        let oplog = a
+            .with_doc_state(|state| state.oplog.upgrade())
+            .ok_or(LoroError::MisuseDetachedContainer { method: "blame" })?;
+        let oplog = oplog.try_lock().unwrap();
+        let mut ans: Vec<TextBlameSpan> = Vec::new();
+        let mut pos = 0;
+        for (len, id) in id_spans {
+            let mut offset = 0;
+            while offset < len {
+                let id = id.inc(offset as i32);
+                let (change, span_len) = match oplog.get_change_at(id.id()) {
+                    Some(change) => {
+                        let change_end = change.id.counter + change.len() as Counter;
+                        let span_len = ((change_end - id.counter) as usize).min(len - offset);
+                        (Some(ChangeMeta::from_change(&change)), span_len)
+                    }
+                    None => (None, len - offset),
+                };
+
+                let new_range = pos..pos + span_len;
+                match ans.last_mut() {
+                    Some(last)
+                        if last.range.end == pos
+                            && last.id.peer == id.peer
+                            && last.id.counter + last.range.len() as Counter == id.counter
+                            && last.lamport + last.range.len() as Lamport == id.lamport
+                            && last.change.as_ref().map(|c| c.id)
+                                == change.as_ref().map(|c| c.id) =>
+                    {
+                        last.range.end = new_range.end;
+                    }
+                    _ => ans.push(TextBlameSpan {
+                        range: new_range,
+                        id: id.id(),
+                        lamport: id.lamport,
+                        change,
+                    }),
+                }
+
+                pos += span_len;
+                offset += span_len;
+            }
+        }
+
+        Ok(ans)
+    }
+
     pub fn is_empty(&self) -> bool {
         match &self.inner {
             MaybeDetached::Detached(t) => t.try_lock().unwrap().value.is_empty(),
@@ -2682,12 +2827,11 @@ impl ListHandler {
                             }
 
                             for v in value.iter() {
-                                match v {
-                                    ValueOrHandler::Value(v) => {
-                                        self.insert(index, v.clone())?;
+                                match v.container_id() {
+                                    None => {
+                                        self.insert(index, v.to_value())?;
                                     }
-                                    ValueOrHandler::Handler(h) => {
-                                        let old_id = h.id();
+                                    Some(old_id) => {
                                         let new_h = self.insert_container(
                                             index,
                                             Handler::new_unattached(old_id.container_type()),
@@ -218,15 +218,14 @@ impl MovableListHandler {
         context: &mut ReplacementContext,
     ) -> LoroResult<()> {
         for v in values.iter() {
-            match v {
-                ValueOrHandler::Value(value) => {
-                    self.insert(*context.index, value.clone())?;
+            match v.container_id() {
+                None => {
+                    self.insert(*context.index, v.to_value())?;
                     Self::update_positions_on_insert(context.to_delete, *context.index, 1);
                     *context.index += 1;
                     *context.index_shift += 1;
                 }
-                ValueOrHandler::Handler(handler) => {
-                    let mut old_id = handler.id();
+                Some(mut old_id) => {
                     if !context.to_delete.contains_key(&old_id) {
                         while let Some(new_id) = context.container_remap.get(&old_id) {
                             old_id = new_id.clone();
@@ -0,0 +1,131 @@
+//! Accepting or rejecting the imported changes, see [crate::LoroDoc::set_import_filter].
+//!
+//! A rejected change is put into the quarantine together with all the imported changes that
+//! depend on it, including the pending changes of the earlier imports. The changes in the
+//! quarantine are never added to the [OpLog], so they are never applied to the
+//! [crate::DocState].
+use std::{fmt::Debug, sync::Arc};
+
+use itertools::Itertools;
+use loro_common::{ContainerID, HasCounterSpan, HasIdSpan, IdSpan};
+
+use crate::{arena::SharedArena, change::Change, version::VersionRange, ChangeMeta, OpLog};
+
+/// Decides whether an imported change is accepted, given its meta and the containers
+/// its ops touch.
+#[derive(Clone)]
+pub struct ImportFilter(Arc<dyn Fn(&ChangeMeta, &[ContainerID]) -> bool + Send + Sync>);
+
+impl ImportFilter {
+    pub fn new(f: impl Fn(&ChangeMeta, &[ContainerID]) -> bool + Send + Sync + 'static) -> Self {
+        Self(Arc::new(f))
+    }
+
+    fn accept(&self, change: &Change, arena: &SharedArena) -> bool {
+        let containers = change
+            .ops
+            .iter()
+            .map(|op| op.container)
+            .unique()
+            .map(|idx| arena.get_container_id(idx).unwrap())
+            .collect_vec();
+        (self.0)(&ChangeMeta::from_change(change), &containers)
+    }
+}
+
+impl Debug for ImportFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ImportFilter").finish_non_exhaustive()
+    }
+}
+
+impl OpLog {
+    /// Move the changes rejected by the import filter, and the changes that depend on
+    /// the quarantined ones, out of `changes`. The pending changes that depend on the newly
+    /// quarantined changes are removed from the [OpLog] too.
+    ///
+    /// Return the range of the changes that are quarantined by this call.
+    pub(crate) fn quarantine_rejected_changes(
+        &mut self,
+        changes: &mut Vec<Change>,
+    ) -> VersionRange {
+        let mut quarantined = VersionRange::new();
+        let Some(filter) = self.configure.import_filter() else {
+            return quarantined;
+        };
+
+        let vv = self.vv();
+        let mut rejected = Vec::with_capacity(changes.len());
+        for change in changes.iter() {
+            let is_new = change.ctr_end() > vv.get(&change.id.peer).copied().unwrap_or(0);
+            rejected.push(is_new && !filter.accept(change, &self.arena));
+        }
+
+        // The changes are not sorted causally, so iterate until no more descendant is found
+        let is_quarantined =
+            |range: &VersionRange, id| range.contains_id(id) && !vv.includes_id(id);
+        loop {
+            let mut changed = false;
+            for (change, rejected) in changes.iter().zip(rejected.iter_mut()) {
+                if *rejected {
+                    if !quarantined.contains_id_span(change.id_span()) {
+                        quarantined.extends_to_include_id_span(change.id_span());
+                        changed = true;
+                    }
+                    continue;
+                }
+
+                if change.deps.iter().any(|dep| {
+                    is_quarantined(&quarantined, dep) || is_quarantined(&self.quarantine, dep)
+                }) {
+                    *rejected = true;
+                    changed = true;
+                }
+            }
+
+            if !changed {
+                break;
+            }
+        }
+
+        let mut rejected = rejected.into_iter();
+        changes.retain(|_| !rejected.next().unwrap());
+        if quarantined.is_empty() {
+            return quarantined;
+        }
+
+        // The pending changes waiting for the quarantined changes can never be applied
+        let vv = vv.clone();
+        loop {
+            let removed = self.pending_changes.extract_if(|c| {
+                c.deps
+                    .iter()
+                    .any(|dep| quarantined.contains_id(dep) && !vv.includes_id(dep))
+            });
+            if removed.is_empty() {
+                break;
+            }
+
+            for change in removed {
+                quarantined.extends_to_include_id_span(change.id_span());
+            }
+        }
+
+        for (&peer, &(start, end)) in quarantined.iter() {
+            self.quarantine
+                .extends_to_include_id_span(IdSpan::new(peer, start, end));
+        }
+
+        quarantined
+    }
+
+    /// The changes that are put into the quarantine by the import filter
+    pub fn quarantine(&self) -> &VersionRange {
+        &self.quarantine
+    }
+
+    /// Forget the quarantined changes, so they can be imported again
+    pub fn clear_quarantine(&mut self) {
+        self.quarantine = VersionRange::new();
+    }
+}
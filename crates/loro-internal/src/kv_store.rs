@@ -1,8 +1,11 @@
 use bytes::Bytes;
-pub use loro_kv_store::compress::CompressionType;
+use loro_common::{LoroError, LoroResult};
+pub use loro_kv_store::compress::{CompressionDict, CompressionType};
+pub use loro_kv_store::encrypt::EncryptionKey;
 pub use loro_kv_store::MemKvStore;
 use std::{
     collections::BTreeMap,
+    io::Write,
     ops::Bound,
     sync::{Arc, Mutex},
 };
@@ -22,7 +25,34 @@ pub trait KvStore: std::fmt::Debug + Send + Sync {
     fn is_empty(&self) -> bool;
     fn size(&self) -> usize;
     fn export_all(&mut self) -> Bytes;
+    /// Write the bytes that [KvStore::export_all] would return to `w`, for the stores that
+    /// can write them out while they're encoded
+    fn export_to(&mut self, w: &mut dyn Write) -> std::io::Result<()> {
+        w.write_all(&self.export_all())
+    }
     fn import_all(&mut self, bytes: Bytes) -> Result<(), String>;
+    /// Like [KvStore::import_all], but keeps the kind of the error, e.g.
+    /// [LoroError::DecryptionError] or [LoroError::LimitExceeded]
+    fn try_import_all(&mut self, bytes: Bytes) -> LoroResult<()> {
+        self.import_all(bytes)
+            .map_err(|e| LoroError::DecodeError(e.into_boxed_str()))
+    }
+    /// Check that all the imported data can be read, for the stores that decode it lazily
+    fn check_all(&self) -> LoroResult<()> {
+        Ok(())
+    }
+    /// Set how the exported data is compressed and encrypted, and the dictionary and the key
+    /// to read the imported data, for the stores that encode their data into blocks
+    fn set_codec(
+        &mut self,
+        _compression_type: CompressionType,
+        _dict: Option<CompressionDict>,
+        _key: Option<EncryptionKey>,
+    ) {
+    }
+    /// Limit the total decompressed length of the data imported later, for the stores that
+    /// compress their data
+    fn set_max_decompressed_len(&mut self, _max: Option<usize>) {}
     fn clone_store(&self) -> Arc<Mutex<dyn KvStore>>;
 }
 
@@ -89,10 +119,35 @@ impl KvStore for MemKvStore {
         self.export_all()
     }
 
+    fn export_to(&mut self, w: &mut dyn Write) -> std::io::Result<()> {
+        MemKvStore::export_to(self, w)
+    }
+
     fn import_all(&mut self, bytes: Bytes) -> Result<(), String> {
         self.import_all(bytes)
     }
 
+    fn try_import_all(&mut self, bytes: Bytes) -> LoroResult<()> {
+        self.try_import_all(bytes)
+    }
+
+    fn check_all(&self) -> LoroResult<()> {
+        self.check_all_blocks()
+    }
+
+    fn set_codec(
+        &mut self,
+        compression_type: CompressionType,
+        dict: Option<CompressionDict>,
+        key: Option<EncryptionKey>,
+    ) {
+        self.set_codec(compression_type, dict, key)
+    }
+
+    fn set_max_decompressed_len(&mut self, max: Option<usize>) {
+        self.set_max_decompressed_len(max)
+    }
+
     fn clone_store(&self) -> Arc<Mutex<dyn KvStore>> {
         Arc::new(Mutex::new(self.clone()))
     }
@@ -146,24 +201,21 @@ mod default_binary_format {
             let mut key = Vec::new();
             if last_key.is_empty() {
                 let key_len = leb128::read::unsigned(&mut bytes).map_err(|e| e.to_string())?;
-                let key_len = key_len as usize;
-                key.extend_from_slice(&bytes[..key_len]);
-                bytes = &bytes[key_len..];
+                key.extend_from_slice(take(&mut bytes, key_len)?);
             } else {
-                let common_prefix_len = bytes[0] as usize;
-                bytes = &bytes[1..];
-                key.extend_from_slice(&last_key[..common_prefix_len]);
+                let common_prefix_len = take(&mut bytes, 1)?[0] as usize;
+                key.extend_from_slice(
+                    last_key
+                        .get(..common_prefix_len)
+                        .ok_or_else(|| "Invalid common prefix length".to_string())?,
+                );
                 let suffix_len = leb128::read::unsigned(&mut bytes).map_err(|e| e.to_string())?;
-                let suffix_len = suffix_len as usize;
-                key.extend_from_slice(&bytes[..suffix_len]);
-                bytes = &bytes[suffix_len..];
+                key.extend_from_slice(take(&mut bytes, suffix_len)?);
             }
 
             // Read the value
             let value_len = leb128::read::unsigned(&mut bytes).map_err(|e| e.to_string())?;
-            let value_len = value_len as usize;
-            let value = Bytes::copy_from_slice(&bytes[..value_len]);
-            bytes = &bytes[value_len..];
+            let value = Bytes::copy_from_slice(take(&mut bytes, value_len)?);
 
             // Store the key-value pair
             store.set(&key, value);
@@ -173,6 +225,15 @@ mod default_binary_format {
 
         Ok(())
     }
+
+    fn take<'a>(bytes: &mut &'a [u8], len: u64) -> Result<&'a [u8], String> {
+        if (bytes.len() as u64) < len {
+            return Err("Unexpected end of input".to_string());
+        }
+        let (ans, rest) = bytes.split_at(len as usize);
+        *bytes = rest;
+        Ok(ans)
+    }
 }
 
 impl KvStore for BTreeMap<Bytes, Bytes> {
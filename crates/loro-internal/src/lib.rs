@@ -19,7 +19,7 @@ use arena::SharedArena;
 use configure::Configure;
 use diff_calc::DiffCalculator;
 
-pub use change_meta::ChangeMeta;
+pub use change_meta::{ChangeMeta, ChangeWithOps};
 pub use event::{ContainerDiff, DiffEvent, DocDiff, ListDiff, ListDiffInsertItem, ListDiffItem};
 pub use fxhash::FxHashMap;
 pub use handler::{
@@ -29,6 +29,7 @@ pub use handler::{
 pub use loro_common;
 pub use oplog::OpLog;
 pub use state::DocState;
+pub use state::{MerkleNode, StateHash};
 pub use state::{TreeNode, TreeNodeWithChildren, TreeParentId};
 use subscription::{LocalUpdateCallback, Observer, PeerIdUpdateCallback};
 use txn::Transaction;
@@ -39,18 +40,23 @@ pub mod allocation;
 pub mod awareness;
 pub mod change;
 pub mod configure;
+pub mod conflict;
 pub mod container;
 pub mod cursor;
 pub mod dag;
 pub mod encoding;
 pub(crate) mod fork;
 pub mod id;
+pub mod import_filter;
 #[cfg(feature = "jsonpath")]
 pub mod jsonpath;
 pub mod kv_store;
 pub mod loro;
 pub mod op;
 pub mod oplog;
+pub(crate) mod redact;
+pub mod refs;
+pub mod signature;
 pub mod subscription;
 pub mod txn;
 pub mod version;
@@ -123,4 +129,8 @@ pub struct LoroDoc {
     detached: AtomicBool,
     local_update_subs: SubscriberSetWithQueue<(), LocalUpdateCallback, Vec<u8>>,
     peer_id_change_subs: SubscriberSetWithQueue<(), PeerIdUpdateCallback, ID>,
+    /// The branch that the doc is checked out to, see [refs]
+    current_branch: Arc<Mutex<Option<String>>>,
+    /// The spans applied by [LoroDoc::cherry_pick]
+    cherry_picked: Arc<Mutex<loro::PickedSpans>>,
 }
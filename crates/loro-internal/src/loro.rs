@@ -2,15 +2,16 @@ use either::Either;
 use fxhash::{FxHashMap, FxHashSet};
 use itertools::Itertools;
 use loro_common::{
-    ContainerID, ContainerType, HasIdSpan, HasLamportSpan, IdSpan, LoroEncodeError, LoroResult,
-    LoroValue, ID,
+    ContainerID, ContainerType, Counter, HasIdSpan, HasLamportSpan, IdSpan, Lamport,
+    LoroEncodeError, LoroResult, LoroValue, ID,
 };
 use rle::HasLength;
 use std::{
     borrow::Cow,
     cmp::Ordering,
     collections::BinaryHeap,
-    ops::ControlFlow,
+    io::{Read, Write},
+    ops::{ControlFlow, Range},
     sync::{
         atomic::{
             AtomicBool,
@@ -21,6 +22,7 @@ use std::{
 };
 use tracing::{debug_span, info, info_span, instrument, warn};
 
+use crate::encoding::stream::{unwrap_stream, BodyReader, LimitedReader};
 use crate::{
     arena::SharedArena,
     change::Timestamp,
@@ -30,27 +32,32 @@ use crate::{
         IntoContainerId,
     },
     cursor::{AbsolutePosition, CannotFindRelativePosition, Cursor, PosQueryResult},
-    dag::Dag,
+    dag::{Dag, DagUtils},
     diff_calc::DiffCalculator,
     encoding::{
-        self, decode_snapshot, export_fast_snapshot, export_fast_updates,
-        export_fast_updates_in_range, export_shallow_snapshot, export_snapshot, export_snapshot_at,
-        export_state_only_snapshot, json_schema::json::JsonSchema, parse_header_and_body,
-        EncodeMode, ImportBlobMetadata, ImportStatus, ParsedHeaderAndBody,
+        self, decode_snapshot, decode_snapshot_from_reader, downgrade_to_fast, export_containers,
+        export_fast_snapshot, export_fast_updates, export_fast_updates_in_range, export_outdated,
+        export_shallow_snapshot, export_snapshot, export_snapshot_at, export_state_only_snapshot,
+        export_to_writer, json_schema::json::JsonSchema, parse_header_and_body, EncodeMode,
+        EncodingVersion, ImportBlobMetadata, ImportOptions, ImportPreview, ImportStatus,
+        ParsedHeaderAndBody, MIN_HEADER_SIZE,
     },
-    event::{str_to_path, EventTriggerKind, Index, InternalDocDiff},
+    event::{str_to_path, Diff, EventTriggerKind, Index, InternalDocDiff},
     handler::{Handler, MovableListHandler, TextHandler, TreeHandler, ValueOrHandler},
     id::PeerID,
+    import_filter::ImportFilter,
+    kv_store::{CompressionDict, CompressionType, EncryptionKey},
     op::InnerContent,
     oplog::{loro_dag::FrontiersNotIncluded, OpLog},
-    state::DocState,
+    signature::{ChangeSigner, ChangeVerifier},
+    state::{DocState, MerkleNode, StateHash},
     subscription::{LocalUpdateCallback, Observer, Subscriber},
     txn::Transaction,
     undo::DiffBatch,
     utils::subscription::{SubscriberSetWithQueue, Subscription},
-    version::{shrink_frontiers, Frontiers, ImVersionVector},
-    ChangeMeta, DocDiff, HandlerTrait, InternalString, ListHandler, LoroError, MapHandler,
-    VersionVector,
+    version::{shrink_frontiers, Frontiers, ImVersionVector, VersionRange},
+    ChangeMeta, ChangeWithOps, DocDiff, HandlerTrait, InternalString, ListHandler, LoroError,
+    MapHandler, VersionVector,
 };
 
 pub use crate::encoding::ExportMode;
@@ -79,10 +86,16 @@ impl LoroDoc {
         let arena = oplog.arena.clone();
         let global_txn = Arc::new(Mutex::new(None));
         let config: Configure = oplog.configure.clone();
+        let oplog = Arc::new(Mutex::new(oplog));
         // share arena
-        let state = DocState::new_arc(arena.clone(), Arc::downgrade(&global_txn), config.clone());
+        let state = DocState::new_arc(
+            arena.clone(),
+            Arc::downgrade(&global_txn),
+            Arc::downgrade(&oplog),
+            config.clone(),
+        );
         Self {
-            oplog: Arc::new(Mutex::new(oplog)),
+            oplog,
             state,
             config,
             detached: AtomicBool::new(false),
@@ -93,6 +106,8 @@ impl LoroDoc {
             arena,
             local_update_subs: SubscriberSetWithQueue::new(),
             peer_id_change_subs: SubscriberSetWithQueue::new(),
+            current_branch: Default::default(),
+            cherry_picked: Default::default(),
         }
     }
 
@@ -104,6 +119,7 @@ impl LoroDoc {
         self.commit_then_stop();
         let snapshot = encoding::fast_snapshot::encode_snapshot_inner(self);
         let doc = Self::new();
+        doc.set_codec_config(&self.config);
         encoding::fast_snapshot::decode_snapshot_inner(snapshot, &doc).unwrap();
         doc.set_config(&self.config);
         if self.auto_commit.load(std::sync::atomic::Ordering::Relaxed) {
@@ -137,6 +153,91 @@ impl LoroDoc {
         self.config.set_merge_interval(interval);
     }
 
+    /// Set the compression of the stores in the exported snapshots. The default is LZ4.
+    ///
+    /// Snapshots compressed with zstd can only be imported with the `zstd` feature enabled.
+    /// Older versions of Loro reject them with [LoroError::IncompatibleFutureEncodingError].
+    ///
+    /// The dictionary is used by [CompressionType::ZstdWithDict]. A doc needs the same
+    /// dictionary to import the snapshots compressed with it.
+    pub fn set_snapshot_compression(
+        &self,
+        compression_type: CompressionType,
+        dict: Option<CompressionDict>,
+    ) -> LoroResult<()> {
+        if compression_type.is_zstd() && !cfg!(feature = "zstd") {
+            return Err(LoroError::NotImplemented(
+                "zstd compression requires the `zstd` feature",
+            ));
+        }
+
+        self.config.set_snapshot_compression(compression_type, dict);
+        Ok(())
+    }
+
+    /// Set the key to encrypt the stores in the exported snapshots, or `None` to stop encrypting.
+    ///
+    /// Every block of the stores is encrypted with ChaCha20-Poly1305. The same key is
+    /// required to import the snapshots, otherwise the import fails with
+    /// [LoroError::DecryptionError]. Updates are not encrypted.
+    ///
+    /// The first and the last key of every block stay in plaintext, which are the IDs of
+    /// some changes, i.e. peer IDs and counters, and the IDs of some containers, including
+    /// the names of the root containers. The values, e.g. the ops and the states, are hidden.
+    ///
+    /// A key can only be created with the `encryption` feature enabled.
+    pub fn set_encryption_key(&self, key: Option<EncryptionKey>) {
+        self.config.set_encryption_key(key);
+    }
+
+    /// Set the signer of the local changes, or `None` to stop signing.
+    ///
+    /// Every change committed afterwards is signed and the signature is exported with it.
+    /// Signed changes are never merged with each other.
+    pub fn set_change_signer(&self, signer: Option<ChangeSigner>) {
+        self.config.set_change_signer(signer);
+    }
+
+    /// Set the verifier of the imported changes, or `None` to stop verifying.
+    ///
+    /// Every new change is verified before anything is imported. If any of them is
+    /// rejected, the import fails with [LoroError::InvalidChangeSignature] and the doc
+    /// is not changed.
+    ///
+    /// The states in the snapshots are not signed, so they are calculated from the verified
+    /// changes instead. The shallow snapshots are rejected with
+    /// [LoroError::UnverifiableShallowSnapshot] when they initialize the doc.
+    pub fn set_change_verifier(&self, verifier: Option<ChangeVerifier>) {
+        self.config.set_change_verifier(verifier);
+    }
+
+    /// Set the filter of the imported changes, or `None` to accept all of them.
+    ///
+    /// The rejected changes and the imported changes that depend on them are put into the
+    /// quarantine and reported by [ImportStatus::quarantined]. They are never applied to
+    /// the [DocState]. Shallow snapshots cannot be imported into an empty doc with a filter.
+    pub fn set_import_filter(&self, filter: Option<ImportFilter>) {
+        self.config.set_import_filter(filter);
+    }
+
+    /// Get the range of the changes in the quarantine of the import filter
+    pub fn quarantine(&self) -> VersionRange {
+        self.oplog.try_lock().unwrap().quarantine().clone()
+    }
+
+    /// Forget the quarantined changes, so they are filtered again when they are imported
+    pub fn clear_quarantine(&self) {
+        self.oplog.try_lock().unwrap().clear_quarantine();
+    }
+
+    /// Set the resource limits of the imported data, or `None` to remove them.
+    ///
+    /// Use it when importing blobs from untrusted sources. An import that exceeds any of
+    /// the limits fails with [LoroError::LimitExceeded] and the doc is not changed.
+    pub fn set_import_options(&self, options: Option<ImportOptions>) {
+        self.config.set_import_options(options);
+    }
+
     /// Enables editing of the document in detached mode.
     ///
     /// By default, the document cannot be edited in detached mode (after calling
@@ -190,7 +291,8 @@ impl LoroDoc {
 
     pub fn from_snapshot(bytes: &[u8]) -> LoroResult<Self> {
         let doc = Self::new();
-        let ParsedHeaderAndBody { mode, body, .. } = parse_header_and_body(bytes, true)?;
+        let bytes = unwrap_stream(bytes)?;
+        let ParsedHeaderAndBody { mode, body, .. } = parse_header_and_body(&bytes, true)?;
         if mode.is_snapshot() {
             decode_snapshot(&doc, mode, body)?;
             Ok(doc)
@@ -213,6 +315,11 @@ impl LoroDoc {
             return false;
         }
 
+        // The snapshot must be imported as updates to filter its changes
+        if oplog.configure.import_filter().is_some() {
+            return false;
+        }
+
         oplog.is_empty() && self.state.try_lock().unwrap().can_import_snapshot()
     }
 
@@ -417,6 +524,114 @@ impl LoroDoc {
         ans
     }
 
+    /// Preview the effect of importing `bytes` without changing the doc.
+    ///
+    /// The bytes are imported into a fork of the latest version of the doc, which also has
+    /// the pending changes and the quarantine of the doc, so the oplog and the state of the
+    /// doc stay untouched. The diff is only calculated when `with_diff` is true.
+    ///
+    /// The fork is created by encoding the whole doc into a snapshot and decoding it, so
+    /// every call takes time and memory in proportion to the size of the doc.
+    pub fn import_preview(&self, bytes: &[u8], with_diff: bool) -> LoroResult<ImportPreview> {
+        self.commit_then_stop();
+        let snapshot = encoding::fast_snapshot::encode_snapshot_inner(self);
+        let pending = encoding::export_pending_changes(self);
+        let quarantine = self.oplog.try_lock().unwrap().quarantine().clone();
+        self.renew_txn_if_auto_commit();
+
+        let fork = Self::new();
+        fork.set_codec_config(&self.config);
+        encoding::fast_snapshot::decode_snapshot_inner(snapshot, &fork)?;
+        fork.set_config(&self.config);
+        fork.oplog.try_lock().unwrap().quarantine = quarantine;
+        if let Some(pending) = pending {
+            fork.import(&pending)?;
+        }
+
+        let old_frontiers = fork.oplog_frontiers();
+        let status = fork.import(bytes)?;
+        let frontiers = fork.oplog_frontiers();
+        let mut changed_containers = FxHashSet::default();
+        {
+            let oplog = fork.oplog.try_lock().unwrap();
+            for (&peer, &(start, end)) in status.success.iter() {
+                for op in oplog.iter_ops(IdSpan::new(peer, start, end)) {
+                    let id = oplog.arena.get_container_id(op.container()).unwrap();
+                    changed_containers.insert(id);
+                }
+            }
+        }
+
+        let diff = if with_diff {
+            Some(fork.diff(&old_frontiers, &frontiers)?)
+        } else {
+            None
+        };
+        Ok(ImportPreview {
+            status,
+            changed_containers,
+            frontiers,
+            diff,
+        })
+    }
+
+    /// Import updates or a snapshot from a reader.
+    ///
+    /// The blobs written by [LoroDoc::export_to_writer] are read part by part: the change
+    /// blocks of the updates are decoded as they are read, and a snapshot imported into an
+    /// empty doc is used by the stores part by part. Only the oplog part of a snapshot is kept
+    /// for a non-empty doc. The checksum is checked before anything is imported. The blobs
+    /// exported by [LoroDoc::export] are read the same way, except the outdated ones, which
+    /// are read to the end into memory first.
+    ///
+    /// The input is rejected as soon as it exceeds [ImportOptions::max_input_bytes].
+    pub fn import_from_reader<R: Read>(&self, reader: R) -> Result<ImportStatus, LoroError> {
+        self.commit_then_stop();
+        let ans = self._import_from_reader(reader);
+        self.renew_txn_if_auto_commit();
+        ans
+    }
+
+    fn _import_from_reader<R: Read>(&self, reader: R) -> Result<ImportStatus, LoroError> {
+        let max = self
+            .config
+            .import_options()
+            .and_then(|options| options.max_input_bytes);
+        let mut reader = LimitedReader::new(reader, max);
+        let result = self.import_from_limited_reader(&mut reader);
+        match max {
+            Some(max) if reader.is_exceeded() => Err(LoroError::LimitExceeded {
+                limit: "max_input_bytes",
+                max,
+            }),
+            _ => result,
+        }
+    }
+
+    fn import_from_limited_reader(&self, reader: &mut dyn Read) -> Result<ImportStatus, LoroError> {
+        let mut header = [0; MIN_HEADER_SIZE];
+        reader.read_exact(&mut header)?;
+        let mode = parse_header_and_body(&header, false)?.mode;
+        if matches!(mode, EncodeMode::OutdatedRle | EncodeMode::OutdatedSnapshot) {
+            let mut bytes = header.to_vec();
+            reader.read_to_end(&mut bytes)?;
+            return self._import_with(&bytes, Default::default());
+        }
+
+        let (body, mode) = BodyReader::new(reader, &header)?;
+        let result = if mode.is_fast_snapshot() && self.can_reset_with_snapshot() {
+            ensure_cov::notify_cov("loro_internal::import::snapshot_from_reader");
+            decode_snapshot_from_reader(self, body)
+        } else {
+            self.update_oplog_and_apply_delta_to_state_if_needed(
+                |oplog| encoding::decode_oplog_from_reader(oplog, mode, body),
+                Default::default(),
+            )
+        };
+        self.emit_events();
+        result
+    }
+
     #[tracing::instrument(skip_all)]
     fn _import_with(
         &self,
@@ -424,8 +639,13 @@ impl LoroDoc {
         origin: InternalString,
     ) -> Result<ImportStatus, LoroError> {
         ensure_cov::notify_cov("loro_internal::import");
-        let parsed = parse_header_and_body(bytes, true)?;
+        if let Some(options) = self.config.import_options() {
+            options.check_input_len(bytes.len())?;
+        }
+        let bytes = unwrap_stream(bytes)?;
+        let parsed = parse_header_and_body(&bytes, true)?;
         info!("Importing with mode={:?}", &parsed.mode);
+
         let result = match parsed.mode {
             EncodeMode::OutdatedRle => {
                 if self.state.try_lock().unwrap().is_in_txn() {
@@ -454,7 +674,9 @@ impl LoroDoc {
                     )
                 }
             }
-            EncodeMode::FastSnapshot => {
+            EncodeMode::FastSnapshot
+            | EncodeMode::FastSnapshotZstd
+            | EncodeMode::FastSnapshotEncrypted => {
                 if self.can_reset_with_snapshot() {
                     ensure_cov::notify_cov("loro_internal::import::snapshot");
                     tracing::info!("Init by fast snapshot {}", self.peer_id());
@@ -475,9 +697,7 @@ impl LoroDoc {
                 |oplog| oplog.decode(parsed),
                 origin,
             ),
-            EncodeMode::Auto => {
-                unreachable!()
-            }
+            EncodeMode::FastStream | EncodeMode::Auto => unreachable!(),
         };
 
         self.emit_events();
@@ -578,6 +798,18 @@ impl LoroDoc {
         json
     }
 
+    /// Get the changes between the two versions, with their ops.
+    ///
+    /// The changes are sliced by the versions, and sorted by lamport.
+    pub fn changes_between(&self, from: &VersionVector, to: &VersionVector) -> Vec<ChangeWithOps> {
+        self.commit_then_stop();
+        let oplog = self.oplog.try_lock().unwrap();
+        let ans = crate::encoding::json_schema::export_changes_with_ops(&oplog, from, to);
+        drop(oplog);
+        self.renew_txn_if_auto_commit();
+        ans
+    }
+
     /// Get the version vector of the current OpLog
     #[inline]
     pub fn oplog_vv(&self) -> VersionVector {
@@ -825,26 +1057,146 @@ impl LoroDoc {
     }
 
     /// Calculate the diff between the current state and the target state, and apply the diff to the current state.
+    ///
+    /// The applied ops are not committed. Use [LoroDoc::revert_to] to commit them as a revert.
     pub fn diff_and_apply(&self, target: &Frontiers) -> LoroResult<()> {
         let f = self.state_frontiers();
         let diff = self.diff(&f, target)?;
         self.apply_diff(diff, &mut Default::default(), false)
     }
 
+    /// Revert the current state to the target version by committing new local operations.
+    ///
+    /// It calculates the diff between the current state and the target state, and applies
+    /// the diff to the current state. The history is kept untouched, so the revert can be
+    /// merged by other peers like any normal edit.
+    #[instrument(level = "info", skip(self))]
+    pub fn revert_to(&self, target: &Frontiers) -> LoroResult<()> {
+        if !self.can_edit() {
+            return Err(LoroError::EditWhenDetached);
+        }
+
+        let f = self.state_frontiers();
+        let diff = self.diff(&f, target)?;
+        self.apply_diff(diff, &mut Default::default(), false)?;
+        self.commit_then_renew();
+        Ok(())
+    }
+
+    /// Apply the effect of the given changes on top of the current state as new local operations.
+    ///
+    /// Only the ops inside the spans are picked, their causal history is not applied.
+    /// For each continuous part of a span, it calculates the diff from the deps of the part to
+    /// the end of the part, transforms it based on the diff from the deps to the current state,
+    /// and applies it to the current state. The spans that are already included by the current
+    /// state are skipped.
+    ///
+    /// The spans picked by this doc are recorded, so picking them again doesn't apply them twice.
+    /// The record is kept in memory only, it's not shared with other peers or persisted in
+    /// the exported data.
+    ///
+    /// The containers edited by both sides are reported as conflicts in the result.
+    #[instrument(level = "info", skip(self))]
+    pub fn cherry_pick(&self, spans: &[IdSpan]) -> LoroResult<CherryPickResult> {
+        if !self.can_edit() {
+            return Err(LoroError::EditWhenDetached);
+        }
+
+        let mut parts = Vec::new();
+        {
+            let oplog = self.oplog.try_lock().unwrap();
+            let picked = self.cherry_picked.try_lock().unwrap();
+            for span in spans {
+                let mut span = *span;
+                span.normalize_();
+                if span.counter.start >= span.counter.end {
+                    continue;
+                }
+
+                if !oplog.vv().includes_id(span.id_last()) {
+                    return Err(LoroError::FrontiersNotFound(span.id_last()));
+                }
+
+                for span in picked.not_picked(span) {
+                    parts.extend(oplog.split_span_based_on_deps(span));
+                }
+            }
+        }
+
+        let mut container_remap = FxHashMap::default();
+        let mut ans = CherryPickResult::default();
+        for (mut span, mut deps) in parts {
+            let current = self.state_frontiers();
+            let applied_end = self
+                .oplog
+                .try_lock()
+                .unwrap()
+                .dag
+                .frontiers_to_vv(&current)
+                .unwrap()
+                .get(&span.peer)
+                .copied()
+                .unwrap_or(0);
+            if applied_end >= span.counter.end {
+                continue;
+            }
+
+            if applied_end > span.counter.start {
+                // The beginning of the span is already in the current state
+                span.counter.start = applied_end;
+                deps = ID::new(span.peer, applied_end - 1).into();
+            }
+
+            // The diffs share the checkout to the deps, and the last one ends at the current state
+            let [mut pick, concurrent]: [DiffBatch; 2] = self
+                .diff_many(&deps, &[span.id_last().into(), current])?
+                .try_into()
+                .unwrap();
+            collect_cherry_pick_conflicts(span, &pick, &concurrent, &mut ans.conflicts);
+            pick.transform(&concurrent, true);
+
+            let unreachable: Vec<ContainerID> = {
+                let mut state = self.state.try_lock().unwrap();
+                pick.order
+                    .iter()
+                    .filter(|id| !container_remap.contains_key(*id) && !state.get_reachable(id))
+                    .cloned()
+                    .collect()
+            };
+
+            self.apply_diff(pick, &mut container_remap, true)?;
+            self.commit_then_renew();
+            self.cherry_picked.try_lock().unwrap().insert(span);
+            for container in unreachable {
+                if !container_remap.contains_key(&container) {
+                    ans.conflicts
+                        .push(CherryPickConflict::Unreachable { span, container });
+                }
+            }
+
+            ans.applied.push(span);
+        }
+
+        Ok(ans)
+    }
+
     /// Calculate the diff between two versions so that apply diff on a will make the state same as b.
     ///
     /// NOTE: This method will make the doc enter the **detached mode**.
     // FIXME: This method needs testing (no event should be emitted during processing this)
     pub fn diff(&self, a: &Frontiers, b: &Frontiers) -> LoroResult<DiffBatch> {
+        Ok(self.diff_many(a, std::slice::from_ref(b))?.pop().unwrap())
+    }
+
+    /// Calculate the diffs from `a` to each of the targets.
+    ///
+    /// The doc is checked out to `a` before each target. If the last target is the current
+    /// state, checking out back to the current state at the end is free.
+    fn diff_many(&self, a: &Frontiers, targets: &[Frontiers]) -> LoroResult<Vec<DiffBatch>> {
         {
-            // check whether a and b are valid
+            // check whether a and the targets are valid
             let oplog = self.oplog.try_lock().unwrap();
-            for id in a.iter() {
-                if !oplog.dag.contains(id) {
-                    return Err(LoroError::FrontiersNotFound(id));
-                }
-            }
-            for id in b.iter() {
+            for id in a.iter().chain(targets.iter().flat_map(|b| b.iter())) {
                 if !oplog.dag.contains(id) {
                     return Err(LoroError::FrontiersNotFound(id));
                 }
@@ -856,21 +1208,31 @@ impl LoroDoc {
         let ans = {
             let was_detached = self.is_detached();
             let old_frontiers = self.state_frontiers();
+            let was_recording = self.state.try_lock().unwrap().is_recording();
             self.state.try_lock().unwrap().stop_and_clear_recording();
-            self.checkout_without_emitting(a, true).unwrap();
-            self.state.try_lock().unwrap().start_recording();
-            self.checkout_without_emitting(b, true).unwrap();
-            let mut state = self.state.try_lock().unwrap();
-            let e = state.take_events();
-            state.stop_and_clear_recording();
+            let mut ans = Vec::with_capacity(targets.len());
+            for b in targets {
+                self.checkout_without_emitting(a, true).unwrap();
+                self.state.try_lock().unwrap().start_recording();
+                self.checkout_without_emitting(b, true).unwrap();
+                let mut state = self.state.try_lock().unwrap();
+                let e = state.take_events();
+                state.stop_and_clear_recording();
+                ans.push(DiffBatch::new(e));
+            }
+
             self.checkout_without_emitting(&old_frontiers, false)
                 .unwrap();
             if !was_detached {
                 self.set_detached(false);
             }
-            DiffBatch::new(e)
+            if was_recording {
+                self.state.try_lock().unwrap().start_recording();
+            }
+            ans
         };
 
+        self.renew_txn_if_auto_commit();
         Ok(ans)
     }
 
@@ -895,22 +1257,59 @@ impl LoroDoc {
             return Err(LoroError::EditWhenDetached);
         }
 
-        // Sort container from the top to the bottom, so that we can have correct container remap
-        let containers = diff.0.keys().cloned().sorted_by_cached_key(|cid| {
-            let idx = self.arena.id_to_idx(cid).unwrap();
-            self.arena.get_depth(idx).unwrap().get()
-        });
+        // Check every target before applying anything, so that a missing container doesn't
+        // leave the diff partially applied. A container unknown to this doc is only reachable
+        // if it's created by the batch and remapped.
+        let created = diff.created_containers();
+        let missing: Vec<ContainerID> = diff
+            .order
+            .iter()
+            .filter(|cid| {
+                let mut id = *cid;
+                while let Some(rid) = container_remap.get(id) {
+                    id = rid;
+                }
+                id.is_normal() && self.arena.id_to_idx(id).is_none() && !created.contains(*cid)
+            })
+            .cloned()
+            .collect();
+        if !missing.is_empty() {
+            return Err(LoroError::NotFoundError(
+                format!("Containers {:?}", missing).into_boxed_str(),
+            ));
+        }
+
+        // Sort container from the top to the bottom, so that we can have correct container remap.
+        // The containers unknown to this doc keep their relative order in the batch and are applied
+        // at last, because they can only be reached by remapping.
+        let containers = std::mem::take(&mut diff.order)
+            .into_iter()
+            .sorted_by_cached_key(|cid| {
+                self.arena
+                    .id_to_idx(cid)
+                    .and_then(|idx| self.arena.get_depth(idx))
+                    .map(|d| d.get())
+                    .unwrap_or(u16::MAX)
+            });
 
         let mut ans: LoroResult<()> = Ok(());
+        let mut missing_containers: Vec<ContainerID> = Vec::new();
         for mut id in containers {
             let mut remapped = false;
-            let diff = diff.0.remove(&id).unwrap();
+            let Some(diff) = diff.cid_to_events.remove(&id) else {
+                continue;
+            };
 
             while let Some(rid) = container_remap.get(&id) {
                 remapped = true;
                 id = rid.clone();
             }
 
+            if id.is_normal() && self.arena.id_to_idx(&id).is_none() {
+                missing_containers.push(id);
+                continue;
+            }
+
             if skip_unreachable && !remapped && !self.state.try_lock().unwrap().get_reachable(&id) {
                 continue;
             }
@@ -921,6 +1320,12 @@ impl LoroDoc {
             }
         }
 
+        if !missing_containers.is_empty() {
+            return Err(LoroError::NotFoundError(
+                format!("Containers {:?}", missing_containers).into_boxed_str(),
+            ));
+        }
+
         ans
     }
 
@@ -995,9 +1400,15 @@ impl LoroDoc {
             return self.import(&bytes[0]).map(|_| ());
         }
 
+        let (_, dict) = self.config.snapshot_compression();
+        let key = self.config.encryption_key();
         let mut meta_arr = bytes
             .iter()
-            .map(|b| Ok((LoroDoc::decode_import_blob_meta(b, false)?, b)))
+            .map(|b| {
+                let meta =
+                    LoroDoc::decode_import_blob_meta_with(b, false, dict.clone(), key.clone())?;
+                Ok((meta, b))
+            })
             .collect::<LoroResult<Vec<(ImportBlobMetadata, &Vec<u8>)>>>()?;
         meta_arr.sort_by(|a, b| {
             a.0.mode
@@ -1055,7 +1466,29 @@ impl LoroDoc {
         self.state.try_lock().unwrap().get_deep_value_with_id()
     }
 
+    /// Get a digest of the current state, see [DocState::state_hash].
+    #[inline]
+    pub fn state_hash(&self) -> StateHash {
+        self.state.try_lock().unwrap().state_hash()
+    }
+
+    /// Get the root of the Merkle tree over the states of the containers,
+    /// see [DocState::merkle_root].
+    #[inline]
+    pub fn merkle_root(&self) -> MerkleNode {
+        self.state.try_lock().unwrap().merkle_root()
+    }
+
+    /// Get the child containers of the container and their digests in the Merkle tree.
+    ///
+    /// Return `None` if the container doesn't exist in the doc.
+    pub fn merkle_children(&self, id: &ContainerID) -> Option<Vec<(ContainerID, StateHash)>> {
+        let idx = self.arena.id_to_idx(id)?;
+        Some(self.state.try_lock().unwrap().merkle_node(idx).children)
+    }
+
     pub fn checkout_to_latest(&self) {
+        self.leave_branch();
         self.commit_then_renew();
         if !self.is_detached() {
             return;
@@ -1083,6 +1516,7 @@ impl LoroDoc {
     /// This will make the current [DocState] detached from the latest version of [OpLog].
     /// Any further import will not be reflected on the [DocState], until user call [LoroDoc::attach()]
     pub fn checkout(&self, frontiers: &Frontiers) -> LoroResult<()> {
+        self.leave_branch();
         self.checkout_without_emitting(frontiers, true)?;
         self.emit_events();
         if self.config.detached_editing() {
@@ -1093,6 +1527,27 @@ impl LoroDoc {
         Ok(())
     }
 
+    /// Get the version that contains every change whose timestamp is not greater than the
+    /// given timestamp (in seconds), and the causal dependencies of them.
+    ///
+    /// The timestamps are only recorded when [LoroDoc::set_record_timestamp] is enabled or
+    /// when they are set explicitly in the commit options.
+    pub fn frontiers_at_timestamp(&self, timestamp: Timestamp) -> LoroResult<Frontiers> {
+        self.oplog
+            .try_lock()
+            .unwrap()
+            .frontiers_at_timestamp(timestamp)
+    }
+
+    /// Checkout [DocState] to the version at the given timestamp (in seconds).
+    ///
+    /// See [LoroDoc::frontiers_at_timestamp] and [LoroDoc::checkout].
+    pub fn checkout_at_timestamp(&self, timestamp: Timestamp) -> LoroResult<()> {
+        self.commit_then_renew();
+        let frontiers = self.frontiers_at_timestamp(timestamp)?;
+        self.checkout(&frontiers)
+    }
+
     #[instrument(level = "info", skip(self))]
     pub(crate) fn checkout_without_emitting(
         &self,
@@ -1272,6 +1727,8 @@ impl LoroDoc {
 
                 // Step 2: Create a new document and import the initial snapshot.
                 let doc = LoroDoc::new();
+                // The snapshot may need the dictionary or the key of this doc
+                doc.set_config(&self.config);
                 doc.import(&initial_snapshot).unwrap();
                 self.checkout(&self.shallow_since_frontiers()).unwrap();
                 assert_eq!(self.get_deep_value(), doc.get_deep_value());
@@ -1512,6 +1969,28 @@ impl LoroDoc {
         self.oplog.try_lock().unwrap().compact_change_store();
     }
 
+    /// Squash the changes of this peer that are not included by `since` into as few changes as
+    /// possible, all of which carry the given commit message.
+    ///
+    /// `since` marks the boundary of the local history that has not been shared yet. The ops are
+    /// kept as they are, so the state of the doc doesn't change. Consecutive changes can only
+    /// be squashed into one if the latter only depends on the former, so the local changes
+    /// that depend on imported changes still start new changes.
+    ///
+    /// The caller must make sure the changes after `since` have not been exported, otherwise
+    /// other peers keep the old changes and their metadata. It returns an error if a change from
+    /// another peer depends on them.
+    pub fn squash_local(&self, since: &Frontiers, commit_msg: Option<&str>) -> LoroResult<()> {
+        self.commit_then_stop();
+        let ans = self.oplog.try_lock().unwrap().squash_local(
+            self.peer_id(),
+            since,
+            commit_msg.filter(|x| !x.is_empty()).map(Arc::from),
+        );
+        self.renew_txn_if_auto_commit();
+        ans
+    }
+
     /// Analyze the container info of the doc
     ///
     /// This is used for development and debugging
@@ -1520,6 +1999,14 @@ impl LoroDoc {
         DocAnalysis::analyze(self)
     }
 
+    /// The causal graph of the changes as a mermaid flowchart.
+    ///
+    /// You can visualize it at https://mermaid.live/. This is used for debugging
+    pub fn dag_mermaid(&self) -> String {
+        let oplog = self.oplog.try_lock().unwrap();
+        oplog.dag().mermaid()
+    }
+
     /// Get the path from the root to the container
     pub fn get_path_to_container(&self, id: &ContainerID) -> Option<Vec<(ContainerID, Index)>> {
         let mut state = self.state.try_lock().unwrap();
@@ -1542,12 +2029,50 @@ impl LoroDoc {
                 None => export_state_only_snapshot(self, &self.oplog_frontiers())?,
             },
             ExportMode::SnapshotAt { version } => export_snapshot_at(self, &version)?,
+            ExportMode::Containers { roots } => export_containers(self, &roots)?,
         };
 
         self.renew_txn_if_auto_commit();
         Ok(ans)
     }
 
+    /// Export the document in the given mode, in the newest format that the readers of
+    /// `version` can import.
+    ///
+    /// It fails with [LoroEncodeError::IncompatibleEncodingVersion] if the blob would use a
+    /// feature that the format of `version` cannot represent.
+    pub fn export_with_version(
+        &self,
+        mode: ExportMode,
+        version: EncodingVersion,
+    ) -> Result<Vec<u8>, LoroEncodeError> {
+        match version {
+            EncodingVersion::Latest => self.export(mode),
+            EncodingVersion::Fast => downgrade_to_fast(self, self.export(mode)?),
+            EncodingVersion::Outdated => {
+                self.commit_then_stop();
+                let ans = export_outdated(self, mode);
+                self.renew_txn_if_auto_commit();
+                ans
+            }
+        }
+    }
+
+    /// Export the document in the given mode to a writer.
+    ///
+    /// The change blocks and the blocks of the stores are written as they are encoded, followed
+    /// by the checksum. It's imported like the blobs exported by [`LoroDoc::export`].
+    pub fn export_to_writer<W: Write>(
+        &self,
+        mode: ExportMode,
+        mut writer: W,
+    ) -> Result<(), LoroEncodeError> {
+        self.commit_then_stop();
+        let ans = export_to_writer(self, mode, &mut writer);
+        self.renew_txn_if_auto_commit();
+        ans
+    }
+
     /// The doc only contains the history since the shallow history start version vector.
     ///
     /// This is empty if the doc is not shallow.
@@ -1596,6 +2121,135 @@ pub enum ChangeTravelError {
     TargetVersionNotIncluded,
 }
 
+/// A span of the text created by continuous ops of the same change, see [TextHandler::blame].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextBlameSpan {
+    /// The unicode range of the span in the current state of the text.
+    pub range: std::ops::Range<usize>,
+    /// The id of the op that inserted the first char of the span.
+    pub id: ID,
+    /// The lamport of the op that inserted the first char of the span.
+    pub lamport: Lamport,
+    /// The change that contains the ops.
+    pub change: Option<ChangeMeta>,
+}
+
+/// The spans applied by [LoroDoc::cherry_pick], the ranges of the counters of each peer
+/// are sorted and don't overlap.
+#[derive(Debug, Default)]
+pub(crate) struct PickedSpans(FxHashMap<PeerID, Vec<Range<Counter>>>);
+
+impl PickedSpans {
+    /// The parts of the normalized span that are not picked yet
+    fn not_picked(&self, span: IdSpan) -> Vec<IdSpan> {
+        let mut ans = Vec::new();
+        let mut start = span.counter.start;
+        let end = span.counter.end;
+        if let Some(ranges) = self.0.get(&span.peer) {
+            for r in ranges {
+                if r.end <= start {
+                    continue;
+                }
+                if r.start >= end {
+                    break;
+                }
+                if r.start > start {
+                    ans.push(IdSpan::new(span.peer, start, r.start));
+                }
+                start = r.end;
+            }
+        }
+        if start < end {
+            ans.push(IdSpan::new(span.peer, start, end));
+        }
+        ans
+    }
+
+    fn insert(&mut self, span: IdSpan) {
+        let ranges = self.0.entry(span.peer).or_default();
+        let mut new = span.counter.start..span.counter.end;
+        ranges.retain(|r| {
+            if r.end < new.start || r.start > new.end {
+                return true;
+            }
+            new.start = new.start.min(r.start);
+            new.end = new.end.max(r.end);
+            false
+        });
+        let i = ranges.partition_point(|r| r.start < new.start);
+        ranges.insert(i, new);
+    }
+}
+
+/// The result of [LoroDoc::cherry_pick].
+#[derive(Debug, Clone, Default)]
+pub struct CherryPickResult {
+    /// The spans whose effect was applied to the current state.
+    pub applied: Vec<IdSpan>,
+    /// The conflicts between the picked changes and the current state.
+    pub conflicts: Vec<CherryPickConflict>,
+}
+
+/// A conflict between the changes picked by [LoroDoc::cherry_pick] and the current state.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CherryPickConflict {
+    /// The container was edited by both the picked span and the current state since the span's deps.
+    ///
+    /// The picked diff was transformed and applied on top of the current state.
+    Concurrent {
+        span: IdSpan,
+        container: ContainerID,
+    },
+    /// The same key of the map was set by both the picked span and the current state.
+    ///
+    /// The value from the picked span wins.
+    MapKey {
+        span: IdSpan,
+        container: ContainerID,
+        key: InternalString,
+    },
+    /// The container is not reachable in the current state, so the picked edits to it were dropped.
+    Unreachable {
+        span: IdSpan,
+        container: ContainerID,
+    },
+}
+
+fn collect_cherry_pick_conflicts(
+    span: IdSpan,
+    pick: &DiffBatch,
+    concurrent: &DiffBatch,
+    conflicts: &mut Vec<CherryPickConflict>,
+) {
+    for (container, diff) in pick.iter() {
+        let Some(other) = concurrent.cid_to_events.get(container) else {
+            continue;
+        };
+
+        match (diff, other) {
+            (Diff::Map(a), Diff::Map(b)) => {
+                for key in a.updated.keys() {
+                    if b.updated.contains_key(key) {
+                        conflicts.push(CherryPickConflict::MapKey {
+                            span,
+                            container: container.clone(),
+                            key: key.clone(),
+                        });
+                    }
+                }
+            }
+            #[cfg(feature = "counter")]
+            (Diff::Counter(_), Diff::Counter(_)) => {
+                // Counter edits are commutative
+            }
+            _ => conflicts.push(CherryPickConflict::Concurrent {
+                span,
+                container: container.clone(),
+            }),
+        }
+    }
+}
+
 impl LoroDoc {
     pub fn travel_change_ancestors(
         &self,
@@ -7,7 +7,7 @@ use std::borrow::Cow;
 use std::cell::RefCell;
 use std::cmp::Ordering;
 use std::rc::Rc;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 use tracing::{debug, trace, trace_span};
 
 use self::change_store::iter::MergedChangeIter;
@@ -24,10 +24,10 @@ use crate::history_cache::ContainerHistoryCache;
 use crate::id::{Counter, PeerID, ID};
 use crate::op::{FutureInnerContent, ListSlice, RawOpContent, RemoteOp, RichOp};
 use crate::span::{HasCounterSpan, HasLamportSpan};
-use crate::version::{Frontiers, ImVersionVector, VersionVector};
+use crate::version::{Frontiers, ImVersionVector, VersionRange, VersionVector};
 use crate::LoroError;
-use change_store::BlockOpRef;
-use loro_common::{IdLp, IdSpan};
+use change_store::{BatchDecodeInfo, BlockOpRef};
+use loro_common::{IdLp, IdSpan, LoroResult};
 use rle::{HasLength, RleVec, Sliceable};
 use smallvec::SmallVec;
 
@@ -54,6 +54,9 @@ pub struct OpLog {
     /// If so the Dag's frontiers won't be updated until the batch is finished.
     pub(crate) batch_importing: bool,
     pub(crate) configure: Configure,
+    /// The changes rejected by the import filter and their descendants.
+    /// They are never added to the oplog.
+    pub(crate) quarantine: VersionRange,
 }
 
 impl std::fmt::Debug for OpLog {
@@ -70,7 +73,7 @@ impl OpLog {
     pub(crate) fn new() -> Self {
         let arena = SharedArena::new();
         let cfg = Configure::default();
-        let change_store = ChangeStore::new_mem(&arena, cfg.merge_interval.clone());
+        let change_store = ChangeStore::new_mem(&arena, cfg.clone());
         Self {
             history_cache: Mutex::new(ContainerHistoryCache::new(change_store.clone(), None)),
             dag: AppDag::new(change_store.clone()),
@@ -79,6 +82,7 @@ impl OpLog {
             pending_changes: Default::default(),
             batch_importing: false,
             configure: cfg,
+            quarantine: Default::default(),
         }
     }
 
@@ -117,11 +121,49 @@ impl OpLog {
         timestamp
     }
 
+    /// Get the version that contains every change whose timestamp is not greater than the
+    /// given timestamp, and the causal dependencies of them.
+    pub fn frontiers_at_timestamp(&self, timestamp: Timestamp) -> LoroResult<Frontiers> {
+        let mut vv = VersionVector::default();
+        for (&peer, _) in self.vv().iter() {
+            if let Some(end) = self
+                .change_store
+                .get_counter_end_by_timestamp_lte(peer, timestamp)?
+            {
+                vv.insert(peer, end);
+            }
+        }
+
+        // The timestamps of the changes are non-decreasing along the causal order when they
+        // are created by Loro, so `vv` is already causally closed in most cases. But the
+        // timestamps of the remote changes cannot be trusted, so we still need to include the
+        // missing deps here.
+        let frontiers = self.dag.vv_to_frontiers(&vv);
+        Ok(match self.dag.frontiers_to_vv(&frontiers) {
+            Some(closure) if closure != vv => self.dag.vv_to_frontiers(&closure),
+            _ => frontiers,
+        })
+    }
+
     #[inline]
     pub fn is_empty(&self) -> bool {
         self.dag.is_empty() && self.arena.can_import_snapshot()
     }
 
+    /// Replace the empty change store with the one imported from a snapshot, whose version
+    /// is described by `info`
+    pub(crate) fn set_change_store(
+        &mut self,
+        change_store: ChangeStore,
+        info: BatchDecodeInfo,
+    ) -> LoroResult<()> {
+        debug_assert!(self.dag.is_empty());
+        self.history_cache = Mutex::new(ContainerHistoryCache::new(change_store.clone(), None));
+        self.dag = AppDag::new(change_store.clone());
+        self.change_store = change_store;
+        self.dag.set_version_by_fast_snapshot_import(info)
+    }
+
     /// This is the **only** place to update the `OpLog.changes`
     pub(crate) fn insert_new_change(&mut self, change: Change, from_local: bool) {
         let s = trace_span!(
@@ -357,6 +399,34 @@ impl OpLog {
         self.change_store.export_blocks_in_range(spans, w)
     }
 
+    /// Call `f` with the bytes of each block of the changes after `vv` as soon as it's encoded
+    pub(crate) fn for_each_block_from(
+        &self,
+        vv: &VersionVector,
+        f: &mut dyn FnMut(Bytes) -> std::io::Result<()>,
+    ) -> std::io::Result<()> {
+        self.change_store
+            .for_each_block_from(vv, self.shallow_since_vv(), self.vv(), f)
+    }
+
+    /// Call `f` with the bytes of each block of the changes in `spans` as soon as it's encoded
+    #[inline(always)]
+    pub(crate) fn for_each_block_in_range(
+        &self,
+        spans: &[IdSpan],
+        f: &mut dyn FnMut(Bytes) -> std::io::Result<()>,
+    ) -> std::io::Result<()> {
+        self.change_store.for_each_block_in_range(spans, f)
+    }
+
+    pub(crate) fn export_redacted_block_bytes(
+        &self,
+        range: &VersionRange,
+    ) -> LoroResult<Vec<Bytes>> {
+        self.change_store
+            .export_redacted_block_bytes(range, self.vv())
+    }
+
     pub(crate) fn fork_changes_up_to(&self, frontiers: &Frontiers) -> Option<Bytes> {
         let vv = self.dag.frontiers_to_vv(frontiers)?;
         Some(
@@ -566,6 +636,72 @@ impl OpLog {
             .flush_and_compact(self.dag.vv(), self.dag.frontiers());
     }
 
+    /// Squash the changes of the peer that are not included by `since` into as few changes as
+    /// possible, then compact the change store.
+    pub(crate) fn squash_local(
+        &mut self,
+        peer: PeerID,
+        since: &Frontiers,
+        commit_msg: Option<Arc<str>>,
+    ) -> Result<(), LoroError> {
+        for id in since.iter() {
+            if !self.dag.contains(id) {
+                return Err(LoroError::FrontiersNotFound(id));
+            }
+        }
+
+        let since_vv = self.dag.frontiers_to_vv(since).unwrap();
+        let start = since_vv.get(&peer).copied().unwrap_or(0);
+        let end = self.vv().get(&peer).copied().unwrap_or(0);
+        if start >= end {
+            return Ok(());
+        }
+
+        // If a change from another peer depends on the changes to squash, they must have been
+        // shared already
+        for (&other, &other_end) in self.vv().iter() {
+            if other == peer || other_end == 0 {
+                continue;
+            }
+
+            let vv = self
+                .dag
+                .frontiers_to_vv(&ID::new(other, other_end - 1).into())
+                .unwrap();
+            if vv.get(&peer).copied().unwrap_or(0) > start {
+                return Err(LoroError::ArgErr(
+                    format!(
+                        "The local changes after `since` have been shared, peer {} depends on them",
+                        other
+                    )
+                    .into_boxed_str(),
+                ));
+            }
+        }
+
+        let signer = self.configure.change_signer();
+        self.change_store
+            .squash_changes(peer, start, commit_msg, signer.as_ref())?;
+        self.compact_change_store();
+        Ok(())
+    }
+
+    /// Verify the signatures of the changes that are not in the oplog yet,
+    /// if a [crate::signature::ChangeVerifier] is set
+    pub(crate) fn verify_change_signatures(&self, changes: &[Change]) -> Result<(), LoroError> {
+        let Some(verifier) = self.configure.change_verifier() else {
+            return Ok(());
+        };
+
+        let vv = self.vv();
+        verifier.verify(
+            changes
+                .iter()
+                .filter(|c| c.ctr_end() > vv.get(&c.id.peer).copied().unwrap_or(0)),
+            &self.arena,
+        )
+    }
+
     #[inline]
     pub fn change_store_kv_size(&self) -> usize {
         self.change_store.kv_size()
@@ -576,6 +712,12 @@ impl OpLog {
             .encode_all(self.dag.vv(), self.dag.frontiers())
     }
 
+    /// Write the bytes that [OpLog::encode_change_store] would return to `w`
+    pub(crate) fn encode_change_store_to(&self, w: &mut dyn std::io::Write) -> std::io::Result<()> {
+        self.change_store
+            .encode_all_to(self.dag.vv(), self.dag.frontiers(), w)
+    }
+
     pub fn check_dag_correctness(&self) {
         self.dag.check_dag_correctness();
     }
@@ -631,6 +773,7 @@ pub(crate) fn convert_change_to_remote(
         lamport: change.lamport,
         timestamp: change.timestamp,
         commit_msg: change.commit_msg.clone(),
+        signature: change.signature.clone(),
     }
 }
 
@@ -1,13 +1,19 @@
-use self::block_encode::{decode_block, decode_header, encode_block, ChangesBlockHeader};
+use self::block_encode::{
+    decode_block, decode_header, decode_timestamps, encode_block, ChangesBlockHeader,
+};
 use super::{loro_dag::AppDagNodeInner, AppDagNode};
 use crate::{
     arena::SharedArena,
-    change::Change,
+    change::{Change, Timestamp},
+    configure::Configure,
+    encoding::{value::ValueLimits, ImportOptions},
     estimated_size::EstimatedSize,
     kv_store::KvStore,
     op::Op,
     parent::register_container_and_parent_link,
-    version::{Frontiers, ImVersionVector},
+    redact::redact_change,
+    signature::ChangeSigner,
+    version::{Frontiers, ImVersionVector, VersionRange},
     VersionVector,
 };
 use block_encode::decode_block_range;
@@ -24,7 +30,7 @@ use std::{
     cmp::Ordering,
     collections::{BTreeMap, VecDeque},
     ops::{Bound, Deref},
-    sync::{atomic::AtomicI64, Arc, Mutex},
+    sync::{Arc, Mutex},
 };
 use tracing::{debug, info_span, trace, warn};
 
@@ -67,7 +73,9 @@ pub struct ChangeStore {
     external_kv: Arc<Mutex<dyn KvStore>>,
     /// The version vector of the external kv store.
     external_vv: Arc<Mutex<VersionVector>>,
-    merge_interval: Arc<AtomicI64>,
+    /// The merge interval of the changes, and the compression and the encryption of the
+    /// blocks in external_kv
+    configure: Configure,
 }
 
 #[derive(Debug, Clone)]
@@ -112,7 +120,7 @@ pub const VV_KEY: &[u8] = b"vv";
 pub const FRONTIERS_KEY: &[u8] = b"fr";
 
 impl ChangeStore {
-    pub fn new_mem(a: &SharedArena, merge_interval: Arc<AtomicI64>) -> Self {
+    pub fn new_mem(a: &SharedArena, configure: Configure) -> Self {
         Self {
             inner: Arc::new(Mutex::new(ChangeStoreInner {
                 start_vv: ImVersionVector::new(),
@@ -123,21 +131,42 @@ impl ChangeStore {
             external_vv: Arc::new(Mutex::new(VersionVector::new())),
             external_kv: Arc::new(Mutex::new(MemKvStore::new(MemKvConfig::default()))),
             // external_kv: Arc::new(Mutex::new(BTreeMap::default())),
-            merge_interval,
+            configure,
         }
     }
 
+    /// A store that never merges the inserted changes
+    fn new_without_merging(a: &SharedArena) -> Self {
+        let configure = Configure::default();
+        configure.set_merge_interval(0);
+        Self::new_mem(a, configure)
+    }
+
     #[cfg(test)]
     fn new_for_test() -> Self {
-        Self::new_mem(&SharedArena::new(), Arc::new(AtomicI64::new(0)))
+        Self::new_without_merging(&SharedArena::new())
     }
 
     pub(super) fn encode_all(&self, vv: &VersionVector, frontiers: &Frontiers) -> Bytes {
         self.flush_and_compact(vv, frontiers);
         let mut kv = self.external_kv.try_lock().unwrap();
+        self.configure.apply_codec(&mut *kv);
         kv.export_all()
     }
 
+    /// Write the bytes that [ChangeStore::encode_all] would return to `w`
+    pub(super) fn encode_all_to(
+        &self,
+        vv: &VersionVector,
+        frontiers: &Frontiers,
+        w: &mut dyn std::io::Write,
+    ) -> std::io::Result<()> {
+        self.flush_and_compact(vv, frontiers);
+        let mut kv = self.external_kv.try_lock().unwrap();
+        self.configure.apply_codec(&mut *kv);
+        kv.export_to(w)
+    }
+
     #[tracing::instrument(skip(self), level = "debug")]
     pub(super) fn export_from(
         &self,
@@ -146,7 +175,7 @@ impl ChangeStore {
         latest_vv: &VersionVector,
         latest_frontiers: &Frontiers,
     ) -> Bytes {
-        let new_store = ChangeStore::new_mem(&self.arena, self.merge_interval.clone());
+        let new_store = ChangeStore::new_mem(&self.arena, self.configure.clone());
         for span in latest_vv.sub_iter(start_vv) {
             // PERF: this can be optimized by reusing the current encoded blocks
             // In the current method, it needs to parse and re-encode the blocks
@@ -175,7 +204,18 @@ impl ChangeStore {
     }
 
     pub(super) fn export_blocks_in_range<W: std::io::Write>(&self, spans: &[IdSpan], w: &mut W) {
-        let new_store = ChangeStore::new_mem(&self.arena, self.merge_interval.clone());
+        self.for_each_block_in_range(spans, &mut |bytes| write_block(w, &bytes))
+            .unwrap();
+    }
+
+    /// Encode the changes in the given spans into blocks, calling `f` with the bytes of each
+    /// block as soon as it's encoded
+    pub(super) fn for_each_block_in_range(
+        &self,
+        spans: &[IdSpan],
+        f: &mut dyn FnMut(Bytes) -> std::io::Result<()>,
+    ) -> std::io::Result<()> {
+        let new_store = ChangeStore::new_mem(&self.arena, self.configure.clone());
         for span in spans {
             let mut span = *span;
             span.normalize_();
@@ -193,7 +233,43 @@ impl ChangeStore {
             }
         }
 
-        encode_blocks_in_store(new_store, &self.arena, w);
+        for_each_block_in_store(new_store, &self.arena, f)
+    }
+
+    /// Encode all the changes into blocks with the ops in `range` redacted.
+    ///
+    /// The redacted content is allocated in a fork of the arena, so the ops in this store
+    /// are not changed.
+    pub(super) fn export_redacted_block_bytes(
+        &self,
+        range: &VersionRange,
+        latest_vv: &VersionVector,
+    ) -> LoroResult<Vec<Bytes>> {
+        let arena = self.arena.fork();
+        let new_store = ChangeStore::new_mem(&arena, self.configure.clone());
+        for (&peer, &end) in latest_vv.iter() {
+            for c in self.iter_changes(IdSpan::new(peer, 0, end)) {
+                let mut change = Change::clone(&c);
+                redact_change(&mut change, range, &arena)?;
+                new_store.insert_change(change, false);
+            }
+        }
+
+        Ok(blocks_bytes_in_store(new_store, &arena))
+    }
+
+    /// Encode the changes into blocks without a doc. The changes of each peer should be
+    /// sorted by counter and continuous.
+    pub(crate) fn encode_changes_to_blocks(
+        changes: Vec<Change>,
+        arena: &SharedArena,
+    ) -> Vec<Bytes> {
+        let store = ChangeStore::new_without_merging(arena);
+        for change in changes {
+            store.insert_change(change, false);
+        }
+
+        blocks_bytes_in_store(store, arena)
     }
 
     fn encode_from(
@@ -212,29 +288,64 @@ impl ChangeStore {
             inner.start_vv = ImVersionVector::from_vv(start_vv);
         }
         self.flush_and_compact(latest_vv, latest_frontiers);
-        self.external_kv.try_lock().unwrap().export_all()
+        let mut kv = self.external_kv.try_lock().unwrap();
+        self.configure.apply_codec(&mut *kv);
+        kv.export_all()
     }
 
+    /// Decode the changes in the encoded store that are not included by `self_vv`.
+    ///
+    /// The blocks of the store are read with the dictionary and the key of `configure`,
+    /// and they are checked against its import options.
     pub(crate) fn decode_snapshot_for_updates(
         bytes: Bytes,
         arena: &SharedArena,
+        configure: &Configure,
         self_vv: &VersionVector,
     ) -> Result<Vec<Change>, LoroError> {
-        let change_store = ChangeStore::new_mem(arena, Arc::new(AtomicI64::new(0)));
+        let change_store = ChangeStore::new_mem(arena, configure.clone());
         let _ = change_store.import_all(bytes)?;
-        let mut changes = Vec::new();
-        change_store.visit_all_changes(&mut |c| {
-            let cnt_threshold = self_vv.get(&c.id.peer).copied().unwrap_or(0);
-            if c.id.counter >= cnt_threshold {
-                changes.push(c.clone());
-                return;
-            }
+        change_store
+            .decode_external_changes(self_vv, &configure.import_options().unwrap_or_default())
+    }
 
-            let change_end = c.ctr_end();
-            if change_end > cnt_threshold {
-                changes.push(c.slice((cnt_threshold - c.id.counter) as usize, c.atom_len()));
+    /// Decode the changes in the imported blocks that are not included by `self_vv`.
+    ///
+    /// The ops are counted from the block headers and checked against `options.max_ops`
+    /// before any op is decoded. The values are decoded within the limits of `options`.
+    pub(crate) fn decode_external_changes(
+        &self,
+        self_vv: &VersionVector,
+        options: &ImportOptions,
+    ) -> Result<Vec<Change>, LoroError> {
+        let blocks: Vec<Bytes> = self
+            .external_kv
+            .try_lock()
+            .unwrap()
+            .scan(Bound::Unbounded, Bound::Unbounded)
+            .filter(|(id, _)| id.len() == 12)
+            .map(|(_, bytes)| bytes)
+            .collect();
+        if options.max_ops.is_some() {
+            let mut ops = 0;
+            for block in blocks.iter() {
+                let block = ChangesBlock::from_bytes(block.clone())?;
+                let start = self_vv.get(&block.peer).copied().unwrap_or(0);
+                let end = block.counter_range.1;
+                ops += end.saturating_sub(start.max(block.counter_range.0)).max(0) as usize;
             }
-        });
+            options.check_ops(ops)?;
+        }
+
+        let mut changes = Vec::new();
+        for block in blocks {
+            changes.extend(Self::decode_block_bytes(
+                block,
+                &self.arena,
+                self_vv,
+                options.value_limits(),
+            )?);
+        }
 
         Ok(changes)
     }
@@ -243,8 +354,9 @@ impl ChangeStore {
         bytes: Bytes,
         arena: &SharedArena,
         self_vv: &VersionVector,
+        limits: ValueLimits,
     ) -> LoroResult<Vec<Change>> {
-        let mut ans = ChangesBlockBytes::new(bytes).parse(arena)?;
+        let mut ans = ChangesBlockBytes::new(bytes).parse_with_limits(arena, limits)?;
         if ans.is_empty() {
             return Ok(ans);
         }
@@ -432,6 +544,47 @@ impl ChangeStore {
         block
     }
 
+    /// Get the end counter of the last change of the peer whose timestamp is not greater than
+    /// the given timestamp.
+    ///
+    /// The timestamps of the changes from the same peer are usually non-decreasing, but the
+    /// timestamps of the remote changes cannot be trusted. So the blocks of the peer are visited
+    /// from the last one, and the first block whose smallest timestamp is not greater than the
+    /// given timestamp contains the answer. Only the headers and the timestamps of the visited
+    /// blocks are decoded, the blocks before the answer are not loaded.
+    pub(crate) fn get_counter_end_by_timestamp_lte(
+        &self,
+        peer: PeerID,
+        timestamp: Timestamp,
+    ) -> LoroResult<Option<Counter>> {
+        let mut end_id = ID::new(peer, Counter::MAX);
+        loop {
+            self.ensure_id_lte(end_id);
+            let block = {
+                let inner = self.inner.try_lock().unwrap();
+                let Some((_, block)) = inner.mem_parsed_kv.range(..=end_id).next_back() else {
+                    return Ok(None);
+                };
+                block.clone()
+            };
+
+            if block.peer != peer {
+                return Ok(None);
+            }
+
+            let (min, _) = block.content.timestamp_range()?;
+            if min <= timestamp {
+                return block.content.get_counter_end_by_timestamp_lte(timestamp);
+            }
+
+            if block.counter_range.0 == 0 {
+                return Ok(None);
+            }
+
+            end_id = ID::new(peer, block.counter_range.0 - 1);
+        }
+    }
+
     pub fn change_num(&self) -> usize {
         self.ensure_block_loaded_in_range(Bound::Unbounded, Bound::Unbounded);
         let mut inner = self.inner.try_lock().unwrap();
@@ -445,7 +598,7 @@ impl ChangeStore {
     pub fn fork(
         &self,
         arena: SharedArena,
-        merge_interval: Arc<AtomicI64>,
+        configure: Configure,
         vv: &VersionVector,
         frontiers: &Frontiers,
     ) -> Self {
@@ -460,7 +613,7 @@ impl ChangeStore {
             arena,
             external_vv: Arc::new(Mutex::new(self.external_vv.try_lock().unwrap().clone())),
             external_kv: self.external_kv.try_lock().unwrap().clone_store(),
-            merge_interval,
+            configure,
         }
     }
 
@@ -480,7 +633,22 @@ impl ChangeStore {
         latest_vv: &VersionVector,
         w: &mut W,
     ) {
-        let new_store = ChangeStore::new_mem(&self.arena, self.merge_interval.clone());
+        self.for_each_block_from(start_vv, shallow_since_vv, latest_vv, &mut |bytes| {
+            write_block(w, &bytes)
+        })
+        .unwrap();
+    }
+
+    /// Encode the changes after `start_vv` into blocks, calling `f` with the bytes of each
+    /// block as soon as it's encoded
+    pub(crate) fn for_each_block_from(
+        &self,
+        start_vv: &VersionVector,
+        shallow_since_vv: &ImVersionVector,
+        latest_vv: &VersionVector,
+        f: &mut dyn FnMut(Bytes) -> std::io::Result<()>,
+    ) -> std::io::Result<()> {
+        let new_store = ChangeStore::new_mem(&self.arena, self.configure.clone());
         for mut span in latest_vv.sub_iter(start_vv) {
             let counter_lower_bound = shallow_since_vv.get(&span.peer).copied().unwrap_or(0);
             span.counter.start = span.counter.start.max(counter_lower_bound);
@@ -505,8 +673,7 @@ impl ChangeStore {
             }
         }
 
-        let arena = &self.arena;
-        encode_blocks_in_store(new_store, arena, w);
+        for_each_block_in_store(new_store, &self.arena, f)
     }
 
     pub(crate) fn fork_changes_up_to(
@@ -515,7 +682,7 @@ impl ChangeStore {
         frontiers: &Frontiers,
         vv: &VersionVector,
     ) -> Bytes {
-        let new_store = ChangeStore::new_mem(&self.arena, self.merge_interval.clone());
+        let new_store = ChangeStore::new_mem(&self.arena, self.configure.clone());
         for mut span in vv.sub_iter_im(start_vv) {
             let counter_lower_bound = start_vv.get(&span.peer).copied().unwrap_or(0);
             span.counter.start = span.counter.start.max(counter_lower_bound);
@@ -544,17 +711,32 @@ impl ChangeStore {
     }
 }
 
-fn encode_blocks_in_store<W: std::io::Write>(
+fn blocks_bytes_in_store(new_store: ChangeStore, arena: &SharedArena) -> Vec<Bytes> {
+    let mut ans = Vec::new();
+    for_each_block_in_store(new_store, arena, &mut |bytes| {
+        ans.push(bytes);
+        Ok(())
+    })
+    .unwrap();
+    ans
+}
+
+fn for_each_block_in_store(
     new_store: ChangeStore,
     arena: &SharedArena,
-    w: &mut W,
-) {
+    f: &mut dyn FnMut(Bytes) -> std::io::Result<()>,
+) -> std::io::Result<()> {
     let mut inner = new_store.inner.try_lock().unwrap();
     for (_id, block) in inner.mem_parsed_kv.iter_mut() {
-        let bytes = block.to_bytes(arena);
-        leb128::write::unsigned(w, bytes.bytes.len() as u64).unwrap();
-        w.write_all(&bytes.bytes).unwrap();
+        f(block.to_bytes(arena).bytes)?;
     }
+    Ok(())
+}
+
+/// Write the block prefixed by its length, as in the body of an updates blob
+fn write_block<W: std::io::Write>(w: &mut W, bytes: &[u8]) -> std::io::Result<()> {
+    leb128::write::unsigned(w, bytes.len() as u64)?;
+    w.write_all(bytes)
 }
 
 mod mut_external_kv {
@@ -571,16 +753,34 @@ mod mut_external_kv {
                 kv_store.len() <= 2,
                 "kv store should be empty when using decode_all"
             );
-            kv_store
-                .import_all(bytes)
-                .map_err(|e| LoroError::DecodeError(e.into_boxed_str()))?;
+            self.configure.apply_codec(&mut *kv_store);
+            kv_store.try_import_all(bytes)?;
+            if self.configure.import_options().is_some_and(|x| x.strict) {
+                kv_store.check_all()?;
+            }
+
+            // Besides the vv and frontiers, every key should be the id of the block's first change.
+            // The headers of the blocks are checked here, so they can be loaded lazily later.
+            for (k, v) in kv_store.scan(Bound::Unbounded, Bound::Unbounded) {
+                if k.len() == 12 {
+                    let block = ChangesBlock::from_bytes(v)?;
+                    if ID::from_bytes(&k) != ID::new(block.peer, block.counter_range.0) {
+                        return Err(LoroError::DecodeDataCorruptionError);
+                    }
+                } else if ![VV_KEY, FRONTIERS_KEY, START_VV_KEY, START_FRONTIERS_KEY]
+                    .contains(&&k[..])
+                {
+                    return Err(LoroError::DecodeDataCorruptionError);
+                }
+            }
+
             let vv_bytes = kv_store.get(VV_KEY).unwrap_or_default();
-            let vv = VersionVector::decode(&vv_bytes).unwrap();
+            let vv = VersionVector::decode(&vv_bytes)?;
             let start_vv_bytes = kv_store.get(START_VV_KEY).unwrap_or_default();
             let start_vv = if start_vv_bytes.is_empty() {
                 Default::default()
             } else {
-                VersionVector::decode(&start_vv_bytes).unwrap()
+                VersionVector::decode(&start_vv_bytes)?
             };
 
             #[cfg(test)]
@@ -595,19 +795,21 @@ mod mut_external_kv {
 
             *self.external_vv.try_lock().unwrap() = vv.clone();
             let frontiers_bytes = kv_store.get(FRONTIERS_KEY).unwrap_or_default();
-            let frontiers = Frontiers::decode(&frontiers_bytes).unwrap();
+            let frontiers = Frontiers::decode(&frontiers_bytes)?;
             let start_frontiers = kv_store.get(START_FRONTIERS_KEY).unwrap_or_default();
             let start_frontiers = if start_frontiers.is_empty() {
                 Default::default()
             } else {
-                Frontiers::decode(&start_frontiers).unwrap()
+                Frontiers::decode(&start_frontiers)?
             };
 
             let mut max_lamport = None;
             let mut max_timestamp = 0;
             drop(kv_store);
             for id in frontiers.iter() {
-                let c = self.get_change(id).unwrap();
+                let c = self
+                    .try_get_change(id)?
+                    .ok_or(LoroError::DecodeDataCorruptionError)?;
                 debug_assert_ne!(c.atom_len(), 0);
                 let l = c.lamport_last();
                 if let Some(x) = max_lamport {
@@ -624,6 +826,13 @@ mod mut_external_kv {
                 }
             }
 
+            for id in start_frontiers.iter() {
+                // Parse the block of the start of a shallow snapshot, which is required when
+                // setting the version of the dag
+                self.try_get_change(id)?
+                    .ok_or(LoroError::DecodeDataCorruptionError)?;
+            }
+
             Ok(BatchDecodeInfo {
                 vv,
                 frontiers,
@@ -677,6 +886,120 @@ mod mut_external_kv {
             store.set(VV_KEY, vv_bytes.into());
             store.set(FRONTIERS_KEY, frontiers_bytes.into());
         }
+
+        /// Rewrite the changes of the peer from the given counter into as few changes as
+        /// possible, all of which carry the given commit message.
+        ///
+        /// A change is merged into the previous one if it only depends on the previous one,
+        /// which is the same rule used when merging changes at commit time, and if the merged
+        /// change still fits in a block. The ops, their ids and lamports are untouched, so the
+        /// dag and the doc state stay valid.
+        ///
+        /// All the affected blocks are parsed before the store is modified, so the store is left
+        /// untouched if any of them is corrupted.
+        ///
+        /// The affected blocks are removed from the external kv store. They will be written back
+        /// by the next [ChangeStore::flush_and_compact].
+        pub(crate) fn squash_changes(
+            &self,
+            peer: PeerID,
+            start: Counter,
+            commit_msg: Option<Arc<str>>,
+            signer: Option<&ChangeSigner>,
+        ) -> LoroResult<()> {
+            self.ensure_block_loaded_in_range(
+                Bound::Included(ID::new(peer, start)),
+                Bound::Included(ID::new(peer, Counter::MAX)),
+            );
+            let mut changes: Vec<Change> = Vec::new();
+            {
+                let mut inner = self.inner.try_lock().unwrap();
+                let first_block_id = inner
+                    .mem_parsed_kv
+                    .range(..=ID::new(peer, start))
+                    .next_back()
+                    .filter(|(id, _)| id.peer == peer)
+                    .map_or(ID::new(peer, start), |(id, _)| *id);
+                let block_ids = inner
+                    .mem_parsed_kv
+                    .range(first_block_id..=ID::new(peer, Counter::MAX))
+                    .map(|(id, _)| *id)
+                    .collect_vec();
+                for id in block_ids.iter() {
+                    let block = inner.mem_parsed_kv.get_mut(id).unwrap();
+                    block.ensure_changes(&self.arena)?;
+                    changes.extend(block.content.try_changes().unwrap().iter().cloned());
+                }
+
+                let mut store = self.external_kv.try_lock().unwrap();
+                let mut external_vv = self.external_vv.try_lock().unwrap();
+                for id in block_ids {
+                    inner.mem_parsed_kv.remove(&id);
+                    store.remove(&id.to_bytes());
+                }
+
+                if let Some(end) = external_vv.get_mut(&peer) {
+                    *end = (*end).min(first_block_id.counter);
+                }
+            }
+
+            let mut squashed: Vec<Change> = Vec::with_capacity(changes.len());
+            let mut last_size = 0;
+            for change in changes {
+                if change.ctr_end() <= start {
+                    squashed.push(change);
+                    continue;
+                }
+
+                let change = if change.id.counter < start {
+                    // Keep the part before `start` untouched
+                    let offset = (start - change.id.counter) as usize;
+                    let mut head = change.slice(0, offset);
+                    if let Some(signer) = signer.filter(|_| change.signature.is_some()) {
+                        signer.sign(&mut head, &self.arena);
+                    }
+                    squashed.push(head);
+                    change.slice(offset, change.atom_len())
+                } else {
+                    change
+                };
+
+                let size = change.estimate_storage_size();
+                match squashed.last_mut() {
+                    Some(last)
+                        if last.id.counter >= start
+                            && last.ctr_end() == change.id.counter
+                            && change.deps_on_self()
+                            && last_size + size <= MAX_BLOCK_SIZE =>
+                    {
+                        last_size += size;
+                        for op in change.ops.into_iter() {
+                            last.ops.push(op);
+                        }
+                    }
+                    _ => {
+                        last_size = size;
+                        squashed.push(Change {
+                            commit_msg: commit_msg.clone(),
+                            signature: None,
+                            ..change
+                        })
+                    }
+                }
+            }
+
+            if let Some(signer) = signer {
+                for change in squashed.iter_mut().filter(|c| c.id.counter >= start) {
+                    signer.sign(change, &self.arena);
+                }
+            }
+
+            for change in squashed {
+                self.insert_change(change, false);
+            }
+
+            Ok(())
+        }
     }
 }
 
@@ -700,7 +1023,8 @@ mod mut_inner_kv {
             let s = info_span!("change_store insert_change", id = ?change.id);
             let _e = s.enter();
             let estimated_size = change.estimate_storage_size();
-            if estimated_size > MAX_BLOCK_SIZE && split_when_exceeds {
+            // The signature covers the whole change, so a signed change is never split
+            if estimated_size > MAX_BLOCK_SIZE && split_when_exceeds && change.signature.is_none() {
                 self.split_change_then_insert(change);
                 return;
             }
@@ -718,7 +1042,8 @@ mod mut_inner_kv {
                     match block.push_change(
                         change,
                         estimated_size,
-                        self.merge_interval
+                        self.configure
+                            .merge_interval
                             .load(std::sync::atomic::Ordering::Acquire),
                         &self.arena,
                     ) {
@@ -740,11 +1065,21 @@ mod mut_inner_kv {
         }
 
         pub fn get_change(&self, id: ID) -> Option<BlockChangeRef> {
-            let block = self.get_parsed_block(id)?;
-            Some(BlockChangeRef {
-                change_index: block.get_change_index_by_counter(id.counter).unwrap(),
-                block: block.clone(),
-            })
+            self.try_get_change(id).expect("Parse block error")
+        }
+
+        /// Get the change with the given id, return an error if its block is corrupted.
+        pub(super) fn try_get_change(&self, id: ID) -> LoroResult<Option<BlockChangeRef>> {
+            let Some(block) = self.try_get_parsed_block(id)? else {
+                return Ok(None);
+            };
+            let change_index = block
+                .get_change_index_by_counter(id.counter)
+                .map_err(|_| LoroError::DecodeDataCorruptionError)?;
+            Ok(Some(BlockChangeRef {
+                change_index,
+                block,
+            }))
         }
 
         /// Get the change with the given peer and lamport.
@@ -908,6 +1243,7 @@ mod mut_inner_kv {
                 lamport: change.lamport,
                 timestamp: change.timestamp,
                 commit_msg: change.commit_msg.clone(),
+                signature: None,
             };
 
             let mut total_len = 0;
@@ -981,6 +1317,7 @@ mod mut_inner_kv {
                 lamport: next_lamport,
                 timestamp: new_change.timestamp,
                 commit_msg: new_change.commit_msg.clone(),
+                signature: None,
             };
 
             self.insert_change(new_change, false);
@@ -988,15 +1325,13 @@ mod mut_inner_kv {
             ans
         }
 
-        fn get_parsed_block(&self, id: ID) -> Option<Arc<ChangesBlock>> {
+        fn try_get_parsed_block(&self, id: ID) -> LoroResult<Option<Arc<ChangesBlock>>> {
             let mut inner = self.inner.try_lock().unwrap();
             // trace!("inner: {:#?}", &inner);
             if let Some((_id, block)) = inner.mem_parsed_kv.range_mut(..=id).next_back() {
                 if block.peer == id.peer && block.counter_range.1 > id.counter {
-                    block
-                        .ensure_changes(&self.arena)
-                        .expect("Parse block error");
-                    return Some(block.clone());
+                    block.ensure_changes(&self.arena)?;
+                    return Ok(Some(block.clone()));
                 }
             }
 
@@ -1015,9 +1350,11 @@ mod mut_inner_kv {
             // );
             // println!("id {:?}", id);
 
-            let (b_id, b_bytes) = iter.next_back()?;
+            let Some((b_id, b_bytes)) = iter.next_back() else {
+                return Ok(None);
+            };
             let block_id: ID = ID::from_bytes(&b_id[..]);
-            let block = ChangesBlock::from_bytes(b_bytes).unwrap();
+            let block = ChangesBlock::from_bytes(b_bytes)?;
             trace!(
                 "block_id={:?} id={:?} counter_range={:?}",
                 block_id,
@@ -1029,14 +1366,12 @@ mod mut_inner_kv {
                 && block.counter_range.1 > id.counter
             {
                 let mut arc_block = Arc::new(block);
-                arc_block
-                    .ensure_changes(&self.arena)
-                    .expect("Parse block error");
+                arc_block.ensure_changes(&self.arena)?;
                 inner.mem_parsed_kv.insert(block_id, arc_block.clone());
-                return Some(arc_block);
+                return Ok(Some(arc_block));
             }
 
-            None
+            Ok(None)
         }
 
         /// Load all the blocks that have overlapped with the given ID range into `inner_mem_parsed_kv`
@@ -1170,6 +1505,7 @@ impl ChangesBlock {
     fn from_bytes(bytes: Bytes) -> LoroResult<Self> {
         let len = bytes.len();
         let mut bytes = ChangesBlockBytes::new(bytes);
+        bytes.ensure_header()?;
         let peer = bytes.peer();
         let counter_range = bytes.counter_range();
         let lamport_range = bytes.lamport_range();
@@ -1462,6 +1798,45 @@ impl ChangesBlockContent {
             ChangesBlockContent::Bytes(bytes) => bytes.len_changes(),
         }
     }
+
+    /// The smallest and the largest timestamps of the changes in the block
+    fn timestamp_range(&self) -> LoroResult<(Timestamp, Timestamp)> {
+        let range = match self {
+            ChangesBlockContent::Changes(changes) | ChangesBlockContent::Both(changes, _) => {
+                changes.iter().map(|c| c.timestamp).minmax()
+            }
+            ChangesBlockContent::Bytes(bytes) => bytes.timestamps()?.iter().copied().minmax(),
+        };
+
+        range
+            .into_option()
+            .ok_or(LoroError::DecodeDataCorruptionError)
+    }
+
+    /// Get the end counter of the last change whose timestamp is not greater than the given timestamp
+    ///
+    /// The timestamps in the block may not be sorted, so they are scanned from the end.
+    fn get_counter_end_by_timestamp_lte(
+        &self,
+        timestamp: Timestamp,
+    ) -> LoroResult<Option<Counter>> {
+        match self {
+            ChangesBlockContent::Changes(changes) | ChangesBlockContent::Both(changes, _) => {
+                Ok(changes
+                    .iter()
+                    .rposition(|c| c.timestamp <= timestamp)
+                    .map(|index| changes[index].ctr_end()))
+            }
+            ChangesBlockContent::Bytes(bytes) => {
+                let Some(index) = bytes.timestamps()?.iter().rposition(|t| *t <= timestamp) else {
+                    return Ok(None);
+                };
+
+                // counters[i + 1] is the end counter of the ith change
+                Ok(Some(bytes.header.get().unwrap().counters[index + 1]))
+            }
+        }
+    }
 }
 
 impl std::fmt::Debug for ChangesBlockContent {
@@ -1492,13 +1867,18 @@ impl ChangesBlockBytes {
 
     fn ensure_header(&self) -> LoroResult<()> {
         self.header
-            .get_or_init(|| Arc::new(decode_header(&self.bytes).unwrap()));
+            .get_or_try_init(|| decode_header(&self.bytes).map(Arc::new))?;
         Ok(())
     }
 
     fn parse(&self, a: &SharedArena) -> LoroResult<Vec<Change>> {
+        self.parse_with_limits(a, ValueLimits::default())
+    }
+
+    fn parse_with_limits(&self, a: &SharedArena, limits: ValueLimits) -> LoroResult<Vec<Change>> {
         self.ensure_header()?;
-        let ans: Vec<Change> = decode_block(&self.bytes, a, self.header.get().map(|h| h.as_ref()))?;
+        let header = self.header.get().map(|h| h.as_ref());
+        let ans: Vec<Change> = decode_block(&self.bytes, a, header, limits)?;
         for c in ans.iter() {
             // PERF: This can be made faster (low priority)
             register_container_and_parent_link(a, c)
@@ -1541,6 +1921,15 @@ impl ChangesBlockBytes {
         self.ensure_header().unwrap();
         self.header.get().unwrap().n_changes
     }
+
+    fn timestamps(&self) -> LoroResult<&[Timestamp]> {
+        self.ensure_header()?;
+        let header = self.header.get().unwrap();
+        header
+            .timestamps
+            .get_or_try_init(|| decode_timestamps(&self.bytes, header.n_changes))
+            .map(|x| x.as_slice())
+    }
 }
 
 #[cfg(test)]
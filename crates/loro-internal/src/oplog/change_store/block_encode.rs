@@ -29,6 +29,9 @@
 //! ┌────────────────────────────────┬─────────────────────────────┐
 //! │    N Rle Commit Msg Lengths    │       Commit Messages       │
 //! └────────────────────────────────┴─────────────────────────────┘
+//! ┌────────────────────────────────┬─────────────────────────────┐
+//! │   N Rle Signature Lengths      │  Signatures (only if any)   │
+//! └────────────────────────────────┴─────────────────────────────┘
 //!
 //!  ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ Encoded Operations ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─
 //!
@@ -279,6 +282,34 @@ pub fn encode_block(block: &[Change], arena: &SharedArena) -> Vec<u8> {
     ans
 }
 
+/// The signatures are the optional tail of the change meta
+fn decode_signatures(bytes: &[u8], n_changes: usize) -> LoroResult<Vec<Option<Arc<[u8]>>>> {
+    if bytes.is_empty() {
+        return Ok(vec![None; n_changes]);
+    }
+
+    let decoder = AnyRleDecoder::<u32>::new(bytes);
+    let (lens, mut bytes) = decoder
+        .take_n_finalize(n_changes)
+        .map_err(|_| LoroError::DecodeDataCorruptionError)?;
+    let mut ans = Vec::with_capacity(n_changes);
+    for len in lens {
+        let len = len as usize;
+        if len == 0 {
+            ans.push(None);
+            continue;
+        }
+
+        if len > bytes.len() {
+            return Err(LoroError::DecodeDataCorruptionError);
+        }
+        ans.push(Some(Arc::from(&bytes[..len])));
+        bytes = &bytes[len..];
+    }
+
+    Ok(ans)
+}
+
 fn encode_keys(keys: Vec<loro_common::InternalString>) -> Vec<u8> {
     let mut keys_bytes = Vec::new();
     for key in keys {
@@ -289,16 +320,22 @@ fn encode_keys(keys: Vec<loro_common::InternalString>) -> Vec<u8> {
     keys_bytes
 }
 
-fn decode_keys(mut bytes: &[u8]) -> Vec<InternalString> {
+fn decode_keys(mut bytes: &[u8]) -> LoroResult<Vec<InternalString>> {
     let mut keys = Vec::new();
     while !bytes.is_empty() {
-        let len = leb128::read::unsigned(&mut bytes).unwrap();
-        let key = std::str::from_utf8(&bytes[..len as usize]).unwrap();
+        let len = leb128::read::unsigned(&mut bytes)
+            .map_err(|_| LoroError::DecodeDataCorruptionError)? as usize;
+        if len > bytes.len() {
+            return Err(LoroError::DecodeDataCorruptionError);
+        }
+
+        let key =
+            std::str::from_utf8(&bytes[..len]).map_err(|_| LoroError::DecodeDataCorruptionError)?;
         keys.push(key.into());
-        bytes = &bytes[len as usize..];
+        bytes = &bytes[len..];
     }
 
-    keys
+    Ok(keys)
 }
 
 struct Registers {
@@ -309,8 +346,8 @@ struct Registers {
 }
 
 use crate::encoding::value::{
-    RawTreeMove, Value, ValueDecodedArenasTrait, ValueEncodeRegister, ValueKind, ValueReader,
-    ValueWriter,
+    RawTreeMove, Value, ValueDecodedArenasTrait, ValueEncodeRegister, ValueKind, ValueLimits,
+    ValueReader, ValueWriter,
 };
 use crate::oplog::change_store::block_meta_encode::encode_changes;
 use crate::version::Frontiers;
@@ -380,6 +417,8 @@ pub(crate) struct ChangesBlockHeader {
     pub deps_groups: Vec<Frontiers>,
     pub keys: OnceCell<Vec<InternalString>>,
     pub cids: OnceCell<Vec<ContainerID>>,
+    /// The timestamps of the changes. They are decoded lazily from the change meta.
+    pub timestamps: OnceCell<Vec<Timestamp>>,
 }
 
 pub fn decode_header(m_bytes: &[u8]) -> LoroResult<ChangesBlockHeader> {
@@ -408,7 +447,7 @@ fn decode_header_from_doc(doc: &EncodedBlock) -> Result<ChangesBlockHeader, Loro
         *counter_len as Counter,
         *lamport_start,
         *lamport_len,
-    );
+    )?;
     Ok(ans)
 }
 
@@ -457,7 +496,8 @@ impl<'a> ValueDecodedArenasTrait for ValueDecodeArena<'a> {
         _op: encoding::value::EncodedTreeMove,
         _id: ID,
     ) -> LoroResult<tree_op::TreeOp> {
-        unreachable!()
+        // The changes blocks only use `RawTreeMove`
+        Err(LoroError::DecodeDataCorruptionError)
     }
 }
 
@@ -474,13 +514,25 @@ pub fn decode_block_range(
         ));
     }
 
-    let counter_start = leb128::read::unsigned(&mut bytes).unwrap() as Counter;
-    let counter_len = leb128::read::unsigned(&mut bytes).unwrap() as Counter;
-    let lamport_start = leb128::read::unsigned(&mut bytes).unwrap() as Lamport;
-    let lamport_len = leb128::read::unsigned(&mut bytes).unwrap() as Lamport;
+    let mut read =
+        || leb128::read::unsigned(&mut bytes).map_err(|_| LoroError::DecodeDataCorruptionError);
+    let counter_start = read()? as Counter;
+    let counter_len = read()? as Counter;
+    let lamport_start = read()? as Lamport;
+    let lamport_len = read()? as Lamport;
     Ok((
-        (counter_start, counter_start + counter_len),
-        (lamport_start, lamport_start + lamport_len),
+        (
+            counter_start,
+            counter_start
+                .checked_add(counter_len)
+                .ok_or(LoroError::DecodeDataCorruptionError)?,
+        ),
+        (
+            lamport_start,
+            lamport_start
+                .checked_add(lamport_len)
+                .ok_or(LoroError::DecodeDataCorruptionError)?,
+        ),
     ))
 }
 
@@ -507,40 +559,52 @@ pub fn decode_cids(
     }
 
     let EncodedBlock { cids, keys, .. } = doc;
-    let keys = header.keys.get_or_init(|| decode_keys(&keys));
+    let keys = header.keys.get_or_try_init(|| decode_keys(&keys))?;
     let decode_arena = ValueDecodeArena {
         peers: &header.peers,
         keys,
     };
 
-    header
-        .cids
-        .set(
-            ContainerArena::decode(&cids)
-                .unwrap()
-                .iter()
-                .map(|x| x.as_container_id(&decode_arena))
-                .try_collect()
-                .unwrap(),
-        )
-        .unwrap();
+    let cids: Vec<ContainerID> = ContainerArena::decode(&cids)?
+        .iter()
+        .map(|x| x.as_container_id(&decode_arena))
+        .try_collect()?;
+    let _ = header.cids.set(cids);
     Ok(header)
 }
 
+/// Decode the timestamps of the changes in the block without decoding the ops
+pub fn decode_timestamps(bytes: &[u8], n_changes: usize) -> LoroResult<Vec<Timestamp>> {
+    let doc: EncodedBlock = postcard::from_bytes(bytes).map_err(|e| {
+        LoroError::DecodeError(format!("Decode block error {}", e).into_boxed_str())
+    })?;
+    let timestamp_decoder = DeltaOfDeltaDecoder::<i64>::new(&doc.change_meta).map_err(|e| {
+        LoroError::DecodeError(format!("Decode timestamps error {:?}", e).into_boxed_str())
+    })?;
+    let (timestamps, _) = timestamp_decoder.take_n_finalize(n_changes).map_err(|e| {
+        LoroError::DecodeError(format!("Decode timestamps error {:?}", e).into_boxed_str())
+    })?;
+    Ok(timestamps.into_iter().map(|t| t as Timestamp).collect())
+}
+
 // MARK: decode_block
 pub fn decode_block(
     m_bytes: &[u8],
     shared_arena: &SharedArena,
     header: Option<&ChangesBlockHeader>,
+    limits: ValueLimits,
 ) -> LoroResult<Vec<Change>> {
     let doc = postcard::from_bytes(m_bytes).map_err(|e| {
         LoroError::DecodeError(format!("Decode block error {}", e).into_boxed_str())
     })?;
-    let mut header_on_stack = None;
-    let header = header.unwrap_or_else(|| {
-        header_on_stack = Some(decode_header_from_doc(&doc).unwrap());
-        header_on_stack.as_ref().unwrap()
-    });
+    let header_on_stack;
+    let header = match header {
+        Some(header) => header,
+        None => {
+            header_on_stack = decode_header_from_doc(&doc)?;
+            &header_on_stack
+        }
+    };
     let EncodedBlock {
         n_changes,
         counter_start: first_counter,
@@ -554,36 +618,51 @@ pub fn decode_block(
         ..
     } = doc;
     let n_changes = n_changes as usize;
+    if n_changes != header.n_changes {
+        return Err(LoroError::DecodeDataCorruptionError);
+    }
+
     let mut changes = Vec::with_capacity(n_changes);
-    let timestamp_decoder = DeltaOfDeltaDecoder::<i64>::new(&change_meta).unwrap();
-    let (timestamps, bytes) = timestamp_decoder.take_n_finalize(n_changes).unwrap();
+    let timestamp_decoder = DeltaOfDeltaDecoder::<i64>::new(&change_meta)?;
+    let (timestamps, bytes) = timestamp_decoder.take_n_finalize(n_changes)?;
     let commit_msg_len_decoder = AnyRleDecoder::<u32>::new(bytes);
-    let (commit_msg_lens, commit_msgs) = commit_msg_len_decoder.take_n_finalize(n_changes).unwrap();
+    let (commit_msg_lens, commit_msgs) = commit_msg_len_decoder.take_n_finalize(n_changes)?;
+    if timestamps.len() != n_changes || commit_msg_lens.len() != n_changes {
+        return Err(LoroError::DecodeDataCorruptionError);
+    }
+
     let mut commit_msg_index = 0;
-    let keys = header.keys.get_or_init(|| decode_keys(&keys));
+    let commit_msgs_len = commit_msg_lens.iter().map(|&x| x as usize).sum::<usize>();
+    if commit_msgs_len > commit_msgs.len() {
+        return Err(LoroError::DecodeDataCorruptionError);
+    }
+    let signatures = decode_signatures(&commit_msgs[commit_msgs_len..], n_changes)?;
+    if signatures.len() != n_changes {
+        return Err(LoroError::DecodeDataCorruptionError);
+    }
+
+    let keys = header.keys.get_or_try_init(|| decode_keys(&keys))?;
     let decode_arena = ValueDecodeArena {
         peers: &header.peers,
         keys,
     };
     let positions = PositionArena::decode_v2(&positions)?;
-    let positions = positions.parse_to_positions();
-    let cids: &Vec<ContainerID> = header.cids.get_or_init(|| {
-        ContainerArena::decode(&cids)
-            .unwrap()
+    let positions = positions.parse_to_positions()?;
+    let cids: &Vec<ContainerID> = header.cids.get_or_try_init(|| {
+        ContainerArena::decode(&cids)?
             .iter()
             .map(|x| x.as_container_id(&decode_arena))
             .try_collect()
-            .unwrap()
-    });
-    let mut value_reader = ValueReader::new(&values);
-    let encoded_ops_iters = serde_columnar::iter_from_bytes::<EncodedOps>(&ops).unwrap();
+    })?;
+    let mut value_reader = ValueReader::with_limits(&values, limits);
+    let encoded_ops_iters = serde_columnar::iter_from_bytes::<EncodedOps>(&ops)?;
     let op_iter = encoded_ops_iters.ops;
     let encoded_delete_id_starts: EncodedDeleteStartIds = if delete_start_ids.is_empty() {
         EncodedDeleteStartIds {
             delete_start_ids: Vec::new(),
         }
     } else {
-        serde_columnar::from_bytes(&delete_start_ids).unwrap()
+        serde_columnar::from_bytes(&delete_start_ids)?
     };
     let mut del_iter = encoded_delete_id_starts
         .delete_start_ids
@@ -615,6 +694,7 @@ pub fn decode_block(
             lamport: header.lamports[i],
             timestamp: timestamps[i] as Timestamp,
             commit_msg,
+            signature: signatures[i].clone(),
         })
     }
 
@@ -635,7 +715,9 @@ pub fn decode_block(
             ID::new(peer, counter),
         )?;
 
-        let cid = &cids[container_index as usize];
+        let cid = cids
+            .get(container_index as usize)
+            .ok_or(LoroError::DecodeDataCorruptionError)?;
         let content = decode_op(
             cid,
             value,
@@ -653,13 +735,30 @@ pub fn decode_block(
             container: c_idx,
             content,
         };
+        if op.atom_len() != len as usize {
+            return Err(LoroError::DecodeDataCorruptionError);
+        }
 
-        changes[change_index].ops.push(op);
-        counter += len as Counter;
+        changes
+            .get_mut(change_index)
+            .ok_or(LoroError::DecodeDataCorruptionError)?
+            .ops
+            .push(op);
+        counter = counter
+            .checked_add(len as Counter)
+            .ok_or(LoroError::DecodeDataCorruptionError)?;
         if counter >= header.counters[change_index + 1] {
             change_index += 1;
         }
     }
+
+    // The ops should fill the counter range of every change
+    for (change, end) in changes.iter().zip(header.counters[1..].iter()) {
+        if change.id.counter + change.atom_len() as Counter != *end {
+            return Err(LoroError::DecodeDataCorruptionError);
+        }
+    }
+
     Ok(changes)
 }
 
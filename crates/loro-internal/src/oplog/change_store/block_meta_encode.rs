@@ -1,4 +1,4 @@
-use loro_common::{Counter, Lamport, PeerID, ID};
+use loro_common::{Counter, Lamport, LoroError, LoroResult, PeerID, ID};
 use once_cell::sync::OnceCell;
 use rle::HasLength;
 use serde_columnar::{
@@ -19,6 +19,8 @@ pub(crate) fn encode_changes(
     let mut lamport_encoder = DeltaOfDeltaEncoder::new();
     let mut commit_msg_len_encoder = AnyRleEncoder::<u32>::new();
     let mut commit_msgs = String::new();
+    let mut signature_len_encoder = AnyRleEncoder::<u32>::new();
+    let mut signatures = Vec::new();
     let mut dep_self_encoder = BoolRleEncoder::new();
     let mut dep_len_encoder = AnyRleEncoder::<usize>::new();
     let mut encoded_deps = EncodedDeps {
@@ -43,6 +45,12 @@ pub(crate) fn encode_changes(
         } else {
             commit_msg_len_encoder.append(0).unwrap();
         }
+        if let Some(sig) = c.signature.as_ref() {
+            signature_len_encoder.append(sig.len() as u32).unwrap();
+            signatures.extend_from_slice(sig);
+        } else {
+            signature_len_encoder.append(0).unwrap();
+        }
 
         let mut dep_on_self = false;
         for dep in c.deps().iter() {
@@ -83,6 +91,11 @@ pub(crate) fn encode_changes(
     meta.append(&mut t);
     meta.append(&mut cml);
     meta.append(&mut cms);
+    // Blocks without signatures end here, so they can be read by older versions
+    if !signatures.is_empty() {
+        meta.append(&mut signature_len_encoder.finish().unwrap());
+        meta.append(&mut signatures);
+    }
 
     (ans, meta)
 }
@@ -94,9 +107,20 @@ pub(crate) fn decode_changes_header(
     counter_len: Counter,
     lamport_start: Lamport,
     lamport_len: Lamport,
-) -> ChangesBlockHeader {
-    let mut this_counter = first_counter;
-    let peer_num = leb128::read::unsigned(&mut bytes).unwrap() as usize;
+) -> LoroResult<ChangesBlockHeader> {
+    if n_changes == 0 || first_counter < 0 || counter_len <= 0 {
+        return Err(LoroError::DecodeDataCorruptionError);
+    }
+
+    let counter_end = first_counter
+        .checked_add(counter_len)
+        .ok_or(LoroError::DecodeDataCorruptionError)?;
+    let peer_num = leb128::read::unsigned(&mut bytes)
+        .map_err(|_| LoroError::DecodeDataCorruptionError)? as usize;
+    if peer_num == 0 || peer_num > bytes.len() / 8 {
+        return Err(LoroError::DecodeDataCorruptionError);
+    }
+
     let mut peers = Vec::with_capacity(peer_num);
     for i in 0..peer_num {
         let peer_id = PeerID::from_le_bytes((&bytes[(8 * i)..(8 * (i + 1))]).try_into().unwrap());
@@ -108,64 +132,111 @@ pub(crate) fn decode_changes_header(
     // │ LEB First Counter │         N LEB128 Change AtomLen          │◁───┼─────  Important metadata
     // └───────────────────┴──────────────────────────────────────────┘    │
 
+    // Each length takes at least one byte
+    if n_changes - 1 > bytes.len() {
+        return Err(LoroError::DecodeDataCorruptionError);
+    }
+
     let mut lengths = Vec::with_capacity(n_changes);
     for _ in 0..n_changes - 1 {
-        lengths.push(leb128::read::unsigned(&mut bytes).unwrap() as Counter);
+        let len =
+            leb128::read::unsigned(&mut bytes).map_err(|_| LoroError::DecodeDataCorruptionError)?;
+        lengths.push(Counter::try_from(len).map_err(|_| LoroError::DecodeDataCorruptionError)?);
+    }
+    let sum = lengths
+        .iter()
+        .try_fold(0 as Counter, |acc, &len| acc.checked_add(len))
+        .ok_or(LoroError::DecodeDataCorruptionError)?;
+    lengths.push(counter_len - sum);
+    if lengths.iter().any(|&len| len <= 0) {
+        return Err(LoroError::DecodeDataCorruptionError);
+    }
+
+    let mut counters = Vec::with_capacity(n_changes + 1);
+    let mut last = first_counter;
+    for len in lengths.iter() {
+        counters.push(last);
+        last += *len;
     }
-    lengths.push(counter_len - lengths.iter().sum::<i32>());
 
     // ┌───────────────────┬────────────────────────┬─────────────────┐    │
     // │N DepOnSelf BoolRle│ N Delta Rle Deps Lens  │    N Dep IDs    │◁───┘
     // └───────────────────┴────────────────────────┴─────────────────┘
 
     let dep_self_decoder = BoolRleDecoder::new(bytes);
-    let (dep_self, bytes) = dep_self_decoder.take_n_finalize(n_changes).unwrap();
+    let (dep_self, bytes) = dep_self_decoder
+        .take_n_finalize(n_changes)
+        .map_err(|_| LoroError::DecodeDataCorruptionError)?;
     let dep_len_decoder = AnyRleDecoder::<usize>::new(bytes);
-    let (deps_len, bytes) = dep_len_decoder.take_n_finalize(n_changes).unwrap();
+    let (deps_len, bytes) = dep_len_decoder
+        .take_n_finalize(n_changes)
+        .map_err(|_| LoroError::DecodeDataCorruptionError)?;
+    // A change depends on at most one id of each peer
+    if dep_self.len() != n_changes
+        || deps_len.len() != n_changes
+        || deps_len.iter().any(|&len| len > peer_num)
+    {
+        return Err(LoroError::DecodeDataCorruptionError);
+    }
+
     let other_dep_num = deps_len.iter().sum::<usize>();
     let dep_peer_decoder = AnyRleDecoder::<usize>::new(bytes);
-    let (dep_peers, bytes) = dep_peer_decoder.take_n_finalize(other_dep_num).unwrap();
+    let (dep_peers, bytes) = dep_peer_decoder
+        .take_n_finalize(other_dep_num)
+        .map_err(|_| LoroError::DecodeDataCorruptionError)?;
     let mut deps_peers_iter = dep_peers.into_iter();
-    let dep_counter_decoder = DeltaOfDeltaDecoder::<u32>::new(bytes).unwrap();
-    let (dep_counters, bytes) = dep_counter_decoder.take_n_finalize(other_dep_num).unwrap();
+    let dep_counter_decoder =
+        DeltaOfDeltaDecoder::<u32>::new(bytes).map_err(|_| LoroError::DecodeDataCorruptionError)?;
+    let (dep_counters, bytes) = dep_counter_decoder
+        .take_n_finalize(other_dep_num)
+        .map_err(|_| LoroError::DecodeDataCorruptionError)?;
     let mut deps_counters_iter = dep_counters.into_iter();
     let mut deps = Vec::with_capacity(n_changes);
     for i in 0..n_changes {
         let mut f = Frontiers::default();
         if dep_self[i] {
-            f.push(ID::new(peers[0], this_counter - 1))
+            if counters[i] == 0 {
+                return Err(LoroError::DecodeDataCorruptionError);
+            }
+
+            f.push(ID::new(peers[0], counters[i] - 1))
         }
 
         let len = deps_len[i];
         for _ in 0..len {
-            let peer_idx = deps_peers_iter.next().unwrap();
-            let peer = peers[peer_idx];
-            let counter = deps_counters_iter.next().unwrap() as Counter;
-            f.push(ID::new(peer, counter));
+            let peer = deps_peers_iter
+                .next()
+                .and_then(|peer_idx| peers.get(peer_idx))
+                .ok_or(LoroError::DecodeDataCorruptionError)?;
+            let counter = deps_counters_iter
+                .next()
+                .and_then(|counter| Counter::try_from(counter).ok())
+                .ok_or(LoroError::DecodeDataCorruptionError)?;
+            f.push(ID::new(*peer, counter));
         }
 
         deps.push(f);
-        this_counter += lengths[i];
-    }
-    let mut counters = Vec::with_capacity(n_changes);
-    let mut last = first_counter;
-    for len in lengths.iter() {
-        counters.push(last);
-        last += *len;
     }
 
-    let lamport_decoder = DeltaOfDeltaDecoder::new(bytes).unwrap();
+    let lamport_decoder =
+        DeltaOfDeltaDecoder::new(bytes).map_err(|_| LoroError::DecodeDataCorruptionError)?;
     let (mut lamports, rest) = lamport_decoder
-        .take_n_finalize(n_changes.saturating_sub(1))
-        .unwrap();
+        .take_n_finalize(n_changes - 1)
+        .map_err(|_| LoroError::DecodeDataCorruptionError)?;
     // the last lamport
-    lamports.push((lamport_start + lamport_len - *lengths.last().unwrap_or(&0) as u32) as Lamport);
+    let last_lamport = lamport_start
+        .checked_add(lamport_len)
+        .and_then(|x| x.checked_sub(*lengths.last().unwrap() as u32))
+        .ok_or(LoroError::DecodeDataCorruptionError)?;
+    lamports.push(last_lamport);
 
     // we need counter range, so encode
-    counters.push(first_counter + counter_len);
-    debug_assert!(rest.is_empty());
+    counters.push(counter_end);
+    if !rest.is_empty() || lamports.len() != n_changes {
+        return Err(LoroError::DecodeDataCorruptionError);
+    }
 
-    ChangesBlockHeader {
+    Ok(ChangesBlockHeader {
         peer: peers[0],
         counter: first_counter,
         n_changes,
@@ -175,7 +246,8 @@ pub(crate) fn decode_changes_header(
         lamports,
         keys: OnceCell::new(),
         cids: OnceCell::new(),
-    }
+        timestamps: OnceCell::new(),
+    })
 }
 
 struct EncodedDeps {
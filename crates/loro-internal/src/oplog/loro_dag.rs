@@ -4,7 +4,9 @@ use crate::id::{Counter, ID};
 use crate::span::{HasId, HasLamport};
 use crate::version::{shrink_frontiers, Frontiers, ImVersionVector, VersionVector};
 use fxhash::FxHashSet;
-use loro_common::{HasCounter, HasCounterSpan, HasIdSpan, HasLamportSpan, PeerID};
+use loro_common::{
+    HasCounter, HasCounterSpan, HasIdSpan, HasLamportSpan, LoroError, LoroResult, PeerID,
+};
 use once_cell::sync::OnceCell;
 use rle::{HasIndex, HasLength, Mergable, Sliceable};
 use smallvec::SmallVec;
@@ -433,22 +435,29 @@ impl AppDag {
         self.map.try_lock().unwrap().len()
     }
 
-    pub(crate) fn set_version_by_fast_snapshot_import(&mut self, v: BatchDecodeInfo) {
+    pub(crate) fn set_version_by_fast_snapshot_import(
+        &mut self,
+        v: BatchDecodeInfo,
+    ) -> LoroResult<()> {
         assert!(self.vv.is_empty());
         *self.unparsed_vv.try_lock().unwrap() = v.vv.clone();
         self.vv = v.vv;
         self.frontiers = v.frontiers;
         if let Some((vv, f)) = v.start_version {
             if !f.is_empty() {
-                assert!(f.len() == 1);
-                let id = f.as_single().unwrap();
-                let node = self.get(id).unwrap();
-                assert!(node.cnt == id.counter);
+                let id = f.as_single().ok_or(LoroError::DecodeDataCorruptionError)?;
+                let node = self.get(id).ok_or(LoroError::DecodeDataCorruptionError)?;
+                if node.cnt != id.counter {
+                    return Err(LoroError::DecodeDataCorruptionError);
+                }
+
                 self.shallow_root_frontiers_deps = node.deps.clone();
             }
             self.shallow_since_frontiers = f;
             self.shallow_since_vv = ImVersionVector::from_vv(&vv);
         }
+
+        Ok(())
     }
 
     /// This method is slow and should only be used for debugging and testing.
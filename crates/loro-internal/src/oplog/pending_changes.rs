@@ -37,6 +37,31 @@ impl PendingChanges {
     pub fn is_empty(&self) -> bool {
         self.changes.is_empty()
     }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &Change> + '_ {
+        self.changes
+            .values()
+            .flat_map(|tree| tree.values())
+            .flat_map(|changes| changes.iter().map(|c| c.deref()))
+    }
+
+    /// Remove the pending changes that match `f` and return them
+    pub(crate) fn extract_if(&mut self, mut f: impl FnMut(&Change) -> bool) -> Vec<Change> {
+        let mut ans = Vec::new();
+        self.changes.retain(|_, tree| {
+            tree.retain(|_, changes| {
+                let (removed, kept): (Vec<_>, Vec<_>) =
+                    std::mem::take(changes).into_iter().partition(|c| f(c));
+                *changes = kept;
+                ans.extend(removed.into_iter().map(|c| match c {
+                    PendingChange::Unknown(c) | PendingChange::Known(c) => c,
+                }));
+                !changes.is_empty()
+            });
+            !tree.is_empty()
+        });
+        ans
+    }
 }
 
 impl OpLog {
@@ -0,0 +1,137 @@
+//! Redacting the content of the ops in the binary change store, see [LoroDoc::redact].
+//!
+//! It follows the same rules as [crate::json::redact]. The ids and the lengths of the ops are
+//! kept, so the redacted doc can still sync with the other peers.
+//!
+//! The new doc is built by importing the redacted changes, so its state is derived from the
+//! redacted ops only. Nothing is copied from the state of the original doc, which may contain
+//! the redacted content.
+use std::ops::Range;
+
+use loro_common::{Counter, HasCounterSpan, HasIdSpan, LoroError, LoroResult, LoroValue};
+use rle::HasLength;
+
+use crate::{
+    arena::SharedArena,
+    change::Change,
+    container::list::list_op::InnerListOp,
+    op::{FutureInnerContent, InnerContent, SliceRange},
+    version::VersionRange,
+    LoroDoc,
+};
+
+impl LoroDoc {
+    /// Create a new doc with the content inserted by the ops in `range` replaced by placeholders.
+    ///
+    /// - The inserted text is replaced by the Unicode replacement character (U+FFFD)
+    /// - The inserted values of lists and maps and the values of text marks become `Null`
+    /// - The counter ops are reset to zero
+    /// - The deletions, moves and the creations of child containers are kept
+    ///
+    /// The redacted changes lose their signatures. It returns an error if the doc is shallow,
+    /// or if an op in `range` is from an unknown container type.
+    pub fn redact(&self, range: &VersionRange) -> LoroResult<LoroDoc> {
+        if self.is_shallow() {
+            return Err(LoroError::NotImplemented("redacting a shallow doc"));
+        }
+
+        self.commit_then_stop();
+        let bytes = crate::encoding::export_redacted_updates(self, range);
+        self.renew_txn_if_auto_commit();
+        let bytes = bytes?;
+
+        let doc = LoroDoc::new();
+        doc.import(&bytes)?;
+        // The redacted changes would be rejected by the change verifier
+        doc.set_config(&self.config);
+        if self.auto_commit.load(std::sync::atomic::Ordering::Relaxed) {
+            doc.start_auto_commit();
+        }
+        Ok(doc)
+    }
+}
+
+/// Redact the ops of `change` in `range`. The new content is allocated in `arena`.
+pub(crate) fn redact_change(
+    change: &mut Change,
+    range: &VersionRange,
+    arena: &SharedArena,
+) -> LoroResult<()> {
+    if !range.has_overlap_with(change.id_span()) {
+        return Ok(());
+    }
+
+    let &(start, end) = range.get(&change.id.peer).unwrap();
+    for op in change.ops.iter_mut() {
+        if op.ctr_end() <= start || op.counter >= end {
+            continue;
+        }
+
+        let len = op.atom_len() as Counter;
+        let range = (start - op.counter).max(0) as usize..(end - op.counter).min(len) as usize;
+        redact_content(&mut op.content, range, arena)?;
+    }
+
+    change.signature = None;
+    Ok(())
+}
+
+fn redact_content(
+    content: &mut InnerContent,
+    range: Range<usize>,
+    arena: &SharedArena,
+) -> LoroResult<()> {
+    match content {
+        InnerContent::List(InnerListOp::Insert { slice, .. }) => {
+            if slice.is_unknown() {
+                return Ok(());
+            }
+
+            let mut values = arena.get_values(slice.to_range());
+            values[range].iter_mut().for_each(redact_value);
+            let new = arena.alloc_values(values.into_iter());
+            *slice = SliceRange::new(new.start as u32..new.end as u32);
+        }
+        InnerContent::List(InnerListOp::InsertText {
+            slice,
+            unicode_start,
+            ..
+        }) => {
+            let text = std::str::from_utf8(slice.as_bytes()).unwrap();
+            let text = text
+                .chars()
+                .enumerate()
+                .map(|(i, c)| if range.contains(&i) { '\u{FFFD}' } else { c })
+                .collect::<String>();
+            let (new_slice, result) = arena.alloc_str_with_slice(&text);
+            *slice = new_slice;
+            *unicode_start = result.start as u32;
+        }
+        InnerContent::List(InnerListOp::Set { value, .. }) => redact_value(value),
+        InnerContent::List(InnerListOp::StyleStart { value, .. }) => *value = LoroValue::Null,
+        InnerContent::List(
+            InnerListOp::Delete(_) | InnerListOp::Move { .. } | InnerListOp::StyleEnd,
+        ) => {}
+        InnerContent::Map(map) => {
+            if let Some(value) = &mut map.value {
+                redact_value(value);
+            }
+        }
+        InnerContent::Tree(_) => {}
+        #[cfg(feature = "counter")]
+        InnerContent::Future(FutureInnerContent::Counter(x)) => *x = 0.,
+        InnerContent::Future(FutureInnerContent::Unknown { .. }) => {
+            return Err(LoroError::NotImplemented(
+                "redacting the ops of an unknown container type",
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+fn redact_value(v: &mut LoroValue) {
+    if !matches!(v, LoroValue::Container(_)) {
+        *v = LoroValue::Null;
+    }
+}
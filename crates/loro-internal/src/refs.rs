@@ -0,0 +1,328 @@
+//! Named branches and tags of a document.
+//!
+//! A ref is a named [Frontiers]. Refs are stored in a reserved root map container named
+//! [REFS_CONTAINER_NAME], so they are persisted in snapshots and synced between peers like
+//! any other edit. Concurrent updates to the same ref are resolved by the map's LWW semantics.
+//!
+//! - A branch is a movable ref. When the doc is checked out to a branch with detached editing
+//!   enabled, the new local changes advance the branch. The head of the branch is saved when the
+//!   doc leaves the branch (by `checkout`, `attach`, or checking out another branch) or when
+//!   another branch is merged into it. Reading the refs never writes to the doc.
+//! - A tag is an immutable ref. It's only immutable through this API: any peer can overwrite
+//!   or delete it by editing the refs container directly, so tags are not tamper-proof.
+//!
+//! The refs container is left out of [LoroDoc::get_value] and [LoroDoc::get_deep_value].
+//!
+//! The refs are always read from and written to the latest version of the [crate::OpLog],
+//! even if the doc is detached.
+//!
+//! Note that the latest version of the doc always includes the changes made on all branches,
+//! because every change is recorded in the same [crate::OpLog]. Branches only make a difference
+//! when the doc is checked out.
+use loro_common::{ContainerID, ContainerType, LoroError, LoroResult, LoroValue, ID};
+use tracing::warn;
+
+use crate::{change::Lamport, version::Frontiers, LoroDoc, MapHandler};
+
+/// The name of the root map container that stores the refs.
+pub const REFS_CONTAINER_NAME: &str = "__loro_refs";
+
+/// Whether the container is the reserved root container of the refs.
+pub(crate) fn is_reserved_root(id: &ContainerID) -> bool {
+    matches!(id, ContainerID::Root { name, .. } if name.as_str() == REFS_CONTAINER_NAME)
+}
+
+const BRANCH_PREFIX: &str = "branch:";
+const TAG_PREFIX: &str = "tag:";
+
+fn frontiers_to_value(frontiers: &Frontiers) -> LoroValue {
+    frontiers
+        .iter()
+        .map(|id| LoroValue::from(id.to_string()))
+        .collect::<Vec<_>>()
+        .into()
+}
+
+fn value_to_frontiers(value: &LoroValue) -> Option<Frontiers> {
+    let list = value.as_list()?;
+    let mut frontiers = Frontiers::default();
+    for id in list.iter() {
+        frontiers.push(ID::try_from(id.as_string()?.as_str()).ok()?);
+    }
+    Some(frontiers)
+}
+
+fn check_ref_name(name: &str) -> LoroResult<()> {
+    if name.is_empty() {
+        return Err(LoroError::ArgErr(
+            "The name of a ref cannot be empty".into(),
+        ));
+    }
+
+    Ok(())
+}
+
+impl LoroDoc {
+    fn refs_map(&self) -> MapHandler {
+        self.get_map(REFS_CONTAINER_NAME)
+    }
+
+    /// Get the entries of the refs container at the latest version of the [crate::OpLog].
+    ///
+    /// If the doc is detached, they are read from the history cache of the oplog instead of
+    /// the state, so the doc is neither checked out nor committed.
+    fn latest_refs(&self) -> Vec<(String, LoroValue)> {
+        if !self.is_detached() {
+            let mut ans = Vec::new();
+            self.refs_map()
+                .for_each(|key, value| ans.push((key.to_string(), value.to_value())));
+            return ans;
+        }
+
+        let id = ContainerID::new_root(REFS_CONTAINER_NAME, ContainerType::Map);
+        let Some(idx) = self.arena.id_to_idx(&id) else {
+            return Vec::new();
+        };
+
+        let oplog = self.oplog.try_lock().unwrap();
+        oplog.with_history_cache(|h| {
+            h.get_checkout_index()
+                .map
+                .get_container_latest_op_at_vv(idx, oplog.vv(), Lamport::MAX, &oplog)
+                .into_iter()
+                .filter_map(|(key, op)| Some((key.to_string(), op.value?)))
+                .collect()
+        })
+    }
+
+    /// Edit the refs on the latest version of the doc in `f`, and switch back to the current
+    /// version after it.
+    ///
+    /// No event is emitted for the version switching.
+    fn with_latest_state<R>(&self, f: impl FnOnce() -> LoroResult<R>) -> LoroResult<R> {
+        if !self.is_detached() {
+            return f();
+        }
+
+        self.commit_then_stop();
+        let frontiers = self.state_frontiers();
+        let was_recording = {
+            let mut state = self.state.try_lock().unwrap();
+            let was_recording = state.is_recording();
+            state.stop_and_clear_recording();
+            was_recording
+        };
+
+        let latest = self.oplog_frontiers();
+        self.checkout_without_emitting(&latest, false)?;
+        self.set_detached(false);
+        self.renew_txn_if_auto_commit();
+        let ans = f();
+        self.commit_then_stop();
+        self.checkout_without_emitting(&frontiers, false)?;
+        if self.config.detached_editing() {
+            // The peer has created ops on the latest version, it cannot be used on the
+            // detached version anymore
+            self.renew_peer_id();
+        }
+
+        if was_recording {
+            self.state.try_lock().unwrap().start_recording();
+        }
+
+        self.renew_txn_if_auto_commit();
+        ans
+    }
+
+    fn get_ref(&self, key: &str) -> LoroResult<Option<Frontiers>> {
+        Ok(self
+            .latest_refs()
+            .into_iter()
+            .find(|(k, _)| k == key)
+            .and_then(|(_, v)| value_to_frontiers(&v)))
+    }
+
+    fn set_ref(&self, key: &str, frontiers: &Frontiers) -> LoroResult<()> {
+        {
+            let oplog = self.oplog.try_lock().unwrap();
+            for id in frontiers.iter() {
+                if !oplog.dag.contains(id) {
+                    return Err(LoroError::FrontiersNotFound(id));
+                }
+            }
+        }
+
+        self.with_latest_state(|| self.refs_map().insert(key, frontiers_to_value(frontiers)))
+    }
+
+    fn list_refs(&self, prefix: &str) -> LoroResult<Vec<(String, Frontiers)>> {
+        let mut ans: Vec<_> = self
+            .latest_refs()
+            .into_iter()
+            .filter_map(|(key, value)| {
+                let name = key.strip_prefix(prefix)?;
+                Some((name.to_string(), value_to_frontiers(&value)?))
+            })
+            .collect();
+        if prefix == BRANCH_PREFIX {
+            if let Some((name, head)) = self.unsaved_branch_head() {
+                if let Some(entry) = ans.iter_mut().find(|(n, _)| *n == name) {
+                    entry.1 = head;
+                }
+            }
+        }
+        ans.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(ans)
+    }
+
+    /// The name and the live head of the checked out branch, which may not be saved yet.
+    fn unsaved_branch_head(&self) -> Option<(String, Frontiers)> {
+        let name = self.current_branch.try_lock().unwrap().clone()?;
+        Some((name, self.state_frontiers()))
+    }
+
+    /// Save the current version as the head of the checked out branch, if it has changed.
+    pub(crate) fn save_current_branch_head(&self) {
+        let Some(name) = self.current_branch.try_lock().unwrap().clone() else {
+            return;
+        };
+
+        let key = format!("{}{}", BRANCH_PREFIX, name);
+        let frontiers = self.state_frontiers();
+        let ans = self.get_ref(&key).and_then(|head| {
+            if head.as_ref() == Some(&frontiers) {
+                Ok(())
+            } else {
+                self.set_ref(&key, &frontiers)
+            }
+        });
+        if let Err(e) = ans {
+            warn!("Failed to save the head of branch {}: {}", name, e);
+        }
+    }
+
+    /// Save the head of the checked out branch and leave it.
+    pub(crate) fn leave_branch(&self) {
+        self.save_current_branch_head();
+        self.current_branch.try_lock().unwrap().take();
+    }
+
+    /// Create a branch pointing to the given version.
+    pub fn create_branch(&self, name: &str, frontiers: &Frontiers) -> LoroResult<()> {
+        check_ref_name(name)?;
+        let key = format!("{}{}", BRANCH_PREFIX, name);
+        if self.get_ref(&key)?.is_some() {
+            return Err(LoroError::RefAlreadyExists(name.into()));
+        }
+
+        self.set_ref(&key, frontiers)
+    }
+
+    /// Delete the branch. If the doc is checked out to the branch, it stays on the current version.
+    pub fn delete_branch(&self, name: &str) -> LoroResult<()> {
+        let key = format!("{}{}", BRANCH_PREFIX, name);
+        if self.get_ref(&key)?.is_none() {
+            return Err(LoroError::RefNotFound(name.into()));
+        }
+
+        {
+            let mut current = self.current_branch.try_lock().unwrap();
+            if current.as_deref() == Some(name) {
+                current.take();
+            }
+        }
+
+        self.with_latest_state(|| self.refs_map().delete(&key))
+    }
+
+    /// Get the head of the branch.
+    pub fn branch_frontiers(&self, name: &str) -> LoroResult<Option<Frontiers>> {
+        let Some(head) = self.get_ref(&format!("{}{}", BRANCH_PREFIX, name))? else {
+            return Ok(None);
+        };
+
+        match self.unsaved_branch_head() {
+            Some((current, live)) if current == name => Ok(Some(live)),
+            _ => Ok(Some(head)),
+        }
+    }
+
+    /// Get all the branches and their heads, sorted by name.
+    pub fn list_branches(&self) -> LoroResult<Vec<(String, Frontiers)>> {
+        self.list_refs(BRANCH_PREFIX)
+    }
+
+    /// Get the name of the checked out branch.
+    pub fn current_branch(&self) -> Option<String> {
+        self.current_branch.try_lock().unwrap().clone()
+    }
+
+    /// Checkout the doc to the head of the branch.
+    ///
+    /// The doc becomes detached. If detached editing is enabled, the new local changes
+    /// advance the branch.
+    pub fn checkout_branch(&self, name: &str) -> LoroResult<()> {
+        self.leave_branch();
+        let Some(frontiers) = self.get_ref(&format!("{}{}", BRANCH_PREFIX, name))? else {
+            return Err(LoroError::RefNotFound(name.into()));
+        };
+
+        self.checkout(&frontiers)?;
+        *self.current_branch.try_lock().unwrap() = Some(name.to_string());
+        Ok(())
+    }
+
+    /// Merge the branch into the current version.
+    ///
+    /// If the doc is checked out to a branch, the doc is checked out to the merged version and
+    /// the head of the current branch is moved to it. If the doc is attached, the changes of
+    /// the branch are already included, so nothing happens.
+    pub fn merge_branch(&self, name: &str) -> LoroResult<()> {
+        let Some(other) = self.branch_frontiers(name)? else {
+            return Err(LoroError::RefNotFound(name.into()));
+        };
+
+        if !self.is_detached() {
+            return Ok(());
+        }
+
+        self.commit_then_stop();
+        let merged = {
+            let oplog = self.oplog.try_lock().unwrap();
+            let mut vv = oplog.dag.frontiers_to_vv(&self.state_frontiers()).unwrap();
+            vv.merge(&oplog.dag.frontiers_to_vv(&other).unwrap());
+            oplog.dag.vv_to_frontiers(&vv)
+        };
+
+        let current = self.current_branch.try_lock().unwrap().take();
+        self.renew_txn_if_auto_commit();
+        self.checkout(&merged)?;
+        if let Some(current) = current {
+            *self.current_branch.try_lock().unwrap() = Some(current);
+            self.save_current_branch_head();
+        }
+
+        Ok(())
+    }
+
+    /// Create an immutable tag pointing to the given version.
+    pub fn tag(&self, name: &str, frontiers: &Frontiers) -> LoroResult<()> {
+        check_ref_name(name)?;
+        let key = format!("{}{}", TAG_PREFIX, name);
+        if self.get_ref(&key)?.is_some() {
+            return Err(LoroError::RefAlreadyExists(name.into()));
+        }
+
+        self.set_ref(&key, frontiers)
+    }
+
+    /// Get the version of the tag.
+    pub fn tag_frontiers(&self, name: &str) -> LoroResult<Option<Frontiers>> {
+        self.get_ref(&format!("{}{}", TAG_PREFIX, name))
+    }
+
+    /// Get all the tags and their versions, sorted by name.
+    pub fn list_tags(&self) -> LoroResult<Vec<(String, Frontiers)>> {
+        self.list_refs(TAG_PREFIX)
+    }
+}
@@ -0,0 +1,151 @@
+//! Optional signatures of the changes.
+//!
+//! When a [ChangeSigner] is set, every committed change is signed and the signature is
+//! stored with the change in the change store blocks. When a [ChangeVerifier] is set,
+//! every imported change is verified before it's applied to the doc.
+//!
+//! The signatures only survive the binary encodings. The JSON and the outdated encodings
+//! don't carry them.
+//!
+//! A signature covers the whole change. Signed changes are never merged or split in the
+//! change store, but `Change::slice` drops the signature unless the slice is the whole
+//! change. So exporting the updates from a version in the middle of a signed change
+//! exports the rest of it unsigned, and a verifier rejects it.
+//!
+//! # Payload
+//!
+//! The signed bytes ([ChangeSignPayload::bytes]) don't depend on the block encoding of
+//! the change store. They are, with all the integers in little endian:
+//!
+//! ```text
+//! version: u8 (= SIGN_PAYLOAD_VERSION)
+//! peer: u64, counter: i32, lamport: u32, timestamp: i64
+//! deps: u32 count, then (peer: u64, counter: i32) for each dep sorted by peer and counter
+//! msg: u8 (0 = none, 1 = some), then u32 byte length and the UTF-8 bytes if some
+//! ops: u32 byte length, then the JSON array of the ops with the real peer ids
+//! ```
+//!
+//! The ops use the JSON format of [crate::json::JsonOp], serialized compactly with the
+//! object keys sorted.
+use std::{fmt::Debug, sync::Arc};
+
+use loro_common::{LoroError, LoroResult, ID};
+
+use crate::{
+    arena::SharedArena, change::Change, encoding::json_schema::encode_ops_with_peers,
+    version::Frontiers,
+};
+
+/// The version of the signed payload format, see the [module docs](self)
+pub const SIGN_PAYLOAD_VERSION: u8 = 1;
+
+/// Encode the signed payload of the change, see the [module docs](self)
+pub(crate) fn encode_sign_payload(change: &Change, arena: &SharedArena) -> Vec<u8> {
+    let mut ans = vec![SIGN_PAYLOAD_VERSION];
+    ans.extend_from_slice(&change.id.peer.to_le_bytes());
+    ans.extend_from_slice(&change.id.counter.to_le_bytes());
+    ans.extend_from_slice(&change.lamport.to_le_bytes());
+    ans.extend_from_slice(&change.timestamp.to_le_bytes());
+
+    let mut deps: Vec<ID> = change.deps.iter().collect();
+    deps.sort();
+    ans.extend_from_slice(&(deps.len() as u32).to_le_bytes());
+    for dep in deps {
+        ans.extend_from_slice(&dep.peer.to_le_bytes());
+        ans.extend_from_slice(&dep.counter.to_le_bytes());
+    }
+
+    match change.message() {
+        Some(msg) => {
+            ans.push(1);
+            ans.extend_from_slice(&(msg.len() as u32).to_le_bytes());
+            ans.extend_from_slice(msg.as_bytes());
+        }
+        None => ans.push(0),
+    }
+
+    // `serde_json::Value` keeps the object keys sorted, which makes the ops canonical
+    let ops = serde_json::to_value(encode_ops_with_peers(change, arena)).unwrap();
+    let ops = serde_json::to_vec(&ops).unwrap();
+    ans.extend_from_slice(&(ops.len() as u32).to_le_bytes());
+    ans.extend_from_slice(&ops);
+    ans
+}
+
+/// The content of a change that is signed and verified
+#[derive(Debug)]
+pub struct ChangeSignPayload<'a> {
+    pub id: ID,
+    pub deps: &'a Frontiers,
+    /// The canonical encoding of the change, see the [module docs](self). It covers the ID,
+    /// the deps, the lamport, the timestamp, the commit message and the ops, but not the
+    /// signature itself.
+    pub bytes: &'a [u8],
+}
+
+/// Creates the signature of a local change when it's committed, e.g. an ed25519 signature
+/// of [ChangeSignPayload::bytes].
+#[derive(Clone)]
+pub struct ChangeSigner(Arc<dyn Fn(&ChangeSignPayload) -> Vec<u8> + Send + Sync>);
+
+impl ChangeSigner {
+    pub fn new(f: impl Fn(&ChangeSignPayload) -> Vec<u8> + Send + Sync + 'static) -> Self {
+        Self(Arc::new(f))
+    }
+
+    pub(crate) fn sign(&self, change: &mut Change, arena: &SharedArena) {
+        let bytes = encode_sign_payload(change, arena);
+        let signature = (self.0)(&ChangeSignPayload {
+            id: change.id,
+            deps: &change.deps,
+            bytes: &bytes,
+        });
+        change.signature = Some(signature.into());
+    }
+}
+
+impl Debug for ChangeSigner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ChangeSigner").finish_non_exhaustive()
+    }
+}
+
+/// Checks the signature of an imported change. The signature is `None` if the change is not
+/// signed. Returning `false` rejects the import with [LoroError::InvalidChangeSignature].
+#[derive(Clone)]
+pub struct ChangeVerifier(Arc<dyn Fn(&ChangeSignPayload, Option<&[u8]>) -> bool + Send + Sync>);
+
+impl ChangeVerifier {
+    pub fn new(
+        f: impl Fn(&ChangeSignPayload, Option<&[u8]>) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        Self(Arc::new(f))
+    }
+
+    /// Verify all the changes before any of them is imported
+    pub(crate) fn verify<'a>(
+        &self,
+        changes: impl IntoIterator<Item = &'a Change>,
+        arena: &SharedArena,
+    ) -> LoroResult<()> {
+        for change in changes {
+            let bytes = encode_sign_payload(change, arena);
+            let payload = ChangeSignPayload {
+                id: change.id,
+                deps: &change.deps,
+                bytes: &bytes,
+            };
+            if !(self.0)(&payload, change.signature()) {
+                return Err(LoroError::InvalidChangeSignature(change.id));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Debug for ChangeVerifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ChangeVerifier").finish_non_exhaustive()
+    }
+}
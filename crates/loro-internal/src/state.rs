@@ -15,6 +15,7 @@ use fxhash::{FxHashMap, FxHashSet};
 use itertools::Itertools;
 use loro_common::{ContainerID, LoroError, LoroResult};
 use loro_delta::DeltaItem;
+use state_hash::MerkleCache;
 use tracing::{info_span, instrument, warn};
 
 use crate::{
@@ -43,6 +44,7 @@ mod list_state;
 mod map_state;
 mod movable_list_state;
 mod richtext_state;
+mod state_hash;
 mod tree_state;
 mod unknown_state;
 
@@ -51,6 +53,7 @@ pub(crate) use container_store::GcStore;
 pub(crate) use list_state::ListState;
 pub(crate) use map_state::MapState;
 pub(crate) use richtext_state::RichtextState;
+pub use state_hash::{MerkleNode, StateHash};
 pub(crate) use tree_state::{get_meta_value, FractionalIndexGenResult, NodePosition, TreeState};
 pub use tree_state::{TreeNode, TreeNodeWithChildren, TreeParentId};
 
@@ -72,6 +75,7 @@ pub struct DocState {
     // resolve event stuff
     weak_state: Weak<Mutex<DocState>>,
     global_txn: Weak<Mutex<Option<Transaction>>>,
+    pub(crate) oplog: Weak<Mutex<OpLog>>,
     // txn related stuff
     in_txn: bool,
     changed_idx_in_txn: FxHashSet<ContainerIdx>,
@@ -80,6 +84,7 @@ pub struct DocState {
     event_recorder: EventRecorder,
 
     dead_containers_cache: DeadContainersCache,
+    merkle_cache: MerkleCache,
 }
 
 impl std::fmt::Debug for DocState {
@@ -367,6 +372,7 @@ impl DocState {
     pub fn new_arc(
         arena: SharedArena,
         global_txn: Weak<Mutex<Option<Transaction>>>,
+        oplog: Weak<Mutex<OpLog>>,
         config: Configure,
     ) -> Arc<Mutex<Self>> {
         let peer = DefaultRandom.next_u64();
@@ -381,10 +387,12 @@ impl DocState {
                 weak_state: weak.clone(),
                 config,
                 global_txn,
+                oplog,
                 in_txn: false,
                 changed_idx_in_txn: FxHashSet::default(),
                 event_recorder: Default::default(),
                 dead_containers_cache: Default::default(),
+                merkle_cache: Default::default(),
             })
         })
     }
@@ -393,6 +401,7 @@ impl DocState {
         &mut self,
         arena: SharedArena,
         global_txn: Weak<Mutex<Option<Transaction>>>,
+        oplog: Weak<Mutex<OpLog>>,
         config: Configure,
     ) -> Arc<Mutex<Self>> {
         let peer = Arc::new(AtomicU64::new(DefaultRandom.next_u64()));
@@ -406,10 +415,12 @@ impl DocState {
                 config,
                 weak_state: weak.clone(),
                 global_txn,
+                oplog,
                 in_txn: false,
                 changed_idx_in_txn: FxHashSet::default(),
                 event_recorder: Default::default(),
                 dead_containers_cache: Default::default(),
+                merkle_cache: Default::default(),
             })
         })
     }
@@ -635,6 +646,7 @@ impl DocState {
                         if self.in_txn {
                             self.changed_idx_in_txn.insert(idx);
                         }
+                        self.merkle_cache.invalidate(idx, &self.arena);
                         let state = self.store.get_or_create_mut(idx);
                         if is_recording {
                             // process bring_back before apply
@@ -733,6 +745,7 @@ impl DocState {
     pub fn apply_local_op(&mut self, raw_op: &RawOp, op: &Op) -> LoroResult<()> {
         // set parent first, `MapContainer` will only be created for TreeID that does not contain
         self.set_container_parent_by_raw_op(raw_op);
+        self.merkle_cache.invalidate(op.container, &self.arena);
         let state = self.store.get_or_create_mut(op.container);
         if self.in_txn {
             self.changed_idx_in_txn.insert(op.container);
@@ -834,6 +847,8 @@ impl DocState {
         unknown_containers: Vec<ContainerIdx>,
         need_to_register_parent: bool,
     ) {
+        // The states are replaced by the decoded ones
+        self.merkle_cache.clear();
         self.pre_txn(Default::default(), EventTriggerKind::Import);
         if need_to_register_parent {
             for state in self.store.iter_and_decode_all() {
@@ -980,8 +995,20 @@ impl DocState {
         !self.in_txn && self.arena.can_import_snapshot() && self.store.can_import_snapshot()
     }
 
+    /// The root containers that are visible to users, i.e. without the reserved refs container.
+    fn user_root_containers(&self) -> Vec<ContainerIdx> {
+        let mut roots = self.arena.root_containers();
+        roots.retain(|idx| {
+            !self
+                .arena
+                .idx_to_id(*idx)
+                .is_some_and(|id| crate::refs::is_reserved_root(&id))
+        });
+        roots
+    }
+
     pub fn get_value(&self) -> LoroValue {
-        let roots = self.arena.root_containers();
+        let roots = self.user_root_containers();
         let ans: loro_common::LoroMapValue = roots
             .into_iter()
             .map(|idx| {
@@ -1000,7 +1027,7 @@ impl DocState {
     }
 
     pub fn get_deep_value(&mut self) -> LoroValue {
-        let roots = self.arena.root_containers();
+        let roots = self.user_root_containers();
         let mut ans = FxHashMap::with_capacity_and_hasher(roots.len(), Default::default());
         for root_idx in roots {
             let id = self.arena.idx_to_id(root_idx).unwrap();
@@ -1018,7 +1045,7 @@ impl DocState {
     }
 
     pub fn get_deep_value_with_id(&mut self) -> LoroValue {
-        let roots = self.arena.root_containers();
+        let roots = self.user_root_containers();
         let mut ans = FxHashMap::with_capacity_and_hasher(roots.len(), Default::default());
         for root_idx in roots {
             let id = self.arena.idx_to_id(root_idx).unwrap();
@@ -1,7 +1,7 @@
 use super::{ContainerCreationContext, State};
 use crate::{
     arena::SharedArena, configure::Configure, container::idx::ContainerIdx,
-    utils::kv_wrapper::KvWrapper, version::Frontiers,
+    encoding::value::ValueLimits, utils::kv_wrapper::KvWrapper, version::Frontiers,
 };
 use bytes::Bytes;
 use inner_store::InnerStore;
@@ -70,7 +70,7 @@ macro_rules! ctx {
 impl ContainerStore {
     pub fn new(arena: SharedArena, conf: Configure, peer: Arc<AtomicU64>) -> Self {
         ContainerStore {
-            store: InnerStore::new(arena.clone()),
+            store: InnerStore::new(arena.clone(), &conf),
             arena,
             conf,
             shallow_root_store: None,
@@ -113,6 +113,11 @@ impl ContainerStore {
         self.store.encode()
     }
 
+    /// Write the bytes that [ContainerStore::encode] would return to `w`
+    pub(crate) fn encode_to(&mut self, w: &mut dyn std::io::Write) -> std::io::Result<()> {
+        self.store.encode_to(w)
+    }
+
     pub(crate) fn flush(&mut self) {
         self.store.flush()
     }
@@ -133,7 +138,7 @@ impl ContainerStore {
         start_frontiers: Frontiers,
     ) -> LoroResult<Option<Frontiers>> {
         assert!(self.shallow_root_store.is_none());
-        let mut inner = InnerStore::new(self.arena.clone());
+        let mut inner = InnerStore::new(self.arena.clone(), &self.conf);
         let f = inner.decode(shallow_bytes)?;
         self.shallow_root_store = Some(Arc::new(GcStore {
             shallow_root_frontiers: start_frontiers,
@@ -164,6 +169,40 @@ impl ContainerStore {
         })
     }
 
+    /// Check all the decoded containers, including the ones of the shallow root, before
+    /// the store is used by a doc.
+    ///
+    /// The depths and the values of the containers are checked against `limits`. In `strict`
+    /// mode the states are decoded too, and they are kept for the doc. They are decoded
+    /// lazily otherwise.
+    pub(crate) fn check_all_containers(
+        &mut self,
+        limits: ValueLimits,
+        strict: bool,
+    ) -> LoroResult<()> {
+        let ctx = ctx!(self);
+        let check = |idx: &ContainerIdx, c: &mut ContainerWrapper| -> LoroResult<()> {
+            c.check_value(*idx, ctx, limits)?;
+            if strict {
+                c.decode_state(*idx, ctx)?;
+            }
+
+            Ok(())
+        };
+        for (idx, c) in self.store.iter_all_containers_mut() {
+            check(idx, c)?;
+        }
+
+        if let Some(gc) = self.shallow_root_store.as_ref() {
+            let mut store = gc.store.try_lock().unwrap();
+            for (idx, c) in store.iter_all_containers_mut() {
+                check(idx, c)?;
+            }
+        }
+
+        Ok(())
+    }
+
     pub fn get_kv(&self) -> &KvWrapper {
         self.store.get_kv()
     }
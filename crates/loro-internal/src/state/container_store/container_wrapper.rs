@@ -1,17 +1,23 @@
 use bytes::Bytes;
-use loro_common::{ContainerID, ContainerType, LoroResult, LoroValue};
+use loro_common::{ContainerID, ContainerType, LoroError, LoroResult, LoroValue};
 
 #[cfg(feature = "counter")]
 use crate::state::counter_state::CounterState;
 use crate::{
     arena::SharedArena,
     container::idx::ContainerIdx,
+    encoding::value::ValueLimits,
     state::{
         unknown_state::UnknownState, ContainerCreationContext, ContainerState, FastStateSnapshot,
         ListState, MapState, MovableListState, RichtextState, State, TreeState,
     },
 };
 
+/// The imported states are decoded lazily, and they are only checked on import in the
+/// [crate::encoding::ImportOptions::strict] mode
+const DECODE_STATE_ERROR: &str =
+    "Failed to decode the imported state, import untrusted snapshots in the strict mode";
+
 #[derive(Debug)]
 pub(crate) struct ContainerWrapper {
     depth: usize,
@@ -63,7 +69,7 @@ impl ContainerWrapper {
 
     /// It will decode the state if it is not decoded
     pub fn get_state(&mut self, idx: ContainerIdx, ctx: ContainerCreationContext) -> &State {
-        self.decode_state(idx, ctx).unwrap();
+        self.decode_state(idx, ctx).expect(DECODE_STATE_ERROR);
         self.state.as_ref().expect("ContainerWrapper is empty")
     }
 
@@ -73,7 +79,7 @@ impl ContainerWrapper {
         idx: ContainerIdx,
         ctx: ContainerCreationContext,
     ) -> &mut State {
-        self.decode_state(idx, ctx).unwrap();
+        self.decode_state(idx, ctx).expect(DECODE_STATE_ERROR);
         self.bytes = None;
         self.value = None;
         self.flushed = false;
@@ -85,7 +91,7 @@ impl ContainerWrapper {
             return v.clone();
         }
 
-        self.decode_value(idx, ctx).unwrap();
+        self.decode_value(idx, ctx).expect(DECODE_STATE_ERROR);
         if self.value.is_none() {
             return self.state.as_mut().unwrap().get_value();
         }
@@ -123,13 +129,17 @@ impl ContainerWrapper {
         parent
     }
 
-    pub fn new_from_bytes(bytes: Bytes) -> Self {
-        let kind = ContainerType::try_from_u8(bytes[0]).unwrap();
-        let mut reader = &bytes[1..];
-        let depth = leb128::read::unsigned(&mut reader).unwrap();
-        let (parent, reader) = postcard::take_from_bytes(reader).unwrap();
+    pub fn new_from_bytes(bytes: Bytes) -> LoroResult<Self> {
+        let (&kind, mut reader) = bytes
+            .split_first()
+            .ok_or(LoroError::DecodeDataCorruptionError)?;
+        let kind = ContainerType::try_from_u8(kind)?;
+        let depth = leb128::read::unsigned(&mut reader)
+            .map_err(|_| LoroError::DecodeDataCorruptionError)?;
+        let (parent, reader) =
+            postcard::take_from_bytes(reader).map_err(|_| LoroError::DecodeDataCorruptionError)?;
         let size = bytes.len() - reader.len();
-        Self {
+        Ok(Self {
             depth: depth as usize,
             kind,
             parent,
@@ -139,7 +149,11 @@ impl ContainerWrapper {
             bytes_offset_for_value: Some(size),
             bytes_offset_for_state: None,
             flushed: true,
-        }
+        })
+    }
+
+    pub fn kind(&self) -> ContainerType {
+        self.kind
     }
 
     #[allow(unused)]
@@ -149,7 +163,7 @@ impl ContainerWrapper {
             let value = self.state.as_mut().unwrap().get_value();
             self.value = Some(value);
         } else {
-            self.decode_value(idx, ctx).unwrap();
+            self.decode_value(idx, ctx).expect(DECODE_STATE_ERROR);
         }
 
         self.value.as_ref().unwrap()
@@ -165,11 +179,11 @@ impl ContainerWrapper {
         };
 
         if self.bytes_offset_for_value.is_none() {
-            let mut reader: &[u8] = bytes;
-            reader = &reader[1..];
-            let _depth = leb128::read::unsigned(&mut reader).unwrap();
-            let (_parent, reader) =
-                postcard::take_from_bytes::<Option<ContainerID>>(reader).unwrap();
+            let mut reader: &[u8] = bytes.get(1..).ok_or(LoroError::DecodeDataCorruptionError)?;
+            let _depth = leb128::read::unsigned(&mut reader)
+                .map_err(|_| LoroError::DecodeDataCorruptionError)?;
+            let (_parent, reader) = postcard::take_from_bytes::<Option<ContainerID>>(reader)
+                .map_err(|_| LoroError::DecodeDataCorruptionError)?;
             // SAFETY: bytes is a slice of b
             let size = bytes.len() - reader.len();
             self.bytes_offset_for_value = Some(size);
@@ -206,6 +220,32 @@ impl ContainerWrapper {
         Ok(())
     }
 
+    /// Check the depth of the container and its value against `limits`.
+    ///
+    /// Only the values of the maps and the lists are checked, whose elements are the values
+    /// written by the users. The value of a text is its whole content.
+    pub(super) fn check_value(
+        &mut self,
+        idx: ContainerIdx,
+        ctx: ContainerCreationContext,
+        limits: ValueLimits,
+    ) -> LoroResult<()> {
+        limits.check_depth(self.depth)?;
+        if !matches!(
+            self.kind,
+            ContainerType::Map | ContainerType::List | ContainerType::MovableList
+        ) {
+            return Ok(());
+        }
+
+        self.decode_value(idx, ctx)?;
+        match self.value.as_ref() {
+            Some(LoroValue::Map(map)) => map.values().try_for_each(|v| limits.check_value(v)),
+            Some(LoroValue::List(list)) => list.iter().try_for_each(|v| limits.check_value(v)),
+            _ => Ok(()),
+        }
+    }
+
     pub(super) fn decode_state(
         &mut self,
         idx: ContainerIdx,
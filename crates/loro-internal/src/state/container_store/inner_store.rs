@@ -4,11 +4,14 @@ use crate::{
 };
 use bytes::Bytes;
 use fxhash::FxHashMap;
-use loro_common::ContainerID;
+use loro_common::{ContainerID, LoroError};
 use std::ops::Bound;
 
 use super::ContainerWrapper;
 
+/// The entries of `kv` are checked by [InnerStore::decode_all_containers] when they are imported
+const KV_CHECKED: &str = "The entries of the kv store should be checked on import";
+
 /// The invariants about this struct:
 ///
 /// - `len` is the number of containers in the store. If a container is in both kv and store,
@@ -42,7 +45,7 @@ impl InnerStore {
                 let key = id.to_bytes();
                 if !self.all_loaded {
                     if let Some(v) = self.kv.get(&key) {
-                        let c = ContainerWrapper::new_from_bytes(v);
+                        let c = ContainerWrapper::new_from_bytes(v).expect(KV_CHECKED);
                         return e.insert(c);
                     }
                 }
@@ -74,7 +77,7 @@ impl InnerStore {
             let key = id.to_bytes();
             if !self.all_loaded {
                 if let Some(v) = self.kv.get(&key) {
-                    let c = ContainerWrapper::new_from_bytes(v);
+                    let c = ContainerWrapper::new_from_bytes(v).expect(KV_CHECKED);
                     e.insert(c);
                 }
             }
@@ -103,6 +106,11 @@ impl InnerStore {
         self.kv.export()
     }
 
+    pub(crate) fn encode_to(&mut self, w: &mut dyn std::io::Write) -> std::io::Result<()> {
+        self.flush();
+        self.kv.export_to(w)
+    }
+
     pub(crate) fn flush(&mut self) {
         self.kv
             .set_all(self.store.iter_mut().filter_map(|(idx, c)| {
@@ -130,33 +138,12 @@ impl InnerStore {
         assert!(self.kv.is_empty());
         assert_eq!(self.len, self.store.len());
         let mut fr = None;
-        self.kv.import(bytes);
+        self.kv.import(bytes)?;
         if let Some(f) = self.kv.remove(FRONTIERS_KEY) {
             fr = Some(Frontiers::decode(&f)?);
         }
 
-        self.kv.with_kv(|kv| {
-            let mut count = self.len;
-            self.arena.with_guards(|guards| {
-                let iter = kv.scan(Bound::Unbounded, Bound::Unbounded);
-                for (k, v) in iter {
-                    count += 1;
-                    let cid = ContainerID::from_bytes(&k);
-                    let c = ContainerWrapper::new_from_bytes(v);
-                    let parent = c.parent();
-                    let idx = guards.register_container(&cid);
-                    let p = parent.as_ref().map(|p| guards.register_container(p));
-                    guards.set_parent(idx, p);
-                    if self.store.insert(idx, c).is_some() {
-                        count -= 1;
-                    }
-                }
-            });
-
-            self.len = count;
-        });
-
-        self.all_loaded = true;
+        self.decode_all_containers()?;
         Ok(fr)
     }
 
@@ -168,17 +155,25 @@ impl InnerStore {
         assert!(self.kv.is_empty());
         assert_eq!(self.len, self.store.len());
         // TODO: add assert that all containers in the store should be empty right now
-        self.kv.import(bytes_a);
-        self.kv.import(bytes_b);
+        self.kv.import(bytes_a)?;
+        self.kv.import(bytes_b)?;
         self.kv.remove(FRONTIERS_KEY);
+        self.decode_all_containers()
+    }
+
+    fn decode_all_containers(&mut self) -> Result<(), loro_common::LoroError> {
         self.kv.with_kv(|kv| {
             let mut count = self.len;
-            self.arena.with_guards(|guards| {
+            let result = self.arena.with_guards(|guards| {
                 let iter = kv.scan(Bound::Unbounded, Bound::Unbounded);
                 for (k, v) in iter {
                     count += 1;
-                    let cid = ContainerID::from_bytes(&k);
-                    let c = ContainerWrapper::new_from_bytes(v);
+                    let cid = ContainerID::try_from_bytes(&k)?;
+                    let c = ContainerWrapper::new_from_bytes(v)?;
+                    if c.kind() != cid.container_type() {
+                        return Err(LoroError::DecodeDataCorruptionError);
+                    }
+
                     let parent = c.parent();
                     let idx = guards.register_container(&cid);
                     let p = parent.as_ref().map(|p| guards.register_container(p));
@@ -187,10 +182,13 @@ impl InnerStore {
                         count -= 1;
                     }
                 }
+
+                Ok(())
             });
 
             self.len = count;
-        });
+            result
+        })?;
 
         self.all_loaded = true;
         Ok(())
@@ -213,7 +211,7 @@ impl InnerStore {
                         continue;
                     }
 
-                    let container = ContainerWrapper::new_from_bytes(v);
+                    let container = ContainerWrapper::new_from_bytes(v).expect(KV_CHECKED);
                     self.store.insert(idx, container);
                 }
             });
@@ -232,20 +230,20 @@ impl InnerStore {
 }
 
 impl InnerStore {
-    pub(crate) fn new(arena: SharedArena) -> Self {
+    pub(crate) fn new(arena: SharedArena, config: &Configure) -> Self {
         Self {
             arena,
             store: FxHashMap::default(),
-            kv: KvWrapper::new_mem(),
+            kv: KvWrapper::new_mem(config.clone()),
             len: 0,
             all_loaded: true,
         }
     }
 
-    pub(crate) fn fork(&mut self, arena: SharedArena, _config: &Configure) -> InnerStore {
+    pub(crate) fn fork(&mut self, arena: SharedArena, config: &Configure) -> InnerStore {
         // PERF: we can try to reuse
         let bytes = self.encode();
-        let mut new_store = Self::new(arena);
+        let mut new_store = Self::new(arena, config);
         new_store.decode(bytes).unwrap();
         new_store
     }
@@ -131,10 +131,10 @@ mod snapshot {
         }
 
         fn decode_value(bytes: &[u8]) -> LoroResult<(LoroValue, &[u8])> {
-            Ok((
-                LoroValue::Double(f64::from_le_bytes(bytes.try_into().unwrap())),
-                bytes,
-            ))
+            let value: [u8; 8] = bytes
+                .try_into()
+                .map_err(|_| LoroError::DecodeDataCorruptionError)?;
+            Ok((LoroValue::Double(f64::from_le_bytes(value)), bytes))
         }
 
         fn decode_snapshot_fast(
@@ -563,7 +563,7 @@ impl ContainerState for ListState {
 mod snapshot {
     use std::io::Read;
 
-    use loro_common::{Counter, Lamport, PeerID};
+    use loro_common::{Counter, Lamport, LoroError, PeerID};
     use serde_columnar::columnar;
 
     use crate::{encoding::value_register::ValueRegister, state::ContainerCreationContext};
@@ -636,26 +636,37 @@ mod snapshot {
         where
             Self: Sized,
         {
-            let peer_num = leb128::read::unsigned(&mut bytes).unwrap() as usize;
-            let mut peers = Vec::with_capacity(peer_num);
+            let peer_num = leb128::read::unsigned(&mut bytes)
+                .map_err(|_| LoroError::DecodeDataCorruptionError)?
+                as usize;
+            let mut peers = Vec::with_capacity(peer_num.min(bytes.len() / 8));
             for _ in 0..peer_num {
                 let mut buf = [0u8; 8];
-                bytes.read_exact(&mut buf).unwrap();
+                bytes
+                    .read_exact(&mut buf)
+                    .map_err(|_| LoroError::DecodeDataCorruptionError)?;
                 peers.push(PeerID::from_le_bytes(buf));
             }
 
-            let EncodedListIds { ids } = serde_columnar::from_bytes(bytes).unwrap();
+            let EncodedListIds { ids } = serde_columnar::from_bytes(bytes)?;
 
             let list = v.as_list().unwrap();
+            if ids.len() != list.len() {
+                return Err(LoroError::DecodeDataCorruptionError);
+            }
+
             let mut ans = Self::new(idx);
             for (i, id) in ids.into_iter().enumerate() {
+                let peer = *peers
+                    .get(id.peer_idx)
+                    .ok_or(LoroError::DecodeDataCorruptionError)?;
                 ans.insert(
                     i,
                     list[i].clone(),
                     IdFull::new(
-                        peers[id.peer_idx],
+                        peer,
                         id.counter as Counter,
-                        (id.lamport_sub_counter + id.counter) as Lamport,
+                        id.lamport_sub_counter.wrapping_add(id.counter) as Lamport,
                     ),
                 );
             }
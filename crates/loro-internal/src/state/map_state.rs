@@ -300,7 +300,7 @@ impl MapState {
 mod snapshot {
 
     use fxhash::{FxHashMap, FxHashSet};
-    use loro_common::{InternalString, LoroValue};
+    use loro_common::{InternalString, LoroError, LoroValue};
     use serde_columnar::Itertools;
 
     use crate::{
@@ -377,7 +377,12 @@ mod snapshot {
             let keys_with_none_value: FxHashSet<_> = keys_with_none_value.into_iter().collect();
 
             // peers
-            let peer_count = leb128::read::unsigned(&mut bytes).unwrap() as usize;
+            let peer_count = leb128::read::unsigned(&mut bytes)
+                .map_err(|_| LoroError::DecodeDataCorruptionError)?
+                as usize;
+            if peer_count > bytes.len() / 8 {
+                return Err(LoroError::DecodeDataCorruptionError);
+            }
             let mut peers = Vec::with_capacity(peer_count);
             for _ in 0..peer_count {
                 let peer = u64::from_le_bytes(bytes[..8].try_into().unwrap());
@@ -392,9 +397,15 @@ mod snapshot {
             keys.sort_unstable();
 
             for key in keys {
-                let peer_idx = leb128::read::unsigned(&mut bytes).unwrap() as usize;
-                let lamp = leb128::read::unsigned(&mut bytes).unwrap() as u32;
-                let peer = peers[peer_idx];
+                let peer_idx = leb128::read::unsigned(&mut bytes)
+                    .map_err(|_| LoroError::DecodeDataCorruptionError)?
+                    as usize;
+                let lamp = leb128::read::unsigned(&mut bytes)
+                    .map_err(|_| LoroError::DecodeDataCorruptionError)?
+                    as u32;
+                let peer = *peers
+                    .get(peer_idx)
+                    .ok_or(LoroError::DecodeDataCorruptionError)?;
 
                 if keys_with_none_value.contains(&key) {
                     ans.insert(
@@ -1600,7 +1600,8 @@ struct EncodedFastSnapshot {
 mod snapshot {
     use std::io::Read;
 
-    use loro_common::{IdFull, IdLp, LoroValue, PeerID};
+    use fxhash::FxHashSet;
+    use loro_common::{IdFull, IdLp, LoroError, LoroResult, LoroValue, PeerID};
 
     use crate::{
         encoding::value_register::ValueRegister,
@@ -1716,22 +1717,39 @@ mod snapshot {
         where
             Self: Sized,
         {
-            let peer_num = leb128::read::unsigned(&mut bytes).unwrap() as usize;
-            let mut peers = Vec::with_capacity(peer_num);
+            let peer_num = leb128::read::unsigned(&mut bytes)
+                .map_err(|_| LoroError::DecodeDataCorruptionError)?
+                as usize;
+            let mut peers = Vec::with_capacity(peer_num.min(bytes.len() / 8));
             for _ in 0..peer_num {
                 let mut buf = [0u8; 8];
-                bytes.read_exact(&mut buf).unwrap();
+                bytes
+                    .read_exact(&mut buf)
+                    .map_err(|_| LoroError::DecodeDataCorruptionError)?;
                 peers.push(PeerID::from_le_bytes(buf));
             }
+            let peer = |peer_idx: usize| {
+                peers
+                    .get(peer_idx)
+                    .copied()
+                    .ok_or(LoroError::DecodeDataCorruptionError)
+            };
+            fn next<T>(
+                iter: &mut impl Iterator<Item = Result<T, serde_columnar::ColumnarError>>,
+            ) -> LoroResult<T> {
+                Ok(iter.next().ok_or(LoroError::DecodeDataCorruptionError)??)
+            }
 
             let mut ans = MovableListState::new(idx);
 
-            let iters = serde_columnar::iter_from_bytes::<EncodedFastSnapshot>(bytes).unwrap();
+            let iters = serde_columnar::iter_from_bytes::<EncodedFastSnapshot>(bytes)?;
             let mut elem_iter = iters.elem_ids;
             let item_iter = iters.items;
             let mut list_item_id_iter = iters.list_item_ids;
             let mut last_set_id_iter = iters.last_set_ids;
             let mut is_first = true;
+            // The ids must be unique, or the lookups by the ids would be inconsistent
+            let mut list_item_ids = FxHashSet::default();
 
             let list_value = list_value.into_list().unwrap();
             let mut list_value_iter = list_value.iter();
@@ -1740,35 +1758,42 @@ mod snapshot {
                     invisible_list_item,
                     pos_id_eq_elem_id,
                     elem_id_eq_last_set_id,
-                } = item.unwrap();
+                } = item?;
 
                 if !is_first {
                     let EncodedIdFull {
                         peer_idx,
                         counter,
                         lamport_sub_counter,
-                    } = list_item_id_iter.next().unwrap().unwrap();
+                    } = next(&mut list_item_id_iter)?;
                     let id_full = IdFull::new(
-                        peers[peer_idx],
+                        peer(peer_idx)?,
                         counter,
-                        (lamport_sub_counter + counter) as u32,
+                        lamport_sub_counter.wrapping_add(counter) as u32,
                     );
                     let elem_id = if pos_id_eq_elem_id {
                         id_full.idlp()
                     } else {
-                        let EncodedId { peer_idx, lamport } = elem_iter.next().unwrap().unwrap();
-                        IdLp::new(peers[peer_idx], lamport)
+                        let EncodedId { peer_idx, lamport } = next(&mut elem_iter)?;
+                        IdLp::new(peer(peer_idx)?, lamport)
                     };
 
                     let last_set_id = if elem_id_eq_last_set_id {
                         elem_id
                     } else {
-                        let EncodedId { peer_idx, lamport } =
-                            last_set_id_iter.next().unwrap().unwrap();
-                        IdLp::new(peers[peer_idx], lamport)
+                        let EncodedId { peer_idx, lamport } = next(&mut last_set_id_iter)?;
+                        IdLp::new(peer(peer_idx)?, lamport)
                     };
 
-                    let value = list_value_iter.next().unwrap();
+                    let value = list_value_iter
+                        .next()
+                        .ok_or(LoroError::DecodeDataCorruptionError)?;
+                    if elem_id.lamport == u32::MAX
+                        || !list_item_ids.insert(id_full.idlp())
+                        || ans.inner.elements().contains_key(&elem_id.compact())
+                    {
+                        return Err(LoroError::DecodeDataCorruptionError);
+                    }
                     ans.inner.push_inner(
                         id_full,
                         Some(PushElemInfo {
@@ -1785,20 +1810,26 @@ mod snapshot {
                         peer_idx,
                         counter,
                         lamport_sub_counter,
-                    } = list_item_id_iter.next().unwrap().unwrap();
+                    } = next(&mut list_item_id_iter)?;
                     let id_full = IdFull::new(
-                        peers[peer_idx],
+                        peer(peer_idx)?,
                         counter,
-                        (counter + lamport_sub_counter) as u32,
+                        counter.wrapping_add(lamport_sub_counter) as u32,
                     );
+                    if !list_item_ids.insert(id_full.idlp()) {
+                        return Err(LoroError::DecodeDataCorruptionError);
+                    }
                     ans.inner.push_inner(id_full, None);
                 }
             }
 
-            debug_assert!(elem_iter.next().is_none());
-            debug_assert!(list_item_id_iter.next().is_none());
-            debug_assert!(last_set_id_iter.next().is_none());
-            debug_assert!(list_value_iter.next().is_none());
+            if elem_iter.next().is_some()
+                || list_item_id_iter.next().is_some()
+                || last_set_id_iter.next().is_some()
+                || list_value_iter.next().is_some()
+            {
+                return Err(LoroError::DecodeDataCorruptionError);
+            }
 
             Ok(ans)
         }
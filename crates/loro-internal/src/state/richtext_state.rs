@@ -932,7 +932,7 @@ impl RichtextStateLoader {
 
 mod snapshot {
     use fxhash::FxHashMap;
-    use loro_common::{IdFull, InternalString, LoroValue, PeerID};
+    use loro_common::{IdFull, InternalString, LoroError, LoroValue, PeerID, ID};
     use serde_columnar::columnar;
     use std::{io::Read, sync::Arc};
 
@@ -1093,17 +1093,21 @@ mod snapshot {
         {
             let mut text = RichtextState::new(idx, ctx.configure.text_style_config.clone());
             let mut loader = RichtextStateLoader::default();
-            let peer_num = leb128::read::unsigned(&mut bytes).unwrap() as usize;
-            let mut peers = Vec::with_capacity(peer_num);
+            let peer_num = leb128::read::unsigned(&mut bytes)
+                .map_err(|_| LoroError::DecodeDataCorruptionError)?
+                as usize;
+            let mut peers = Vec::with_capacity(peer_num.min(bytes.len() / 8));
             for _ in 0..peer_num {
                 let mut buf = [0u8; 8];
-                bytes.read_exact(&mut buf).unwrap();
+                bytes
+                    .read_exact(&mut buf)
+                    .map_err(|_| LoroError::DecodeDataCorruptionError)?;
                 peers.push(PeerID::from_le_bytes(buf));
             }
 
             let string = string.into_string().unwrap();
             let mut s = StrSlice::new_from_str(&string);
-            let iters = serde_columnar::from_bytes::<EncodedText>(bytes).unwrap();
+            let iters = serde_columnar::from_bytes::<EncodedText>(bytes)?;
             let keys = iters.keys;
             let span_iter = iters.spans.into_iter();
             let mut mark_iter = iters.marks.into_iter();
@@ -1115,11 +1119,11 @@ mod snapshot {
                     lamport_sub_counter,
                     len,
                 } = span;
-                let id_full = IdFull::new(
-                    peers[peer_idx],
-                    counter,
-                    (lamport_sub_counter + counter) as u32,
-                );
+                let peer = *peers
+                    .get(peer_idx)
+                    .ok_or(LoroError::DecodeDataCorruptionError)?;
+                let lamport = lamport_sub_counter.wrapping_add(counter) as u32;
+                let id_full = IdFull::new(peer, counter, lamport);
                 let chunk = match len {
                     0 => {
                         // Style Start
@@ -1127,12 +1131,17 @@ mod snapshot {
                             key_idx,
                             value,
                             info,
-                        } = mark_iter.next().unwrap();
+                        } = mark_iter
+                            .next()
+                            .ok_or(LoroError::DecodeDataCorruptionError)?;
                         let style_op = Arc::new(StyleOp {
-                            lamport: (lamport_sub_counter + counter) as u32,
+                            lamport,
                             peer: id_full.peer,
                             cnt: id_full.counter,
-                            key: keys[key_idx].clone(),
+                            key: keys
+                                .get(key_idx)
+                                .ok_or(LoroError::DecodeDataCorruptionError)?
+                                .clone(),
                             value,
                             info: TextStyleInfoFlag::from_byte(info),
                         });
@@ -1141,19 +1150,31 @@ mod snapshot {
                     }
                     -1 => {
                         // Style End
-                        let style = id_to_style.remove(&id_full.id().inc(-1)).unwrap();
+                        let style = id_to_style
+                            .remove(&ID::new(peer, counter.wrapping_sub(1)))
+                            .ok_or(LoroError::DecodeDataCorruptionError)?;
                         RichtextStateChunk::new_style(style, richtext::AnchorType::End)
                     }
-                    len => {
+                    len if len > 0 => {
                         // Text
                         let (new, rest) = s.split_at_unicode_pos(len as usize);
+                        if new.as_str().chars().count() != len as usize {
+                            return Err(LoroError::DecodeDataCorruptionError);
+                        }
+
                         s = rest;
                         RichtextStateChunk::new_text(new.bytes().clone(), id_full)
                     }
+                    _ => return Err(LoroError::DecodeDataCorruptionError),
                 };
 
                 loader.push(chunk);
             }
+
+            if !s.as_str().is_empty() || mark_iter.next().is_some() {
+                return Err(LoroError::DecodeDataCorruptionError);
+            }
+
             text.state = LazyLoad::Src(loader);
             Ok(text)
         }
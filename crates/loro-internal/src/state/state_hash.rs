@@ -0,0 +1,275 @@
+//! A stable digest of the document state, see [DocState::state_hash], and the Merkle tree
+//! over the states of the containers, see [DocState::merkle_root].
+//!
+//! The digest is computed from a canonical encoding of the values: the map entries are sorted
+//! by key, and every child container is encoded as its id followed by the digest of its own
+//! state. So it only depends on the state and the container ids, not on the history, the
+//! internal layout of the states or the iteration order of the hash maps.
+//!
+//! The digests are SHA-256, so they can be compared with the digests from untrusted peers.
+//!
+//! The nodes of the Merkle tree are cached per container, see [MerkleCache].
+use fxhash::FxHashMap;
+use loro_common::{ContainerID, ContainerType, LoroValue};
+use sha2::{Digest, Sha256};
+
+use super::{ContainerState, DocState, State};
+use crate::{arena::SharedArena, container::idx::ContainerIdx};
+
+/// The digest of the state of a doc or a container
+pub type StateHash = [u8; 32];
+
+const NULL: u8 = 0;
+const BOOL: u8 = 1;
+const DOUBLE: u8 = 2;
+const I64: u8 = 3;
+const STRING: u8 = 4;
+const BINARY: u8 = 5;
+const LIST: u8 = 6;
+const MAP: u8 = 7;
+const CONTAINER: u8 = 8;
+
+struct Hasher(Sha256);
+
+impl Hasher {
+    fn new() -> Self {
+        Self(Sha256::new())
+    }
+
+    fn finish(self) -> StateHash {
+        self.0.finalize().into()
+    }
+
+    fn write(&mut self, bytes: impl AsRef<[u8]>) {
+        self.0.update(bytes);
+    }
+
+    fn tag(&mut self, tag: u8) {
+        self.write([tag]);
+    }
+
+    fn len(&mut self, len: usize) {
+        self.write((len as u64).to_le_bytes());
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.len(bytes.len());
+        self.write(bytes);
+    }
+}
+
+/// A node of the Merkle tree over the states of the containers.
+///
+/// The children of a container are the containers in its current value, so the tree follows
+/// the parent links of the alive containers. The children of the root node are the root
+/// containers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MerkleNode {
+    /// The digest of the node, which covers the digests of its children
+    pub hash: StateHash,
+    /// The child containers and their digests, sorted by the container ids
+    pub children: Vec<(ContainerID, StateHash)>,
+}
+
+/// The Merkle nodes of the containers that are already calculated.
+///
+/// The node of a container covers its descendants, so a change of a container invalidates
+/// the nodes of the container and all its ancestors. The other nodes are reused, so after
+/// an edit only the path from the edited container to the root is hashed again.
+#[derive(Debug, Default)]
+pub(super) struct MerkleCache {
+    nodes: FxHashMap<ContainerIdx, CachedNode>,
+}
+
+#[derive(Debug)]
+struct CachedNode {
+    node: MerkleNode,
+    /// Whether the state is empty, so the container is skipped if it's a root
+    is_empty: bool,
+}
+
+impl MerkleCache {
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+    }
+
+    /// Remove the nodes of the container and its ancestors
+    pub fn invalidate(&mut self, idx: ContainerIdx, arena: &SharedArena) {
+        if self.nodes.is_empty() {
+            return;
+        }
+
+        arena.with_ancestors(idx, |c, _| {
+            self.nodes.remove(&c);
+        });
+    }
+}
+
+impl DocState {
+    /// Get a digest of the current state.
+    ///
+    /// Two docs with the same state have the same digest, no matter how the state was
+    /// reached. The root containers with an empty state are skipped, because they may only
+    /// exist on the peers that accessed them. The reserved root containers, e.g. the refs,
+    /// are skipped too, because they are not part of the doc value.
+    pub fn state_hash(&mut self) -> StateHash {
+        self.merkle_root().hash
+    }
+
+    /// Get the root of the Merkle tree. Its digest is [DocState::state_hash].
+    pub fn merkle_root(&mut self) -> MerkleNode {
+        let mut children = Vec::new();
+        for idx in self.user_root_containers() {
+            let cached = self.cached_merkle_node(idx);
+            if cached.is_empty {
+                continue;
+            }
+
+            let hash = cached.node.hash;
+            children.push((self.arena.idx_to_id(idx).unwrap(), hash));
+        }
+        sort_children(&mut children);
+
+        let mut hasher = Hasher::new();
+        hasher.tag(MAP);
+        hasher.len(children.len());
+        for (id, hash) in children.iter() {
+            hasher.bytes(id.to_string().as_bytes());
+            hasher.write(hash);
+        }
+        MerkleNode {
+            hash: hasher.finish(),
+            children,
+        }
+    }
+
+    /// Get the node of the container in the Merkle tree.
+    /// Its digest is the digest of the state of the container and its descendants.
+    pub fn merkle_node(&mut self, idx: ContainerIdx) -> MerkleNode {
+        self.cached_merkle_node(idx).node.clone()
+    }
+
+    /// Get a digest of the state of the container and its descendants.
+    pub fn container_state_hash(&mut self, idx: ContainerIdx) -> StateHash {
+        self.cached_merkle_node(idx).node.hash
+    }
+
+    fn cached_merkle_node(&mut self, idx: ContainerIdx) -> &CachedNode {
+        if !self.merkle_cache.nodes.contains_key(&idx) {
+            let id = self.arena.idx_to_id(idx).unwrap();
+            let value = self.get_hashed_value(idx);
+            let mut children = Vec::new();
+            let hash = self.hash_value(&id, &value, &mut children);
+            sort_children(&mut children);
+            self.merkle_cache.nodes.insert(
+                idx,
+                CachedNode {
+                    node: MerkleNode { hash, children },
+                    is_empty: is_empty_value(&value),
+                },
+            );
+        }
+
+        &self.merkle_cache.nodes[&idx]
+    }
+
+    /// The shallow value of the container. The text includes its styles.
+    fn get_hashed_value(&mut self, idx: ContainerIdx) -> LoroValue {
+        match self.store.get_container_mut(idx) {
+            Some(State::RichtextState(s)) => s.get_richtext_value(),
+            Some(State::UnknownState(_)) => LoroValue::Null,
+            Some(s) => s.get_value(),
+            None => match idx.get_type() {
+                ContainerType::Text => LoroValue::List(Default::default()),
+                ContainerType::Unknown(_) => LoroValue::Null,
+                t => t.default_value(),
+            },
+        }
+    }
+
+    /// Hash the value of the container. The child containers and their digests are pushed
+    /// to `children`.
+    fn hash_value(
+        &mut self,
+        id: &ContainerID,
+        value: &LoroValue,
+        children: &mut Vec<(ContainerID, StateHash)>,
+    ) -> StateHash {
+        let mut hasher = Hasher::new();
+        hasher.bytes(id.to_string().as_bytes());
+        self.write_value(&mut hasher, value, children);
+        hasher.finish()
+    }
+
+    fn write_value(
+        &mut self,
+        hasher: &mut Hasher,
+        value: &LoroValue,
+        children: &mut Vec<(ContainerID, StateHash)>,
+    ) {
+        match value {
+            LoroValue::Null => hasher.tag(NULL),
+            LoroValue::Bool(b) => {
+                hasher.tag(BOOL);
+                hasher.write([*b as u8]);
+            }
+            LoroValue::Double(d) => {
+                hasher.tag(DOUBLE);
+                // -0.0 and 0.0 are the same value
+                let d = if *d == 0. { 0. } else { *d };
+                hasher.write(d.to_bits().to_le_bytes());
+            }
+            LoroValue::I64(i) => {
+                hasher.tag(I64);
+                hasher.write(i.to_le_bytes());
+            }
+            LoroValue::String(s) => {
+                hasher.tag(STRING);
+                hasher.bytes(s.as_bytes());
+            }
+            LoroValue::Binary(b) => {
+                hasher.tag(BINARY);
+                hasher.bytes(b);
+            }
+            LoroValue::List(list) => {
+                hasher.tag(LIST);
+                hasher.len(list.len());
+                for v in list.iter() {
+                    self.write_value(hasher, v, children);
+                }
+            }
+            LoroValue::Map(map) => {
+                hasher.tag(MAP);
+                hasher.len(map.len());
+                let mut entries = map.iter().collect::<Vec<_>>();
+                entries.sort_unstable_by(|a, b| a.0.cmp(b.0));
+                for (k, v) in entries {
+                    hasher.bytes(k.as_bytes());
+                    self.write_value(hasher, v, children);
+                }
+            }
+            LoroValue::Container(id) => {
+                // The digest of the child includes its id
+                let idx = self.arena.register_container(id);
+                let hash = self.container_state_hash(idx);
+                hasher.tag(CONTAINER);
+                hasher.write(hash);
+                children.push((id.clone(), hash));
+            }
+        }
+    }
+}
+
+fn sort_children(children: &mut [(ContainerID, StateHash)]) {
+    children.sort_by_cached_key(|(id, _)| id.to_string());
+}
+
+fn is_empty_value(value: &LoroValue) -> bool {
+    match value {
+        LoroValue::List(l) => l.is_empty(),
+        LoroValue::Map(m) => m.is_empty(),
+        LoroValue::String(s) => s.is_empty(),
+        LoroValue::Double(d) => *d == 0.,
+        _ => false,
+    }
+}
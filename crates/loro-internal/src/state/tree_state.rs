@@ -9,7 +9,7 @@ use loro_common::{
 };
 use rand::SeedableRng;
 use rle::HasLength;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
 use std::fmt::Debug;
 use std::ops::{Deref, DerefMut};
@@ -71,7 +71,7 @@ pub(crate) struct NodePosition {
     pub(crate) idlp: IdLp,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EnumAsInner, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EnumAsInner, Serialize, Deserialize)]
 pub enum TreeParentId {
     Node(TreeID),
     Root,
@@ -712,7 +712,10 @@ impl TreeState {
         last_move_op: IdFull,
         position: Option<FractionalIndex>,
     ) -> Result<(), LoroError> {
-        debug_assert!(!self.trees.contains_key(&target));
+        // The nodes are decoded from a snapshot, which may be malformed
+        if self.trees.contains_key(&target) {
+            return Err(LoroError::DecodeDataCorruptionError);
+        }
         let entry = self.children.entry(parent).or_default();
         let node_position =
             NodePosition::new(position.clone().unwrap_or_default(), last_move_op.idlp());
@@ -1581,7 +1584,7 @@ mod snapshot {
     use fractional_index::FractionalIndex;
     use fxhash::FxHashMap;
     use itertools::Itertools;
-    use loro_common::{IdFull, Lamport, PeerID, TreeID};
+    use loro_common::{IdFull, Lamport, LoroError, PeerID, TreeID};
 
     use serde_columnar::columnar;
 
@@ -1590,7 +1593,7 @@ mod snapshot {
         state::FastStateSnapshot,
     };
 
-    use super::{TreeNode, TreeParentId, TreeState};
+    use super::{NodePosition, TreeNode, TreeParentId, TreeState};
     #[columnar(vec, ser, de, iterable)]
     #[derive(Debug, Clone)]
     struct EncodedTreeNodeId {
@@ -1740,46 +1743,68 @@ mod snapshot {
         where
             Self: Sized,
         {
-            let peer_num = leb128::read::unsigned(&mut bytes).unwrap() as usize;
-            let mut peers = Vec::with_capacity(peer_num);
+            let peer_num = leb128::read::unsigned(&mut bytes)
+                .map_err(|_| LoroError::DecodeDataCorruptionError)?
+                as usize;
+            let mut peers = Vec::with_capacity(peer_num.min(bytes.len() / 8));
             for _ in 0..peer_num {
                 let mut buf = [0u8; 8];
-                bytes.read_exact(&mut buf).unwrap();
+                bytes
+                    .read_exact(&mut buf)
+                    .map_err(|_| LoroError::DecodeDataCorruptionError)?;
                 peers.push(PeerID::from_le_bytes(buf));
             }
+            let peer = |peer_idx: usize| {
+                peers
+                    .get(peer_idx)
+                    .copied()
+                    .ok_or(LoroError::DecodeDataCorruptionError)
+            };
 
             let mut tree = TreeState::new(idx, ctx.peer);
             let encoded: EncodedTree = serde_columnar::from_bytes(bytes)?;
-            let fractional_indexes = PositionArena::decode(&encoded.fractional_indexes).unwrap();
-            let fractional_indexes = fractional_indexes.parse_to_positions();
-            let node_ids = encoded
+            let fractional_indexes = PositionArena::decode(&encoded.fractional_indexes)?;
+            let fractional_indexes = fractional_indexes.parse_to_positions()?;
+            let node_ids: Vec<TreeID> = encoded
                 .node_ids
                 .iter()
-                .map(|x| TreeID::new(peers[x.peer_idx], x.counter))
-                .collect_vec();
-            for (node_id, node) in node_ids.iter().zip(encoded.nodes.into_iter()) {
+                .map(|x| Ok(TreeID::new(peer(x.peer_idx)?, x.counter)))
+                .try_collect()?;
+            let mut last: Option<(TreeParentId, NodePosition)> = None;
+            for (i, (node_id, node)) in node_ids.iter().zip(encoded.nodes.into_iter()).enumerate() {
                 // PERF: we don't need to mov the deleted node, instead we can cache them
                 // If the parent is TreeParentId::Deleted, then all the nodes afterwards are deleted
-                tree._init_push_tree_node_in_order(
-                    *node_id,
-                    match node.parent_idx_plus_two {
-                        0 => TreeParentId::Root,
-                        1 => TreeParentId::Deleted,
-                        n => {
-                            let id = node_ids[n - 2];
-                            TreeParentId::from(Some(id))
-                        }
-                    },
-                    IdFull::new(
-                        peers[node.last_set_peer_idx],
-                        node.last_set_counter,
-                        (node.last_set_lamport_sub_counter + node.last_set_counter) as Lamport,
-                    ),
-                    Some(FractionalIndex::from_bytes(
-                        fractional_indexes[node.fractional_index_idx].clone(),
-                    )),
-                )
-                .unwrap();
+                let parent = match node.parent_idx_plus_two {
+                    0 => TreeParentId::Root,
+                    1 => TreeParentId::Deleted,
+                    // The parents are encoded before their children
+                    n if n - 2 < i => TreeParentId::from(Some(node_ids[n - 2])),
+                    _ => return Err(LoroError::DecodeDataCorruptionError),
+                };
+                let position = fractional_indexes
+                    .get(node.fractional_index_idx)
+                    .ok_or(LoroError::DecodeDataCorruptionError)?;
+                let position = FractionalIndex::from_bytes(position.clone());
+                let last_move_op = IdFull::new(
+                    peer(node.last_set_peer_idx)?,
+                    node.last_set_counter,
+                    node.last_set_lamport_sub_counter
+                        .wrapping_add(node.last_set_counter) as Lamport,
+                );
+                // The children of a parent are encoded consecutively and in order
+                let node_position = NodePosition::new(position.clone(), last_move_op.idlp());
+                let in_order = match &last {
+                    Some((last_parent, last_position)) if *last_parent == parent => {
+                        *last_position < node_position
+                    }
+                    _ => !tree.children.contains_key(&parent),
+                };
+                if !in_order {
+                    return Err(LoroError::DecodeDataCorruptionError);
+                }
+
+                last = Some((parent, node_position));
+                tree._init_push_tree_node_in_order(*node_id, parent, last_move_op, Some(position))?;
             }
 
             Ok(tree)
@@ -414,7 +414,7 @@ impl Transaction {
         let ops = std::mem::take(&mut self.local_ops);
         let mut oplog = self.oplog.try_lock().unwrap();
         let deps = take(&mut self.frontiers);
-        let change = Change {
+        let mut change = Change {
             lamport: self.start_lamport,
             ops,
             deps,
@@ -424,7 +424,11 @@ impl Transaction {
                     .unwrap_or_else(|| oplog.get_timestamp_for_next_txn()),
             ),
             commit_msg: take(&mut self.msg),
+            signature: None,
         };
+        if let Some(signer) = oplog.configure.change_signer() {
+            signer.sign(&mut change, &oplog.arena);
+        }
 
         let diff = if state.is_recording() {
             Some(change_to_diff(
@@ -4,7 +4,7 @@ use std::{
 };
 
 use either::Either;
-use fxhash::FxHashMap;
+use fxhash::{FxHashMap, FxHashSet};
 use loro_common::{
     ContainerID, Counter, CounterSpan, HasIdSpan, IdSpan, LoroError, LoroResult, LoroValue, PeerID,
 };
@@ -19,50 +19,141 @@ use crate::{
     ContainerDiff, DocDiff, LoroDoc, Subscription,
 };
 
+/// A batch of container diffs.
+///
+/// Applying it on the `from` version of [LoroDoc::diff] brings the state to the `to` version.
 #[derive(Debug, Clone, Default)]
-pub struct DiffBatch(pub(crate) FxHashMap<ContainerID, Diff>);
+pub struct DiffBatch {
+    pub(crate) cid_to_events: FxHashMap<ContainerID, Diff>,
+    /// The containers in the batch, ordered from the top to the bottom.
+    ///
+    /// It's used to apply the diffs of the containers unknown to the target doc in
+    /// the correct order, so that the child containers can be remapped.
+    pub(crate) order: Vec<ContainerID>,
+}
 
 impl DiffBatch {
     pub fn new(diff: Vec<DocDiff>) -> Self {
         let mut map: FxHashMap<ContainerID, Diff> = Default::default();
+        let mut order: Vec<ContainerID> = Vec::with_capacity(diff.len());
         for d in diff.into_iter() {
             for item in d.diff.into_iter() {
                 let old = map.insert(item.id.clone(), item.diff);
                 assert!(old.is_none());
+                order.push(item.id.clone());
             }
         }
 
-        Self(map)
+        Self {
+            cid_to_events: map,
+            order,
+        }
     }
 
     pub fn compose(&mut self, other: &Self) {
-        if other.0.is_empty() {
+        if other.cid_to_events.is_empty() {
             return;
         }
 
-        for (idx, diff) in other.0.iter() {
-            if let Some(this_diff) = self.0.get_mut(idx) {
+        for (idx, diff) in other.iter() {
+            if let Some(this_diff) = self.cid_to_events.get_mut(idx) {
                 this_diff.compose_ref(diff);
             } else {
-                self.0.insert(idx.clone(), diff.clone());
+                self.cid_to_events.insert(idx.clone(), diff.clone());
+                self.order.push(idx.clone());
             }
         }
     }
 
     pub fn transform(&mut self, other: &Self, left_priority: bool) {
-        if other.0.is_empty() || self.0.is_empty() {
+        if other.cid_to_events.is_empty() || self.cid_to_events.is_empty() {
             return;
         }
 
-        for (idx, diff) in self.0.iter_mut() {
-            if let Some(b_diff) = other.0.get(idx) {
+        for (idx, diff) in self.cid_to_events.iter_mut() {
+            if let Some(b_diff) = other.cid_to_events.get(idx) {
                 diff.transform(b_diff, left_priority);
             }
         }
     }
 
     pub fn clear(&mut self) {
-        self.0.clear();
+        self.cid_to_events.clear();
+        self.order.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cid_to_events.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.cid_to_events.len()
+    }
+
+    /// Get the diff of the container
+    pub fn get(&self, cid: &ContainerID) -> Option<&Diff> {
+        self.cid_to_events.get(cid)
+    }
+
+    /// The containers in the batch, ordered from the top to the bottom
+    pub fn containers(&self) -> &[ContainerID] {
+        &self.order
+    }
+
+    /// Iterate the diffs in the order of [DiffBatch::order]
+    pub fn iter(&self) -> impl Iterator<Item = (&ContainerID, &Diff)> + '_ {
+        self.order
+            .iter()
+            .filter_map(|cid| self.cid_to_events.get(cid).map(|diff| (cid, diff)))
+    }
+
+    /// The containers that are created when the batch is applied, i.e. the child containers
+    /// inserted by the diffs and the meta containers of the created tree nodes.
+    pub(crate) fn created_containers(&self) -> FxHashSet<ContainerID> {
+        let mut ans = FxHashSet::default();
+        for diff in self.cid_to_events.values() {
+            match diff {
+                Diff::List(list) => {
+                    for item in list.iter() {
+                        if let loro_delta::DeltaItem::Replace { value, .. } = item {
+                            ans.extend(value.iter().filter_map(|v| v.container_id()));
+                        }
+                    }
+                }
+                Diff::Map(map) => {
+                    ans.extend(
+                        map.updated
+                            .values()
+                            .filter_map(|v| v.value.as_ref().and_then(|v| v.container_id())),
+                    );
+                }
+                Diff::Tree(tree) => {
+                    for item in tree.diff.iter() {
+                        if matches!(
+                            item.action,
+                            TreeExternalDiff::Create { .. } | TreeExternalDiff::Move { .. }
+                        ) {
+                            ans.insert(item.target.associated_meta_container());
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        ans
+    }
+
+    /// Insert the diff of a container at the end of the batch.
+    ///
+    /// If the batch already contains the container, the diff is returned back as error.
+    pub fn push(&mut self, cid: ContainerID, diff: Diff) -> Result<(), Diff> {
+        if self.cid_to_events.contains_key(&cid) {
+            return Err(diff);
+        }
+
+        self.cid_to_events.insert(cid.clone(), diff);
+        self.order.push(cid);
+        Ok(())
     }
 }
 
@@ -77,7 +168,7 @@ fn transform_cursor(
         cid = new_cid;
     }
 
-    if let Some(diff) = remote_diff.0.get(cid) {
+    if let Some(diff) = remote_diff.cid_to_events.get(cid) {
         let new_pos = diff.transform_cursor(cursor_with_pos.pos.pos, false);
         cursor_with_pos.pos.pos = new_pos;
     };
@@ -253,7 +344,7 @@ impl Stack {
         while self.stack.back().unwrap().0.is_empty() && self.stack.len() > 1 {
             let (_, diff) = self.stack.pop_back().unwrap();
             let diff = diff.try_lock().unwrap();
-            if !diff.0.is_empty() {
+            if !diff.is_empty() {
                 self.stack
                     .back_mut()
                     .unwrap()
@@ -285,7 +376,7 @@ impl Stack {
     pub fn push_with_merge(&mut self, span: CounterSpan, meta: UndoItemMeta, can_merge: bool) {
         let last = self.stack.back_mut().unwrap();
         let last_remote_diff = last.1.try_lock().unwrap();
-        if !last_remote_diff.0.is_empty() {
+        if !last_remote_diff.is_empty() {
             // If the remote diff is not empty, we cannot merge
             drop(last_remote_diff);
             let mut v = VecDeque::new();
@@ -318,10 +409,13 @@ impl Stack {
         let remote_diff = &mut self.stack.back_mut().unwrap().1;
         let mut remote_diff = remote_diff.try_lock().unwrap();
         for e in diff {
-            if let Some(d) = remote_diff.0.get_mut(&e.id) {
+            if let Some(d) = remote_diff.cid_to_events.get_mut(&e.id) {
                 d.compose_ref(&e.diff);
             } else {
-                remote_diff.0.insert(e.id.clone(), e.diff.clone());
+                remote_diff
+                    .cid_to_events
+                    .insert(e.id.clone(), e.diff.clone());
+                remote_diff.order.push(e.id.clone());
             }
         }
     }
@@ -1,49 +1,71 @@
 use std::{
     collections::BTreeSet,
+    io::Write,
     ops::Bound,
     sync::{Arc, Mutex},
 };
 
 use bytes::Bytes;
+use loro_common::LoroResult;
 use loro_kv_store::{mem_store::MemKvConfig, MemKvStore};
 
-use crate::kv_store::KvStore;
+use crate::{configure::Configure, kv_store::KvStore};
 
 /// This thin wrapper aims to limit the ability to modify the kv store and make
 /// it easy to find all the modifications.
 pub(crate) struct KvWrapper {
     kv: Arc<Mutex<dyn KvStore>>,
+    /// The compression and the encryption of the exported and the imported bytes
+    configure: Configure,
 }
 
 impl Clone for KvWrapper {
     fn clone(&self) -> Self {
         Self {
             kv: self.kv.try_lock().unwrap().clone_store(),
+            configure: self.configure.clone(),
         }
     }
 }
 
 impl KvWrapper {
-    pub fn new_mem() -> Self {
+    pub fn new_mem(configure: Configure) -> Self {
         Self {
             // kv: Arc::new(Mutex::new(BTreeMap::new())),
             kv: Arc::new(Mutex::new(MemKvStore::new(
                 // set false because it's depended by GC snapshot's import & export
                 MemKvConfig::default().should_encode_none(false),
             ))),
+            configure,
         }
     }
 
-    pub fn import(&self, bytes: Bytes) {
+    /// Import the bytes. They are decoded lazily when they are read, unless they are imported
+    /// in the [crate::encoding::ImportOptions::strict] mode.
+    pub fn import(&self, bytes: Bytes) -> LoroResult<()> {
         let mut kv = self.kv.try_lock().unwrap();
-        kv.import_all(bytes).unwrap();
+        self.configure.apply_codec(&mut *kv);
+        kv.try_import_all(bytes)?;
+        if self.configure.import_options().is_some_and(|x| x.strict) {
+            kv.check_all()?;
+        }
+
+        Ok(())
     }
 
     pub fn export(&self) -> Bytes {
         let mut kv = self.kv.try_lock().unwrap();
+        self.configure.apply_codec(&mut *kv);
         kv.export_all()
     }
 
+    /// Write the bytes that [KvWrapper::export] would return to `w`
+    pub fn export_to(&self, w: &mut dyn Write) -> std::io::Result<()> {
+        let mut kv = self.kv.try_lock().unwrap();
+        self.configure.apply_codec(&mut *kv);
+        kv.export_to(w)
+    }
+
     pub fn get(&self, key: &[u8]) -> Option<Bytes> {
         let kv = self.kv.try_lock().unwrap();
         kv.get(key)
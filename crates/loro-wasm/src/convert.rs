@@ -394,6 +394,15 @@ pub(crate) fn import_status_to_js_value(status: ImportStatus) -> JsValue {
         },
     )
     .unwrap();
+    js_sys::Reflect::set(
+        &obj,
+        &JsValue::from_str("quarantined"),
+        &match status.quarantined {
+            None => JsValue::null(),
+            Some(quarantined) => id_span_vector_to_js_value(quarantined),
+        },
+    )
+    .unwrap();
     obj.into()
 }
 
@@ -5165,7 +5165,8 @@ export type CounterSpan = { start: number, end: number };
 
 export type ImportStatus = {
   success: Map<PeerID, CounterSpan>,
-  pending: Map<PeerID, CounterSpan> | null
+  pending: Map<PeerID, CounterSpan> | null,
+  quarantined: Map<PeerID, CounterSpan> | null
 }
 
 export type Frontiers = OpId[];
@@ -1,14 +1,21 @@
 //! Loro event handling.
+use delta::array_vec::ArrayVec;
+use delta::DeltaRope;
 use enum_as_inner::EnumAsInner;
+use loro_common::{IdLp, LoroEncodeError, LoroError, LoroResult, LoroValue, TreeID};
 use loro_internal::container::ContainerID;
-use loro_internal::delta::TreeDiff;
-use loro_internal::event::EventTriggerKind;
+use loro_internal::delta::{
+    ResolvedMapDelta, ResolvedMapValue, TreeDiff, TreeDiffItem, TreeExternalDiff,
+};
+use loro_internal::event::{EventTriggerKind, ListDeltaMeta};
 use loro_internal::handler::{TextDelta, ValueOrHandler};
-use loro_internal::FxHashMap;
+use loro_internal::undo::DiffBatch as DiffBatchInner;
 use loro_internal::{
     event::{Diff as DiffInner, Index},
     ContainerDiff as ContainerDiffInner, DiffEvent as DiffEventInner,
 };
+use loro_internal::{FractionalIndex, FxHashMap, TreeParentId};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::sync::Arc;
 
 use crate::ValueOrContainer;
@@ -186,3 +193,353 @@ impl From<ValueOrHandler> for ValueOrContainer {
         }
     }
 }
+
+/// A batch of diffs between two versions of a [super::LoroDoc].
+///
+/// It's created by [super::LoroDoc::diff] and can be applied by [super::LoroDoc::apply_diff].
+/// It doesn't carry any history, so it can be applied on a document that doesn't share
+/// history with the one that produced it, e.g. to copy the content of a template into
+/// a new document.
+///
+/// It can be serialized by serde or encoded into a compact binary format by [DiffBatch::encode].
+#[derive(Debug, Clone, Default)]
+pub struct DiffBatch(pub(crate) DiffBatchInner);
+
+impl DiffBatch {
+    /// Whether the batch contains no diff.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Iterate the diffs of the containers in the batch.
+    ///
+    /// Parent containers come before their children.
+    pub fn iter(&self) -> impl Iterator<Item = (&ContainerID, Diff<'_>)> + '_ {
+        self.0.iter().map(|(id, diff)| (id, diff.into()))
+    }
+
+    /// Encode the batch into bytes.
+    pub fn encode(&self) -> Result<Vec<u8>, LoroEncodeError> {
+        postcard::to_allocvec(self).map_err(|e| LoroEncodeError::SerializeError(e.to_string()))
+    }
+
+    /// Decode the batch from the bytes created by [DiffBatch::encode].
+    pub fn decode(bytes: &[u8]) -> LoroResult<Self> {
+        postcard::from_bytes(bytes).map_err(|e| {
+            LoroError::DecodeError(format!("Failed to decode diff batch: {}", e).into_boxed_str())
+        })
+    }
+}
+
+impl From<DiffBatchInner> for DiffBatch {
+    fn from(value: DiffBatchInner) -> Self {
+        DiffBatch(value)
+    }
+}
+
+impl From<DiffBatch> for DiffBatchInner {
+    fn from(value: DiffBatch) -> Self {
+        value.0
+    }
+}
+
+/// The serialized form of [DiffBatch].
+///
+/// Child containers inside the values are stored as [LoroValue::Container], so that
+/// they can be recreated and remapped when the batch is applied.
+#[derive(Serialize, Deserialize)]
+struct EncodedDiffBatch {
+    diffs: Vec<(ContainerID, EncodedDiff)>,
+}
+
+#[derive(Serialize, Deserialize)]
+enum EncodedDiff {
+    List(Vec<EncodedListItem>),
+    Text(Vec<EncodedTextItem>),
+    Map(Vec<(String, Option<LoroValue>)>),
+    Tree(Vec<EncodedTreeItem>),
+    Counter(f64),
+    Unknown,
+}
+
+#[derive(Serialize, Deserialize)]
+enum EncodedListItem {
+    Insert {
+        insert: Vec<LoroValue>,
+        is_move: bool,
+    },
+    Delete {
+        delete: usize,
+    },
+    Retain {
+        retain: usize,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+enum EncodedTextItem {
+    Insert {
+        insert: String,
+        attributes: Option<FxHashMap<String, LoroValue>>,
+    },
+    Delete {
+        delete: usize,
+    },
+    Retain {
+        retain: usize,
+        attributes: Option<FxHashMap<String, LoroValue>>,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+struct EncodedTreeItem {
+    target: TreeID,
+    action: EncodedTreeAction,
+}
+
+#[derive(Serialize, Deserialize)]
+enum EncodedTreeAction {
+    Create {
+        parent: TreeParentId,
+        index: usize,
+        position: FractionalIndex,
+    },
+    Move {
+        parent: TreeParentId,
+        index: usize,
+        position: FractionalIndex,
+        old_parent: TreeParentId,
+        old_index: usize,
+    },
+    Delete {
+        old_parent: TreeParentId,
+        old_index: usize,
+    },
+}
+
+fn encode_value(v: &ValueOrHandler) -> LoroValue {
+    match v {
+        ValueOrHandler::Value(v) => v.clone(),
+        ValueOrHandler::Handler(h) => LoroValue::Container(h.id()),
+    }
+}
+
+impl From<&DiffInner> for EncodedDiff {
+    fn from(value: &DiffInner) -> Self {
+        match value {
+            DiffInner::List(l) => {
+                let mut ans = Vec::new();
+                for item in l.iter() {
+                    match item {
+                        delta::DeltaItem::Retain { len, .. } => {
+                            ans.push(EncodedListItem::Retain { retain: *len });
+                        }
+                        delta::DeltaItem::Replace {
+                            value,
+                            delete,
+                            attr,
+                        } => {
+                            if value.len() > 0 {
+                                ans.push(EncodedListItem::Insert {
+                                    insert: value.iter().map(encode_value).collect(),
+                                    is_move: attr.from_move,
+                                });
+                            }
+                            if *delete > 0 {
+                                ans.push(EncodedListItem::Delete { delete: *delete });
+                            }
+                        }
+                    }
+                }
+                EncodedDiff::List(ans)
+            }
+            DiffInner::Text(t) => EncodedDiff::Text(
+                TextDelta::from_text_diff(t.iter())
+                    .into_iter()
+                    .map(|d| match d {
+                        TextDelta::Retain { retain, attributes } => {
+                            EncodedTextItem::Retain { retain, attributes }
+                        }
+                        TextDelta::Insert { insert, attributes } => {
+                            EncodedTextItem::Insert { insert, attributes }
+                        }
+                        TextDelta::Delete { delete } => EncodedTextItem::Delete { delete },
+                    })
+                    .collect(),
+            ),
+            DiffInner::Map(m) => EncodedDiff::Map(
+                m.updated
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), v.value.as_ref().map(encode_value)))
+                    .collect(),
+            ),
+            DiffInner::Tree(t) => EncodedDiff::Tree(
+                t.diff
+                    .iter()
+                    .map(|item| EncodedTreeItem {
+                        target: item.target,
+                        action: match &item.action {
+                            TreeExternalDiff::Create {
+                                parent,
+                                index,
+                                position,
+                            } => EncodedTreeAction::Create {
+                                parent: *parent,
+                                index: *index,
+                                position: position.clone(),
+                            },
+                            TreeExternalDiff::Move {
+                                parent,
+                                index,
+                                position,
+                                old_parent,
+                                old_index,
+                            } => EncodedTreeAction::Move {
+                                parent: *parent,
+                                index: *index,
+                                position: position.clone(),
+                                old_parent: *old_parent,
+                                old_index: *old_index,
+                            },
+                            TreeExternalDiff::Delete {
+                                old_parent,
+                                old_index,
+                            } => EncodedTreeAction::Delete {
+                                old_parent: *old_parent,
+                                old_index: *old_index,
+                            },
+                        },
+                    })
+                    .collect(),
+            ),
+            #[cfg(feature = "counter")]
+            DiffInner::Counter(c) => EncodedDiff::Counter(*c),
+            _ => EncodedDiff::Unknown,
+        }
+    }
+}
+
+impl From<EncodedDiff> for DiffInner {
+    fn from(value: EncodedDiff) -> Self {
+        match value {
+            EncodedDiff::List(items) => {
+                let mut ans = DeltaRope::new();
+                for item in items {
+                    match item {
+                        EncodedListItem::Insert { insert, is_move } => {
+                            let values = insert.into_iter().map(ValueOrHandler::Value);
+                            for arr in ArrayVec::from_many(values) {
+                                ans.push_insert(arr, ListDeltaMeta { from_move: is_move });
+                            }
+                        }
+                        EncodedListItem::Delete { delete } => {
+                            ans.push_delete(delete);
+                        }
+                        EncodedListItem::Retain { retain } => {
+                            ans.push_retain(retain, Default::default());
+                        }
+                    }
+                }
+                DiffInner::List(ans)
+            }
+            EncodedDiff::Text(items) => {
+                let items = items.into_iter().map(|d| match d {
+                    EncodedTextItem::Retain { retain, attributes } => {
+                        TextDelta::Retain { retain, attributes }
+                    }
+                    EncodedTextItem::Insert { insert, attributes } => {
+                        TextDelta::Insert { insert, attributes }
+                    }
+                    EncodedTextItem::Delete { delete } => TextDelta::Delete { delete },
+                });
+                DiffInner::Text(TextDelta::into_text_diff(items))
+            }
+            EncodedDiff::Map(updated) => DiffInner::Map(ResolvedMapDelta {
+                updated: updated
+                    .into_iter()
+                    .map(|(k, v)| {
+                        (
+                            k.into(),
+                            ResolvedMapValue {
+                                value: v.map(ValueOrHandler::Value),
+                                idlp: IdLp::NONE_ID,
+                            },
+                        )
+                    })
+                    .collect(),
+            }),
+            EncodedDiff::Tree(items) => DiffInner::Tree(TreeDiff {
+                diff: items
+                    .into_iter()
+                    .map(|item| TreeDiffItem {
+                        target: item.target,
+                        action: match item.action {
+                            EncodedTreeAction::Create {
+                                parent,
+                                index,
+                                position,
+                            } => TreeExternalDiff::Create {
+                                parent,
+                                index,
+                                position,
+                            },
+                            EncodedTreeAction::Move {
+                                parent,
+                                index,
+                                position,
+                                old_parent,
+                                old_index,
+                            } => TreeExternalDiff::Move {
+                                parent,
+                                index,
+                                position,
+                                old_parent,
+                                old_index,
+                            },
+                            EncodedTreeAction::Delete {
+                                old_parent,
+                                old_index,
+                            } => TreeExternalDiff::Delete {
+                                old_parent,
+                                old_index,
+                            },
+                        },
+                    })
+                    .collect(),
+            }),
+            #[cfg(feature = "counter")]
+            EncodedDiff::Counter(c) => DiffInner::Counter(c),
+            #[cfg(not(feature = "counter"))]
+            EncodedDiff::Counter(_) => DiffInner::Unknown,
+            EncodedDiff::Unknown => DiffInner::Unknown,
+        }
+    }
+}
+
+impl Serialize for DiffBatch {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        EncodedDiffBatch {
+            diffs: self
+                .0
+                .iter()
+                .map(|(id, diff)| (id.clone(), diff.into()))
+                .collect(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for DiffBatch {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let encoded = EncodedDiffBatch::deserialize(deserializer)?;
+        let mut batch = DiffBatchInner::default();
+        for (id, diff) in encoded.diffs {
+            if batch.push(id, diff.into()).is_err() {
+                return Err(serde::de::Error::custom(
+                    "duplicated container in diff batch",
+                ));
+            }
+        }
+        Ok(DiffBatch(batch))
+    }
+}
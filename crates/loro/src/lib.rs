@@ -24,7 +24,9 @@ use loro_internal::{
     TextHandler as InnerTextHandler, TreeHandler as InnerTreeHandler,
     UnknownHandler as InnerUnknownHandler,
 };
+pub use loro_internal::{MerkleNode, StateHash};
 use std::cmp::Ordering;
+use std::io::{Read, Write};
 use std::ops::ControlFlow;
 use std::ops::Range;
 use std::sync::Arc;
@@ -34,30 +36,43 @@ pub use loro_internal::diff::diff_impl::UpdateOptions;
 pub use loro_internal::diff::diff_impl::UpdateTimeoutError;
 pub use loro_internal::subscription::LocalUpdateCallback;
 pub use loro_internal::subscription::PeerIdUpdateCallback;
-pub use loro_internal::ChangeMeta;
+pub use loro_internal::{ChangeMeta, ChangeWithOps};
 pub mod event;
+pub mod sync;
+pub use event::DiffBatch;
 pub use loro_internal::awareness;
 pub use loro_internal::change::Timestamp;
 pub use loro_internal::configure::Configure;
 pub use loro_internal::configure::{StyleConfig, StyleConfigMap};
+pub use loro_internal::conflict::{Conflict, ConflictValue};
 pub use loro_internal::container::richtext::ExpandType;
 pub use loro_internal::container::{ContainerID, ContainerType, IntoContainerId};
 pub use loro_internal::cursor;
 pub use loro_internal::delta::{TreeDeltaItem, TreeDiff, TreeDiffItem, TreeExternalDiff};
+pub use loro_internal::encoding::EncodingVersion;
 pub use loro_internal::encoding::ExportMode;
 pub use loro_internal::encoding::ImportBlobMetadata;
+pub use loro_internal::encoding::ImportOptions;
 pub use loro_internal::event::{EventTriggerKind, Index};
 pub use loro_internal::handler::TextDelta;
+pub use loro_internal::import_filter::ImportFilter;
 pub use loro_internal::json;
 pub use loro_internal::json::{
     FutureOp as JsonFutureOp, FutureOpWrapper as JsonFutureOpWrapper, JsonChange, JsonOp,
     JsonOpContent, JsonSchema, ListOp as JsonListOp, MapOp as JsonMapOp,
     MovableListOp as JsonMovableListOp, TextOp as JsonTextOp, TreeOp as JsonTreeOp,
 };
-pub use loro_internal::kv_store::{KvStore, MemKvStore};
+pub use loro_internal::kv_store::{
+    CompressionDict, CompressionType, EncryptionKey, KvStore, MemKvStore,
+};
 pub use loro_internal::loro::CommitOptions;
 pub use loro_internal::loro::DocAnalysis;
+pub use loro_internal::loro::{CherryPickConflict, CherryPickResult, TextBlameSpan};
 pub use loro_internal::oplog::FrontiersNotIncluded;
+pub use loro_internal::refs::REFS_CONTAINER_NAME;
+pub use loro_internal::signature::{
+    ChangeSignPayload, ChangeSigner, ChangeVerifier, SIGN_PAYLOAD_VERSION,
+};
 pub use loro_internal::undo;
 pub use loro_internal::version::{Frontiers, VersionRange, VersionVector, VersionVectorDiff};
 pub use loro_internal::ApplyDiff;
@@ -83,6 +98,59 @@ mod counter;
 #[cfg(feature = "counter")]
 pub use counter::LoroCounter;
 
+/// Merge the updates blobs into one blob, which contains the union of their changes.
+///
+/// The blobs should be exported with [ExportMode::Updates] or [ExportMode::UpdatesInRange].
+/// The change blocks are decoded and encoded again without loading a [LoroDoc], so it's
+/// much cheaper than importing the blobs and exporting the updates. The overlapping
+/// changes in the blobs are deduplicated.
+///
+/// # Example
+/// ```
+/// # use loro::{ExportMode, LoroDoc, merge_updates};
+/// let doc = LoroDoc::new();
+/// doc.get_text("text").insert(0, "Hello").unwrap();
+/// let first = doc.export(ExportMode::all_updates()).unwrap();
+/// let vv = doc.oplog_vv();
+/// doc.get_text("text").insert(5, " world").unwrap();
+/// let second = doc.export(ExportMode::updates(&vv)).unwrap();
+/// let all = doc.export(ExportMode::all_updates()).unwrap();
+///
+/// let merged = merge_updates(&[&first, &second, &all]).unwrap();
+/// let new_doc = LoroDoc::new();
+/// new_doc.import(&merged).unwrap();
+/// assert_eq!(new_doc.get_text("text").to_string(), "Hello world");
+/// ```
+pub fn merge_updates(blobs: &[&[u8]]) -> LoroResult<Vec<u8>> {
+    loro_internal::encoding::merge_updates(blobs)
+}
+
+/// The effect of importing a blob, calculated by [LoroDoc::import_preview] without
+/// changing the doc.
+#[derive(Debug, Clone)]
+pub struct ImportPreview {
+    /// The changes that would be applied and the ones that would stay pending
+    pub status: ImportStatus,
+    /// The containers that would be changed by the applied changes
+    pub changed_containers: FxHashSet<ContainerID>,
+    /// The oplog frontiers after the import
+    pub frontiers: Frontiers,
+    /// The diff from the current oplog version to the version after the import.
+    /// It's only calculated when requested.
+    pub diff: Option<DiffBatch>,
+}
+
+impl From<loro_internal::encoding::ImportPreview> for ImportPreview {
+    fn from(value: loro_internal::encoding::ImportPreview) -> Self {
+        Self {
+            status: value.status,
+            changed_containers: value.changed_containers,
+            frontiers: value.frontiers,
+            diff: value.diff.map(DiffBatch::from),
+        }
+    }
+}
+
 /// `LoroDoc` is the entry for the whole document.
 /// When it's dropped, all the associated [`Handler`]s will be invalidated.
 #[derive(Debug)]
@@ -138,6 +206,38 @@ impl LoroDoc {
         LoroDoc::_new(new_doc)
     }
 
+    /// Create a new document with the content inserted by the ops in `range` replaced by
+    /// placeholders. It follows the same rules as [`json::redact`].
+    ///
+    /// The ids and the lengths of the ops are kept, so the redacted document can still sync
+    /// with the other peers. Its state is rebuilt from the redacted history, so a snapshot
+    /// exported from it never contains the redacted content.
+    ///
+    /// It returns an error if the document is shallow.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use loro::{ExportMode, LoroDoc, VersionRange};
+    ///
+    /// let doc = LoroDoc::new();
+    /// doc.set_peer_id(1).unwrap();
+    /// doc.get_text("text").insert(0, "my password is hunter2").unwrap();
+    /// let mut range = VersionRange::new();
+    /// range.insert(1, 15, 22);
+    /// let redacted = doc.redact(&range).unwrap();
+    /// assert_eq!(redacted.get_text("text").to_string(), "my password is �������");
+    ///
+    /// let snapshot = redacted.export(ExportMode::Snapshot).unwrap();
+    /// let new_doc = LoroDoc::new();
+    /// new_doc.import(&snapshot).unwrap();
+    /// assert_eq!(new_doc.get_text("text").to_string(), "my password is �������");
+    /// ```
+    pub fn redact(&self, range: &VersionRange) -> LoroResult<LoroDoc> {
+        let new_doc = self.doc.redact(range)?;
+        Ok(LoroDoc::_new(new_doc))
+    }
+
     /// Get the configurations of the document.
     #[inline]
     pub fn config(&self) -> &Configure {
@@ -162,7 +262,37 @@ impl LoroDoc {
         Some(ChangeMeta::from_change(&change))
     }
 
+    /// Get the changes between the two versions, with their ops.
+    ///
+    /// The changes are sliced by the versions and sorted by lamport. Each op carries its
+    /// container id and typed content, such as the inserted text, the map key and value,
+    /// the tree move or the counter delta. The peer ids inside the ops are not compressed.
+    ///
+    /// # Example
+    /// ```
+    /// # use loro::{LoroDoc, VersionVector, JsonOpContent, JsonTextOp};
+    /// let doc = LoroDoc::new();
+    /// doc.get_text("text").insert(0, "Hello").unwrap();
+    /// doc.commit();
+    /// let changes = doc.changes_between(&VersionVector::default(), &doc.oplog_vv());
+    /// assert_eq!(changes.len(), 1);
+    /// match &changes[0].ops[0].content {
+    ///     JsonOpContent::Text(JsonTextOp::Insert { pos, text }) => {
+    ///         assert_eq!(*pos, 0);
+    ///         assert_eq!(text, "Hello");
+    ///     }
+    ///     _ => unreachable!(),
+    /// }
+    /// ```
+    #[inline]
+    pub fn changes_between(&self, from: &VersionVector, to: &VersionVector) -> Vec<ChangeWithOps> {
+        self.doc.changes_between(from, to)
+    }
+
     /// Decodes the metadata for an imported blob from the provided bytes.
+    ///
+    /// The snapshots compressed with a dictionary or encrypted can't be decoded without
+    /// the dictionary or the key, see [LoroDoc::decode_import_blob_meta_with].
     #[inline]
     pub fn decode_import_blob_meta(
         bytes: &[u8],
@@ -171,6 +301,21 @@ impl LoroDoc {
         InnerLoroDoc::decode_import_blob_meta(bytes, check_checksum)
     }
 
+    /// Decodes the metadata for an imported blob from the provided bytes, reading the snapshots
+    /// with the given compression dictionary and encryption key.
+    ///
+    /// They are the ones given to [LoroDoc::set_snapshot_compression] and
+    /// [LoroDoc::set_encryption_key] of the doc that exported the snapshot.
+    #[inline]
+    pub fn decode_import_blob_meta_with(
+        bytes: &[u8],
+        check_checksum: bool,
+        dict: Option<CompressionDict>,
+        key: Option<EncryptionKey>,
+    ) -> LoroResult<ImportBlobMetadata> {
+        InnerLoroDoc::decode_import_blob_meta_with(bytes, check_checksum, dict, key)
+    }
+
     /// Set whether to record the timestamp of each change. Default is `false`.
     ///
     /// If enabled, the Unix timestamp will be recorded for each change automatically.
@@ -225,6 +370,226 @@ impl LoroDoc {
         self.doc.set_change_merge_interval(interval);
     }
 
+    /// Set the compression of the stores in the exported snapshots. The default is LZ4.
+    ///
+    /// Snapshots compressed with zstd can only be imported with the `zstd` feature enabled.
+    /// Older versions of Loro reject them with [LoroError::IncompatibleFutureEncodingError].
+    ///
+    /// The dictionary is used by [CompressionType::ZstdWithDict]. A doc needs the same
+    /// dictionary to import the snapshots compressed with it.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use loro::{CompressionType, ExportMode, LoroDoc};
+    ///
+    /// let doc = LoroDoc::new();
+    /// doc.get_text("text").insert(0, "Hello").unwrap();
+    /// doc.set_snapshot_compression(CompressionType::None, None).unwrap();
+    /// let snapshot = doc.export(ExportMode::Snapshot).unwrap();
+    /// let new_doc = LoroDoc::new();
+    /// new_doc.import(&snapshot).unwrap();
+    /// assert_eq!(new_doc.get_text("text").to_string(), "Hello");
+    /// ```
+    #[inline]
+    pub fn set_snapshot_compression(
+        &self,
+        compression_type: CompressionType,
+        dict: Option<CompressionDict>,
+    ) -> LoroResult<()> {
+        self.doc.set_snapshot_compression(compression_type, dict)
+    }
+
+    /// Set the key to encrypt the stores in the exported snapshots, or `None` to stop encrypting.
+    ///
+    /// Every block of the stores is encrypted with ChaCha20-Poly1305. The same key is
+    /// required to import the snapshots, otherwise the import fails with
+    /// [LoroError::DecryptionError]. Updates are not encrypted.
+    ///
+    /// The first and the last key of every block stay in plaintext, which are the IDs of
+    /// some changes, i.e. peer IDs and counters, and the IDs of some containers, including
+    /// the names of the root containers. The values, e.g. the ops and the states, are hidden.
+    ///
+    /// A key can only be created with the `encryption` feature enabled.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[cfg(feature = "encryption")]
+    /// # {
+    /// use loro::{EncryptionKey, ExportMode, LoroDoc, LoroError};
+    ///
+    /// let key = EncryptionKey::new(1, [7; 32]);
+    /// let doc = LoroDoc::new();
+    /// doc.get_text("text").insert(0, "Hello").unwrap();
+    /// doc.set_encryption_key(Some(key.clone()));
+    /// let snapshot = doc.export(ExportMode::Snapshot).unwrap();
+    /// assert!(matches!(
+    ///     LoroDoc::new().import(&snapshot),
+    ///     Err(LoroError::DecryptionError(_))
+    /// ));
+    ///
+    /// let new_doc = LoroDoc::new();
+    /// new_doc.set_encryption_key(Some(key));
+    /// new_doc.import(&snapshot).unwrap();
+    /// assert_eq!(new_doc.get_text("text").to_string(), "Hello");
+    /// # }
+    /// ```
+    #[inline]
+    pub fn set_encryption_key(&self, key: Option<EncryptionKey>) {
+        self.doc.set_encryption_key(key)
+    }
+
+    /// Set the signer of the local changes, or `None` to stop signing.
+    ///
+    /// Every change committed afterwards is signed over [ChangeSignPayload::bytes], a
+    /// versioned canonical encoding of its ID, deps, lamport, timestamp, commit message and
+    /// ops, which doesn't depend on how the change is stored. The signature is
+    /// exported with the change in the binary encodings. The JSON updates don't carry it.
+    ///
+    /// Signed changes are never merged with each other, even within the merge interval.
+    /// [LoroDoc::squash_local] re-signs the squashed changes with the current signer.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use loro::{ChangeSigner, ChangeVerifier, ExportMode, LoroDoc, LoroError};
+    ///
+    /// // Use a real signature scheme such as ed25519 instead
+    /// let sign = |bytes: &[u8]| vec![bytes.iter().fold(0u8, |a, b| a ^ b)];
+    /// let doc = LoroDoc::new();
+    /// doc.set_change_signer(Some(ChangeSigner::new(move |payload| sign(payload.bytes))));
+    /// doc.get_text("text").insert(0, "Hello").unwrap();
+    /// doc.commit();
+    ///
+    /// let new_doc = LoroDoc::new();
+    /// new_doc.set_change_verifier(Some(ChangeVerifier::new(move |payload, sig| {
+    ///     sig == Some(sign(payload.bytes).as_slice())
+    /// })));
+    /// new_doc.import(&doc.export(ExportMode::all_updates()).unwrap()).unwrap();
+    /// assert_eq!(new_doc.get_text("text").to_string(), "Hello");
+    ///
+    /// // Unsigned changes are rejected
+    /// let unsigned = LoroDoc::new();
+    /// unsigned.get_text("text").insert(0, "Hi").unwrap();
+    /// let updates = unsigned.export(ExportMode::all_updates()).unwrap();
+    /// assert!(matches!(
+    ///     new_doc.import(&updates),
+    ///     Err(LoroError::InvalidChangeSignature(_))
+    /// ));
+    /// ```
+    #[inline]
+    pub fn set_change_signer(&self, signer: Option<ChangeSigner>) {
+        self.doc.set_change_signer(signer)
+    }
+
+    /// Set the verifier of the imported changes, or `None` to stop verifying.
+    ///
+    /// The verifier receives the signature of every change that is new to the doc, or
+    /// `None` if the change is unsigned. If it rejects any change, the import fails with
+    /// [LoroError::InvalidChangeSignature] and nothing is imported.
+    ///
+    /// The states in the snapshots are not signed, so they are calculated from the verified
+    /// changes instead. The shallow snapshots are rejected with
+    /// [LoroError::UnverifiableShallowSnapshot] when they initialize the doc, because the
+    /// history before their shallow root is missing.
+    ///
+    /// See [LoroDoc::set_change_signer] for an example.
+    #[inline]
+    pub fn set_change_verifier(&self, verifier: Option<ChangeVerifier>) {
+        self.doc.set_change_verifier(verifier)
+    }
+
+    /// Set the filter that accepts or rejects the imported changes, or `None` to accept all.
+    ///
+    /// The filter receives the meta of every new change and the containers its ops touch.
+    /// The rejected changes and the imported changes that depend on them are put into the
+    /// quarantine and reported by [ImportStatus::quarantined]. They are never applied to
+    /// the doc state, and the rest of the import still succeeds.
+    ///
+    /// With a filter, snapshots are imported as updates, so a shallow snapshot cannot be
+    /// imported into an empty doc.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use loro::{ContainerID, ContainerType, ExportMode, ImportFilter, LoroDoc};
+    ///
+    /// let settings = ContainerID::new_root("settings", ContainerType::Map);
+    /// let server = LoroDoc::new();
+    /// server.set_import_filter(Some(ImportFilter::new(move |_meta, containers| {
+    ///     containers.iter().all(|c| c == &settings)
+    /// })));
+    ///
+    /// let client = LoroDoc::new();
+    /// client.set_change_merge_interval(0);
+    /// client.get_map("settings").insert("theme", "dark").unwrap();
+    /// client.commit();
+    /// client.get_text("body").insert(0, "Hello").unwrap();
+    /// client.commit();
+    ///
+    /// let status = server
+    ///     .import(&client.export(ExportMode::all_updates()).unwrap())
+    ///     .unwrap();
+    /// assert!(status.quarantined.is_some());
+    /// assert_eq!(server.get_map("settings").get_deep_value().to_json_value()["theme"], "dark");
+    /// assert!(server.get_text("body").is_empty());
+    /// ```
+    #[inline]
+    pub fn set_import_filter(&self, filter: Option<ImportFilter>) {
+        self.doc.set_import_filter(filter)
+    }
+
+    /// Get the range of all the changes in the quarantine of the import filter.
+    ///
+    /// The quarantined changes are dropped, not stored. The changes that depend on them are
+    /// quarantined when they are imported, even if the filter accepts them.
+    #[inline]
+    pub fn quarantine(&self) -> VersionRange {
+        self.doc.quarantine()
+    }
+
+    /// Forget the quarantined changes.
+    ///
+    /// Afterwards the quarantined changes and their descendants can be imported again,
+    /// e.g. after the filter is changed. They are still checked by the current filter.
+    #[inline]
+    pub fn clear_quarantine(&self) {
+        self.doc.clear_quarantine()
+    }
+
+    /// Set the resource limits of the imported data, or `None` to remove them.
+    ///
+    /// Use it when the doc imports blobs from untrusted sources, such as a public sync
+    /// endpoint. The limits are checked before anything is imported, and an import that
+    /// exceeds any of them fails with [LoroError::LimitExceeded]. Set [ImportOptions::strict]
+    /// to also check every part of the imported snapshots eagerly.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use loro::{ExportMode, ImportOptions, LoroDoc, LoroError};
+    ///
+    /// let server = LoroDoc::new();
+    /// server.set_import_options(Some(ImportOptions {
+    ///     max_value_len: Some(16),
+    ///     ..Default::default()
+    /// }));
+    ///
+    /// let client = LoroDoc::new();
+    /// client.get_map("map").insert("key", "a very long string value").unwrap();
+    /// let updates = client.export(ExportMode::all_updates()).unwrap();
+    /// assert!(matches!(
+    ///     server.import(&updates),
+    ///     Err(LoroError::LimitExceeded { .. })
+    /// ));
+    /// assert!(server.get_map("map").is_empty());
+    /// ```
+    #[inline]
+    pub fn set_import_options(&self, options: Option<ImportOptions>) {
+        self.doc.set_import_options(options)
+    }
+
     /// Set the rich text format configuration of the document.
     ///
     /// You need to config it if you use rich text `mark` method.
@@ -261,6 +626,46 @@ impl LoroDoc {
         self.doc.checkout(frontiers)
     }
 
+    /// Get the version that contains every change whose timestamp is not greater than the
+    /// given timestamp (in seconds), and the causal dependencies of them.
+    ///
+    /// The timestamps are only recorded when `set_record_timestamp` is enabled or when they are
+    /// set by [CommitOptions]. The lookup visits the blocks of each peer from the latest one
+    /// and stops at the first block that has an early enough change, so it usually doesn't
+    /// scan all the changes. The timestamps don't need to be sorted.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use loro::{CommitOptions, LoroDoc};
+    ///
+    /// let doc = LoroDoc::new();
+    /// // Don't merge the changes so that they keep their own timestamps
+    /// doc.set_change_merge_interval(0);
+    /// let text = doc.get_text("text");
+    /// text.insert(0, "Hello").unwrap();
+    /// doc.commit_with(CommitOptions::new().timestamp(100));
+    /// let v100 = doc.state_frontiers();
+    /// text.insert(5, " world").unwrap();
+    /// doc.commit_with(CommitOptions::new().timestamp(200));
+    ///
+    /// assert_eq!(doc.frontiers_at_timestamp(150).unwrap(), v100);
+    /// doc.checkout_at_timestamp(150).unwrap();
+    /// assert_eq!(text.to_string(), "Hello");
+    /// ```
+    #[inline]
+    pub fn frontiers_at_timestamp(&self, timestamp: Timestamp) -> LoroResult<Frontiers> {
+        self.doc.frontiers_at_timestamp(timestamp)
+    }
+
+    /// Checkout the `DocState` to the version at the given timestamp (in seconds).
+    ///
+    /// See [LoroDoc::frontiers_at_timestamp] and [LoroDoc::checkout].
+    #[inline]
+    pub fn checkout_at_timestamp(&self, timestamp: Timestamp) -> LoroResult<()> {
+        self.doc.checkout_at_timestamp(timestamp)
+    }
+
     /// Checkout the `DocState` to the latest version.
     ///
     /// > The document becomes detached during a `checkout` operation.
@@ -274,6 +679,120 @@ impl LoroDoc {
         self.doc.checkout_to_latest()
     }
 
+    /// Revert the current document state back to the target version.
+    ///
+    /// Unlike `checkout`, it doesn't detach the document. It generates and commits a series
+    /// of local operations that bring the current state to the state of the target version.
+    /// The history is kept, so other peers can merge the revert like any normal edit.
+    #[inline]
+    pub fn revert_to(&self, version: &Frontiers) -> LoroResult<()> {
+        self.doc.revert_to(version)
+    }
+
+    /// Calculate the diff between two versions.
+    ///
+    /// Applying the returned [DiffBatch] on a document at version `a` brings it to version `b`.
+    /// The batch can be serialized and applied on documents that don't share history with this one.
+    #[inline]
+    pub fn diff(&self, a: &Frontiers, b: &Frontiers) -> LoroResult<DiffBatch> {
+        self.doc.diff(a, b).map(DiffBatch::from)
+    }
+
+    /// Apply a diff batch to the current document state.
+    ///
+    /// The changes are recorded as new local operations in the current transaction.
+    /// Child containers created by the diff get new ids in this document.
+    #[inline]
+    pub fn apply_diff(&self, diff: &DiffBatch) -> LoroResult<()> {
+        self.doc
+            .apply_diff(diff.0.clone(), &mut Default::default(), true)
+    }
+
+    /// Apply the effect of the given changes on top of the current state as new local operations.
+    ///
+    /// Unlike `import`, the causal history of the changes is not applied. The picked ops are
+    /// transformed based on the edits made since their deps, and the containers edited by both
+    /// sides are reported in [CherryPickResult::conflicts].
+    #[inline]
+    pub fn cherry_pick(&self, spans: &[IdSpan]) -> LoroResult<CherryPickResult> {
+        self.doc.cherry_pick(spans)
+    }
+
+    /// Create a branch pointing to the given version.
+    ///
+    /// Branches and tags are stored in the reserved root map [REFS_CONTAINER_NAME], so they
+    /// are persisted in snapshots and synced between peers. The map is left out of
+    /// [LoroDoc::get_value] and [LoroDoc::get_deep_value].
+    #[inline]
+    pub fn create_branch(&self, name: &str, frontiers: &Frontiers) -> LoroResult<()> {
+        self.doc.create_branch(name, frontiers)
+    }
+
+    /// Delete the branch.
+    #[inline]
+    pub fn delete_branch(&self, name: &str) -> LoroResult<()> {
+        self.doc.delete_branch(name)
+    }
+
+    /// Get the head of the branch.
+    #[inline]
+    pub fn branch_frontiers(&self, name: &str) -> LoroResult<Option<Frontiers>> {
+        self.doc.branch_frontiers(name)
+    }
+
+    /// Get all the branches and their heads, sorted by name.
+    #[inline]
+    pub fn list_branches(&self) -> LoroResult<Vec<(String, Frontiers)>> {
+        self.doc.list_branches()
+    }
+
+    /// Get the name of the checked out branch.
+    #[inline]
+    pub fn current_branch(&self) -> Option<String> {
+        self.doc.current_branch()
+    }
+
+    /// Checkout the doc to the head of the branch.
+    ///
+    /// The doc becomes detached. If detached editing is enabled by [LoroDoc::set_detached_editing],
+    /// the new local changes advance the branch. Calling `checkout` or `attach` leaves the branch.
+    ///
+    /// > The latest version of the doc always includes the changes made on every branch.
+    #[inline]
+    pub fn checkout_branch(&self, name: &str) -> LoroResult<()> {
+        self.doc.checkout_branch(name)
+    }
+
+    /// Merge the branch into the current version.
+    ///
+    /// If the doc is checked out to a branch, the head of the current branch moves to the merged
+    /// version. If the doc is attached, the changes of the branch are already included.
+    #[inline]
+    pub fn merge_branch(&self, name: &str) -> LoroResult<()> {
+        self.doc.merge_branch(name)
+    }
+
+    /// Create an immutable tag pointing to the given version.
+    ///
+    /// The tag cannot be changed through this API, but it's not tamper-proof: any peer can
+    /// edit the refs container directly.
+    #[inline]
+    pub fn tag(&self, name: &str, frontiers: &Frontiers) -> LoroResult<()> {
+        self.doc.tag(name, frontiers)
+    }
+
+    /// Get the version of the tag.
+    #[inline]
+    pub fn tag_frontiers(&self, name: &str) -> LoroResult<Option<Frontiers>> {
+        self.doc.tag_frontiers(name)
+    }
+
+    /// Get all the tags and their versions, sorted by name.
+    #[inline]
+    pub fn list_tags(&self) -> LoroResult<Vec<(String, Frontiers)>> {
+        self.doc.list_tags()
+    }
+
     /// Compare the frontiers with the current OpLog's version.
     ///
     /// If `other` contains any version that's not contained in the current OpLog, return [Ordering::Less].
@@ -424,6 +943,71 @@ impl LoroDoc {
         self.doc.import_with(bytes, origin.into())
     }
 
+    /// Preview the effect of importing `bytes` without changing the doc.
+    ///
+    /// It reports the changes that would be applied or stay pending, the containers that
+    /// would change and the frontiers after the import. The [DiffBatch] from the current
+    /// oplog version to the version after the import is calculated if `with_diff` is true.
+    ///
+    /// The import runs on a fork of the whole doc, which is encoded into a snapshot and
+    /// decoded on every call. So a preview costs time and memory in proportion to the size
+    /// of the doc, not to the size of `bytes`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use loro::{ExportMode, LoroDoc};
+    ///
+    /// let doc = LoroDoc::new();
+    /// let other = LoroDoc::new();
+    /// let text = other.get_text("text");
+    /// text.insert(0, "Hello").unwrap();
+    /// let updates = other.export(ExportMode::all_updates()).unwrap();
+    ///
+    /// let preview = doc.import_preview(&updates, true).unwrap();
+    /// assert!(preview.changed_containers.contains(&text.id()));
+    /// assert_eq!(preview.frontiers, other.oplog_frontiers());
+    /// assert!(preview.diff.is_some());
+    /// // The doc is not changed
+    /// assert!(doc.oplog_vv().is_empty());
+    /// assert_eq!(doc.get_text("text").to_string(), "");
+    /// ```
+    #[inline]
+    pub fn import_preview(&self, bytes: &[u8], with_diff: bool) -> LoroResult<ImportPreview> {
+        self.doc
+            .import_preview(bytes, with_diff)
+            .map(ImportPreview::from)
+    }
+
+    /// Import updates/snapshot from a reader, e.g. a file.
+    ///
+    /// The blobs written by [`LoroDoc::export_to_writer`] and the ones returned by
+    /// [`LoroDoc::export`] are read part by part. The change blocks of updates are decoded as
+    /// they are read, and nothing is imported before the checksum is checked. Only the blobs
+    /// of [`EncodingVersion::Outdated`] are read to the end into memory first.
+    ///
+    /// The input is rejected with [`LoroError::LimitExceeded`] as soon as it exceeds
+    /// [ImportOptions::max_input_bytes].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use loro::{ExportMode, LoroDoc};
+    ///
+    /// let doc = LoroDoc::new();
+    /// doc.get_text("text").insert(0, "Hello").unwrap();
+    /// let mut buf = Vec::new();
+    /// doc.export_to_writer(ExportMode::Snapshot, &mut buf).unwrap();
+    ///
+    /// let new_doc = LoroDoc::new();
+    /// new_doc.import_from_reader(buf.as_slice()).unwrap();
+    /// assert_eq!(new_doc.get_text("text").to_string(), "Hello");
+    /// ```
+    #[inline]
+    pub fn import_from_reader<R: Read>(&self, reader: R) -> Result<ImportStatus, LoroError> {
+        self.doc.import_from_reader(reader)
+    }
+
     /// Import the json schema updates.
     ///
     /// only supports backward compatibility but not forward compatibility.
@@ -565,6 +1149,70 @@ impl LoroDoc {
             .get_deep_value_with_id()
     }
 
+    /// Get a digest of the current state.
+    ///
+    /// It only depends on the deep value and the container ids, not on the history, so two
+    /// peers that have converged get the same digest and can compare them to check whether
+    /// they are in sync. The empty root containers are skipped, and the styles of the rich
+    /// text are included.
+    ///
+    /// # Example
+    /// ```
+    /// # use loro::{LoroDoc, ExportMode};
+    /// let a = LoroDoc::new();
+    /// a.get_text("text").insert(0, "Hello").unwrap();
+    /// let b = LoroDoc::new();
+    /// b.get_text("text").insert(0, "Hello").unwrap();
+    /// // The same state reached by different histories
+    /// assert_eq!(a.state_hash(), b.state_hash());
+    /// b.import(&a.export(ExportMode::all_updates()).unwrap()).unwrap();
+    /// assert_ne!(a.state_hash(), b.state_hash());
+    /// a.import(&b.export(ExportMode::all_updates()).unwrap()).unwrap();
+    /// assert_eq!(a.state_hash(), b.state_hash());
+    /// ```
+    pub fn state_hash(&self) -> StateHash {
+        self.doc.state_hash()
+    }
+
+    /// Get the root of the Merkle tree over the states of the containers.
+    ///
+    /// Its digest is [LoroDoc::state_hash] and its children are the non-empty root containers.
+    /// When two peers have different roots, they can compare the children and walk down the
+    /// tree with [LoroDoc::merkle_children] to find the containers that diverged.
+    ///
+    /// # Example
+    /// ```
+    /// # use loro::LoroDoc;
+    /// let a = LoroDoc::new();
+    /// a.get_map("map").insert("key", "a").unwrap();
+    /// a.get_text("text").insert(0, "Hello").unwrap();
+    /// let b = LoroDoc::new();
+    /// b.get_map("map").insert("key", "b").unwrap();
+    /// b.get_text("text").insert(0, "Hello").unwrap();
+    ///
+    /// let (root_a, root_b) = (a.merkle_root(), b.merkle_root());
+    /// assert_ne!(root_a.hash, root_b.hash);
+    /// let diverged: Vec<_> = root_a
+    ///     .children
+    ///     .iter()
+    ///     .zip(root_b.children.iter())
+    ///     .filter(|(x, y)| x != y)
+    ///     .map(|(x, _)| x.0.clone())
+    ///     .collect();
+    /// assert_eq!(diverged, vec![a.get_map("map").id()]);
+    /// ```
+    pub fn merkle_root(&self) -> MerkleNode {
+        self.doc.merkle_root()
+    }
+
+    /// Get the child containers of the container and their digests in the Merkle tree,
+    /// sorted by the container ids. See [LoroDoc::merkle_root].
+    ///
+    /// Return `None` if the container doesn't exist in the doc.
+    pub fn merkle_children(&self, id: &ContainerID) -> Option<Vec<(ContainerID, StateHash)>> {
+        self.doc.merkle_children(id)
+    }
+
     /// Get the `Frontiers` version of `OpLog`
     #[inline]
     pub fn oplog_frontiers(&self) -> Frontiers {
@@ -763,11 +1411,134 @@ impl LoroDoc {
         self.doc.compact_change_store()
     }
 
+    /// Squash the changes of this peer that are not included by `since` into as few changes as
+    /// possible, all of which carry the given commit message.
+    ///
+    /// This is useful after a long offline session that produced many small changes.
+    /// `since` marks the boundary of the local history that has not been shared yet, e.g. the
+    /// version of the last export. The state of the document is not changed.
+    ///
+    /// The caller must make sure the changes after `since` have not been exported yet.
+    /// It returns an error if a change from another peer depends on them.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use loro::LoroDoc;
+    ///
+    /// let doc = LoroDoc::new();
+    /// doc.set_change_merge_interval(0);
+    /// let text = doc.get_text("text");
+    /// text.insert(0, "Hello").unwrap();
+    /// doc.commit();
+    /// let since = doc.oplog_frontiers();
+    /// for (i, c) in " world".chars().enumerate() {
+    ///     text.insert(5 + i, &c.to_string()).unwrap();
+    ///     doc.commit();
+    /// }
+    /// assert_eq!(doc.len_changes(), 7);
+    /// doc.squash_local(&since, Some("Add world")).unwrap();
+    /// assert_eq!(doc.len_changes(), 2);
+    /// assert_eq!(text.to_string(), "Hello world");
+    /// ```
+    #[inline]
+    pub fn squash_local(&self, since: &Frontiers, commit_msg: Option<&str>) -> LoroResult<()> {
+        self.doc.squash_local(since, commit_msg)
+    }
+
+    /// Get the conflicts that are resolved by the CRDT rules when version `a` and `b` are merged.
+    ///
+    /// Only the edits made after the common ancestor of `a` and `b` are compared. It reports
+    ///
+    /// - concurrent sets on the same key of a [LoroMap]
+    /// - concurrent `set` or `mov` on the same element of a [LoroMovableList]
+    /// - concurrent moves in a [LoroTree] where one of them is dropped because it would create a cycle
+    /// - overlapping concurrent deletions in a [LoroText]
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use loro::{Conflict, LoroDoc, LoroValue};
+    ///
+    /// let doc = LoroDoc::new();
+    /// doc.set_peer_id(1).unwrap();
+    /// let map = doc.get_map("map");
+    /// map.insert("key", "a").unwrap();
+    /// doc.commit();
+    /// let a = doc.oplog_frontiers();
+    ///
+    /// let other = LoroDoc::new();
+    /// other.set_peer_id(2).unwrap();
+    /// other.get_map("map").insert("key", "b").unwrap();
+    /// other.commit();
+    /// doc.import(&other.export(loro::ExportMode::all_updates()).unwrap()).unwrap();
+    /// let b = other.oplog_frontiers();
+    ///
+    /// let conflicts = doc.conflicts(&a, &b).unwrap();
+    /// assert_eq!(conflicts.len(), 1);
+    /// let Conflict::MapKey { winner, loser, .. } = &conflicts[0] else {
+    ///     unreachable!()
+    /// };
+    /// assert_eq!(winner.value, Some(LoroValue::from("b")));
+    /// assert_eq!(loser.value, Some(LoroValue::from("a")));
+    /// assert_eq!(map.get("key").unwrap().into_value().unwrap(), LoroValue::from("b"));
+    /// ```
+    #[inline]
+    pub fn conflicts(&self, a: &Frontiers, b: &Frontiers) -> LoroResult<Vec<Conflict>> {
+        self.doc.conflicts(a, b)
+    }
+
     /// Export the document in the given mode.
     pub fn export(&self, mode: ExportMode) -> Result<Vec<u8>, LoroEncodeError> {
         self.doc.export(mode)
     }
 
+    /// Export the document in the given mode, in the newest format that the readers of
+    /// `version` can import.
+    ///
+    /// Use it to send the blobs to the peers running older versions of Loro. It fails with
+    /// [LoroEncodeError::IncompatibleEncodingVersion] if the blob would use a feature that
+    /// the older format cannot represent, e.g. an encrypted snapshot.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use loro::{EncodingVersion, ExportMode, LoroDoc};
+    ///
+    /// let doc = LoroDoc::new();
+    /// doc.get_text("text").insert(0, "Hello").unwrap();
+    /// let bytes = doc
+    ///     .export_with_version(ExportMode::Snapshot, EncodingVersion::Outdated)
+    ///     .unwrap();
+    ///
+    /// let new_doc = LoroDoc::new();
+    /// new_doc.import(&bytes).unwrap();
+    /// assert_eq!(new_doc.get_text("text").to_string(), "Hello");
+    /// ```
+    pub fn export_with_version(
+        &self,
+        mode: ExportMode,
+        version: EncodingVersion,
+    ) -> Result<Vec<u8>, LoroEncodeError> {
+        self.doc.export_with_version(mode, version)
+    }
+
+    /// Export the document in the given mode to a writer, e.g. a file.
+    ///
+    /// The change blocks of updates and the blocks of the stores of [`ExportMode::Snapshot`]
+    /// are written as they are encoded, and the checksum is written after them. The other
+    /// snapshots are built in memory first. The output can be imported by [`LoroDoc::import`]
+    /// and [`LoroDoc::import_from_reader`], but not by the versions that don't support
+    /// this streaming format.
+    #[inline]
+    pub fn export_to_writer<W: Write>(
+        &self,
+        mode: ExportMode,
+        writer: W,
+    ) -> Result<(), LoroEncodeError> {
+        self.doc.export_to_writer(mode, writer)
+    }
+
     /// Analyze the container info of the doc
     ///
     /// This is used for development and debugging. It can be slow.
@@ -775,6 +1546,13 @@ impl LoroDoc {
         self.doc.analyze()
     }
 
+    /// Get the causal graph of the changes as a [mermaid](https://mermaid.live/) flowchart
+    ///
+    /// This is used for development and debugging.
+    pub fn dag_mermaid(&self) -> String {
+        self.doc.dag_mermaid()
+    }
+
     /// Get the path from the root to the container
     pub fn get_path_to_container(&self, id: &ContainerID) -> Option<Vec<(ContainerID, Index)>> {
         self.doc.get_path_to_container(id)
@@ -897,6 +1675,13 @@ pub trait ContainerTrait: SealedTrait {
         Self: Sized;
     /// Whether the container is deleted.
     fn is_deleted(&self) -> bool;
+    /// Get a digest of the state of the container and its descendants,
+    /// see [LoroDoc::state_hash].
+    ///
+    /// It returns an error if the container is detached.
+    fn state_hash(&self) -> LoroResult<StateHash> {
+        self.to_handler().state_hash()
+    }
 }
 
 /// LoroList container. It's used to model array.
@@ -1642,6 +2427,35 @@ impl LoroText {
         self.handler.get_cursor(pos, side)
     }
 
+    /// Get who wrote each part of the text in its current state.
+    ///
+    /// It returns run-length encoded spans in the order of the text. Each span carries the id,
+    /// lamport and [ChangeMeta] (timestamp, commit message) of the ops that inserted it.
+    /// The range of a span is in unicode.
+    ///
+    /// It works on any checked out version. The chars inserted by the pending transaction
+    /// have no [ChangeMeta] until they are committed.
+    ///
+    /// # Example
+    /// ```
+    /// # use loro::LoroDoc;
+    /// let doc = LoroDoc::new();
+    /// doc.set_peer_id(1).unwrap();
+    /// let text = doc.get_text("text");
+    /// text.insert(0, "Hello").unwrap();
+    /// doc.commit();
+    /// doc.set_peer_id(2).unwrap();
+    /// text.insert(5, " world").unwrap();
+    /// doc.commit();
+    /// let spans = text.blame().unwrap();
+    /// assert_eq!(spans.len(), 2);
+    /// assert_eq!(spans[1].range, 5..11);
+    /// assert_eq!(spans[1].id.peer, 2);
+    /// ```
+    pub fn blame(&self) -> LoroResult<Vec<TextBlameSpan>> {
+        self.handler.blame()
+    }
+
     /// Whether the text container is deleted.
     pub fn is_deleted(&self) -> bool {
         self.handler.is_deleted()
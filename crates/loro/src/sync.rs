@@ -0,0 +1,272 @@
+//! A transport-agnostic protocol to keep two docs in sync, see [SyncSession].
+//!
+//! The session doesn't send anything by itself. It produces [SyncMessage]s that the caller
+//! sends to the remote peer in any way, and consumes the messages received from the remote
+//! peer. The messages can be dropped, duplicated or reordered by the transport:
+//!
+//! - Importing the same updates twice is a no-op, and the versions in the messages are only
+//!   merged into what the session knows, so handling a message again is harmless
+//! - A hello replaces what the session knows about the remote peer, because the remote peer
+//!   may have restarted with less than before, e.g. from an older copy of the doc. A late
+//!   duplicated hello only makes the session send some changes again
+//! - When the updates depend on changes that have not arrived, the receiver requests
+//!   the changes it misses
+//! - [SyncSession::retry] sends again what may have been lost. Call it when no message has
+//!   been received for a while
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{ExportMode, LoroDoc, LoroEncodeError, LoroError, LoroResult, VersionVector};
+
+/// A message of the sync protocol.
+///
+/// Every message carries the version of the sender, so the receiver learns what the sender
+/// already has from any of them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SyncMessage {
+    /// Start the sync. The receiver replies with the changes the sender misses.
+    ///
+    /// The receiver forgets what it knew about the version of the sender, and only trusts
+    /// the version in the hello.
+    Hello { version: VersionVector },
+    /// The updates exported by the sender, whose version is `version` after the export.
+    Updates {
+        version: VersionVector,
+        updates: Vec<u8>,
+    },
+    /// The updates have been imported, and the sender has `version` now.
+    Ack { version: VersionVector },
+    /// The sender misses some changes, e.g. the dependencies of its pending changes.
+    /// The receiver replies with the changes after `version`.
+    Request { version: VersionVector },
+}
+
+impl SyncMessage {
+    /// The version of the sender
+    pub fn version(&self) -> &VersionVector {
+        match self {
+            SyncMessage::Hello { version }
+            | SyncMessage::Updates { version, .. }
+            | SyncMessage::Ack { version }
+            | SyncMessage::Request { version } => version,
+        }
+    }
+
+    /// Encode the message into bytes.
+    pub fn encode(&self) -> Vec<u8> {
+        postcard::to_allocvec(self).unwrap()
+    }
+
+    /// Decode the message from the bytes created by [SyncMessage::encode].
+    pub fn decode(bytes: &[u8]) -> LoroResult<Self> {
+        postcard::from_bytes(bytes).map_err(|e| {
+            LoroError::DecodeError(format!("Failed to decode sync message: {}", e).into_boxed_str())
+        })
+    }
+}
+
+/// The error of handling a [SyncMessage]
+#[derive(Debug)]
+pub enum SyncError {
+    /// Failed to import the received updates
+    Import(LoroError),
+    /// Failed to export the updates for the remote peer, e.g. the remote peer is behind
+    /// the start of the history of a shallow doc
+    Export(LoroEncodeError),
+}
+
+impl Display for SyncError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SyncError::Import(e) => write!(f, "Failed to import the updates: {}", e),
+            SyncError::Export(e) => write!(f, "Failed to export the updates: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for SyncError {}
+
+impl From<LoroError> for SyncError {
+    fn from(e: LoroError) -> Self {
+        SyncError::Import(e)
+    }
+}
+
+impl From<LoroEncodeError> for SyncError {
+    fn from(e: LoroEncodeError) -> Self {
+        SyncError::Export(e)
+    }
+}
+
+/// The state of the sync between a doc and a remote peer.
+///
+/// # Example
+///
+/// ```
+/// # use loro::{LoroDoc, sync::SyncSession};
+/// let a = LoroDoc::new();
+/// let b = LoroDoc::new();
+/// a.get_text("text").insert(0, "Hello").unwrap();
+/// b.get_map("map").insert("key", "value").unwrap();
+/// a.commit();
+/// b.commit();
+/// let mut session_a = SyncSession::new(&a);
+/// let mut session_b = SyncSession::new(&b);
+///
+/// // Deliver the messages until both sides are quiet
+/// let mut to_b = vec![session_a.hello()];
+/// let mut to_a = vec![];
+/// while !to_a.is_empty() || !to_b.is_empty() {
+///     for msg in std::mem::take(&mut to_b) {
+///         to_a.extend(session_b.receive(msg).unwrap());
+///     }
+///     for msg in std::mem::take(&mut to_a) {
+///         to_b.extend(session_a.receive(msg).unwrap());
+///     }
+/// }
+/// assert_eq!(a.get_deep_value(), b.get_deep_value());
+/// assert!(session_a.is_synced() && session_b.is_synced());
+///
+/// // Send the new local changes
+/// a.get_text("text").insert(5, " world").unwrap();
+/// a.commit();
+/// let msg = session_a.poll().unwrap().unwrap();
+/// let ack = session_b.receive(msg).unwrap();
+/// session_a.receive(ack.into_iter().next().unwrap()).unwrap();
+/// assert_eq!(b.get_text("text").to_string(), "Hello world");
+/// assert!(session_a.is_synced());
+/// ```
+#[derive(Debug)]
+pub struct SyncSession<'a> {
+    doc: &'a LoroDoc,
+    /// The version that the remote peer is known to have
+    remote_version: Option<VersionVector>,
+    /// The version that has been sent to the remote peer, which may not have arrived
+    sent_version: VersionVector,
+}
+
+impl<'a> SyncSession<'a> {
+    /// Create a session to sync `doc` with a remote peer.
+    pub fn new(doc: &'a LoroDoc) -> Self {
+        Self {
+            doc,
+            remote_version: None,
+            sent_version: Default::default(),
+        }
+    }
+
+    /// The message to start the sync.
+    pub fn hello(&self) -> SyncMessage {
+        SyncMessage::Hello {
+            version: self.doc.oplog_vv(),
+        }
+    }
+
+    /// The version that the remote peer is known to have. It's `None` before any message
+    /// is received from the remote peer.
+    pub fn remote_version(&self) -> Option<&VersionVector> {
+        self.remote_version.as_ref()
+    }
+
+    /// Whether the remote peer is known to have the same version as the doc.
+    ///
+    /// The remote peer may have new changes that have not been received yet.
+    pub fn is_synced(&self) -> bool {
+        self.remote_version
+            .as_ref()
+            .is_some_and(|v| *v == self.doc.oplog_vv())
+    }
+
+    /// Handle a message from the remote peer and return the replies.
+    pub fn receive(&mut self, msg: SyncMessage) -> Result<Vec<SyncMessage>, SyncError> {
+        match &msg {
+            SyncMessage::Hello { version } => self.reset_remote_version(version),
+            _ => self.merge_remote_version(msg.version()),
+        }
+        let mut ans = Vec::new();
+        match msg {
+            SyncMessage::Hello { version } => {
+                ans.extend(self.poll()?);
+                let self_version = self.doc.oplog_vv();
+                if self_version.includes_vv(&version) {
+                    ans.push(SyncMessage::Ack {
+                        version: self_version,
+                    });
+                } else {
+                    ans.push(SyncMessage::Request {
+                        version: self_version,
+                    });
+                }
+            }
+            SyncMessage::Updates { updates, .. } => {
+                let status = self.doc.import(&updates)?;
+                let version = self.doc.oplog_vv();
+                if status.pending.is_some() {
+                    ans.push(SyncMessage::Request { version });
+                } else {
+                    ans.push(SyncMessage::Ack { version });
+                }
+            }
+            SyncMessage::Ack { .. } => {}
+            SyncMessage::Request { .. } => {
+                self.resend_unacked();
+                ans.extend(self.poll()?);
+            }
+        }
+
+        Ok(ans)
+    }
+
+    /// Export the changes that have not been sent to the remote peer, e.g. the new local
+    /// changes. Call it after committing or importing changes from the other peers.
+    ///
+    /// It returns `None` if there is nothing to send, or if the session doesn't know the
+    /// version of the remote peer yet.
+    pub fn poll(&mut self) -> Result<Option<SyncMessage>, SyncError> {
+        if self.remote_version.is_none() {
+            return Ok(None);
+        }
+
+        let version = self.doc.oplog_vv();
+        if self.sent_version.includes_vv(&version) {
+            return Ok(None);
+        }
+
+        let updates = self.doc.export(ExportMode::updates(&self.sent_version))?;
+        self.sent_version.merge(&version);
+        Ok(Some(SyncMessage::Updates { version, updates }))
+    }
+
+    /// Send again the messages that may have been lost.
+    ///
+    /// It restarts the sync with a hello, and sends the changes that the remote peer is not
+    /// known to have.
+    pub fn retry(&mut self) -> Result<Vec<SyncMessage>, SyncError> {
+        let mut ans = vec![self.hello()];
+        self.resend_unacked();
+        ans.extend(self.poll()?);
+        Ok(ans)
+    }
+
+    /// Forget the sent changes that the remote peer is not known to have, so they are sent
+    /// again by the next [SyncSession::poll]
+    fn resend_unacked(&mut self) {
+        self.sent_version = self.remote_version.clone().unwrap_or_default();
+    }
+
+    /// Replace what the session knows about the remote peer, whose version may be older
+    /// than before, e.g. after a restart. The changes it misses are sent again.
+    fn reset_remote_version(&mut self, version: &VersionVector) {
+        self.remote_version = Some(version.clone());
+        self.sent_version = version.clone();
+    }
+
+    fn merge_remote_version(&mut self, version: &VersionVector) {
+        match &mut self.remote_version {
+            Some(v) => v.merge(version),
+            None => self.remote_version = Some(version.clone()),
+        }
+        self.sent_version.merge(version);
+    }
+}
@@ -0,0 +1,90 @@
+use loro::{CommitOptions, LoroDoc, ID};
+
+#[test]
+fn blame_text_with_change_meta() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1)?;
+    let text = doc.get_text("text");
+    text.insert(0, "Hello world")?;
+    doc.commit_with(CommitOptions::new().commit_msg("init").timestamp(100));
+    let v1 = doc.state_frontiers();
+
+    doc.set_peer_id(2)?;
+    text.insert(5, ",")?;
+    text.insert(12, "!")?;
+    doc.commit_with(
+        CommitOptions::new()
+            .commit_msg("punctuation")
+            .timestamp(200),
+    );
+    assert_eq!(text.to_string(), "Hello, world!");
+
+    let spans = text.blame()?;
+    let ranges: Vec<_> = spans.iter().map(|s| s.range.clone()).collect();
+    assert_eq!(ranges, vec![0..5, 5..6, 6..12, 12..13]);
+    assert_eq!(spans[0].id, ID::new(1, 0));
+    assert_eq!(spans[2].id, ID::new(1, 5));
+    assert_eq!(spans[3].id, ID::new(2, 1));
+    let change = spans[1].change.as_ref().unwrap();
+    assert_eq!(change.message.as_deref(), Some("punctuation"));
+    assert_eq!(change.timestamp, 200);
+    assert_eq!(
+        spans[0].change.as_ref().unwrap().message.as_deref(),
+        Some("init")
+    );
+
+    // Contribution of each peer
+    let peer_1: usize = spans
+        .iter()
+        .filter(|s| s.id.peer == 1)
+        .map(|s| s.range.len())
+        .sum();
+    assert_eq!(peer_1, 11);
+
+    doc.checkout(&v1)?;
+    let spans = text.blame()?;
+    assert_eq!(spans.len(), 1);
+    assert_eq!(spans[0].range, 0..11);
+    assert_eq!(spans[0].lamport, 0);
+    Ok(())
+}
+
+#[test]
+fn blame_text_splits_spans_by_change() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1)?;
+    doc.set_change_merge_interval(0);
+    let text = doc.get_text("text");
+    text.insert(0, "ab")?;
+    doc.commit_with(CommitOptions::new().commit_msg("first"));
+    text.insert(2, "cd")?;
+    doc.commit_with(CommitOptions::new().commit_msg("second"));
+    let spans = text.blame()?;
+    assert_eq!(spans.len(), 2);
+    assert_eq!(spans[0].range, 0..2);
+    assert_eq!(spans[1].range, 2..4);
+    assert_eq!(
+        spans[1].change.as_ref().unwrap().message.as_deref(),
+        Some("second")
+    );
+    assert!(loro::LoroText::new().blame().is_err());
+    Ok(())
+}
+
+#[test]
+fn blame_does_not_commit_the_pending_transaction() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1)?;
+    let text = doc.get_text("text");
+    text.insert(0, "ab")?;
+    doc.commit();
+    text.insert(2, "cd")?;
+    let frontiers = doc.oplog_frontiers();
+    let spans = text.blame()?;
+    assert_eq!(doc.oplog_frontiers(), frontiers);
+    assert_eq!(spans.len(), 2);
+    assert!(spans[0].change.is_some());
+    assert_eq!(spans[1].range, 2..4);
+    assert!(spans[1].change.is_none());
+    Ok(())
+}
@@ -0,0 +1,74 @@
+use loro::{
+    JsonListOp, JsonMapOp, JsonOpContent, JsonTextOp, JsonTreeOp, LoroDoc, LoroValue, TreeParentId,
+    VersionVector, ID,
+};
+
+#[test]
+fn changes_between_yields_typed_ops() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(u64::MAX - 1)?;
+    let text = doc.get_text("text");
+    text.insert(0, "Hello")?;
+    doc.set_next_commit_message("text");
+    doc.commit();
+    let list = doc.get_list("list");
+    list.push(1)?;
+    list.push(2)?;
+    list.delete(0, 1)?;
+    let map = doc.get_map("map");
+    map.insert("key", "value")?;
+    let child = map.insert_container("child", loro::LoroMap::new())?;
+    let tree = doc.get_tree("tree");
+    let node = tree.create(TreeParentId::Root)?;
+    let node_2 = tree.create(TreeParentId::Root)?;
+    tree.mov(node_2, node)?;
+    doc.commit();
+
+    let changes = doc.changes_between(&VersionVector::default(), &doc.oplog_vv());
+    assert_eq!(changes.len(), 2);
+    assert_eq!(changes[0].meta.message(), "text");
+    assert_eq!(changes[0].meta.id, ID::new(u64::MAX - 1, 0));
+    let ops: Vec<_> = changes.iter().flat_map(|c| c.ops.iter()).collect();
+    assert!(matches!(
+        &ops[0].content,
+        JsonOpContent::Text(JsonTextOp::Insert { pos: 0, text }) if text == "Hello"
+    ));
+    assert_eq!(ops[0].container, text.id());
+    assert!(ops.iter().any(|op| matches!(
+        &op.content,
+        JsonOpContent::List(JsonListOp::Delete { pos: 0, len: 1, start_id })
+            if *start_id == ID::new(u64::MAX - 1, 5)
+    )));
+    assert!(ops.iter().any(|op| matches!(
+        &op.content,
+        JsonOpContent::Map(JsonMapOp::Insert { key, value: LoroValue::Container(id) })
+            if key == "child" && *id == child.id()
+    )));
+    // The peer ids are not compressed
+    assert!(ops.iter().any(|op| matches!(
+        &op.content,
+        JsonOpContent::Tree(JsonTreeOp::Move { target, parent: Some(parent), .. })
+            if *target == node_2 && *parent == node
+    )));
+    Ok(())
+}
+
+#[test]
+fn changes_between_slices_changes() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1)?;
+    doc.get_text("text").insert(0, "Hello")?;
+    doc.commit();
+    let from = doc.oplog_vv();
+    doc.get_text("text").insert(5, " world")?;
+    doc.commit();
+    let changes = doc.changes_between(&from, &doc.oplog_vv());
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].meta.id, ID::new(1, 5));
+    assert_eq!(changes[0].ops.len(), 1);
+    assert_eq!(changes[0].ops[0].counter, 5);
+    assert!(doc
+        .changes_between(&doc.oplog_vv(), &doc.oplog_vv())
+        .is_empty());
+    Ok(())
+}
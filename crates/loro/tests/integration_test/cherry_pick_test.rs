@@ -0,0 +1,94 @@
+use loro::{CherryPickConflict, ExportMode, IdSpan, LoroDoc, ToJson};
+use serde_json::json;
+
+/// Create doc `a` whose oplog contains the changes of peer 2 that are not in its state
+fn setup() -> anyhow::Result<LoroDoc> {
+    let a = LoroDoc::new();
+    a.set_peer_id(1)?;
+    a.get_text("text").insert(0, "Hello")?;
+    a.commit();
+
+    let b = LoroDoc::new();
+    b.set_peer_id(2)?;
+    b.import(&a.export(ExportMode::all_updates())?)?;
+    // ID(2, 0..6)
+    b.get_text("text").insert(5, " world")?;
+    b.commit();
+    // ID(2, 6)
+    b.get_map("map").insert("color", "red")?;
+    b.commit();
+
+    a.get_text("text").insert(0, "> ")?;
+    a.get_map("map").insert("color", "blue")?;
+    a.commit();
+    a.set_detached_editing(true);
+    a.checkout(&a.oplog_frontiers())?;
+    a.import(&b.export(ExportMode::all_updates())?)?;
+    assert_eq!(a.get_text("text").to_string(), "> Hello");
+    Ok(a)
+}
+
+#[test]
+fn cherry_pick_single_change() -> anyhow::Result<()> {
+    let doc = setup()?;
+    let ans = doc.cherry_pick(&[IdSpan::new(2, 0, 6)])?;
+    assert_eq!(ans.applied, vec![IdSpan::new(2, 0, 6)]);
+    assert_eq!(doc.get_text("text").to_string(), "> Hello world");
+    assert_eq!(
+        doc.get_deep_value().to_json_value()["map"],
+        json!({"color": "blue"})
+    );
+    assert!(ans.conflicts.iter().any(|c| matches!(
+        c,
+        CherryPickConflict::Concurrent { container, .. } if container == &doc.get_text("text").id()
+    )));
+    Ok(())
+}
+
+#[test]
+fn cherry_pick_reports_map_key_conflict() -> anyhow::Result<()> {
+    let doc = setup()?;
+    let ans = doc.cherry_pick(&[IdSpan::new(2, 6, 7)])?;
+    assert_eq!(doc.get_text("text").to_string(), "> Hello");
+    assert_eq!(
+        doc.get_deep_value().to_json_value()["map"],
+        json!({"color": "red"})
+    );
+    assert_eq!(
+        ans.conflicts,
+        vec![CherryPickConflict::MapKey {
+            span: IdSpan::new(2, 6, 7),
+            container: doc.get_map("map").id(),
+            key: "color".into(),
+        }]
+    );
+    Ok(())
+}
+
+#[test]
+fn cherry_pick_skips_included_changes() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1)?;
+    doc.get_text("text").insert(0, "Hello")?;
+    doc.commit();
+    let ans = doc.cherry_pick(&[IdSpan::new(1, 0, 5)])?;
+    assert!(ans.applied.is_empty());
+    assert!(ans.conflicts.is_empty());
+    assert_eq!(doc.get_text("text").to_string(), "Hello");
+    assert!(doc.cherry_pick(&[IdSpan::new(3, 0, 1)]).is_err());
+    Ok(())
+}
+
+#[test]
+fn cherry_pick_same_span_twice_applies_it_once() -> anyhow::Result<()> {
+    let doc = setup()?;
+    doc.cherry_pick(&[IdSpan::new(2, 0, 6)])?;
+    let ans = doc.cherry_pick(&[IdSpan::new(2, 0, 7)])?;
+    // Only the part that was not picked yet is applied
+    assert_eq!(ans.applied, vec![IdSpan::new(2, 6, 7)]);
+    assert_eq!(doc.get_text("text").to_string(), "> Hello world");
+    let ans = doc.cherry_pick(&[IdSpan::new(2, 0, 7)])?;
+    assert!(ans.applied.is_empty());
+    assert_eq!(doc.get_text("text").to_string(), "> Hello world");
+    Ok(())
+}
@@ -0,0 +1,190 @@
+use loro::{Conflict, ExportMode, Frontiers, IdLp, IdSpan, LoroDoc, LoroValue, TreeParentId, ID};
+
+fn new_doc(peer: u64) -> anyhow::Result<LoroDoc> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(peer)?;
+    doc.set_change_merge_interval(0);
+    Ok(doc)
+}
+
+/// Fork `base` into two docs on peer 1 and 2. Returns the merged doc and the frontiers of both
+fn fork_and_merge(
+    base: &LoroDoc,
+    edit_a: impl FnOnce(&LoroDoc) -> anyhow::Result<()>,
+    edit_b: impl FnOnce(&LoroDoc) -> anyhow::Result<()>,
+) -> anyhow::Result<(LoroDoc, Frontiers, Frontiers)> {
+    let a = new_doc(1)?;
+    a.import(&base.export(ExportMode::all_updates())?)?;
+    let b = new_doc(2)?;
+    b.import(&base.export(ExportMode::all_updates())?)?;
+    edit_a(&a)?;
+    a.commit();
+    edit_b(&b)?;
+    b.commit();
+    let frontiers_a = a.oplog_frontiers();
+    a.import(&b.export(ExportMode::all_updates())?)?;
+    Ok((a, frontiers_a, b.oplog_frontiers()))
+}
+
+#[test]
+fn map_key_conflict() -> anyhow::Result<()> {
+    let base = new_doc(0)?;
+    base.get_map("map").insert("key", 0)?;
+    base.get_map("map").insert("other", 0)?;
+    base.commit();
+    let (doc, a, b) = fork_and_merge(
+        &base,
+        |a| {
+            a.get_map("map").insert("key", "a")?;
+            a.get_map("map").insert("other", 1)?;
+            Ok(())
+        },
+        |b| {
+            b.get_map("map").delete("key")?;
+            Ok(())
+        },
+    )?;
+
+    let conflicts = doc.conflicts(&a, &b)?;
+    assert_eq!(conflicts.len(), 1);
+    let Conflict::MapKey {
+        container,
+        key,
+        winner,
+        loser,
+    } = &conflicts[0]
+    else {
+        panic!("unexpected conflict {:?}", conflicts[0]);
+    };
+    assert_eq!(container, &doc.get_map("map").id());
+    assert_eq!(key.as_str(), "key");
+    assert_eq!(winner.id, IdLp::new(2, 2));
+    assert_eq!(winner.value, None);
+    assert_eq!(loser.id, IdLp::new(1, 2));
+    assert_eq!(loser.value, Some(LoroValue::from("a")));
+    assert!(doc.get_map("map").get("key").is_none());
+    assert_eq!(doc.conflicts(&b, &a)?, conflicts);
+    Ok(())
+}
+
+#[test]
+fn movable_list_conflicts() -> anyhow::Result<()> {
+    let base = new_doc(0)?;
+    let list = base.get_movable_list("list");
+    list.push("a")?;
+    list.push("b")?;
+    list.push("c")?;
+    base.commit();
+    let (doc, a, b) = fork_and_merge(
+        &base,
+        |a| {
+            a.get_movable_list("list").set(0, "x")?;
+            a.get_movable_list("list").mov(2, 0)?;
+            Ok(())
+        },
+        |b| {
+            b.get_movable_list("list").set(0, "y")?;
+            b.get_movable_list("list").mov(2, 1)?;
+            Ok(())
+        },
+    )?;
+
+    let conflicts = doc.conflicts(&a, &b)?;
+    assert_eq!(conflicts.len(), 2);
+    let Conflict::MovableListSet { winner, loser, .. } = &conflicts[0] else {
+        panic!("unexpected conflict {:?}", conflicts[0]);
+    };
+    assert_eq!(winner.id.peer, 2);
+    assert_eq!(winner.value, Some(LoroValue::from("y")));
+    assert_eq!(loser.id.peer, 1);
+    assert_eq!(loser.value, Some(LoroValue::from("x")));
+    let Conflict::MovableListMove { winner, loser, .. } = &conflicts[1] else {
+        panic!("unexpected conflict {:?}", conflicts[1]);
+    };
+    assert_eq!(winner.peer, 2);
+    assert_eq!(loser.peer, 1);
+    assert_eq!(
+        doc.get_movable_list("list").get_deep_value(),
+        vec!["y", "c", "b"].into()
+    );
+    Ok(())
+}
+
+#[test]
+fn tree_cycle_conflict() -> anyhow::Result<()> {
+    let base = new_doc(0)?;
+    let tree = base.get_tree("tree");
+    let x = tree.create(None)?;
+    let y = tree.create(None)?;
+    base.commit();
+    let (doc, a, b) = fork_and_merge(
+        &base,
+        |a| a.get_tree("tree").mov(x, y).map_err(Into::into),
+        |b| b.get_tree("tree").mov(y, x).map_err(Into::into),
+    )?;
+
+    let conflicts = doc.conflicts(&a, &b)?;
+    assert_eq!(
+        conflicts,
+        vec![Conflict::TreeCycle {
+            container: doc.get_tree("tree").id(),
+            target: y,
+            dropped: ID::new(2, 0),
+            parent: TreeParentId::Node(x),
+        }]
+    );
+    assert_eq!(doc.get_tree("tree").parent(x), Some(TreeParentId::Node(y)));
+    assert_eq!(doc.get_tree("tree").parent(y), Some(TreeParentId::Root));
+    Ok(())
+}
+
+#[test]
+fn text_delete_conflict() -> anyhow::Result<()> {
+    let base = new_doc(0)?;
+    base.get_text("text").insert(0, "Hello world")?;
+    base.commit();
+    let (doc, a, b) = fork_and_merge(
+        &base,
+        |a| a.get_text("text").delete(0, 5).map_err(Into::into),
+        |b| {
+            let text = b.get_text("text");
+            text.delete(3, 5)?;
+            text.delete(0, 1)?;
+            Ok(())
+        },
+    )?;
+
+    let conflicts = doc.conflicts(&a, &b)?;
+    let container = doc.get_text("text").id();
+    assert_eq!(
+        conflicts,
+        vec![
+            Conflict::TextDelete {
+                container: container.clone(),
+                span: IdSpan::new(0, 0, 1),
+            },
+            Conflict::TextDelete {
+                container,
+                span: IdSpan::new(0, 3, 5),
+            },
+        ]
+    );
+    assert_eq!(doc.get_text("text").to_string(), "rld");
+    Ok(())
+}
+
+#[test]
+fn no_conflicts_between_causal_versions() -> anyhow::Result<()> {
+    let doc = new_doc(1)?;
+    let map = doc.get_map("map");
+    map.insert("key", 1)?;
+    doc.commit();
+    let a = doc.oplog_frontiers();
+    map.insert("key", 2)?;
+    doc.commit();
+    let b = doc.oplog_frontiers();
+    assert!(doc.conflicts(&a, &b)?.is_empty());
+    assert!(doc.conflicts(&b, &b)?.is_empty());
+    assert!(doc.conflicts(&a, &ID::new(2, 0).into()).is_err());
+    Ok(())
+}
@@ -0,0 +1,216 @@
+use loro::{
+    loro_value, ContainerID, ContainerType, ExportMode, LoroDoc, LoroEncodeError, LoroMap,
+    LoroMovableList, LoroText, LoroValue, ID,
+};
+
+fn new_doc(peer: u64) -> anyhow::Result<LoroDoc> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(peer)?;
+    doc.set_change_merge_interval(0);
+    Ok(doc)
+}
+
+fn get(value: &LoroValue, key: &str) -> LoroValue {
+    value.as_map().unwrap().get(key).cloned().unwrap()
+}
+
+/// Two peers edit the settings, the body and the tree concurrently
+fn concurrent_doc() -> anyhow::Result<LoroDoc> {
+    let a = new_doc(1)?;
+    let b = new_doc(2)?;
+    a.get_text("body").insert(0, "Hello")?;
+    a.get_map("settings").insert("theme", "dark")?;
+    a.commit();
+    b.import(&a.export(ExportMode::all_updates())?)?;
+
+    b.get_text("body").insert(5, " world")?;
+    b.get_map("settings").insert("accent", "blue")?;
+    let fonts = b
+        .get_map("settings")
+        .insert_container("fonts", LoroMovableList::new())?;
+    fonts.push("mono")?;
+    fonts.push("sans")?;
+    fonts.push("serif")?;
+    b.commit();
+    a.get_map("settings").insert("theme", "solarized")?;
+    a.get_text("body").delete(0, 2)?;
+    let tree = a.get_tree("tree");
+    let root = tree.create(None)?;
+    tree.get_meta(root)?.insert("name", "root")?;
+    tree.create(root)?;
+    a.commit();
+
+    a.import(&b.export(ExportMode::all_updates())?)?;
+    b.import(&a.export(ExportMode::all_updates())?)?;
+    a.get_map("settings").insert("size", 12)?;
+    let fonts = a.get_movable_list(fonts.id());
+    fonts.mov(0, 2)?;
+    fonts.set(0, "cursive")?;
+    b.get_text("body").insert(0, ">")?;
+    a.commit();
+    b.commit();
+    a.import(&b.export(ExportMode::all_updates())?)?;
+    Ok(a)
+}
+
+#[test]
+fn export_root_containers() -> anyhow::Result<()> {
+    let doc = concurrent_doc()?;
+    let bytes = doc.export(ExportMode::containers([
+        ContainerID::new_root("settings", ContainerType::Map),
+        ContainerID::new_root("tree", ContainerType::Tree),
+    ]))?;
+
+    let new_doc = LoroDoc::new();
+    new_doc.import(&bytes)?;
+    new_doc.check_state_correctness_slow();
+    // The state of the exported containers is kept
+    let value = new_doc.get_deep_value();
+    assert_eq!(value.as_map().unwrap().len(), 2);
+    assert_eq!(
+        get(&value, "settings"),
+        get(&doc.get_deep_value(), "settings")
+    );
+    // The tree nodes are renumbered
+    let tree = new_doc.get_tree("tree");
+    assert_eq!(tree.nodes().len(), 2);
+    let root = tree.roots()[0];
+    assert_eq!(tree.children(root).unwrap().len(), 1);
+    assert_eq!(
+        tree.get_meta(root)?.get_deep_value(),
+        doc.get_tree("tree")
+            .get_meta(doc.get_tree("tree").roots()[0])?
+            .get_deep_value()
+    );
+    assert!(new_doc.len_ops() < doc.len_ops());
+
+    // The exported doc keeps collaborating with the other exports
+    let other = LoroDoc::new();
+    other.import(&bytes)?;
+    other.get_map("settings").insert("lang", "en")?;
+    new_doc
+        .get_map("settings")
+        .get_or_create_container("fonts", LoroMovableList::new())?
+        .mov(2, 0)?;
+    new_doc.import(&other.export(ExportMode::all_updates())?)?;
+    other.import(&new_doc.export(ExportMode::all_updates())?)?;
+    assert_eq!(new_doc.get_deep_value(), other.get_deep_value());
+    Ok(())
+}
+
+#[test]
+fn export_nested_container() -> anyhow::Result<()> {
+    let doc = new_doc(1)?;
+    let cards = doc.get_list("cards");
+    cards.push("skipped")?;
+    let card = cards.insert_container(1, LoroMap::new())?;
+    card.insert("title", "Todo")?;
+    let text = card.insert_container("text", LoroText::new())?;
+    text.insert(0, "Write tests")?;
+    doc.get_text("notes").insert(0, "private")?;
+    doc.commit();
+    text.delete(0, 6)?;
+    doc.commit();
+
+    let bytes = doc.export(ExportMode::containers([card.id()]))?;
+    let new_doc = LoroDoc::new();
+    new_doc.import(&bytes)?;
+    new_doc.check_state_correctness_slow();
+    let ContainerID::Normal { peer, counter, .. } = card.id() else {
+        unreachable!()
+    };
+    let name = ID::new(peer, counter).to_string();
+    assert_eq!(
+        new_doc.get_deep_value(),
+        LoroValue::Map(
+            [(name.clone(), card.get_deep_value())]
+                .into_iter()
+                .collect()
+        )
+    );
+    assert_eq!(new_doc.get_map(name.as_str()).len(), 2);
+    Ok(())
+}
+
+#[test]
+fn shallow_doc_is_not_supported() -> anyhow::Result<()> {
+    let doc = concurrent_doc()?;
+    let shallow = LoroDoc::new();
+    shallow.import(&doc.export(ExportMode::shallow_snapshot(&doc.oplog_frontiers()))?)?;
+    assert!(matches!(
+        shallow.export(ExportMode::containers([ContainerID::new_root(
+            "settings",
+            ContainerType::Map
+        )])),
+        Err(LoroEncodeError::ContainersExportOnShallowDoc)
+    ));
+    Ok(())
+}
+
+#[test]
+fn export_movable_list_after_concurrent_edits_in_other_containers() -> anyhow::Result<()> {
+    let a = new_doc(1)?;
+    let b = new_doc(2)?;
+    let list = a.get_movable_list("list");
+    list.push("a")?;
+    list.push("b")?;
+    list.push("c")?;
+    a.commit();
+    // The edits of the other containers are removed from the export, so the kept ops after
+    // them are renumbered and their lamports are no longer contiguous
+    a.get_text("other").insert(0, "0123456789")?;
+    a.commit();
+    b.import(&a.export(ExportMode::all_updates())?)?;
+    b.get_text("other").insert(0, "abc")?;
+    b.get_movable_list("list").set(0, "b0")?;
+    b.commit();
+    list.mov(0, 2)?;
+    list.set(1, "x")?;
+    a.commit();
+    a.import(&b.export(ExportMode::all_updates())?)?;
+    list.mov(2, 0)?;
+    list.set(2, "y")?;
+    a.commit();
+
+    let bytes = a.export(ExportMode::containers([list.id()]))?;
+    let new_doc = LoroDoc::new();
+    new_doc.import(&bytes)?;
+    new_doc.check_state_correctness_slow();
+    let new_list = new_doc.get_movable_list("list");
+    assert_eq!(new_list.get_deep_value(), list.get_deep_value());
+
+    // The rewritten elements can still be moved and set
+    let other = LoroDoc::new();
+    other.import(&bytes)?;
+    new_list.mov(0, 2)?;
+    new_list.set(0, "z")?;
+    new_doc.commit();
+    other.import(&new_doc.export(ExportMode::all_updates())?)?;
+    assert_eq!(other.get_deep_value(), new_doc.get_deep_value());
+    Ok(())
+}
+
+#[test]
+fn concurrent_edits_are_resolved_as_in_the_original_doc() -> anyhow::Result<()> {
+    let a = new_doc(1)?;
+    let b = new_doc(2)?;
+    // The edits of the other container give the map insertion of `a` a larger lamport
+    a.get_text("other").insert(0, "0123456789")?;
+    a.commit();
+    a.get_map("map").insert("key", "a")?;
+    a.commit();
+    b.get_map("map").insert("key", "b")?;
+    b.commit();
+    a.import(&b.export(ExportMode::all_updates())?)?;
+    assert_eq!(a.get_map("map").get_deep_value(), loro_value!({"key": "a"}));
+
+    let bytes = a.export(ExportMode::containers([a.get_map("map").id()]))?;
+    let new_doc = LoroDoc::new();
+    new_doc.import(&bytes)?;
+    new_doc.check_state_correctness_slow();
+    assert_eq!(
+        new_doc.get_map("map").get_deep_value(),
+        loro_value!({"key": "a"})
+    );
+    Ok(())
+}
@@ -0,0 +1,148 @@
+use loro::{DiffBatch, Frontiers, LoroDoc, LoroList, LoroMap, LoroText, ToJson, TreeParentId};
+use serde_json::json;
+
+fn create_template() -> anyhow::Result<LoroDoc> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1)?;
+    let text = doc.get_text("text");
+    text.insert(0, "Hello world")?;
+    text.mark(0..5, "bold", true)?;
+    let map = doc.get_map("map");
+    map.insert("title", "template")?;
+    let list = map.insert_container("items", LoroList::new())?;
+    list.push(1)?;
+    let child = list.push_container(LoroMap::new())?;
+    child.insert("name", "child")?;
+    child
+        .insert_container("note", LoroText::new())?
+        .insert(0, "note")?;
+    let tree = doc.get_tree("tree");
+    let root = tree.create(TreeParentId::Root)?;
+    tree.get_meta(root)?.insert("name", "root")?;
+    doc.commit();
+    Ok(doc)
+}
+
+/// Tree node ids differ between docs, so only the meta of the nodes is compared
+fn assert_same_content(a: &LoroDoc, b: &LoroDoc) {
+    let a_value = a.get_deep_value().to_json_value();
+    let b_value = b.get_deep_value().to_json_value();
+    assert_eq!(a_value["text"], b_value["text"]);
+    assert_eq!(a_value["map"], b_value["map"]);
+    let metas = |doc: &LoroDoc| {
+        let tree = doc.get_tree("tree");
+        tree.roots()
+            .into_iter()
+            .map(|id| tree.get_meta(id).unwrap().get_value().to_json_value())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(metas(a), metas(b));
+    assert_eq!(metas(a), vec![json!({"name": "root"})]);
+}
+
+#[test]
+fn apply_diff_on_doc_without_shared_history() -> anyhow::Result<()> {
+    let template = create_template()?;
+    let diff = template.diff(&Frontiers::default(), &template.state_frontiers())?;
+    assert!(!diff.is_empty());
+
+    let doc = LoroDoc::new();
+    doc.set_peer_id(2)?;
+    doc.get_text("text").insert(0, "x")?;
+    doc.get_text("text").delete(0, 1)?;
+    doc.commit();
+    doc.apply_diff(&diff)?;
+    doc.commit();
+    assert_same_content(&doc, &template);
+    assert_eq!(
+        doc.get_text("text").to_delta(),
+        template.get_text("text").to_delta()
+    );
+    doc.check_state_correctness_slow();
+    Ok(())
+}
+
+#[test]
+fn diff_batch_iter_yields_parents_first() -> anyhow::Result<()> {
+    let template = create_template()?;
+    let diff = template.diff(&Frontiers::default(), &template.state_frontiers())?;
+    let ids: Vec<_> = diff.iter().map(|(id, _)| id.clone()).collect();
+    let map_pos = ids.iter().position(|id| id.is_root()).unwrap();
+    let first_normal = ids.iter().position(|id| id.is_normal()).unwrap();
+    assert!(map_pos < first_normal);
+    Ok(())
+}
+
+#[test]
+fn diff_batch_json_round_trip() -> anyhow::Result<()> {
+    let template = create_template()?;
+    let diff = template.diff(&Frontiers::default(), &template.state_frontiers())?;
+    let json = serde_json::to_string(&diff)?;
+    let decoded: DiffBatch = serde_json::from_str(&json)?;
+
+    let doc = LoroDoc::new();
+    doc.apply_diff(&decoded)?;
+    doc.commit();
+    assert_same_content(&doc, &template);
+    Ok(())
+}
+
+#[test]
+fn diff_batch_binary_round_trip() -> anyhow::Result<()> {
+    let template = create_template()?;
+    let v1 = template.state_frontiers();
+    let doc = LoroDoc::new();
+    doc.apply_diff(&DiffBatch::decode(
+        &template.diff(&Frontiers::default(), &v1)?.encode()?,
+    )?)?;
+    doc.commit();
+
+    template.get_text("text").delete(0, 6)?;
+    template.get_map("map").insert("title", "changed")?;
+    template.commit();
+    let patch = template.diff(&v1, &template.state_frontiers())?;
+    doc.apply_diff(&DiffBatch::decode(&patch.encode()?)?)?;
+    doc.commit();
+    assert_eq!(doc.get_text("text").to_string(), "world");
+    assert_eq!(
+        doc.get_map("map").get_deep_value().to_json_value()["title"],
+        json!("changed")
+    );
+    assert!(DiffBatch::decode(&[255, 255, 255]).is_err());
+    Ok(())
+}
+
+#[test]
+fn apply_diff_with_missing_container_changes_nothing() -> anyhow::Result<()> {
+    let template = create_template()?;
+    let v1 = template.state_frontiers();
+    template.get_text("text").insert(0, ">")?;
+    let items = template.get_map("map").get("items").unwrap();
+    let note = items
+        .into_container()
+        .unwrap()
+        .into_list()
+        .unwrap()
+        .get(1)
+        .unwrap()
+        .into_container()
+        .unwrap()
+        .into_map()
+        .unwrap()
+        .get("note")
+        .unwrap()
+        .into_container()
+        .unwrap()
+        .into_text()
+        .unwrap();
+    note.insert(0, "a ")?;
+    template.commit();
+
+    // The nested containers only exist in the template
+    let patch = template.diff(&v1, &template.state_frontiers())?;
+    let doc = LoroDoc::new();
+    assert!(doc.apply_diff(&patch).is_err());
+    doc.commit();
+    assert_eq!(doc.get_text("text").to_string(), "");
+    Ok(())
+}
@@ -0,0 +1,134 @@
+use loro::{CompressionType, EncryptionKey, ExportMode, LoroDoc, LoroError};
+
+fn new_doc_with_history(peer: u64) -> anyhow::Result<LoroDoc> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(peer)?;
+    doc.set_change_merge_interval(0);
+    let map = doc.get_map("map");
+    let text = doc.get_text("text");
+    for i in 0..100 {
+        map.insert(&format!("key{}", i % 10), i)?;
+        text.insert(text.len_unicode(), "top secret ")?;
+        doc.commit();
+    }
+    Ok(doc)
+}
+
+#[test]
+fn encrypted_snapshot() -> anyhow::Result<()> {
+    let key = EncryptionKey::new(1, [42; 32]);
+    let doc = new_doc_with_history(1)?;
+    doc.set_snapshot_compression(CompressionType::None, None)?;
+    doc.set_encryption_key(Some(key.clone()));
+    let snapshot = doc.export(ExportMode::Snapshot)?;
+    assert!(!snapshot.windows(10).any(|w| w == b"top secret"));
+
+    let new_doc = LoroDoc::new();
+    new_doc.set_encryption_key(Some(key.clone()));
+    new_doc.import(&snapshot)?;
+    assert_eq!(new_doc.get_deep_value(), doc.get_deep_value());
+    assert_eq!(new_doc.oplog_vv(), doc.oplog_vv());
+    new_doc.check_state_correctness_slow();
+
+    let mut buf = Vec::new();
+    doc.export_to_writer(ExportMode::Snapshot, &mut buf)?;
+    let new_doc = LoroDoc::new();
+    new_doc.set_encryption_key(Some(key));
+    new_doc.import_from_reader(buf.as_slice())?;
+    assert_eq!(new_doc.get_deep_value(), doc.get_deep_value());
+
+    // Updates are not encrypted
+    let new_doc = LoroDoc::new();
+    new_doc.import(&doc.export(ExportMode::all_updates())?)?;
+    assert_eq!(new_doc.get_deep_value(), doc.get_deep_value());
+    Ok(())
+}
+
+#[test]
+fn wrong_key_is_a_decryption_error() -> anyhow::Result<()> {
+    let doc = new_doc_with_history(1)?;
+    doc.set_encryption_key(Some(EncryptionKey::new(1, [42; 32])));
+    let snapshot = doc.export(ExportMode::Snapshot)?;
+
+    for key in [
+        None,
+        Some(EncryptionKey::new(2, [42; 32])),
+        Some(EncryptionKey::new(1, [0; 32])),
+    ] {
+        let new_doc = LoroDoc::new();
+        new_doc.set_encryption_key(key);
+        assert!(matches!(
+            new_doc.import(&snapshot),
+            Err(LoroError::DecryptionError(_))
+        ));
+        assert_eq!(new_doc.len_changes(), 0);
+    }
+    assert!(matches!(
+        LoroDoc::from_snapshot(&snapshot),
+        Err(LoroError::DecryptionError(_))
+    ));
+    assert!(matches!(
+        LoroDoc::decode_import_blob_meta(&snapshot, true),
+        Err(LoroError::DecryptionError(_))
+    ));
+    Ok(())
+}
+
+#[test]
+fn encrypted_snapshot_modes() -> anyhow::Result<()> {
+    let key = EncryptionKey::new(7, [1; 32]);
+    let doc = new_doc_with_history(1)?;
+    doc.set_encryption_key(Some(key.clone()));
+    let f = doc.oplog_frontiers();
+    for mode in [
+        ExportMode::shallow_snapshot(&f),
+        ExportMode::state_only(None),
+        ExportMode::snapshot_at(&f),
+    ] {
+        let bytes = doc.export(mode)?;
+        assert!(LoroDoc::new().import(&bytes).is_err());
+        let new_doc = LoroDoc::new();
+        new_doc.set_encryption_key(Some(key.clone()));
+        new_doc.import(&bytes)?;
+        assert_eq!(new_doc.get_deep_value(), doc.get_deep_value());
+    }
+
+    // The forked doc keeps the key
+    let fork = doc.fork();
+    let new_doc = LoroDoc::new();
+    new_doc.set_encryption_key(Some(key));
+    new_doc.import(&fork.export(ExportMode::Snapshot)?)?;
+    assert_eq!(new_doc.get_deep_value(), doc.get_deep_value());
+
+    // Stop encrypting
+    doc.set_encryption_key(None);
+    LoroDoc::new().import(&doc.export(ExportMode::Snapshot)?)?;
+    Ok(())
+}
+
+#[test]
+fn imported_stores_are_encrypted_on_export() -> anyhow::Result<()> {
+    let key = EncryptionKey::new(3, [9; 32]);
+    let doc = new_doc_with_history(1)?;
+    doc.set_snapshot_compression(CompressionType::None, None)?;
+    let plain = doc.export(ExportMode::Snapshot)?;
+    assert!(plain.windows(10).any(|w| w == b"top secret"));
+
+    // The stores imported without encryption are encrypted when the doc exports them
+    let new_doc = LoroDoc::new();
+    new_doc.set_snapshot_compression(CompressionType::None, None)?;
+    new_doc.import(&plain)?;
+    new_doc.set_encryption_key(Some(key.clone()));
+    let encrypted = new_doc.export(ExportMode::Snapshot)?;
+    assert!(!encrypted.windows(10).any(|w| w == b"top secret"));
+
+    // And the encrypted stores stay encrypted after they are imported
+    let new_doc = LoroDoc::new();
+    new_doc.set_snapshot_compression(CompressionType::None, None)?;
+    new_doc.set_encryption_key(Some(key));
+    new_doc.import(&encrypted)?;
+    let snapshot = new_doc.export(ExportMode::Snapshot)?;
+    assert!(!snapshot.windows(10).any(|w| w == b"top secret"));
+    assert_eq!(new_doc.get_deep_value(), doc.get_deep_value());
+    Ok(())
+}
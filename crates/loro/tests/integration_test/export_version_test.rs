@@ -0,0 +1,165 @@
+use loro::{
+    ChangeSigner, EncodingVersion, ExportMode, LoroDoc, LoroEncodeError, LoroMap, VersionVector,
+};
+
+use super::gen_action;
+
+fn new_doc(peer: u64) -> anyhow::Result<LoroDoc> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(peer)?;
+    doc.set_change_merge_interval(0);
+    Ok(doc)
+}
+
+fn new_doc_with_history(peer: u64) -> anyhow::Result<LoroDoc> {
+    let doc = new_doc(peer)?;
+    for i in 0..10 {
+        gen_action(&doc, i, 5);
+        doc.commit();
+    }
+    let map = doc
+        .get_map("map")
+        .insert_container("child", LoroMap::new())?;
+    map.insert("key", "value")?;
+    doc.commit();
+    Ok(doc)
+}
+
+/// The encode mode in the header of the blob
+fn encode_mode(blob: &[u8]) -> u16 {
+    u16::from_be_bytes([blob[20], blob[21]])
+}
+
+#[test]
+fn export_in_outdated_encoding() -> anyhow::Result<()> {
+    let doc = new_doc_with_history(1)?;
+    let snapshot = doc.export_with_version(ExportMode::Snapshot, EncodingVersion::Outdated)?;
+    assert_eq!(encode_mode(&snapshot), 2);
+    let new_doc = LoroDoc::new();
+    new_doc.import(&snapshot)?;
+    assert_eq!(new_doc.oplog_vv(), doc.oplog_vv());
+    assert_eq!(new_doc.get_deep_value(), doc.get_deep_value());
+
+    let vv = doc.oplog_vv();
+    gen_action(&doc, 100, 10);
+    doc.commit();
+    let updates = doc.export_with_version(ExportMode::updates(&vv), EncodingVersion::Outdated)?;
+    assert_eq!(encode_mode(&updates), 1);
+    new_doc.import(&updates)?;
+    assert_eq!(new_doc.get_deep_value(), doc.get_deep_value());
+    Ok(())
+}
+
+#[test]
+fn export_in_fast_encoding() -> anyhow::Result<()> {
+    let doc = new_doc_with_history(1)?;
+    for version in [EncodingVersion::Fast, EncodingVersion::Latest] {
+        let snapshot = doc.export_with_version(ExportMode::Snapshot, version)?;
+        assert_eq!(encode_mode(&snapshot), 3);
+        let updates = doc.export_with_version(ExportMode::all_updates(), version)?;
+        assert_eq!(encode_mode(&updates), 4);
+    }
+    Ok(())
+}
+
+#[test]
+fn outdated_snapshot_of_detached_doc() -> anyhow::Result<()> {
+    let doc = new_doc_with_history(1)?;
+    let frontiers = doc.oplog_frontiers();
+    gen_action(&doc, 100, 10);
+    doc.commit();
+    let value = doc.get_deep_value();
+    doc.checkout(&frontiers)?;
+
+    let snapshot = doc.export_with_version(ExportMode::Snapshot, EncodingVersion::Outdated)?;
+    assert!(doc.is_detached());
+    assert_eq!(doc.state_frontiers(), frontiers);
+    let new_doc = LoroDoc::new();
+    new_doc.import(&snapshot)?;
+    assert_eq!(new_doc.get_deep_value(), value);
+    Ok(())
+}
+
+#[test]
+fn reject_features_of_newer_encodings() -> anyhow::Result<()> {
+    let doc = new_doc_with_history(1)?;
+    let frontiers = doc.oplog_frontiers();
+    assert!(matches!(
+        doc.export_with_version(
+            ExportMode::shallow_snapshot(&frontiers),
+            EncodingVersion::Outdated
+        ),
+        Err(LoroEncodeError::IncompatibleEncodingVersion(_))
+    ));
+
+    // The outdated encoding can't carry the signatures
+    let vv = doc.oplog_vv();
+    doc.set_change_signer(Some(ChangeSigner::new(|payload| payload.bytes.to_vec())));
+    doc.get_text("text").insert(0, "signed")?;
+    doc.commit();
+    assert!(matches!(
+        doc.export_with_version(ExportMode::updates(&vv), EncodingVersion::Outdated),
+        Err(LoroEncodeError::IncompatibleEncodingVersion(_))
+    ));
+    assert!(doc
+        .export_with_version(ExportMode::updates(&vv), EncodingVersion::Fast)
+        .is_ok());
+
+    // The history before the shallow root is missing
+    let shallow = new_doc(2)?;
+    shallow.import(&doc.export(ExportMode::shallow_snapshot(&frontiers))?)?;
+    assert!(matches!(
+        shallow.export_with_version(ExportMode::Snapshot, EncodingVersion::Outdated),
+        Err(LoroEncodeError::ShallowSnapshotIncompatibleWithOldFormat)
+    ));
+    assert!(matches!(
+        shallow.export_with_version(
+            ExportMode::updates(&VersionVector::new()),
+            EncodingVersion::Outdated
+        ),
+        Err(LoroEncodeError::ShallowSnapshotIncompatibleWithOldFormat)
+    ));
+    Ok(())
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn recompress_zstd_snapshot_for_fast_encoding() -> anyhow::Result<()> {
+    use loro::CompressionType;
+
+    let doc = new_doc_with_history(1)?;
+    let lz4 = doc.export(ExportMode::Snapshot)?;
+    doc.set_snapshot_compression(CompressionType::Zstd { level: 3 }, None)?;
+    assert_ne!(encode_mode(&doc.export(ExportMode::Snapshot)?), 3);
+    let snapshot = doc.export_with_version(ExportMode::Snapshot, EncodingVersion::Fast)?;
+    assert_eq!(encode_mode(&snapshot), encode_mode(&lz4));
+    let new_doc = LoroDoc::new();
+    new_doc.import(&snapshot)?;
+    assert_eq!(new_doc.get_deep_value(), doc.get_deep_value());
+    Ok(())
+}
+
+#[cfg(feature = "encryption")]
+#[test]
+fn reject_encrypted_snapshot_for_older_encodings() -> anyhow::Result<()> {
+    use loro::EncryptionKey;
+
+    let doc = new_doc_with_history(1)?;
+    doc.set_encryption_key(Some(EncryptionKey::new(1, [42; 32])));
+    assert!(matches!(
+        doc.export_with_version(ExportMode::Snapshot, EncodingVersion::Fast),
+        Err(LoroEncodeError::IncompatibleEncodingVersion(_))
+    ));
+    assert!(matches!(
+        doc.export_with_version(ExportMode::Snapshot, EncodingVersion::Outdated),
+        Err(LoroEncodeError::IncompatibleEncodingVersion(_))
+    ));
+    // The updates are not encrypted
+    assert!(doc
+        .export_with_version(ExportMode::all_updates(), EncodingVersion::Fast)
+        .is_ok());
+    assert!(doc
+        .export_with_version(ExportMode::all_updates(), EncodingVersion::Outdated)
+        .is_ok());
+    Ok(())
+}
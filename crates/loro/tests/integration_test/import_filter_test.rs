@@ -0,0 +1,154 @@
+use loro::{
+    ContainerID, ContainerType, ExportMode, ImportFilter, LoroDoc, VersionRange, VersionVector,
+};
+
+fn settings_only() -> ImportFilter {
+    let settings = ContainerID::new_root("settings", ContainerType::Map);
+    ImportFilter::new(move |_meta, containers| containers.iter().all(|c| c == &settings))
+}
+
+fn new_doc(peer: u64) -> anyhow::Result<LoroDoc> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(peer)?;
+    doc.set_change_merge_interval(0);
+    Ok(doc)
+}
+
+#[test]
+fn rejected_changes_are_quarantined() -> anyhow::Result<()> {
+    let client = new_doc(1)?;
+    client.get_map("settings").insert("theme", "dark")?;
+    client.commit();
+    client.get_text("body").insert(0, "Hello")?;
+    client.commit();
+    client.get_map("settings").insert("font", "mono")?;
+    client.commit();
+
+    let server = new_doc(0)?;
+    server.set_import_filter(Some(settings_only()));
+    let status = server.import(&client.export(ExportMode::all_updates())?)?;
+    // The last change only touches the settings, but it depends on the rejected one
+    assert_eq!(
+        status.quarantined,
+        Some(VersionRange::from_map([(1, (1, 7))].into_iter().collect()))
+    );
+    assert_eq!(
+        status.success,
+        VersionRange::from_map([(1, (0, 1))].into_iter().collect())
+    );
+    assert_eq!(server.oplog_vv(), VersionVector::from_iter([(1, 1)]));
+    assert_eq!(server.get_map("settings").len(), 1);
+    assert!(server.get_text("body").is_empty());
+    server.check_state_correctness_slow();
+
+    // The descendants imported later are quarantined too
+    let other = new_doc(2)?;
+    other.import(&client.export(ExportMode::all_updates())?)?;
+    other.get_map("settings").insert("size", 12)?;
+    other.commit();
+    let status = server.import(&other.export(ExportMode::updates(&server.oplog_vv()))?)?;
+    assert_eq!(
+        status.quarantined,
+        Some(VersionRange::from_map(
+            [(1, (1, 7)), (2, (0, 1))].into_iter().collect()
+        ))
+    );
+    assert!(status.success.is_empty());
+    assert_eq!(server.oplog_vv(), VersionVector::from_iter([(1, 1)]));
+
+    // The changes that don't depend on the quarantine are still accepted
+    let another = new_doc(3)?;
+    another.get_map("settings").insert("lang", "en")?;
+    let status = server.import(&another.export(ExportMode::all_updates())?)?;
+    assert_eq!(status.quarantined, None);
+    assert_eq!(server.get_map("settings").len(), 2);
+
+    server.set_import_filter(None);
+    let status = server.import(&other.export(ExportMode::all_updates())?)?;
+    assert_eq!(status.quarantined, None);
+    assert_eq!(server.get_text("body").to_string(), "Hello");
+    Ok(())
+}
+
+#[test]
+fn filter_receives_change_meta() -> anyhow::Result<()> {
+    let client = new_doc(1)?;
+    client.get_text("body").insert(0, "Hello")?;
+    client.commit_with(loro::CommitOptions::new().commit_msg("rejected"));
+    client.get_text("body").insert(0, "Hi ")?;
+    client.commit_with(loro::CommitOptions::new().commit_msg("accepted"));
+
+    let server = new_doc(0)?;
+    server.set_import_filter(Some(ImportFilter::new(|meta, containers| {
+        assert_eq!(
+            containers,
+            &[ContainerID::new_root("body", ContainerType::Text)]
+        );
+        meta.message.as_deref() != Some("rejected")
+    })));
+    let status = server.import(&client.export(ExportMode::all_updates())?)?;
+    assert!(status.success.is_empty());
+    assert_eq!(
+        status.quarantined,
+        Some(VersionRange::from_map([(1, (0, 8))].into_iter().collect()))
+    );
+    Ok(())
+}
+
+#[test]
+fn snapshot_is_filtered() -> anyhow::Result<()> {
+    let client = new_doc(1)?;
+    client.get_map("settings").insert("theme", "dark")?;
+    client.commit();
+    client.get_text("body").insert(0, "Hello")?;
+    client.commit();
+    let snapshot = client.export(ExportMode::Snapshot)?;
+
+    let server = new_doc(0)?;
+    server.set_import_filter(Some(settings_only()));
+    let status = server.import(&snapshot)?;
+    assert!(status.quarantined.is_some());
+    assert_eq!(server.oplog_vv(), VersionVector::from_iter([(1, 1)]));
+    assert!(server.get_text("body").is_empty());
+
+    let server = new_doc(0)?;
+    server.set_import_filter(Some(settings_only()));
+    let status = server.import_from_reader(snapshot.as_slice())?;
+    assert!(status.quarantined.is_some());
+    assert!(server.get_text("body").is_empty());
+    server.check_state_correctness_slow();
+    Ok(())
+}
+
+#[test]
+fn pending_descendants_are_quarantined() -> anyhow::Result<()> {
+    let client = new_doc(1)?;
+    client.get_text("body").insert(0, "Hello")?;
+    client.commit();
+    let rejected = client.export(ExportMode::all_updates())?;
+    let rejected_vv = client.oplog_vv();
+    client.get_map("settings").insert("theme", "dark")?;
+    client.commit();
+
+    let server = new_doc(0)?;
+    server.set_import_filter(Some(settings_only()));
+    let status = server.import(&client.export(ExportMode::updates(&rejected_vv))?)?;
+    assert!(status.pending.is_some());
+
+    // The pending change depends on the rejected change, so it's quarantined with it
+    let status = server.import(&rejected)?;
+    let quarantined = VersionRange::from_map([(1, (0, 6))].into_iter().collect());
+    assert_eq!(status.quarantined, Some(quarantined.clone()));
+    assert_eq!(server.quarantine(), quarantined);
+    assert!(server.oplog_vv().is_empty());
+
+    // After the quarantine is cleared, the changes can be imported again
+    server.set_import_filter(None);
+    server.clear_quarantine();
+    assert!(server.quarantine().is_empty());
+    let status = server.import(&client.export(ExportMode::all_updates())?)?;
+    assert_eq!(status.quarantined, None);
+    assert_eq!(server.get_text("body").to_string(), "Hello");
+    assert_eq!(server.get_map("settings").len(), 1);
+    Ok(())
+}
@@ -0,0 +1,277 @@
+use loro::{EncodingVersion, ExportMode, ImportOptions, LoroDoc, LoroError, LoroMap, LoroValue};
+
+use super::gen_action;
+
+fn new_doc(peer: u64) -> anyhow::Result<LoroDoc> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(peer)?;
+    doc.set_change_merge_interval(0);
+    Ok(doc)
+}
+
+fn new_doc_with_options(peer: u64, options: ImportOptions) -> anyhow::Result<LoroDoc> {
+    let doc = new_doc(peer)?;
+    doc.set_import_options(Some(options));
+    Ok(doc)
+}
+
+fn is_limit_exceeded<T>(result: Result<T, LoroError>, name: &str) -> bool {
+    matches!(result, Err(LoroError::LimitExceeded { limit, .. }) if limit == name)
+}
+
+#[test]
+fn limit_input_bytes() -> anyhow::Result<()> {
+    let doc = new_doc(1)?;
+    gen_action(&doc, 0, 50);
+    doc.commit();
+    let updates = doc.export(ExportMode::all_updates())?;
+    let snapshot = doc.export(ExportMode::Snapshot)?;
+
+    let options = ImportOptions {
+        max_input_bytes: Some(updates.len() - 1),
+        ..Default::default()
+    };
+    let new_doc = new_doc_with_options(2, options)?;
+    assert!(is_limit_exceeded(
+        new_doc.import(&updates),
+        "max_input_bytes"
+    ));
+    assert!(is_limit_exceeded(
+        new_doc.import_from_reader(snapshot.as_slice()),
+        "max_input_bytes"
+    ));
+    let mut written = Vec::new();
+    doc.export_to_writer(ExportMode::all_updates(), &mut written)?;
+    assert!(written.len() > updates.len() - 1);
+    assert!(is_limit_exceeded(
+        new_doc.import_from_reader(written.as_slice()),
+        "max_input_bytes"
+    ));
+    assert!(new_doc.oplog_vv().is_empty());
+
+    new_doc.set_import_options(Some(ImportOptions {
+        max_input_bytes: Some(updates.len()),
+        ..Default::default()
+    }));
+    new_doc.import(&updates)?;
+    assert_eq!(new_doc.get_deep_value(), doc.get_deep_value());
+    Ok(())
+}
+
+#[test]
+fn limit_decompressed_bytes() -> anyhow::Result<()> {
+    let doc = new_doc(1)?;
+    doc.get_map("map").insert("bytes", vec![0u8; 1_000_000])?;
+    doc.commit();
+    let snapshot = doc.export(ExportMode::Snapshot)?;
+    assert!(snapshot.len() < 100_000);
+
+    let options = ImportOptions {
+        max_decompressed_bytes: Some(100_000),
+        ..Default::default()
+    };
+    let new_doc = new_doc_with_options(2, options)?;
+    assert!(is_limit_exceeded(
+        new_doc.import(&snapshot),
+        "max_decompressed_bytes"
+    ));
+    assert!(new_doc.oplog_vv().is_empty());
+
+    let new_doc = new_doc_with_options(
+        2,
+        ImportOptions {
+            max_decompressed_bytes: Some(2_000_000),
+            ..Default::default()
+        },
+    )?;
+    new_doc.import(&snapshot)?;
+    assert_eq!(new_doc.get_deep_value(), doc.get_deep_value());
+    Ok(())
+}
+
+#[test]
+fn limit_ops() -> anyhow::Result<()> {
+    let doc = new_doc(1)?;
+    let text = doc.get_text("text");
+    text.insert(0, "Hello")?;
+    doc.commit();
+    let first = doc.export(ExportMode::all_updates())?;
+    text.insert(5, " world")?;
+    doc.commit();
+
+    let options = ImportOptions {
+        max_ops: Some(6),
+        ..Default::default()
+    };
+    for blob in [
+        doc.export(ExportMode::all_updates())?,
+        doc.export(ExportMode::Snapshot)?,
+        doc.export_with_version(ExportMode::all_updates(), EncodingVersion::Outdated)?,
+        doc.export_with_version(ExportMode::Snapshot, EncodingVersion::Outdated)?,
+    ] {
+        let new_doc = new_doc_with_options(2, options)?;
+        assert!(is_limit_exceeded(new_doc.import(&blob), "max_ops"));
+        assert!(new_doc.oplog_vv().is_empty());
+        assert_eq!(new_doc.get_text("text").to_string(), "");
+    }
+    let new_doc = new_doc_with_options(2, options)?;
+    let json = doc.export_json_updates(&Default::default(), &doc.oplog_vv());
+    assert!(is_limit_exceeded(
+        new_doc.import_json_updates(json),
+        "max_ops"
+    ));
+    assert!(new_doc.oplog_vv().is_empty());
+
+    // Only the new ops count
+    let new_doc = new_doc_with_options(2, options)?;
+    new_doc.import(&first)?;
+    new_doc.import(&doc.export(ExportMode::all_updates())?)?;
+    assert_eq!(new_doc.get_text("text").to_string(), "Hello world");
+    Ok(())
+}
+
+#[test]
+fn limit_value_len() -> anyhow::Result<()> {
+    let doc = new_doc(1)?;
+    let map = doc.get_map("map");
+    map.insert("str", "a".repeat(100))?;
+    map.insert("bytes", vec![0u8; 100])?;
+    doc.commit();
+
+    let options = ImportOptions {
+        max_value_len: Some(99),
+        ..Default::default()
+    };
+    for blob in [
+        doc.export(ExportMode::all_updates())?,
+        doc.export(ExportMode::Snapshot)?,
+        doc.export_with_version(ExportMode::all_updates(), EncodingVersion::Outdated)?,
+    ] {
+        let new_doc = new_doc_with_options(2, options)?;
+        assert!(is_limit_exceeded(new_doc.import(&blob), "max_value_len"));
+        assert!(new_doc.get_map("map").is_empty());
+    }
+
+    let new_doc = new_doc_with_options(
+        2,
+        ImportOptions {
+            max_value_len: Some(100),
+            ..Default::default()
+        },
+    )?;
+    new_doc.import(&doc.export(ExportMode::all_updates())?)?;
+    assert_eq!(new_doc.get_deep_value(), doc.get_deep_value());
+    Ok(())
+}
+
+#[test]
+fn limit_value_len_of_shallow_snapshot() -> anyhow::Result<()> {
+    let doc = new_doc(1)?;
+    let map = doc.get_map("map");
+    map.insert("str", "a".repeat(100))?;
+    doc.commit();
+    map.insert("other", 1)?;
+    doc.commit();
+    // The long string is only in the state of the shallow root
+    let blob = doc.export(ExportMode::shallow_snapshot(&doc.oplog_frontiers()))?;
+
+    let new_doc = new_doc_with_options(
+        2,
+        ImportOptions {
+            max_value_len: Some(99),
+            ..Default::default()
+        },
+    )?;
+    assert!(is_limit_exceeded(new_doc.import(&blob), "max_value_len"));
+    assert!(new_doc.oplog_vv().is_empty());
+    assert!(new_doc.get_map("map").is_empty());
+
+    new_doc.set_import_options(None);
+    new_doc.import(&blob)?;
+    assert_eq!(new_doc.get_deep_value(), doc.get_deep_value());
+    Ok(())
+}
+
+#[test]
+fn limit_depth() -> anyhow::Result<()> {
+    let options = ImportOptions {
+        max_depth: Some(3),
+        ..Default::default()
+    };
+
+    // The nested values
+    let doc = new_doc(1)?;
+    let mut value = LoroValue::from(0);
+    for _ in 0..4 {
+        value = LoroValue::List(vec![value].into());
+    }
+    doc.get_map("map").insert("value", value)?;
+    doc.commit();
+    let new_doc = new_doc_with_options(2, options)?;
+    assert!(is_limit_exceeded(
+        new_doc.import(&doc.export(ExportMode::all_updates())?),
+        "max_depth"
+    ));
+
+    // The nested containers, whose parents are created in the same blob
+    let doc = new_doc(1)?;
+    let mut map = doc.get_map("map");
+    for _ in 0..2 {
+        map = map.insert_container("child", LoroMap::new())?;
+    }
+    doc.commit();
+    let vv = doc.oplog_vv();
+    let new_doc = new_doc_with_options(2, options)?;
+    new_doc.import(&doc.export(ExportMode::all_updates())?)?;
+
+    // The parents are imported already
+    map.insert_container("child", LoroMap::new())?;
+    doc.commit();
+    let updates = doc.export(ExportMode::updates(&vv))?;
+    assert!(is_limit_exceeded(new_doc.import(&updates), "max_depth"));
+    assert!(is_limit_exceeded(
+        new_doc_with_options(3, options)?.import(&doc.export(ExportMode::Snapshot)?),
+        "max_depth"
+    ));
+    new_doc.set_import_options(None);
+    new_doc.import(&updates)?;
+    assert_eq!(new_doc.get_deep_value(), doc.get_deep_value());
+    Ok(())
+}
+
+#[test]
+fn limit_pending_changes() -> anyhow::Result<()> {
+    let doc = new_doc(1)?;
+    let text = doc.get_text("text");
+    let mut blobs = Vec::new();
+    for i in 0..4 {
+        let vv = doc.oplog_vv();
+        text.insert(0, &i.to_string())?;
+        doc.commit();
+        blobs.push(doc.export(ExportMode::updates(&vv))?);
+    }
+
+    let new_doc = new_doc_with_options(
+        2,
+        ImportOptions {
+            max_pending_changes: Some(1),
+            ..Default::default()
+        },
+    )?;
+    assert!(new_doc.import(&blobs[2])?.pending.is_some());
+    // The pending changes of the doc count too
+    assert!(is_limit_exceeded(
+        new_doc.import(&blobs[3]),
+        "max_pending_changes"
+    ));
+    // The changes that don't add to the pending ones are fine
+    new_doc.import(&blobs[0])?;
+    assert!(is_limit_exceeded(
+        new_doc.import(&blobs[3]),
+        "max_pending_changes"
+    ));
+    assert!(new_doc.import(&blobs[1])?.pending.is_none());
+    new_doc.import(&blobs[3])?;
+    assert_eq!(new_doc.get_deep_value(), doc.get_deep_value());
+    Ok(())
+}
@@ -0,0 +1,131 @@
+use loro::{ContainerID, ContainerType, ExportMode, ImportFilter, LoroDoc, LoroMap};
+
+use super::gen_action;
+
+fn new_doc(peer: u64) -> anyhow::Result<LoroDoc> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(peer)?;
+    doc.set_change_merge_interval(0);
+    Ok(doc)
+}
+
+#[test]
+fn preview_matches_import() -> anyhow::Result<()> {
+    let a = new_doc(1)?;
+    gen_action(&a, 0, 20);
+    a.commit();
+    let b = new_doc(2)?;
+    b.import(&a.export(ExportMode::Snapshot)?)?;
+    gen_action(&a, 1, 20);
+    a.commit();
+    let map = b.get_map("map").insert_container("child", LoroMap::new())?;
+    map.insert("key", "value")?;
+    b.commit();
+
+    let updates = b.export(ExportMode::updates(&a.oplog_vv()))?;
+    let vv = a.oplog_vv();
+    let frontiers = a.oplog_frontiers();
+    let value = a.get_deep_value();
+    let preview = a.import_preview(&updates, true)?;
+    // The doc is untouched
+    assert_eq!(a.oplog_vv(), vv);
+    assert_eq!(a.oplog_frontiers(), frontiers);
+    assert_eq!(a.get_deep_value(), value);
+
+    let changed = [b.get_map("map").id(), map.id()];
+    assert_eq!(preview.changed_containers, changed.into_iter().collect());
+    let status = a.import(&updates)?;
+    assert_eq!(preview.status, status);
+    assert_eq!(preview.frontiers, a.oplog_frontiers());
+
+    let old = a.fork_at(&frontiers);
+    old.apply_diff(&preview.diff.unwrap())?;
+    assert_eq!(
+        old.get_deep_value().to_json_value(),
+        a.get_deep_value().to_json_value()
+    );
+    assert!(a.import_preview(&updates, false)?.diff.is_none());
+    Ok(())
+}
+
+#[test]
+fn preview_pending_changes() -> anyhow::Result<()> {
+    let a = new_doc(1)?;
+    let text = a.get_text("text");
+    text.insert(0, "Hello")?;
+    a.commit();
+    let first = a.export(ExportMode::all_updates())?;
+    let vv = a.oplog_vv();
+    text.insert(5, " world")?;
+    a.commit();
+    let second = a.export(ExportMode::updates(&vv))?;
+
+    let b = new_doc(2)?;
+    let preview = b.import_preview(&second, true)?;
+    assert!(preview.status.success.is_empty());
+    assert!(preview.status.pending.is_some());
+    assert!(preview.changed_containers.is_empty());
+    assert!(preview.frontiers.is_empty());
+
+    // The pending changes of the doc are applied with their missing deps
+    b.import(&second)?;
+    let preview = b.import_preview(&first, false)?;
+    assert_eq!(preview.status.success.get(&1), Some(&(0, 11)));
+    assert!(preview.status.pending.is_none());
+    assert_eq!(preview.frontiers, a.oplog_frontiers());
+    assert_eq!(b.get_text("text").to_string(), "");
+    b.import(&first)?;
+    assert_eq!(b.get_text("text").to_string(), "Hello world");
+    Ok(())
+}
+
+#[test]
+fn preview_on_detached_doc() -> anyhow::Result<()> {
+    let a = new_doc(1)?;
+    let text = a.get_text("text");
+    text.insert(0, "Hello")?;
+    a.commit();
+    let frontiers = a.oplog_frontiers();
+    text.insert(5, " world")?;
+    a.commit();
+    let b = new_doc(2)?;
+    b.import(&a.export(ExportMode::Snapshot)?)?;
+    b.get_text("text").insert(0, "> ")?;
+    b.commit();
+
+    a.checkout(&frontiers)?;
+    let preview = a.import_preview(&b.export(ExportMode::updates(&a.oplog_vv()))?, true)?;
+    assert_eq!(preview.frontiers, b.oplog_frontiers());
+    assert!(preview.changed_containers.contains(&text.id()));
+    assert!(a.is_detached());
+    assert_eq!(a.state_frontiers(), frontiers);
+    assert_eq!(text.to_string(), "Hello");
+    Ok(())
+}
+
+#[test]
+fn preview_keeps_the_quarantine() -> anyhow::Result<()> {
+    let client = new_doc(1)?;
+    client.get_text("body").insert(0, "Hello")?;
+    client.commit();
+    let server = new_doc(0)?;
+    let settings = ContainerID::new_root("settings", ContainerType::Map);
+    server.set_import_filter(Some(ImportFilter::new(move |_meta, containers| {
+        containers.iter().all(|c| c == &settings)
+    })));
+    let status = server.import(&client.export(ExportMode::all_updates())?)?;
+    assert!(status.quarantined.is_some());
+    let quarantined_vv = client.oplog_vv();
+
+    // The new change is accepted by the filter, but it depends on the quarantined one
+    client.get_map("settings").insert("theme", "dark")?;
+    client.commit();
+    let updates = client.export(ExportMode::updates(&quarantined_vv))?;
+    let preview = server.import_preview(&updates, false)?;
+    let status = server.import(&updates)?;
+    assert!(status.quarantined.is_some());
+    assert_eq!(preview.status.quarantined, status.quarantined);
+    assert_eq!(preview.status.pending, None);
+    assert!(preview.changed_containers.is_empty());
+    Ok(())
+}
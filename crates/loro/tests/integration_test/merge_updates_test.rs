@@ -0,0 +1,141 @@
+use loro::{merge_updates, ExportMode, IdSpan, LoroDoc, LoroError};
+
+fn new_doc(peer: u64) -> anyhow::Result<LoroDoc> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(peer)?;
+    doc.set_change_merge_interval(0);
+    Ok(doc)
+}
+
+/// Two peers that edit concurrently and sync, returning the updates of every commit
+fn edit_and_collect_updates() -> anyhow::Result<(LoroDoc, Vec<Vec<u8>>)> {
+    let a = new_doc(1)?;
+    let b = new_doc(2)?;
+    let mut blobs = Vec::new();
+    for i in 0..10 {
+        for doc in [&a, &b] {
+            let vv = doc.oplog_vv();
+            doc.get_text("text").insert(0, &format!("{i}"))?;
+            doc.get_map("map")
+                .insert(&format!("{}", doc.peer_id()), i)?;
+            doc.commit();
+            blobs.push(doc.export(ExportMode::updates(&vv))?);
+        }
+        if i % 3 == 0 {
+            a.import(&b.export(ExportMode::all_updates())?)?;
+            b.import(&a.export(ExportMode::all_updates())?)?;
+        }
+    }
+    a.import(&b.export(ExportMode::all_updates())?)?;
+    Ok((a, blobs))
+}
+
+#[test]
+fn merge_small_updates() -> anyhow::Result<()> {
+    let (doc, blobs) = edit_and_collect_updates()?;
+    let refs = blobs.iter().map(|b| b.as_slice()).collect::<Vec<_>>();
+    let merged = merge_updates(&refs)?;
+    assert!(merged.len() < blobs.iter().map(|b| b.len()).sum::<usize>());
+
+    let new_doc = LoroDoc::new();
+    let status = new_doc.import(&merged)?;
+    assert!(status.pending.is_none());
+    assert_eq!(new_doc.oplog_vv(), doc.oplog_vv());
+    assert_eq!(new_doc.get_deep_value(), doc.get_deep_value());
+
+    // The order of the blobs doesn't matter
+    let reversed = refs.iter().rev().copied().collect::<Vec<_>>();
+    let new_doc = LoroDoc::new();
+    new_doc.import(&merge_updates(&reversed)?)?;
+    assert_eq!(new_doc.get_deep_value(), doc.get_deep_value());
+    Ok(())
+}
+
+#[test]
+fn dedupe_overlapping_updates() -> anyhow::Result<()> {
+    let (doc, blobs) = edit_and_collect_updates()?;
+    let end = doc.oplog_vv().get(&1).copied().unwrap();
+    let peer_2 = IdSpan::new(2, 0, doc.oplog_vv().get(&2).copied().unwrap());
+    let all = doc.export(ExportMode::all_updates())?;
+    let first_half = doc.export(ExportMode::updates_in_range(vec![
+        IdSpan::new(1, 0, end / 2),
+        peer_2,
+    ]))?;
+    // It overlaps with both of the halves
+    let middle = doc.export(ExportMode::updates_in_range(vec![IdSpan::new(
+        1,
+        end / 4,
+        end * 3 / 4,
+    )]))?;
+    let second_half = doc.export(ExportMode::updates_in_range(vec![IdSpan::new(
+        1,
+        end / 2,
+        end,
+    )]))?;
+
+    let merged = merge_updates(&[&second_half, &middle, &first_half])?;
+    let from_halves = merge_updates(&[&first_half, &second_half])?;
+    let a = LoroDoc::new();
+    a.import(&merged)?;
+    let b = LoroDoc::new();
+    b.import(&from_halves)?;
+    assert_eq!(a.oplog_vv(), doc.oplog_vv());
+    assert_eq!(a.get_deep_value(), doc.get_deep_value());
+    assert_eq!(b.get_deep_value(), doc.get_deep_value());
+
+    let mut refs = blobs.iter().map(|b| b.as_slice()).collect::<Vec<_>>();
+    refs.push(&all);
+    refs.push(&middle);
+    let merged = merge_updates(&refs)?;
+    let new_doc = LoroDoc::new();
+    let status = new_doc.import(&merged)?;
+    assert!(status.pending.is_none());
+    assert_eq!(new_doc.len_changes(), doc.len_changes());
+    assert_eq!(new_doc.get_deep_value(), doc.get_deep_value());
+    Ok(())
+}
+
+#[test]
+fn merge_updates_with_gaps() -> anyhow::Result<()> {
+    let doc = new_doc(1)?;
+    let text = doc.get_text("text");
+    text.insert(0, "a")?;
+    doc.commit();
+    let first = doc.export(ExportMode::all_updates())?;
+    let vv = doc.oplog_vv();
+    text.insert(1, "b")?;
+    doc.commit();
+    let missing = doc.export(ExportMode::updates(&vv))?;
+    let vv = doc.oplog_vv();
+    text.insert(2, "c")?;
+    doc.commit();
+    let last = doc.export(ExportMode::updates(&vv))?;
+
+    // The merged blob has a gap, so the last change is pending on import
+    let merged = merge_updates(&[&last, &first])?;
+    let new_doc = LoroDoc::new();
+    let status = new_doc.import(&merged)?;
+    assert!(status.pending.is_some());
+    assert_eq!(new_doc.get_text("text").to_string(), "a");
+    new_doc.import(&missing)?;
+    assert_eq!(new_doc.get_text("text").to_string(), "abc");
+
+    assert!(merge_updates(&[])?.len() < first.len());
+    Ok(())
+}
+
+#[test]
+fn only_updates_can_be_merged() -> anyhow::Result<()> {
+    let doc = new_doc(1)?;
+    doc.get_text("text").insert(0, "Hello")?;
+    let updates = doc.export(ExportMode::all_updates())?;
+    let snapshot = doc.export(ExportMode::Snapshot)?;
+    assert!(matches!(
+        merge_updates(&[&updates, &snapshot]),
+        Err(LoroError::DecodeError(_))
+    ));
+    let mut corrupted = updates.clone();
+    *corrupted.last_mut().unwrap() ^= 0xff;
+    assert!(merge_updates(&[&corrupted]).is_err());
+    Ok(())
+}
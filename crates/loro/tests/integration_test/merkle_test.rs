@@ -0,0 +1,148 @@
+use loro::{
+    ContainerID, ContainerTrait, ContainerType, ExportMode, LoroDoc, LoroList, LoroMap, LoroText,
+    StateHash,
+};
+
+fn new_doc(peer: u64) -> anyhow::Result<LoroDoc> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(peer)?;
+    doc.set_change_merge_interval(0);
+    Ok(doc)
+}
+
+/// Walk down the Merkle trees of the two docs and collect the deepest diverged containers
+fn find_diverged(a: &LoroDoc, b: &LoroDoc) -> Vec<ContainerID> {
+    fn walk(
+        a: &LoroDoc,
+        b: &LoroDoc,
+        children_a: Vec<(ContainerID, StateHash)>,
+        children_b: Vec<(ContainerID, StateHash)>,
+        ans: &mut Vec<ContainerID>,
+    ) {
+        for (id, hash) in children_a {
+            if children_b.contains(&(id.clone(), hash)) {
+                continue;
+            }
+
+            let (Some(a_children), Some(b_children)) =
+                (a.merkle_children(&id), b.merkle_children(&id))
+            else {
+                ans.push(id);
+                continue;
+            };
+            let len = ans.len();
+            walk(a, b, a_children, b_children, ans);
+            if ans.len() == len {
+                ans.push(id);
+            }
+        }
+    }
+
+    let mut ans = Vec::new();
+    walk(
+        a,
+        b,
+        a.merkle_root().children,
+        b.merkle_root().children,
+        &mut ans,
+    );
+    ans
+}
+
+#[test]
+fn merkle_root_is_state_hash() -> anyhow::Result<()> {
+    let doc = new_doc(1)?;
+    let map = doc.get_map("map");
+    let list = map.insert_container("list", LoroList::new())?;
+    let text = list.insert_container(0, LoroText::new())?;
+    text.insert(0, "Hello")?;
+    doc.get_text("empty");
+    doc.commit();
+
+    let root = doc.merkle_root();
+    assert_eq!(root.hash, doc.state_hash());
+    // The empty root containers are skipped
+    assert_eq!(root.children, vec![(map.id(), map.state_hash()?)]);
+    assert_eq!(
+        doc.merkle_children(&map.id()).unwrap(),
+        vec![(list.id(), list.state_hash()?)]
+    );
+    assert_eq!(
+        doc.merkle_children(&list.id()).unwrap(),
+        vec![(text.id(), text.state_hash()?)]
+    );
+    assert!(doc.merkle_children(&text.id()).unwrap().is_empty());
+    assert!(doc
+        .merkle_children(&ContainerID::new_root("unknown", ContainerType::Map))
+        .is_none());
+    Ok(())
+}
+
+#[test]
+fn find_diverged_containers() -> anyhow::Result<()> {
+    let a = new_doc(1)?;
+    let doc_map = a.get_map("doc");
+    let mut texts = Vec::new();
+    for i in 0..8 {
+        let section = doc_map.insert_container(&format!("section_{i}"), LoroMap::new())?;
+        let text = section.insert_container("text", LoroText::new())?;
+        text.insert(0, &format!("Section {i}"))?;
+        texts.push(text.id());
+    }
+    a.get_list("list").push("item")?;
+    a.commit();
+    let b = new_doc(2)?;
+    b.import(&a.export(ExportMode::Snapshot)?)?;
+    assert_eq!(a.merkle_root(), b.merkle_root());
+    assert!(find_diverged(&a, &b).is_empty());
+
+    // Diverge without syncing
+    let text = b.get_text(texts[5].clone());
+    text.insert(0, "> ")?;
+    b.commit();
+    assert_ne!(a.merkle_root().hash, b.merkle_root().hash);
+    assert_eq!(find_diverged(&a, &b), vec![text.id()]);
+
+    a.import(&b.export(ExportMode::updates(&a.oplog_vv()))?)?;
+    assert_eq!(a.merkle_root(), b.merkle_root());
+    Ok(())
+}
+
+#[test]
+fn cached_hashes_follow_the_state() -> anyhow::Result<()> {
+    let doc = new_doc(1)?;
+    let map = doc.get_map("map");
+    let list = map.insert_container("list", LoroList::new())?;
+    let text = list.insert_container(0, LoroText::new())?;
+    text.insert(0, "Hello")?;
+    doc.get_map("other").insert("key", 1)?;
+    doc.commit();
+    let v1 = doc.oplog_frontiers();
+    let hash_v1 = doc.state_hash();
+    let other_hash = doc.get_map("other").state_hash()?;
+
+    // A local edit of a nested container changes the hashes of its ancestors
+    text.insert(5, " world")?;
+    doc.commit();
+    let hash_v2 = doc.state_hash();
+    assert_ne!(hash_v1, hash_v2);
+    assert_eq!(doc.get_map("other").state_hash()?, other_hash);
+    let fresh = LoroDoc::new();
+    fresh.import(&doc.export(ExportMode::Snapshot)?)?;
+    assert_eq!(fresh.state_hash(), hash_v2);
+    assert_eq!(fresh.merkle_root(), doc.merkle_root());
+
+    // So do the imported changes and the checkouts
+    let remote = new_doc(2)?;
+    remote.import(&doc.export(ExportMode::all_updates())?)?;
+    remote.get_text(text.id()).insert(0, ">")?;
+    remote.commit();
+    doc.import(&remote.export(ExportMode::updates(&doc.oplog_vv()))?)?;
+    assert_eq!(doc.state_hash(), remote.state_hash());
+    assert_ne!(doc.state_hash(), hash_v2);
+    doc.checkout(&v1)?;
+    assert_eq!(doc.state_hash(), hash_v1);
+    doc.checkout_to_latest();
+    assert_eq!(doc.state_hash(), remote.state_hash());
+    Ok(())
+}
@@ -1,12 +1,35 @@
 use loro::LoroDoc;
 
+mod blame_test;
+mod changes_between_test;
+mod cherry_pick_test;
+mod conflicts_test;
+mod containers_export_test;
 mod detached_editing_test;
+mod diff_batch_test;
+#[cfg(feature = "encryption")]
+mod encryption_test;
+mod export_version_test;
+mod import_filter_test;
+mod import_limits_test;
+mod import_preview_test;
 #[cfg(feature = "jsonpath")]
 mod jsonpath_test;
+mod merge_updates_test;
+mod merkle_test;
 mod redact_test;
+mod refs_test;
+mod revert_test;
 mod shallow_snapshot_test;
+mod signature_test;
 mod snapshot_at_test;
+mod snapshot_compression_test;
+mod squash_local_test;
+mod state_hash_test;
+mod stream_io_test;
+mod sync_test;
 mod text_update_test;
+mod timestamp_checkout_test;
 mod undo_test;
 
 fn gen_action(doc: &LoroDoc, seed: u64, mut ops_len: usize) {
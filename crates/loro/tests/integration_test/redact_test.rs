@@ -290,3 +290,114 @@ fn redact_should_keep_parent_child_relationship() {
     new_doc.import_json_updates(&redacted_json).unwrap();
     assert_eq!(new_doc.get_deep_value(), doc.get_deep_value());
 }
+
+#[test]
+fn redact_doc_matches_json_redaction() {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1).unwrap();
+    doc.get_text("text").insert(0, "public secret").unwrap();
+    doc.get_text("text")
+        .mark(0..6, "bold", "secret style")
+        .unwrap();
+    doc.get_map("map").insert("key", "secret value").unwrap();
+    let list = doc.get_list("list");
+    list.insert(0, "a").unwrap();
+    list.insert(1, "secret").unwrap();
+    let movable = doc.get_movable_list("movable");
+    movable.insert(0, "secret").unwrap();
+    movable.set(0, "also secret").unwrap();
+    doc.commit();
+    doc.get_text("text").delete(0, 2).unwrap();
+    doc.commit();
+
+    let mut range = VersionRange::new();
+    range.insert(1, 7, 30);
+    let redacted = doc.redact(&range).unwrap();
+    let mut json = doc.export_json_updates(&Default::default(), &doc.oplog_vv());
+    redact(&mut json, range).unwrap();
+    let expected = LoroDoc::new();
+    expected.import_json_updates(json).unwrap();
+
+    assert_eq!(redacted.get_deep_value(), expected.get_deep_value());
+    assert_ne!(redacted.get_deep_value(), doc.get_deep_value());
+    assert_eq!(redacted.oplog_vv(), doc.oplog_vv());
+    assert_eq!(redacted.len_ops(), doc.len_ops());
+    redacted.check_state_correctness_slow();
+    // The original doc is not changed
+    assert_eq!(doc.get_text("text").to_string(), "blic secret");
+}
+
+#[test]
+fn redact_doc_loaded_from_snapshot() {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1).unwrap();
+    doc.get_text("text")
+        .insert(0, "name: Alice Liddell")
+        .unwrap();
+    doc.get_map("user")
+        .insert("email", "alice@example.com")
+        .unwrap();
+    doc.commit();
+
+    // The state in the snapshot is derived from the content to redact
+    let loaded = LoroDoc::new();
+    loaded
+        .import(&doc.export(loro::ExportMode::Snapshot).unwrap())
+        .unwrap();
+    let mut range = VersionRange::new();
+    range.insert(1, 6, 20);
+    let redacted = loaded.redact(&range).unwrap();
+    let snapshot = redacted.export(loro::ExportMode::Snapshot).unwrap();
+    for secret in ["Alice", "alice@example.com"] {
+        assert!(!snapshot
+            .windows(secret.len())
+            .any(|w| w == secret.as_bytes()));
+    }
+
+    let new_doc = LoroDoc::new();
+    new_doc.import(&snapshot).unwrap();
+    assert_eq!(
+        new_doc.get_text("text").to_string(),
+        format!("name: {}", "\u{FFFD}".repeat(13))
+    );
+    assert_eq!(
+        new_doc.get_map("user").get_deep_value(),
+        LoroValue::Map(
+            [("email".to_string(), LoroValue::Null)]
+                .into_iter()
+                .collect()
+        )
+    );
+}
+
+#[test]
+fn redacted_doc_keeps_syncing() {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1).unwrap();
+    doc.get_text("text").insert(0, "secret").unwrap();
+    doc.commit();
+    let mut range = VersionRange::new();
+    range.insert(1, 0, 6);
+    let redacted = doc.redact(&range).unwrap();
+
+    // The ids are kept, so the new edits on the original doc apply to the redacted one
+    let vv = doc.oplog_vv();
+    doc.get_text("text").insert(6, " kept").unwrap();
+    doc.get_text("text").delete(0, 1).unwrap();
+    redacted
+        .import(&doc.export(loro::ExportMode::updates(&vv)).unwrap())
+        .unwrap();
+    assert_eq!(
+        redacted.get_text("text").to_string(),
+        format!("{} kept", "\u{FFFD}".repeat(5))
+    );
+
+    let shallow = LoroDoc::new();
+    shallow
+        .import(
+            &doc.export(loro::ExportMode::shallow_snapshot(&doc.oplog_frontiers()))
+                .unwrap(),
+        )
+        .unwrap();
+    assert!(shallow.redact(&range).is_err());
+}
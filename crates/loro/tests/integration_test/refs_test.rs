@@ -0,0 +1,145 @@
+use loro::{ExportMode, LoroDoc, LoroError, ToJson};
+
+#[test]
+fn branches_and_tags_persist_in_snapshot_and_sync() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1)?;
+    doc.get_text("text").insert(0, "Hello")?;
+    doc.commit();
+    let v1 = doc.state_frontiers();
+    doc.create_branch("draft", &v1)?;
+    doc.tag("v1.0", &v1)?;
+    assert_eq!(
+        doc.list_branches()?,
+        vec![("draft".to_string(), v1.clone())]
+    );
+    assert_eq!(doc.list_tags()?, vec![("v1.0".to_string(), v1.clone())]);
+
+    let snapshot = LoroDoc::new();
+    snapshot.import(&doc.export(ExportMode::Snapshot)?)?;
+    assert_eq!(snapshot.branch_frontiers("draft")?, Some(v1.clone()));
+    assert_eq!(snapshot.tag_frontiers("v1.0")?, Some(v1.clone()));
+
+    let peer = LoroDoc::new();
+    peer.import(&doc.export(ExportMode::all_updates())?)?;
+    assert_eq!(peer.list_branches()?, doc.list_branches()?);
+    assert_eq!(peer.list_tags()?, doc.list_tags()?);
+    Ok(())
+}
+
+#[test]
+fn refs_cannot_be_recreated() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    doc.get_text("text").insert(0, "Hello")?;
+    doc.commit();
+    let v = doc.state_frontiers();
+    doc.tag("release", &v)?;
+    assert_eq!(
+        doc.tag("release", &v),
+        Err(LoroError::RefAlreadyExists("release".into()))
+    );
+    doc.create_branch("main", &v)?;
+    assert!(doc.create_branch("main", &v).is_err());
+    assert_eq!(
+        doc.checkout_branch("missing"),
+        Err(LoroError::RefNotFound("missing".into()))
+    );
+    assert!(doc.create_branch("x", &loro::ID::new(9, 9).into()).is_err());
+    Ok(())
+}
+
+#[test]
+fn editing_on_branch_advances_it() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1)?;
+    let text = doc.get_text("text");
+    text.insert(0, "Hello")?;
+    doc.commit();
+    let base = doc.state_frontiers();
+    doc.create_branch("draft", &base)?;
+    text.insert(5, " world")?;
+    doc.commit();
+
+    doc.set_detached_editing(true);
+    doc.checkout_branch("draft")?;
+    assert_eq!(doc.current_branch(), Some("draft".to_string()));
+    assert_eq!(text.to_string(), "Hello");
+    text.insert(0, "Draft: ")?;
+    doc.commit();
+    let draft_head = doc.state_frontiers();
+    assert_eq!(doc.branch_frontiers("draft")?, Some(draft_head.clone()));
+    assert_eq!(text.to_string(), "Draft: Hello");
+
+    doc.checkout_to_latest();
+    assert_eq!(doc.current_branch(), None);
+    doc.checkout_branch("draft")?;
+    assert_eq!(text.to_string(), "Draft: Hello");
+    assert_eq!(doc.state_frontiers(), draft_head);
+    Ok(())
+}
+
+#[test]
+fn merge_branch_into_checked_out_branch() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1)?;
+    let map = doc.get_map("map");
+    map.insert("base", 0)?;
+    doc.commit();
+    let base = doc.state_frontiers();
+    doc.create_branch("a", &base)?;
+    doc.create_branch("b", &base)?;
+    doc.set_detached_editing(true);
+
+    doc.checkout_branch("a")?;
+    map.insert("a", 1)?;
+    doc.commit();
+    doc.checkout_branch("b")?;
+    assert!(map.get("a").is_none());
+    map.insert("b", 2)?;
+    doc.commit();
+
+    doc.checkout_branch("a")?;
+    doc.merge_branch("b")?;
+    assert_eq!(doc.current_branch(), Some("a".to_string()));
+    assert!(map.get("a").is_some());
+    assert!(map.get("b").is_some());
+    assert_eq!(doc.branch_frontiers("a")?, Some(doc.state_frontiers()));
+    Ok(())
+}
+
+#[test]
+fn reading_refs_does_not_edit_the_doc() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1)?;
+    let text = doc.get_text("text");
+    text.insert(0, "Hello")?;
+    doc.commit();
+    doc.create_branch("draft", &doc.state_frontiers())?;
+    doc.tag("v1.0", &doc.state_frontiers())?;
+    assert_eq!(
+        doc.get_deep_value().to_json_value(),
+        serde_json::json!({"text": "Hello"})
+    );
+
+    doc.set_detached_editing(true);
+    doc.checkout_branch("draft")?;
+    text.insert(5, "!")?;
+    doc.commit();
+    let v1 = doc.tag_frontiers("v1.0")?.unwrap();
+    // The reads don't commit the pending changes of the detached doc
+    text.insert(6, "?")?;
+    let peer = doc.peer_id();
+    let oplog_frontiers = doc.oplog_frontiers();
+    let state_frontiers = doc.state_frontiers();
+    assert_eq!(doc.list_branches()?.len(), 1);
+    assert_eq!(doc.list_tags()?, vec![("v1.0".to_string(), v1.clone())]);
+    assert_eq!(doc.tag_frontiers("v1.0")?, Some(v1));
+    assert_eq!(doc.branch_frontiers("draft")?, Some(doc.state_frontiers()));
+    assert_eq!(doc.get_pending_txn_len(), 1);
+    assert_eq!(doc.peer_id(), peer);
+    assert_eq!(doc.oplog_frontiers(), oplog_frontiers);
+    assert_eq!(doc.state_frontiers(), state_frontiers);
+    doc.commit();
+    assert_eq!(text.to_string(), "Hello!?");
+    Ok(())
+}
@@ -0,0 +1,129 @@
+use loro::{ExportMode, LoroDoc, ToJson, TreeParentId};
+use serde_json::json;
+
+#[test]
+fn revert_to_restores_text_list_and_map() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1)?;
+    let text = doc.get_text("text");
+    let list = doc.get_list("list");
+    let map = doc.get_map("map");
+    text.insert(0, "Hello world")?;
+    text.mark(0..5, "bold", true)?;
+    list.push(1)?;
+    list.push(2)?;
+    map.insert("a", 1)?;
+    doc.commit();
+    let v1 = doc.state_frontiers();
+    let value_v1 = doc.get_deep_value();
+    let delta_v1 = text.to_delta();
+
+    text.unmark(0..3, "bold")?;
+    text.delete(0, 6)?;
+    text.insert(0, "Bye ")?;
+    list.delete(0, 1)?;
+    list.push(3)?;
+    map.insert("a", 2)?;
+    map.insert("b", "new")?;
+    doc.commit();
+    let len_before_revert = doc.len_changes();
+
+    doc.revert_to(&v1)?;
+    assert!(!doc.is_detached());
+    assert_eq!(doc.get_deep_value(), value_v1);
+    assert_eq!(text.to_delta(), delta_v1);
+    // The revert is recorded as new history
+    assert!(doc.len_changes() > len_before_revert);
+    doc.check_state_correctness_slow();
+    Ok(())
+}
+
+#[test]
+fn revert_to_restores_movable_list_and_tree() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1)?;
+    let list = doc.get_movable_list("list");
+    list.push("a")?;
+    list.push("b")?;
+    list.push("c")?;
+    let tree = doc.get_tree("tree");
+    let root = tree.create(TreeParentId::Root)?;
+    let child = tree.create(root)?;
+    tree.get_meta(child)?.insert("name", "child")?;
+    doc.commit();
+    let v1 = doc.state_frontiers();
+    let value_v1 = doc.get_deep_value();
+
+    list.mov(0, 2)?;
+    list.set(1, "x")?;
+    tree.mov(child, TreeParentId::Root)?;
+    tree.delete(root)?;
+    doc.commit();
+
+    doc.revert_to(&v1)?;
+    assert_eq!(
+        doc.get_deep_value().to_json_value()["list"],
+        value_v1.to_json_value()["list"]
+    );
+    assert_eq!(list.get_value().to_json_value(), json!(["a", "b", "c"]));
+    let roots = tree.roots();
+    assert_eq!(roots.len(), 1);
+    let children = tree.children(roots[0]).unwrap();
+    assert_eq!(children.len(), 1);
+    assert_eq!(
+        tree.get_meta(children[0])?.get_value().to_json_value(),
+        json!({"name": "child"})
+    );
+    doc.check_state_correctness_slow();
+    Ok(())
+}
+
+#[cfg(feature = "counter")]
+#[test]
+fn revert_to_restores_counter() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    let counter = doc.get_counter("counter");
+    counter.increment(5.)?;
+    doc.commit();
+    let v1 = doc.state_frontiers();
+    counter.increment(10.)?;
+    counter.decrement(1.)?;
+    doc.commit();
+    doc.revert_to(&v1)?;
+    assert_eq!(counter.get(), 5.);
+    Ok(())
+}
+
+#[test]
+fn revert_to_merges_with_concurrent_edits() -> anyhow::Result<()> {
+    let doc_a = LoroDoc::new();
+    doc_a.set_peer_id(1)?;
+    doc_a.get_text("text").insert(0, "Hello")?;
+    doc_a.commit();
+    let v1 = doc_a.state_frontiers();
+    doc_a.get_text("text").insert(5, " world")?;
+    doc_a.commit();
+
+    let doc_b = LoroDoc::new();
+    doc_b.set_peer_id(2)?;
+    doc_b.import(&doc_a.export(ExportMode::all_updates())?)?;
+
+    doc_a.revert_to(&v1)?;
+    doc_b.get_text("text").insert(0, "> ")?;
+    doc_b.commit();
+
+    doc_a.import(&doc_b.export(ExportMode::updates(&doc_a.oplog_vv()))?)?;
+    doc_b.import(&doc_a.export(ExportMode::updates(&doc_b.oplog_vv()))?)?;
+    assert_eq!(doc_a.get_deep_value(), doc_b.get_deep_value());
+    assert_eq!(doc_a.get_text("text").to_string(), "> Hello");
+    Ok(())
+}
+
+#[test]
+fn revert_to_unknown_version_fails() {
+    let doc = LoroDoc::new();
+    doc.get_text("text").insert(0, "Hello").unwrap();
+    doc.commit();
+    assert!(doc.revert_to(&loro::ID::new(100, 0).into()).is_err());
+    assert_eq!(doc.get_text("text").to_string(), "Hello");
+}
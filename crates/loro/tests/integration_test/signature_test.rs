@@ -0,0 +1,213 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    sync::{Arc, Mutex},
+};
+
+use loro::{
+    ChangeSigner, ChangeVerifier, ExportMode, LoroDoc, LoroError, ID, SIGN_PAYLOAD_VERSION,
+};
+
+/// A keyed hash standing in for a real signature scheme
+fn sign(secret: u64, bytes: &[u8]) -> Vec<u8> {
+    let mut hasher = DefaultHasher::new();
+    secret.hash(&mut hasher);
+    bytes.hash(&mut hasher);
+    hasher.finish().to_le_bytes().to_vec()
+}
+
+fn signer(secret: u64) -> ChangeSigner {
+    ChangeSigner::new(move |payload| sign(secret, payload.bytes))
+}
+
+fn verifier(secret: u64) -> ChangeVerifier {
+    ChangeVerifier::new(move |payload, signature| {
+        signature == Some(sign(secret, payload.bytes).as_slice())
+    })
+}
+
+fn new_signed_doc(peer: u64, secret: u64) -> anyhow::Result<LoroDoc> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(peer)?;
+    doc.set_change_merge_interval(0);
+    doc.set_change_signer(Some(signer(secret)));
+    let text = doc.get_text("text");
+    for i in 0..10 {
+        text.insert(0, &i.to_string())?;
+        doc.get_map("map").insert(&format!("key{}", i % 3), i)?;
+        doc.commit();
+    }
+    Ok(doc)
+}
+
+#[test]
+fn signed_updates() -> anyhow::Result<()> {
+    let doc = new_signed_doc(1, 7)?;
+    let new_doc = LoroDoc::new();
+    new_doc.set_change_verifier(Some(verifier(7)));
+    new_doc.import(&doc.export(ExportMode::all_updates())?)?;
+    assert_eq!(new_doc.get_deep_value(), doc.get_deep_value());
+
+    // The verified doc can still import its own history
+    new_doc.import(&doc.export(ExportMode::all_updates())?)?;
+
+    let other = new_signed_doc(2, 8)?;
+    let updates = other.export(ExportMode::all_updates())?;
+    assert_eq!(
+        new_doc.import(&updates),
+        Err(LoroError::InvalidChangeSignature(ID::new(2, 0)))
+    );
+    assert_eq!(new_doc.oplog_vv(), doc.oplog_vv());
+    assert_eq!(new_doc.get_deep_value(), doc.get_deep_value());
+
+    new_doc.set_change_verifier(None);
+    new_doc.import(&updates)?;
+    assert_eq!(new_doc.len_changes(), 20);
+    Ok(())
+}
+
+#[test]
+fn unsigned_changes_are_rejected() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1)?;
+    doc.get_text("text").insert(0, "unsigned")?;
+    doc.commit();
+    doc.set_change_signer(Some(signer(7)));
+    doc.get_text("text").insert(0, "signed ")?;
+    doc.commit();
+
+    let new_doc = LoroDoc::new();
+    new_doc.set_change_verifier(Some(verifier(7)));
+    assert_eq!(
+        new_doc.import(&doc.export(ExportMode::all_updates())?),
+        Err(LoroError::InvalidChangeSignature(ID::new(1, 0)))
+    );
+    assert!(new_doc.oplog_vv().is_empty());
+
+    // JSON updates carry no signatures
+    let json = doc.export_json_updates(&Default::default(), &doc.oplog_vv());
+    assert!(matches!(
+        new_doc.import_json_updates(json),
+        Err(LoroError::InvalidChangeSignature(_))
+    ));
+    Ok(())
+}
+
+#[test]
+fn signed_changes_are_not_merged() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1)?;
+    doc.set_change_signer(Some(signer(7)));
+    let text = doc.get_text("text");
+    text.insert(0, "a")?;
+    doc.commit();
+    text.insert(1, "b")?;
+    doc.commit();
+    assert_eq!(doc.len_changes(), 2);
+    Ok(())
+}
+
+#[test]
+fn signed_snapshot() -> anyhow::Result<()> {
+    let doc = new_signed_doc(1, 7)?;
+    let snapshot = doc.export(ExportMode::Snapshot)?;
+
+    let new_doc = LoroDoc::new();
+    new_doc.set_change_verifier(Some(verifier(7)));
+    new_doc.import(&snapshot)?;
+    assert_eq!(new_doc.get_deep_value(), doc.get_deep_value());
+    new_doc.check_state_correctness_slow();
+
+    // The signatures are kept in the imported doc
+    let exported = new_doc.export(ExportMode::Snapshot)?;
+    let reader_doc = LoroDoc::new();
+    reader_doc.set_change_verifier(Some(verifier(7)));
+    reader_doc.import_from_reader(exported.as_slice())?;
+    assert_eq!(reader_doc.get_deep_value(), doc.get_deep_value());
+
+    let other = new_signed_doc(2, 7)?;
+    other.set_change_verifier(Some(verifier(7)));
+    other.import(&snapshot)?;
+    assert_eq!(other.len_changes(), 20);
+
+    let wrong = LoroDoc::new();
+    wrong.set_change_verifier(Some(verifier(8)));
+    assert_eq!(
+        wrong.import(&snapshot),
+        Err(LoroError::InvalidChangeSignature(ID::new(1, 0)))
+    );
+    assert!(wrong.oplog_vv().is_empty());
+    assert!(wrong.get_text("text").is_empty());
+    Ok(())
+}
+
+#[test]
+fn squash_local_resigns() -> anyhow::Result<()> {
+    let doc = new_signed_doc(1, 7)?;
+    let since = doc.oplog_frontiers();
+    let text = doc.get_text("text");
+    for i in 0..5 {
+        text.insert(0, &i.to_string())?;
+        doc.commit();
+    }
+    doc.squash_local(&since, Some("squashed"))?;
+    assert_eq!(doc.len_changes(), 11);
+
+    let new_doc = LoroDoc::new();
+    new_doc.set_change_verifier(Some(verifier(7)));
+    new_doc.import(&doc.export(ExportMode::all_updates())?)?;
+    assert_eq!(new_doc.get_deep_value(), doc.get_deep_value());
+    Ok(())
+}
+
+#[test]
+fn shallow_snapshot_cannot_be_verified() -> anyhow::Result<()> {
+    let doc = new_signed_doc(1, 7)?;
+    let shallow = doc.export(ExportMode::shallow_snapshot(&doc.oplog_frontiers()))?;
+    let new_doc = LoroDoc::new();
+    new_doc.set_change_verifier(Some(verifier(7)));
+    assert_eq!(
+        new_doc.import(&shallow),
+        Err(LoroError::UnverifiableShallowSnapshot)
+    );
+    assert!(new_doc.oplog_vv().is_empty());
+    Ok(())
+}
+
+#[test]
+fn sign_payload_does_not_depend_on_the_store() -> anyhow::Result<()> {
+    let signed = Arc::new(Mutex::new(Vec::new()));
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1)?;
+    doc.set_change_signer(Some(ChangeSigner::new({
+        let signed = signed.clone();
+        move |payload| {
+            signed.lock().unwrap().push(payload.bytes.to_vec());
+            sign(7, payload.bytes)
+        }
+    })));
+    doc.get_map("map").insert("key", 1)?;
+    doc.set_next_commit_message("msg");
+    doc.commit();
+
+    // The payload is the same after the change goes through a snapshot of another doc
+    let relay = LoroDoc::new();
+    relay.import(&doc.export(ExportMode::Snapshot)?)?;
+    let verified = Arc::new(Mutex::new(Vec::new()));
+    let new_doc = LoroDoc::new();
+    new_doc.set_change_verifier(Some(ChangeVerifier::new({
+        let verified = verified.clone();
+        move |payload, signature| {
+            verified.lock().unwrap().push(payload.bytes.to_vec());
+            signature == Some(sign(7, payload.bytes).as_slice())
+        }
+    })));
+    new_doc.import(&relay.export(ExportMode::all_updates())?)?;
+
+    let signed = signed.lock().unwrap();
+    assert_eq!(signed.len(), 1);
+    assert_eq!(*signed, *verified.lock().unwrap());
+    assert_eq!(signed[0][0], SIGN_PAYLOAD_VERSION);
+    assert!(signed[0].windows(3).any(|w| w == b"msg"));
+    Ok(())
+}
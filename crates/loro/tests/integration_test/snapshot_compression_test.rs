@@ -0,0 +1,136 @@
+use loro::{CompressionType, ExportMode, LoroDoc};
+
+fn new_doc_with_history(peer: u64) -> anyhow::Result<LoroDoc> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(peer)?;
+    doc.set_change_merge_interval(0);
+    let map = doc.get_map("map");
+    let text = doc.get_text("text");
+    for i in 0..200 {
+        map.insert(
+            &format!("user-{}", i % 20),
+            format!("user-{}@example.com", i),
+        )?;
+        text.insert(text.len_unicode(), "Hello world. ")?;
+        doc.commit();
+    }
+    Ok(doc)
+}
+
+#[test]
+fn uncompressed_snapshot() -> anyhow::Result<()> {
+    let doc = new_doc_with_history(1)?;
+    let lz4 = doc.export(ExportMode::Snapshot)?;
+    doc.set_snapshot_compression(CompressionType::None, None)?;
+    let uncompressed = doc.export(ExportMode::Snapshot)?;
+    assert!(uncompressed.len() > lz4.len());
+    // Readers without the zstd support can still import it
+    assert_eq!(uncompressed[20..22], lz4[20..22]);
+
+    let new_doc = LoroDoc::new();
+    new_doc.import(&uncompressed)?;
+    assert_eq!(new_doc.get_deep_value(), doc.get_deep_value());
+    assert_eq!(new_doc.oplog_vv(), doc.oplog_vv());
+    Ok(())
+}
+
+#[cfg(not(feature = "zstd"))]
+#[test]
+fn zstd_requires_the_feature() -> anyhow::Result<()> {
+    let doc = new_doc_with_history(1)?;
+    assert!(doc
+        .set_snapshot_compression(CompressionType::Zstd { level: 3 }, None)
+        .is_err());
+    Ok(())
+}
+
+#[cfg(feature = "zstd")]
+mod zstd {
+    use super::new_doc_with_history;
+    use loro::{CompressionDict, CompressionType, ExportMode, LoroDoc, LoroError};
+
+    #[test]
+    fn zstd_snapshot() -> anyhow::Result<()> {
+        let doc = new_doc_with_history(1)?;
+        let lz4 = doc.export(ExportMode::Snapshot)?;
+        doc.set_snapshot_compression(CompressionType::Zstd { level: 19 }, None)?;
+        let zstd = doc.export(ExportMode::Snapshot)?;
+        assert!(zstd.len() < lz4.len());
+        // Old readers reject the new encode mode with `IncompatibleFutureEncodingError`
+        assert_ne!(zstd[20..22], lz4[20..22]);
+
+        let new_doc = LoroDoc::new();
+        new_doc.import(&zstd)?;
+        assert_eq!(new_doc.get_deep_value(), doc.get_deep_value());
+        assert_eq!(new_doc.oplog_vv(), doc.oplog_vv());
+        new_doc.check_state_correctness_slow();
+        assert!(LoroDoc::decode_import_blob_meta(&zstd, true)?
+            .mode
+            .is_snapshot());
+
+        // Import into a doc with its own history
+        let other = new_doc_with_history(2)?;
+        other.import(&zstd)?;
+        assert_eq!(other.oplog_vv().get(&1), doc.oplog_vv().get(&1));
+
+        // The other snapshot modes use the same compression
+        let f = doc.oplog_frontiers();
+        for mode in [
+            ExportMode::shallow_snapshot(&f),
+            ExportMode::state_only(None),
+            ExportMode::snapshot_at(&f),
+        ] {
+            let new_doc = LoroDoc::new();
+            new_doc.import(&doc.export(mode)?)?;
+            assert_eq!(new_doc.get_deep_value(), doc.get_deep_value());
+        }
+
+        let mut buf = Vec::new();
+        doc.export_to_writer(ExportMode::Snapshot, &mut buf)?;
+        assert_eq!(buf, zstd);
+        let new_doc = LoroDoc::new();
+        new_doc.import_from_reader(buf.as_slice())?;
+        assert_eq!(new_doc.get_deep_value(), doc.get_deep_value());
+        Ok(())
+    }
+
+    #[test]
+    fn zstd_snapshot_with_dict() -> anyhow::Result<()> {
+        // Train the dictionary with uncompressed snapshots
+        let mut samples = Vec::new();
+        for peer in 0..20 {
+            let doc = new_doc_with_history(peer)?;
+            doc.set_snapshot_compression(CompressionType::None, None)?;
+            samples.push(doc.export(ExportMode::Snapshot)?);
+        }
+        let dict = CompressionDict::train(&samples, 16 * 1024)?;
+
+        let doc = new_doc_with_history(100)?;
+        doc.set_snapshot_compression(
+            CompressionType::ZstdWithDict { level: 3 },
+            Some(dict.clone()),
+        )?;
+        let snapshot = doc.export(ExportMode::Snapshot)?;
+
+        let without_dict = LoroDoc::new();
+        assert!(matches!(
+            without_dict.import(&snapshot),
+            Err(LoroError::DecodeError(_))
+        ));
+        assert_eq!(without_dict.len_changes(), 0);
+        assert!(LoroDoc::decode_import_blob_meta(&snapshot, true).is_err());
+        let meta =
+            LoroDoc::decode_import_blob_meta_with(&snapshot, true, Some(dict.clone()), None)?;
+        assert_eq!(meta.partial_end_vv, doc.oplog_vv());
+
+        let new_doc = LoroDoc::new();
+        new_doc.set_snapshot_compression(CompressionType::LZ4, Some(dict))?;
+        new_doc.import(&snapshot)?;
+        assert_eq!(new_doc.get_deep_value(), doc.get_deep_value());
+
+        // The forked doc keeps the dictionary
+        let fork = doc.fork_at(&doc.oplog_frontiers());
+        assert_eq!(fork.get_deep_value(), doc.get_deep_value());
+        Ok(())
+    }
+}
@@ -0,0 +1,149 @@
+use loro::{ExportMode, LoroDoc, ID};
+
+fn new_doc(peer: u64) -> anyhow::Result<LoroDoc> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(peer)?;
+    doc.set_change_merge_interval(0);
+    Ok(doc)
+}
+
+#[test]
+fn squash_local_keeps_state_and_merges_changes() -> anyhow::Result<()> {
+    let doc = new_doc(1)?;
+    let text = doc.get_text("text");
+    let list = doc.get_list("list");
+    text.insert(0, "Hello")?;
+    doc.set_next_commit_message("init");
+    doc.commit();
+    let since = doc.oplog_frontiers();
+    for i in 0..100 {
+        text.insert(text.len_unicode(), "a")?;
+        list.push(i)?;
+        doc.commit();
+    }
+    doc.compact_change_store();
+    assert_eq!(doc.len_changes(), 101);
+    let value = doc.get_deep_value();
+    let len_ops = doc.len_ops();
+
+    doc.squash_local(&since, Some("offline edits"))?;
+    assert_eq!(doc.len_changes(), 2);
+    assert_eq!(doc.len_ops(), len_ops);
+    assert_eq!(doc.get_deep_value(), value);
+    let init = doc.get_change(ID::new(1, 0)).unwrap();
+    assert_eq!(init.message.as_deref(), Some("init"));
+    assert_eq!(init.len, 5);
+    let squashed = doc.get_change(ID::new(1, 5)).unwrap();
+    assert_eq!(squashed.message.as_deref(), Some("offline edits"));
+    assert_eq!(squashed.len, 200);
+    doc.check_state_correctness_slow();
+
+    // The squashed history can be exported and edited as usual
+    text.insert(0, "> ")?;
+    doc.commit();
+    let other = LoroDoc::new();
+    other.import(&doc.export(ExportMode::Snapshot)?)?;
+    assert_eq!(other.get_deep_value(), doc.get_deep_value());
+    assert_eq!(other.len_changes(), 3);
+    let other = LoroDoc::new();
+    other.import(&doc.export(ExportMode::all_updates())?)?;
+    assert_eq!(other.get_deep_value(), doc.get_deep_value());
+    Ok(())
+}
+
+#[test]
+fn squash_local_splits_the_change_at_since() -> anyhow::Result<()> {
+    let doc = new_doc(1)?;
+    let text = doc.get_text("text");
+    text.insert(0, "abc")?;
+    doc.commit();
+    text.insert(3, "d")?;
+    doc.commit();
+    doc.squash_local(&ID::new(1, 0).into(), Some("squash"))?;
+    assert_eq!(doc.len_changes(), 2);
+    assert_eq!(doc.get_change(ID::new(1, 0)).unwrap().len, 1);
+    let squashed = doc.get_change(ID::new(1, 1)).unwrap();
+    assert_eq!(squashed.len, 3);
+    assert_eq!(squashed.message.as_deref(), Some("squash"));
+    assert_eq!(text.to_string(), "abcd");
+    Ok(())
+}
+
+#[test]
+fn squash_local_keeps_deps_on_imported_changes() -> anyhow::Result<()> {
+    let doc = new_doc(1)?;
+    let remote = new_doc(2)?;
+    let text = doc.get_text("text");
+    text.insert(0, "a")?;
+    doc.commit();
+    let since = doc.oplog_frontiers();
+    remote.import(&doc.export(ExportMode::all_updates())?)?;
+
+    text.insert(1, "b")?;
+    doc.commit();
+    text.insert(2, "c")?;
+    doc.commit();
+    remote.get_text("text").insert(0, "r")?;
+    remote.commit();
+    doc.import(&remote.export(ExportMode::updates(&doc.oplog_vv()))?)?;
+    text.insert(0, "d")?;
+    doc.commit();
+    text.insert(0, "e")?;
+    doc.commit();
+    let value = doc.get_deep_value();
+
+    doc.squash_local(&since, None)?;
+    // The local change that depends on the remote change cannot be merged with the previous one
+    assert_eq!(doc.len_changes(), 4);
+    assert_eq!(doc.get_change(ID::new(1, 1)).unwrap().len, 2);
+    assert_eq!(doc.get_change(ID::new(1, 3)).unwrap().len, 2);
+    assert_eq!(doc.get_deep_value(), value);
+
+    remote.import(&doc.export(ExportMode::updates(&remote.oplog_vv()))?)?;
+    assert_eq!(remote.get_deep_value(), value);
+    Ok(())
+}
+
+#[test]
+fn squash_local_rejects_shared_changes() -> anyhow::Result<()> {
+    let doc = new_doc(1)?;
+    let remote = new_doc(2)?;
+    let text = doc.get_text("text");
+    text.insert(0, "a")?;
+    doc.commit();
+    let since = doc.oplog_frontiers();
+    text.insert(1, "b")?;
+    doc.commit();
+    remote.import(&doc.export(ExportMode::all_updates())?)?;
+    remote.get_text("text").insert(2, "r")?;
+    remote.commit();
+    doc.import(&remote.export(ExportMode::all_updates())?)?;
+
+    assert!(doc.squash_local(&since, None).is_err());
+    assert!(doc.squash_local(&ID::new(3, 0).into(), None).is_err());
+    assert_eq!(doc.len_changes(), 3);
+    Ok(())
+}
+
+#[test]
+fn squash_local_caps_the_size_of_squashed_changes() -> anyhow::Result<()> {
+    let doc = new_doc(1)?;
+    let text = doc.get_text("text");
+    for _ in 0..100 {
+        text.insert(text.len_unicode(), &"a".repeat(100))?;
+        doc.commit();
+    }
+    let value = doc.get_deep_value();
+
+    doc.squash_local(&Default::default(), Some("offline edits"))?;
+    let len_changes = doc.len_changes();
+    assert!(len_changes > 1 && len_changes < 100, "{}", len_changes);
+    let mut counter = 0;
+    while counter < 10_000 {
+        let change = doc.get_change(ID::new(1, counter)).unwrap();
+        assert_eq!(change.message.as_deref(), Some("offline edits"));
+        counter += change.len as i32;
+    }
+    assert_eq!(doc.get_deep_value(), value);
+    Ok(())
+}
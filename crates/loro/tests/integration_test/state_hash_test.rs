@@ -0,0 +1,141 @@
+use loro::{
+    ContainerTrait, ExportMode, LoroDoc, LoroError, LoroList, LoroMap, LoroMovableList, LoroText,
+};
+
+use super::gen_action;
+
+fn new_doc(peer: u64) -> anyhow::Result<LoroDoc> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(peer)?;
+    doc.set_change_merge_interval(0);
+    Ok(doc)
+}
+
+fn sync(a: &LoroDoc, b: &LoroDoc) -> anyhow::Result<()> {
+    a.import(&b.export(ExportMode::updates(&a.oplog_vv()))?)?;
+    b.import(&a.export(ExportMode::updates(&b.oplog_vv()))?)?;
+    Ok(())
+}
+
+#[test]
+fn converged_peers_have_the_same_hash() -> anyhow::Result<()> {
+    let a = new_doc(1)?;
+    let b = new_doc(2)?;
+    gen_action(&a, 1, 50);
+    gen_action(&b, 2, 50);
+    let rich = a.get_text("rich");
+    rich.insert(0, "Hello")?;
+    rich.mark(0..3, "bold", true)?;
+    let tree = b.get_tree("tree");
+    let root = tree.create(None)?;
+    tree.get_meta(root)?.insert("name", "root")?;
+    tree.create(root)?;
+    let list = b
+        .get_map("root")
+        .insert_container("list", LoroMovableList::new())?;
+    list.push(1)?;
+    list.push(2)?;
+    list.mov(0, 1)?;
+    a.commit();
+    b.commit();
+    assert_ne!(a.state_hash(), b.state_hash());
+
+    sync(&a, &b)?;
+    assert_eq!(a.get_deep_value(), b.get_deep_value());
+    assert_eq!(a.state_hash(), b.state_hash());
+
+    // The docs loaded from the snapshots have different internal layouts
+    let snapshot = LoroDoc::new();
+    snapshot.import(&a.export(ExportMode::Snapshot)?)?;
+    assert_eq!(snapshot.state_hash(), a.state_hash());
+    let shallow = LoroDoc::new();
+    shallow.import(&a.export(ExportMode::shallow_snapshot(&a.oplog_frontiers()))?)?;
+    assert_eq!(shallow.state_hash(), a.state_hash());
+    Ok(())
+}
+
+#[test]
+fn hash_is_independent_of_history() -> anyhow::Result<()> {
+    let a = new_doc(1)?;
+    a.get_text("text").insert(0, "Hello world")?;
+    a.get_text("text").delete(5, 6)?;
+    a.get_map("map").insert("b", 2)?;
+    a.get_map("map").insert("a", 1)?;
+    a.commit();
+
+    let b = new_doc(2)?;
+    b.get_map("map").insert("a", 0)?;
+    b.get_map("map").insert("a", 1)?;
+    b.get_map("map").insert("b", 2)?;
+    b.get_text("text").insert(0, "Hello")?;
+    // The empty root containers are skipped
+    b.get_list("empty");
+    b.commit();
+    assert_eq!(a.state_hash(), b.state_hash());
+
+    // The refs are not part of the doc value, so they are skipped too
+    a.tag("v1", &a.oplog_frontiers())?;
+    assert_eq!(a.state_hash(), b.state_hash());
+    assert_eq!(a.merkle_root().children, b.merkle_root().children);
+    Ok(())
+}
+
+#[test]
+fn different_states_have_different_hashes() -> anyhow::Result<()> {
+    let a = new_doc(1)?;
+    let b = new_doc(1)?;
+    a.get_text("text").insert(0, "Hello")?;
+    b.get_text("text").insert(0, "Hello")?;
+    assert_eq!(a.state_hash(), b.state_hash());
+
+    // The styles of the text are included
+    a.get_text("text").mark(0..2, "bold", true)?;
+    assert_ne!(a.state_hash(), b.state_hash());
+    b.get_text("text").mark(0..2, "bold", true)?;
+    assert_eq!(a.state_hash(), b.state_hash());
+
+    // The values are typed
+    a.get_map("map").insert("v", 1)?;
+    b.get_map("map").insert("v", 1.0)?;
+    assert_ne!(a.state_hash(), b.state_hash());
+    b.get_map("map").insert("v", 1)?;
+    assert_eq!(a.state_hash(), b.state_hash());
+
+    // The container ids are included
+    a.get_list("list").insert_container(0, LoroMap::new())?;
+    b.get_list("list").push("padding")?;
+    b.get_list("list").delete(0, 1)?;
+    b.get_list("list").insert_container(0, LoroMap::new())?;
+    assert_eq!(a.get_deep_value(), b.get_deep_value());
+    assert_ne!(a.state_hash(), b.state_hash());
+    Ok(())
+}
+
+#[test]
+fn container_state_hash() -> anyhow::Result<()> {
+    let a = new_doc(1)?;
+    let b = new_doc(2)?;
+    let list = a.get_list("list");
+    let text = list.insert_container(0, LoroText::new())?;
+    text.insert(0, "Hello")?;
+    a.get_map("map").insert("key", "value")?;
+    a.commit();
+    sync(&a, &b)?;
+
+    let hash = list.state_hash()?;
+    assert_eq!(b.get_list("list").state_hash()?, hash);
+    assert_ne!(a.get_map("map").state_hash()?, hash);
+
+    // The hash of the parent changes with the child
+    b.get_text(text.id()).insert(5, " world")?;
+    b.commit();
+    sync(&a, &b)?;
+    assert_ne!(list.state_hash()?, hash);
+    assert_eq!(list.state_hash()?, b.get_list("list").state_hash()?);
+
+    assert!(matches!(
+        LoroList::new().state_hash(),
+        Err(LoroError::MisuseDetachedContainer { .. })
+    ));
+    Ok(())
+}
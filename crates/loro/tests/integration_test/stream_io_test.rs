@@ -0,0 +1,181 @@
+use std::io::Write;
+
+use loro::{ExportMode, Frontiers, LoroDoc, LoroEncodeError, LoroError, ID};
+
+fn new_doc_with_history(peer: u64) -> anyhow::Result<LoroDoc> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(peer)?;
+    doc.set_change_merge_interval(0);
+    let text = doc.get_text("text");
+    let list = doc.get_list("list");
+    for i in 0..100 {
+        text.insert(0, &i.to_string())?;
+        list.push(i)?;
+        doc.get_map("map").insert(&format!("key{}", i % 10), i)?;
+        doc.commit();
+    }
+    Ok(doc)
+}
+
+fn export_to_vec(doc: &LoroDoc, mode: ExportMode) -> Result<Vec<u8>, LoroEncodeError> {
+    let mut buf = Vec::new();
+    doc.export_to_writer(mode, &mut buf)?;
+    Ok(buf)
+}
+
+#[test]
+fn export_to_writer_imports_like_export() -> anyhow::Result<()> {
+    let doc = new_doc_with_history(1)?;
+    let mut vv = doc.oplog_vv();
+    vv.insert(1, 50);
+    let base = LoroDoc::new();
+    base.import(&doc.export(ExportMode::updates_till(&vv))?)?;
+    for (mode, from) in [
+        (ExportMode::Snapshot, LoroDoc::new()),
+        (ExportMode::all_updates(), LoroDoc::new()),
+        (ExportMode::updates(&vv), base.fork()),
+        (ExportMode::Snapshot, base.fork()),
+    ] {
+        let bytes = export_to_vec(&doc, mode.clone())?;
+        let expected = from.fork();
+        expected.import(&doc.export(mode)?)?;
+        let by_import = from.fork();
+        by_import.import(&bytes)?;
+        let by_reader = from.fork();
+        by_reader.import_from_reader(bytes.as_slice())?;
+        for new_doc in [by_import, by_reader] {
+            assert_eq!(new_doc.get_deep_value(), expected.get_deep_value());
+            assert_eq!(new_doc.oplog_vv(), expected.oplog_vv());
+            new_doc.check_state_correctness_slow();
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn import_exported_blobs_from_reader() -> anyhow::Result<()> {
+    let doc = new_doc_with_history(1)?;
+    let other = new_doc_with_history(2)?;
+    for mode in [ExportMode::Snapshot, ExportMode::all_updates()] {
+        let bytes = doc.export(mode)?;
+        for from in [LoroDoc::new(), other.fork()] {
+            let expected = from.fork();
+            expected.import(&bytes)?;
+            from.import_from_reader(bytes.as_slice())?;
+            assert_eq!(from.get_deep_value(), expected.get_deep_value());
+            assert_eq!(from.oplog_vv(), expected.oplog_vv());
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn decode_import_blob_meta_of_written_blobs() -> anyhow::Result<()> {
+    let doc = new_doc_with_history(1)?;
+    for mode in [ExportMode::Snapshot, ExportMode::all_updates()] {
+        let bytes = export_to_vec(&doc, mode.clone())?;
+        let meta = LoroDoc::decode_import_blob_meta(&bytes, true)?;
+        let expected = LoroDoc::decode_import_blob_meta(&doc.export(mode)?, true)?;
+        assert_eq!(meta.mode, expected.mode);
+        assert_eq!(meta.partial_end_vv, expected.partial_end_vv);
+        assert_eq!(meta.change_num, expected.change_num);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn export_to_writer_in_snapshot_modes() -> anyhow::Result<()> {
+    let doc = new_doc_with_history(1)?;
+    let frontiers = doc.oplog_frontiers();
+    let mid: Frontiers = ID::new(1, 150).into();
+    for mode in [
+        ExportMode::shallow_snapshot(&mid),
+        ExportMode::state_only(None),
+        ExportMode::snapshot_at(&mid),
+    ] {
+        let bytes = export_to_vec(&doc, mode.clone())?;
+        let expected = LoroDoc::new();
+        expected.import(&doc.export(mode)?)?;
+        let new_doc = LoroDoc::new();
+        new_doc.import_from_reader(bytes.as_slice())?;
+        assert_eq!(new_doc.get_deep_value(), expected.get_deep_value());
+        assert_eq!(new_doc.oplog_frontiers(), expected.oplog_frontiers());
+    }
+
+    assert_eq!(doc.oplog_frontiers(), frontiers);
+    assert_eq!(doc.state_frontiers(), frontiers);
+    Ok(())
+}
+
+#[test]
+fn import_from_reader() -> anyhow::Result<()> {
+    let doc = new_doc_with_history(1)?;
+    let snapshot = export_to_vec(&doc, ExportMode::Snapshot)?;
+    let new_doc = LoroDoc::new();
+    let status = new_doc.import_from_reader(snapshot.as_slice())?;
+    assert_eq!(status.pending, None);
+    assert_eq!(new_doc.get_deep_value(), doc.get_deep_value());
+    assert_eq!(new_doc.oplog_vv(), doc.oplog_vv());
+    new_doc.check_state_correctness_slow();
+
+    // Import into a doc that has its own history
+    let other = new_doc_with_history(2)?;
+    other.import_from_reader(snapshot.as_slice())?;
+    let updates = export_to_vec(&other, ExportMode::updates(&doc.oplog_vv()))?;
+    doc.import_from_reader(updates.as_slice())?;
+    assert_eq!(doc.get_deep_value(), other.get_deep_value());
+
+    // The doc can still be edited after importing from the reader
+    new_doc.get_text("text").insert(0, "new")?;
+    new_doc.commit();
+    assert_eq!(new_doc.len_changes(), 101);
+    Ok(())
+}
+
+#[test]
+fn import_from_reader_rejects_invalid_data() -> anyhow::Result<()> {
+    let doc = new_doc_with_history(1)?;
+    let mut snapshot = export_to_vec(&doc, ExportMode::Snapshot)?;
+
+    let truncated = &snapshot[..snapshot.len() - 1];
+    assert!(matches!(
+        LoroDoc::new().import_from_reader(truncated),
+        Err(LoroError::IoError(_))
+    ));
+
+    // A byte of the oplog part
+    snapshot[30] ^= 1;
+    assert_eq!(
+        LoroDoc::new().import_from_reader(snapshot.as_slice()),
+        Err(LoroError::DecodeChecksumMismatchError)
+    );
+    assert!(LoroDoc::new().import_from_reader(&b"loro"[..]).is_err());
+    Ok(())
+}
+
+#[test]
+fn export_to_writer_returns_io_error() -> anyhow::Result<()> {
+    struct FailingWriter;
+    impl Write for FailingWriter {
+        fn write(&mut self, _buf: &[u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::other("disk full"))
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let doc = new_doc_with_history(1)?;
+    assert!(matches!(
+        doc.export_to_writer(ExportMode::Snapshot, FailingWriter),
+        Err(LoroEncodeError::IoError(_))
+    ));
+    doc.get_text("text").insert(0, "still editable")?;
+    doc.commit();
+    assert_eq!(doc.len_changes(), 101);
+    Ok(())
+}
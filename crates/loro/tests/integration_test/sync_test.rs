@@ -0,0 +1,223 @@
+use loro::{
+    sync::{SyncMessage, SyncSession},
+    LoroDoc,
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use super::gen_action;
+
+fn new_doc(peer: u64) -> anyhow::Result<LoroDoc> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(peer)?;
+    doc.set_change_merge_interval(0);
+    Ok(doc)
+}
+
+/// One direction of an in-memory duplex channel, which drops, duplicates and reorders
+/// the messages
+#[derive(Default)]
+struct LossyChannel {
+    queue: Vec<Vec<u8>>,
+    drop_rate: f64,
+    duplicate_rate: f64,
+}
+
+impl LossyChannel {
+    fn new(drop_rate: f64, duplicate_rate: f64) -> Self {
+        Self {
+            queue: Vec::new(),
+            drop_rate,
+            duplicate_rate,
+        }
+    }
+
+    fn send(&mut self, rng: &mut StdRng, msgs: impl IntoIterator<Item = SyncMessage>) {
+        for msg in msgs {
+            if rng.gen_bool(self.drop_rate) {
+                continue;
+            }
+
+            let bytes = msg.encode();
+            if rng.gen_bool(self.duplicate_rate) {
+                self.queue.push(bytes.clone());
+            }
+            self.queue.push(bytes);
+        }
+    }
+
+    /// Receive a random message in the queue
+    fn recv(&mut self, rng: &mut StdRng) -> Option<SyncMessage> {
+        if self.queue.is_empty() {
+            return None;
+        }
+
+        let i = rng.gen_range(0..self.queue.len());
+        Some(SyncMessage::decode(&self.queue.swap_remove(i)).unwrap())
+    }
+}
+
+/// Deliver at most `n` messages from `channel` to `session`, and send the replies to `back`
+fn deliver(
+    rng: &mut StdRng,
+    n: usize,
+    channel: &mut LossyChannel,
+    session: &mut SyncSession,
+    back: &mut LossyChannel,
+) -> anyhow::Result<()> {
+    for _ in 0..n {
+        let Some(msg) = channel.recv(rng) else {
+            break;
+        };
+        let replies = session.receive(msg)?;
+        back.send(rng, replies);
+    }
+    Ok(())
+}
+
+fn sync_over_lossy_channel(seed: u64, drop_rate: f64, duplicate_rate: f64) -> anyhow::Result<()> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let a = new_doc(1)?;
+    let b = new_doc(2)?;
+    let mut session_a = SyncSession::new(&a);
+    let mut session_b = SyncSession::new(&b);
+    let mut to_a = LossyChannel::new(drop_rate, duplicate_rate);
+    let mut to_b = LossyChannel::new(drop_rate, duplicate_rate);
+    to_b.send(&mut rng, [session_a.hello()]);
+    to_a.send(&mut rng, [session_b.hello()]);
+
+    for round in 0..200 {
+        if rng.gen_bool(0.3) {
+            gen_action(&a, seed * 1000 + round, 2);
+            a.commit();
+            to_b.send(&mut rng, session_a.poll()?);
+        }
+        if rng.gen_bool(0.3) {
+            gen_action(&b, seed * 2000 + round, 2);
+            b.commit();
+            to_a.send(&mut rng, session_b.poll()?);
+        }
+        if round % 20 == 19 {
+            to_b.send(&mut rng, session_a.retry()?);
+            to_a.send(&mut rng, session_b.retry()?);
+        }
+
+        let n = rng.gen_range(0..4);
+        deliver(&mut rng, n, &mut to_a, &mut session_a, &mut to_b)?;
+        let n = rng.gen_range(0..4);
+        deliver(&mut rng, n, &mut to_b, &mut session_b, &mut to_a)?;
+    }
+
+    // The channel recovers, and the peers retry until they are in sync
+    to_a = LossyChannel::default();
+    to_b = LossyChannel::default();
+    for _ in 0..10 {
+        if session_a.is_synced() && session_b.is_synced() {
+            break;
+        }
+
+        to_b.send(&mut rng, session_a.retry()?);
+        to_a.send(&mut rng, session_b.retry()?);
+        while !to_a.queue.is_empty() || !to_b.queue.is_empty() {
+            deliver(&mut rng, 1, &mut to_a, &mut session_a, &mut to_b)?;
+            deliver(&mut rng, 1, &mut to_b, &mut session_b, &mut to_a)?;
+        }
+    }
+
+    assert!(session_a.is_synced() && session_b.is_synced());
+    assert_eq!(a.oplog_vv(), b.oplog_vv());
+    assert_eq!(a.get_deep_value(), b.get_deep_value());
+    Ok(())
+}
+
+#[test]
+fn sync_over_reliable_channel() -> anyhow::Result<()> {
+    sync_over_lossy_channel(0, 0., 0.)
+}
+
+#[test]
+fn sync_over_unreliable_channel() -> anyhow::Result<()> {
+    for seed in 1..6 {
+        sync_over_lossy_channel(seed, 0.2, 0.2)?;
+    }
+    Ok(())
+}
+
+#[test]
+fn request_missing_changes_when_pending() -> anyhow::Result<()> {
+    let a = new_doc(1)?;
+    let b = new_doc(2)?;
+    let mut session_a = SyncSession::new(&a);
+    let mut session_b = SyncSession::new(&b);
+    for msg in session_b.receive(session_a.hello())? {
+        session_a.receive(msg)?;
+    }
+    assert!(session_a.is_synced());
+
+    a.get_text("text").insert(0, "Hello")?;
+    a.commit();
+    // The first updates are lost
+    let _lost = session_a.poll()?.unwrap();
+    a.get_text("text").insert(5, " world")?;
+    a.commit();
+    let updates = session_a.poll()?.unwrap();
+
+    let replies = session_b.receive(updates.clone())?;
+    assert!(matches!(replies[..], [SyncMessage::Request { .. }]));
+    assert_eq!(b.get_text("text").to_string(), "");
+    let replies = session_a.receive(replies[0].clone())?;
+    assert!(matches!(replies[..], [SyncMessage::Updates { .. }]));
+    let replies = session_b.receive(replies[0].clone())?;
+    assert!(matches!(replies[..], [SyncMessage::Ack { .. }]));
+    assert_eq!(b.get_text("text").to_string(), "Hello world");
+
+    // Handling a duplicated message is harmless
+    let replies = session_b.receive(updates)?;
+    assert!(matches!(replies[..], [SyncMessage::Ack { .. }]));
+    assert_eq!(b.get_text("text").to_string(), "Hello world");
+    for msg in replies {
+        assert!(session_a.receive(msg)?.is_empty());
+    }
+    assert!(session_a.is_synced() && session_b.is_synced());
+    Ok(())
+}
+
+#[test]
+fn hello_resets_the_remote_version() -> anyhow::Result<()> {
+    let a = new_doc(1)?;
+    a.get_text("text").insert(0, "Hello")?;
+    a.commit();
+    let b = new_doc(2)?;
+    let mut session_a = SyncSession::new(&a);
+    let mut session_b = SyncSession::new(&b);
+    let hello = session_b.hello();
+    deliver_all(&mut session_a, &mut session_b, vec![hello])?;
+    assert_eq!(b.get_text("text").to_string(), "Hello");
+    assert!(session_a.is_synced());
+
+    // The remote peer restarts from an empty doc, so it needs all the changes again
+    let b = new_doc(2)?;
+    let mut session_b = SyncSession::new(&b);
+    let hello = session_b.hello();
+    deliver_all(&mut session_a, &mut session_b, vec![hello])?;
+    assert_eq!(b.get_text("text").to_string(), "Hello");
+    assert!(session_a.is_synced() && session_b.is_synced());
+    Ok(())
+}
+
+/// Deliver the messages to `a` and the replies back and forth until both sides are quiet
+fn deliver_all(
+    a: &mut SyncSession,
+    b: &mut SyncSession,
+    mut to_a: Vec<SyncMessage>,
+) -> anyhow::Result<()> {
+    let mut to_b = Vec::new();
+    while !to_a.is_empty() || !to_b.is_empty() {
+        for msg in std::mem::take(&mut to_a) {
+            to_b.extend(a.receive(msg)?);
+        }
+        for msg in std::mem::take(&mut to_b) {
+            to_a.extend(b.receive(msg)?);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,100 @@
+use loro::{CommitOptions, ExportMode, Frontiers, LoroDoc};
+
+/// Create a doc with many changes so that they are stored in multiple blocks.
+/// Returns the version after each change, whose timestamp is `(i + 1) * 10`.
+fn create_doc_with_history(peer: u64, n: usize) -> anyhow::Result<(LoroDoc, Vec<Frontiers>)> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(peer)?;
+    doc.set_change_merge_interval(0);
+    let text = doc.get_text("text");
+    let mut versions = Vec::new();
+    for i in 0..n {
+        text.insert(text.len_unicode(), &format!("{:>100}", i))?;
+        doc.commit_with(CommitOptions::new().timestamp((i as i64 + 1) * 10));
+        versions.push(doc.state_frontiers());
+    }
+
+    Ok((doc, versions))
+}
+
+fn assert_versions_at_timestamps(doc: &LoroDoc, versions: &[Frontiers]) -> anyhow::Result<()> {
+    assert_eq!(doc.frontiers_at_timestamp(5)?, Frontiers::default());
+    for (i, version) in versions.iter().enumerate() {
+        let ts = (i as i64 + 1) * 10;
+        assert_eq!(&doc.frontiers_at_timestamp(ts)?, version);
+        assert_eq!(&doc.frontiers_at_timestamp(ts + 5)?, version);
+    }
+    assert_eq!(doc.frontiers_at_timestamp(i64::MAX)?, doc.oplog_frontiers());
+    Ok(())
+}
+
+#[test]
+fn frontiers_at_timestamp_on_local_and_imported_history() -> anyhow::Result<()> {
+    let (doc, versions) = create_doc_with_history(1, 200)?;
+    assert_versions_at_timestamps(&doc, &versions)?;
+
+    // The blocks of the imported snapshot are not parsed until they are needed
+    let new_doc = LoroDoc::new();
+    new_doc.import(&doc.export(ExportMode::Snapshot)?)?;
+    assert_versions_at_timestamps(&new_doc, &versions)?;
+    Ok(())
+}
+
+#[test]
+fn frontiers_at_timestamp_includes_concurrent_changes() -> anyhow::Result<()> {
+    let (doc_a, versions) = create_doc_with_history(1, 5)?;
+    let doc_b = LoroDoc::new();
+    doc_b.set_peer_id(2)?;
+    doc_b.set_change_merge_interval(0);
+    doc_b.import(&doc_a.export(ExportMode::all_updates())?)?;
+    doc_b.get_text("text").insert(0, "B")?;
+    doc_b.commit_with(CommitOptions::new().timestamp(55));
+    let b_version = doc_b.state_frontiers();
+
+    let text = doc_a.get_text("text");
+    text.insert(0, "A")?;
+    doc_a.commit_with(CommitOptions::new().timestamp(60));
+    let a_version = doc_a.state_frontiers();
+    doc_a.import(&doc_b.export(ExportMode::all_updates())?)?;
+
+    let at_55 = doc_a.frontiers_at_timestamp(55)?;
+    let vv = doc_a.frontiers_to_vv(&at_55).unwrap();
+    assert!(vv.includes_id(versions[4].as_single().unwrap()));
+    assert!(vv.includes_id(b_version.as_single().unwrap()));
+    assert!(!vv.includes_id(a_version.as_single().unwrap()));
+    assert_eq!(at_55, b_version);
+    assert_eq!(doc_a.frontiers_at_timestamp(60)?, doc_a.oplog_frontiers());
+    assert_eq!(doc_a.oplog_frontiers().len(), 2);
+    Ok(())
+}
+
+#[test]
+fn checkout_at_timestamp() -> anyhow::Result<()> {
+    let (doc, versions) = create_doc_with_history(1, 10)?;
+    doc.checkout_at_timestamp(35)?;
+    assert!(doc.is_detached());
+    assert_eq!(doc.state_frontiers(), versions[2]);
+    assert_eq!(doc.get_text("text").len_unicode(), 300);
+
+    doc.checkout_at_timestamp(0)?;
+    assert_eq!(doc.get_text("text").to_string(), "");
+    doc.attach();
+    assert_eq!(doc.get_text("text").len_unicode(), 1000);
+    Ok(())
+}
+
+#[test]
+fn frontiers_at_timestamp_with_unsorted_timestamps() -> anyhow::Result<()> {
+    let (doc, versions) = create_doc_with_history(1, 4)?;
+    // Local changes always have non-decreasing timestamps, but remote ones may not
+    let mut json = doc.export_json_updates(&Default::default(), &doc.oplog_vv());
+    json.changes[2].timestamp = 15;
+    let remote = LoroDoc::new();
+    remote.import_json_updates(json)?;
+
+    // The change at 15 comes after the change at 20, so it includes it
+    assert_eq!(remote.frontiers_at_timestamp(17)?, versions[2]);
+    assert_eq!(remote.frontiers_at_timestamp(12)?, versions[0]);
+    assert_eq!(remote.frontiers_at_timestamp(5)?, Frontiers::default());
+    Ok(())
+}